@@ -1,145 +1,898 @@
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use std::collections::HashMap;
-use text_trees::{FormatCharacters, StringTreeNode, TreeFormatting};
-
-use crate::config::{Config, get_history_path, get_pins_path, load_config};
-use crate::search::{SearchPattern, SearchProvider, SearchResult};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Rect;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use text_trees::{StringTreeNode, TreeFormatting};
+
+use crate::archive::ArchivedSession;
+use crate::config::{
+    Config, LineNumberMode, OccupiedIndex, OpenActionConfig, OpenTarget, SessionCollision,
+    ViewConfig, ViewSort, expand_path, get_archive_path, get_history_path, get_pins_path,
+    get_session_history_path, get_usage_path,
+};
+use crate::doctor::{self, Check, Feature};
+use crate::export;
+use crate::filter::{Filter, parse_duration};
+use crate::hooks::{self, HookContext, ShellRun, with_switch_hooks};
+use crate::input::TextInput;
+use crate::keymap::{Action, Keymap, PIN_TOGGLE_KEYS, Scope};
+use crate::messages::MessageLog;
+use crate::search::{
+    Bookmark, Scan, SearchPattern, SearchProvider, SearchResult, group_counts, new_session_result,
+};
 use crate::tmux::{
-    TmuxSession, TmuxWindow, delete_window, get_current_session_name, get_tmux_sessions,
-    kill_session, rename_session, rename_window, switch_to_session, switch_to_window,
+    EnvVar, OPTION_TOGGLES, OptionToggle, PaneLayout, RealTmux, TmuxBackend, TmuxSession,
+    TmuxWindow, append_command, attach_command, break_window, format_user_options, link_window,
+    move_window, pager_command, socket_name, unlink_window,
 };
+use crate::usage::Usage;
+use crate::widgets::confirm_prompt::ConfirmButton;
+use crate::worker::Worker;
+
+// Quick jobs finish before a spinner would only flicker
+const BUSY_NOTE_DELAY: Duration = Duration::from_millis(250);
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Mode {
     Window,
     Rename,
-    Search,        // Project search mode (F key) - directory scanning
-    QuickSearch,   // Quick search mode (/ key) - search active sessions/windows
-    Session,       // Session management mode (S key) - move/reorder sessions
-    DeleteConfirm, // Delete confirmation mode (x key) - confirm window deletion
+    Link,             // Link window (L key) - type the session to also show it in
+    StartDir,         // Start directory (D key) - where new windows of a session start
+    Search,           // Project search mode (F key) - directory scanning
+    QuickSearch,      // Quick search mode (/ key) - search active sessions/windows
+    Session,          // Session management mode (S key) - move/reorder sessions
+    DeleteConfirm,    // Delete confirmation mode (x/X keys) - confirm window deletion
+    MoveConfirm,      // J/K past the end of a session - confirm moving into the next one
+    CollisionConfirm, // Search result's session holds another project - suffix or reuse
+    Messages,         // Message log (M key) - every info and error shown so far
+    Environment,      // Session environment (E key) - view, set and unset variables
+    Options,          // Options panel (O key) - toggle common window and session options
+    Info,             // Server info (I key) - tmux version, socket and health checks
+    Archive,          // Archived sessions (A key in session mode) - restore or forget them
+    Stats,            // Usage statistics (T key) - most used sessions, unvisited windows
+    Watch,            // `tmux-portal watch` - read-only tree that follows the current window
+    Note,             // Note (" key) - a short note on the selected window or session
+    Shell,     // Shell command (| key) - run a command with the selection in PORTAL_* variables
+    MoveIndex, // Move to index (= key) - give the selected window another index
+}
+
+// What the delete confirmation kills once confirmed
+#[derive(Debug, Clone)]
+enum DeleteTarget {
+    Selected,           // Window or session under the cursor
+    Items(DeleteItems), // A fixed set: marked items, other windows, idle sessions
+}
+
+// A change `.` makes again on whatever is selected then
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    Rename(String), // The new name, with `{name}` standing for the old one
+    MoveDown,
+    MoveUp,
+    Delete,
+}
+
+// `editor` renamed to `old-editor` repeats as `old-{name}`, anything else as is
+fn rename_pattern(old_name: &str, new_name: &str) -> String {
+    if old_name.is_empty() || !new_name.contains(old_name) {
+        return new_name.to_string();
+    }
+    new_name.replacen(old_name, "{name}", 1)
+}
+
+// Character index of the first case-insensitive occurrence of `pattern`
+fn find_ignore_case(text: &str, pattern: &str) -> Option<usize> {
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    if pattern.is_empty() {
+        return None;
+    }
+    text.windows(pattern.len()).position(|window| {
+        window
+            .iter()
+            .zip(&pattern)
+            .all(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+    })
+}
+
+/// A window about to move into a neighbouring session, right before or after
+/// the target window
+#[derive(Debug, Clone)]
+pub struct WindowMove {
+    pub window: TmuxWindow,
+    pub target_session: String,
+    pub target_window_id: String,
+    pub before: bool,
+}
+
+/// Sessions and windows killed together after a single confirmation
+#[derive(Debug, Clone, Default)]
+pub struct DeleteItems {
+    pub sessions: Vec<String>,
+    pub windows: Vec<TmuxWindow>,
 }
 
 #[derive(Debug, Clone)]
 pub struct TreeLine {
     pub line_type: LineType,
     pub content: String,
+    pub suffix: String, // ID and active markers, kept visible when content is truncated
     pub session_name: Option<String>,
     pub window: Option<TmuxWindow>,
 }
 
+/// A window switched to from the portal. The name is only for display and
+/// kept up to date on refresh, the ID is what identifies the window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub session_name: String,
+    pub window_id: String,
+    #[serde(default)]
+    pub window_name: String,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum LineType {
     Session,
     Window,
 }
 
+/// How long a session has gone without activity, for highlighting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Staleness {
+    Fresh,
+    Idle,  // Past `session_idle_after`
+    Stale, // Past `session_stale_after`
+}
+
+/// Where the cursor goes once fresh tmux state arrives
+#[derive(Debug, Clone, PartialEq)]
+pub enum Focus {
+    Active,          // Active window of the attached session
+    Keep,            // Whatever is selected when the refresh lands
+    Window(String),  // Window with this ID
+    Session(String), // Session line with this name
+    FirstSession,    // First session line (session mode)
+    Clamp,           // Same index, clamped to the new tree
+}
+
+/// Results of the jobs App runs on its worker thread
+pub enum TaskOutput {
+    Refreshed {
+        sessions: Result<Vec<TmuxSession>>,
+        current_session: Option<String>,
+        focus: Focus,
+    },
+    Switched(Result<()>),
+    Scanned(Result<Scan>),
+    Created(TmuxWindow),
+    Copied(Result<String>),
+    // Scrollback saved to a file, and `opened` in a pager window
+    Captured {
+        result: Result<PathBuf>,
+        opened: bool,
+        exit: bool,
+    },
+    Opened {
+        result: Result<()>,
+        exit: bool,
+    },
+    // A command from the `|` prompt finished
+    Ran {
+        command: String,
+        result: Result<ShellRun>,
+    },
+    Environment {
+        session_name: String,
+        result: Result<Vec<EnvVar>>,
+    },
+    Options {
+        window_id: String,
+        result: Result<Vec<bool>>,
+    },
+    Info(Vec<Check>),
+    Version(Option<(u32, u32)>),
+    // A session was killed after its layout was captured
+    Archived {
+        archived: ArchivedSession,
+        result: Result<()>,
+    },
+    Restored {
+        archived: ArchivedSession,
+        result: Result<()>,
+    },
+    Done,
+    // Kills of several items, with a message for each one that failed
+    Deleted {
+        failures: Vec<String>,
+    },
+    Failed(String),
+}
+
+impl TaskOutput {
+    // Read tmux state, runs on the worker thread
+    fn refreshed(tmux: &dyn TmuxBackend, focus: Focus) -> Self {
+        TaskOutput::Refreshed {
+            sessions: tmux.list_sessions(),
+            current_session: tmux.current_session_name().ok().flatten(),
+            focus,
+        }
+    }
+}
+
 pub struct App {
     pub mode: Mode,
     pub previous_mode: Mode,
     pub sessions: Vec<TmuxSession>,
+    pub current_session: Option<String>, // Attached session as of the last refresh
     pub tree_lines: Vec<TreeLine>,
     pub selected_index: usize,
     pub scroll_offset: usize,
-    pub error_message: Option<String>,
+    // Toasts and the message log behind them
+    pub messages: MessageLog,
+    pub messages_scroll: usize,
     pub show_popup: bool,
-    pub popup_input: String,
+    pub popup_input: TextInput,
     pub config: Config,
-    pub auto_position_on_active: bool, // Flag to control auto-positioning
     pub search_provider: SearchProvider,
     pub search_results: Vec<SearchResult>,
-    pub search_query: String,
+    pub search_query: TextInput,
     pub search_selected_index: usize,
+    // Result count of every group, collapsed ones included, in display order
+    pub search_groups: Vec<(String, usize)>,
+    // Groups whose results are hidden, kept for the rest of the session
+    pub collapsed_groups: HashSet<String>,
     // Quick search fields (for / key)
-    pub quick_search_query: String,
+    pub quick_search_query: TextInput,
     pub quick_search_results: Vec<usize>, // Indices into tree_lines that match
     pub quick_search_selected_index: usize,
-    // History tracking for digit shortcuts
-    pub history: Vec<(String, String)>, // (session_name, window_id)
+    pub line_number_mode: LineNumberMode,
+    // Windows ordered by most recent activity instead of their index
+    pub sort_by_activity: bool,
+    // Windows as one sorted `session:window` list, without session lines
+    pub flat: bool,
+    // Index into config.views of the view narrowing down the tree
+    pub active_view: Option<usize>,
+    // Digits typed after ':', Enter jumps to that window index in the session
+    pub index_jump: Option<String>,
+    // Windows whose name contains this are highlighted, `n`/`N` jump between them
+    pub tree_search: Option<String>,
+    // Pattern being typed after `?`, and the selection to go back to on Esc
+    pub tree_search_input: Option<TextInput>,
+    tree_search_origin: usize,
+    // Recently visited windows, most recent first
+    pub history: Vec<HistoryEntry>,
+    // Where Ctrl-o/Ctrl-i walking through history currently is
+    pub jump_position: Option<usize>,
+    // Recently visited window IDs of each session, most recent first
+    pub session_history: HashMap<String, Vec<String>>,
+    // Session and position while cycling its recent windows with `w`
+    session_cycle: Option<(String, usize)>,
+    // Keys typed since `Q` started a recording, the last recorded macro, and
+    // the keys of a replay that are still to come
+    recording: Option<Vec<KeyEvent>>,
+    macro_keys: Vec<KeyEvent>,
+    replay_queue: VecDeque<KeyEvent>,
+    last_change: Option<Change>,
+    // Ctrl-r in project search asked for the scan that's running
+    rescan_requested: bool,
+    // Patterns with scan_on_startup = false that no scan has covered yet
+    lazy_scan_pending: bool,
     // Harpoon-style pins: slot 1-9 -> (session_name, window_id)
     pub pins: HashMap<u8, (String, String)>,
+    // Pins listed on screen (' key), the next digit jumps
+    pub show_pin_overlay: bool,
+    delete_target: DeleteTarget,
+    // Waiting for the MoveConfirm answer
+    pub pending_move: Option<WindowMove>,
+    // Waiting for the CollisionConfirm answer: the result and its suffixed twin
+    pub pending_collision: Option<(SearchResult, SearchResult)>,
+    // Waiting for a pane layout for the window this result creates
+    pub pending_layout: Option<SearchResult>,
+    // Button focused in a yes/no confirmation, Enter presses it
+    pub confirm_button: ConfirmButton,
+    // Where the tree was drawn last: each column and its first line
+    pub tree_columns: Vec<(Rect, usize)>,
+    // Window being dragged with the mouse
+    drag: Option<TmuxWindow>,
+    // Multi-selection with Space, deleted together with x
+    pub marked_sessions: HashSet<String>,
+    pub marked_windows: HashSet<String>, // Window IDs
+    // Session shown in the environment view, its variables and the selected one
+    pub env_session: String,
+    pub env_vars: Vec<EnvVar>,
+    pub env_selected: usize,
+    // "NAME=value" being typed in the environment view
+    pub env_input: Option<TextInput>,
+    // Window shown in the options panel, and the values of OPTION_TOGGLES
+    // for it once read
+    pub options_window: Option<TmuxWindow>,
+    pub option_values: Vec<bool>,
+    pub options_selected: usize,
+    // Health report shown in the info panel, empty until it's gathered
+    pub info_checks: Vec<Check>,
+    // Archived sessions, most recent first, and the one selected in the list
+    pub archive: Vec<ArchivedSession>,
+    pub archive_selected: usize,
+    // Switches per window and daily totals, shown in the stats view
+    pub usage: Usage,
+    // Server version once known, features it's too old for are turned off
+    pub tmux_version: Option<(u32, u32)>,
+    // Window and session mode keys after the `[keys]` overrides
+    pub keymap: Keymap,
+    // Background thread for tmux commands and directory scans
+    worker: Worker<TaskOutput>,
+    tmux: Arc<dyn TmuxBackend>,
+    // Whether history and pins are written back to the config dir
+    persist_state: bool,
+    // Local state was updated ahead of tmux, re-read it once the queue drains
+    verify_pending: bool,
+    pub should_quit: bool,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let config = load_config()?;
+    pub fn new(config: Config) -> Self {
+        let tmux = RealTmux::new(format_user_options(&config.window_format));
+        let mut app = Self::with_backend(config, Arc::new(tmux));
+        app.history = Self::load_history().unwrap_or_default();
+        app.session_history = Self::load_session_history().unwrap_or_default();
+        app.pins = Self::load_pins().unwrap_or_default();
+        app.archive = Self::load_archive().unwrap_or_default();
+        app.usage = Self::load_usage().unwrap_or_default();
+        app.persist_state = true;
+        app
+    }
 
+    /// Read-only tree for `tmux-portal watch`, e.g. in a narrow side pane
+    pub fn watch(mut config: Config) -> Self {
+        // Nothing to search in, so nothing to scan
+        config.search_patterns.clear();
+        let tmux = RealTmux::new(format_user_options(&config.window_format));
+        let mut app = Self::with_backend(config, Arc::new(tmux));
+        app.mode = Mode::Watch;
+        app.pins = Self::load_pins().unwrap_or_default();
+        app
+    }
+
+    /// App on top of any tmux backend. History and pins start out empty and
+    /// are only kept in memory
+    pub fn with_backend(config: Config, tmux: Arc<dyn TmuxBackend>) -> Self {
         // Create search patterns from config
         let mut search_patterns = Vec::new();
 
         // Add patterns from new config format
         for pattern_config in &config.search_patterns {
-            fn fun_name(p: &String) -> std::path::PathBuf {
-                std::path::PathBuf::from(p)
-            }
-            let paths: Vec<std::path::PathBuf> =
-                pattern_config.paths.iter().map(fun_name).collect();
+            let paths: Vec<std::path::PathBuf> = pattern_config
+                .paths
+                .iter()
+                .map(|p| expand_path(p))
+                .collect();
 
-            search_patterns.push(SearchPattern::new(
-                pattern_config.name.clone(),
-                paths,
-                pattern_config.pattern.clone(),
-            ));
+            search_patterns.push(
+                SearchPattern::new(
+                    pattern_config.name.clone(),
+                    paths,
+                    pattern_config.pattern.clone(),
+                )
+                .kind(pattern_config.kind)
+                .window_name(pattern_config.window_name.clone())
+                .on_create(pattern_config.on_create.clone())
+                .layout(PaneLayout {
+                    panes: pattern_config.panes.clone(),
+                    layout: pattern_config.layout.clone(),
+                    environment: pattern_config.environment,
+                })
+                .weight(pattern_config.weight)
+                .scan_on_startup(
+                    pattern_config
+                        .scan_on_startup
+                        .unwrap_or(config.scan_on_startup),
+                ),
+            );
         }
 
         // Legacy support: convert old search_paths to git-style pattern
         if !config.search_paths.is_empty() && search_patterns.is_empty() {
-            fn fun_name(p: &String) -> std::path::PathBuf {
-                std::path::PathBuf::from(p)
-            }
-            let paths: Vec<std::path::PathBuf> = config.search_paths.iter().map(fun_name).collect();
+            let paths: Vec<std::path::PathBuf> =
+                config.search_paths.iter().map(|p| expand_path(p)).collect();
 
-            search_patterns.push(SearchPattern::new(
-                "git-style".to_string(),
-                paths,
-                "{session}/{window}".to_string(),
-            ));
+            search_patterns.push(
+                SearchPattern::new(
+                    "git-style".to_string(),
+                    paths,
+                    "{session}/{window}".to_string(),
+                )
+                .scan_on_startup(config.scan_on_startup),
+            );
         }
 
-        let mut search_provider = SearchProvider::new(search_patterns);
-
-        // Scan directories on startup (in background, don't fail if it errors)
-        let _ = search_provider.scan_directories();
+        let bookmarks: Vec<Bookmark> = config
+            .bookmarks
+            .iter()
+            .map(|bookmark| Bookmark {
+                name: bookmark.name.clone(),
+                path: expand_path(&bookmark.path),
+                session_name: bookmark.session.clone(),
+                window_name: bookmark.window.clone(),
+            })
+            .collect();
+        let search_provider = SearchProvider::new(search_patterns).bookmarks(&bookmarks);
+        let line_number_mode = config.line_numbers.mode;
+        let sort_by_activity = config.sort_by_activity;
+        let flat = config.flat_list;
+        let (keymap, key_errors) = Keymap::new(config.keymap, &config.keys);
 
         let mut app = Self {
             mode: Mode::Window,
             previous_mode: Mode::Window,
             sessions: Vec::new(),
+            current_session: None,
             tree_lines: Vec::new(),
             selected_index: 0,
             scroll_offset: 0,
-            error_message: None,
+            messages: MessageLog::new(Duration::from_millis(config.toast_duration_ms)),
+            messages_scroll: 0,
+            env_session: String::new(),
+            env_vars: Vec::new(),
+            env_selected: 0,
+            env_input: None,
+            options_window: None,
+            option_values: Vec::new(),
+            options_selected: 0,
+            info_checks: Vec::new(),
+            tmux_version: None,
+            keymap,
+            archive: Vec::new(),
+            archive_selected: 0,
+            usage: Usage::default(),
             show_popup: false,
-            popup_input: String::new(),
+            popup_input: TextInput::default(),
             config,
-            auto_position_on_active: true, // Enable auto-positioning on startup
             search_provider,
             search_results: Vec::new(),
-            search_query: String::new(),
+            search_groups: Vec::new(),
+            collapsed_groups: HashSet::new(),
+            search_query: TextInput::default(),
             search_selected_index: 0,
-            quick_search_query: String::new(),
+            quick_search_query: TextInput::default(),
             quick_search_results: Vec::new(),
             quick_search_selected_index: 0,
-            history: Self::load_history().unwrap_or_default(),
-            pins: Self::load_pins().unwrap_or_default(),
+            line_number_mode,
+            sort_by_activity,
+            flat,
+            active_view: None,
+            index_jump: None,
+            tree_search: None,
+            tree_search_input: None,
+            tree_search_origin: 0,
+            history: Vec::new(),
+            jump_position: None,
+            session_history: HashMap::new(),
+            session_cycle: None,
+            recording: None,
+            macro_keys: Vec::new(),
+            replay_queue: VecDeque::new(),
+            last_change: None,
+            rescan_requested: false,
+            lazy_scan_pending: false,
+            pins: HashMap::new(),
+            show_pin_overlay: false,
+            delete_target: DeleteTarget::Selected,
+            pending_move: None,
+            pending_collision: None,
+            pending_layout: None,
+            confirm_button: ConfirmButton::default(),
+            tree_columns: Vec::new(),
+            drag: None,
+            marked_sessions: HashSet::new(),
+            marked_windows: HashSet::new(),
+            worker: Worker::spawn(),
+            tmux,
+            persist_state: false,
+            verify_pending: false,
+            should_quit: false,
         };
 
-        app.refresh_sessions()?;
-        Ok(app)
+        for error in key_errors {
+            app.messages.error(error);
+        }
+
+        // Load tmux state and scan directories in the background
+        app.detect_version();
+        app.refresh_sessions(Focus::Active);
+        app.scan_startup_patterns();
+        app
     }
 
-    pub fn refresh_sessions(&mut self) -> Result<()> {
-        self.sessions = get_tmux_sessions()?;
-        self.rebuild_tree_view();
+    fn detect_version(&mut self) {
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            let version = tmux.server_info().ok();
+            TaskOutput::Version(version.and_then(|info| doctor::parse_version(&info.version)))
+        });
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        feature.supported_by(self.tmux_version)
+    }
+
+    // Tells the user instead of letting tmux fail with "unknown command"
+    fn require(&mut self, feature: Feature) -> bool {
+        if !self.supports(feature) {
+            self.messages.info(feature.unsupported_message());
+        }
+        self.supports(feature)
+    }
+
+    /// Refresh on a timer or hook signal. The watch view follows the current
+    /// window, elsewhere the selection stays
+    pub fn refresh_in_background(&mut self) {
+        match self.mode {
+            Mode::Watch => self.refresh_sessions(Focus::Active),
+            _ => self.refresh_sessions(Focus::Keep),
+        }
+    }
+
+    pub fn refresh_sessions(&mut self, focus: Focus) {
+        let tmux = self.tmux.clone();
+        self.worker
+            .submit(move || TaskOutput::refreshed(&*tmux, focus));
+    }
+
+    fn scan_directories(&mut self) {
+        self.lazy_scan_pending = false;
+        let patterns = self.search_provider.patterns().to_vec();
+        self.scan_patterns(patterns);
+    }
+
+    // Heavy patterns wait for the first project search
+    fn scan_startup_patterns(&mut self) {
+        let (eager, lazy): (Vec<_>, Vec<_>) = self
+            .search_provider
+            .patterns()
+            .iter()
+            .cloned()
+            .partition(|p| p.scan_on_startup);
+        self.lazy_scan_pending = !lazy.is_empty();
+        if !eager.is_empty() {
+            self.scan_patterns(eager);
+        }
+    }
+
+    fn scan_lazy_patterns(&mut self) {
+        if !std::mem::take(&mut self.lazy_scan_pending) {
+            return;
+        }
+        let lazy = self
+            .search_provider
+            .patterns()
+            .iter()
+            .filter(|p| !p.scan_on_startup)
+            .cloned()
+            .collect();
+        self.scan_patterns(lazy);
+    }
+
+    fn scan_patterns(&mut self, patterns: Vec<SearchPattern>) {
+        self.worker.submit_as("Scanning projects", move || {
+            TaskOutput::Scanned(SearchProvider::scan(patterns))
+        });
+    }
+
+    /// Apply the results of finished background jobs
+    pub fn process_tasks(&mut self) {
+        while let Some(output) = self.worker.try_recv() {
+            match output {
+                TaskOutput::Refreshed {
+                    sessions,
+                    current_session,
+                    focus,
+                } => match sessions {
+                    Ok(sessions) => {
+                        self.prune_history(&sessions);
+                        self.apply_sessions(sessions, current_session, focus);
+                    }
+                    Err(e) => self.messages.error(format!("Failed to refresh: {e}")),
+                },
+                TaskOutput::Switched(result) => match result {
+                    Ok(_) => self.finish_switch(),
+                    Err(e) => self.messages.error(format!("Failed to switch: {e}")),
+                },
+                TaskOutput::Scanned(result) => match result {
+                    Ok(scan) => {
+                        if std::mem::take(&mut self.rescan_requested) {
+                            self.messages
+                                .info(format!("Found {} projects", scan.results.len()));
+                        }
+                        self.search_provider.set_results(scan);
+                        if self.mode == Mode::Search {
+                            self.update_search_results();
+                        }
+                    }
+                    Err(e) => self.messages.error(format!("Failed to scan: {e}")),
+                },
+                TaskOutput::Created(window) => {
+                    let window_id = window.id.clone();
+                    let mut sessions = self.sessions.clone();
+                    if let Some(session) =
+                        sessions.iter_mut().find(|s| s.name == window.session_name)
+                    {
+                        // It may fill a gap rather than come last
+                        let position = session.windows.partition_point(|w| w.index < window.index);
+                        session.windows.insert(position, window);
+                    }
+                    self.apply_sessions(sessions, None, Focus::Window(window_id));
+                }
+                TaskOutput::Copied(result) => match result {
+                    Ok(text) => self.messages.info(format!("Copied {text}")),
+                    Err(e) => self.messages.error(format!("Failed to copy: {e}")),
+                },
+                TaskOutput::Captured {
+                    result,
+                    opened,
+                    exit,
+                } => match result {
+                    Ok(file) => {
+                        self.messages
+                            .info(format!("Saved scrollback to {}", file.display()));
+                        if opened && exit {
+                            self.should_quit = true;
+                        } else if opened {
+                            self.refresh_sessions(Focus::Keep);
+                        }
+                    }
+                    Err(e) => self
+                        .messages
+                        .error(format!("Failed to save scrollback: {e}")),
+                },
+                TaskOutput::Opened { result, exit } => match result {
+                    Ok(_) if exit => self.should_quit = true,
+                    // Show the window the action may have opened
+                    Ok(_) => self.refresh_sessions(Focus::Keep),
+                    Err(e) => self.messages.error(format!("Failed to open: {e}")),
+                },
+                TaskOutput::Ran { command, result } => {
+                    match result {
+                        Ok(run) => {
+                            let output = match run.output.is_empty() {
+                                true => String::new(),
+                                false => format!(": {}", run.output),
+                            };
+                            match run.status {
+                                Some(0) => self
+                                    .messages
+                                    .info(format!("`{command}` exited with 0{output}")),
+                                Some(code) => self
+                                    .messages
+                                    .error(format!("`{command}` exited with {code}{output}")),
+                                None => self
+                                    .messages
+                                    .error(format!("`{command}` was killed{output}")),
+                            }
+                        }
+                        Err(e) => self
+                            .messages
+                            .error(format!("Failed to run `{command}`: {e}")),
+                    }
+                    // The command may well have changed tmux
+                    self.refresh_sessions(Focus::Keep);
+                }
+                TaskOutput::Environment {
+                    session_name,
+                    result,
+                } => match result {
+                    // Skip answers for a session that is no longer shown
+                    Ok(vars) if session_name == self.env_session => {
+                        self.env_selected = self.env_selected.min(vars.len().saturating_sub(1));
+                        self.env_vars = vars;
+                    }
+                    Ok(_) => {}
+                    Err(e) => self
+                        .messages
+                        .error(format!("Failed to read environment: {e}")),
+                },
+                TaskOutput::Options { window_id, result } => match result {
+                    Ok(values)
+                        if self
+                            .options_window
+                            .as_ref()
+                            .is_some_and(|w| w.id == window_id) =>
+                    {
+                        self.option_values = values
+                    }
+                    Ok(_) => {}
+                    Err(e) => self.messages.error(format!("Failed to read options: {e}")),
+                },
+                TaskOutput::Info(checks) => self.info_checks = checks,
+                TaskOutput::Version(version) => self.tmux_version = version,
+                TaskOutput::Archived { archived, result } => match result {
+                    Ok(_) => {
+                        self.messages.info(format!(
+                            "Archived '{}', restore it from the archive (A)",
+                            archived.name
+                        ));
+                        self.archive.insert(0, archived);
+                        let _ = self.save_archive();
+                    }
+                    Err(e) => {
+                        self.messages
+                            .error(format!("Failed to archive '{}': {e}", archived.name));
+                        self.verify_pending = true;
+                    }
+                },
+                TaskOutput::Restored { archived, result } => match result {
+                    Ok(_) => {
+                        self.messages.info(format!("Restored '{}'", archived.name));
+                        self.archive.retain(|a| a != &archived);
+                        self.archive_selected = self
+                            .archive_selected
+                            .min(self.archive.len().saturating_sub(1));
+                        let _ = self.save_archive();
+                        self.refresh_sessions(Focus::Session(archived.name));
+                    }
+                    Err(e) => self
+                        .messages
+                        .error(format!("Failed to restore '{}': {e}", archived.name)),
+                },
+                TaskOutput::Done => {}
+                TaskOutput::Deleted { failures } => {
+                    if !failures.is_empty() {
+                        for failure in failures {
+                            self.messages.error(failure);
+                        }
+                        self.verify_pending = true;
+                    }
+                }
+                TaskOutput::Failed(message) => {
+                    self.messages.error(message);
+                    // The rest of a macro would act on the wrong state
+                    self.replay_queue.clear();
+                    // Roll back whatever was applied optimistically
+                    self.verify_pending = true;
+                }
+            }
+        }
+
+        // One refresh after a burst of operations instead of one per operation
+        if self.verify_pending && !self.worker.is_busy() {
+            self.verify_pending = false;
+            self.refresh_sessions(Focus::Keep);
+        }
+        self.continue_replay();
+    }
+
+    // Run a tmux operation whose effect was already applied locally
+    fn submit_optimistic<F>(&mut self, job: F)
+    where
+        F: FnOnce() -> TaskOutput + Send + 'static,
+    {
+        self.submit_optimistic_as("", job);
+    }
 
-        // Only auto-position on active window if the flag is set
-        if self.auto_position_on_active {
-            self.position_on_active_window();
-            self.auto_position_on_active = false; // Disable after first use
+    fn submit_optimistic_as<F>(&mut self, label: impl Into<String>, job: F)
+    where
+        F: FnOnce() -> TaskOutput + Send + 'static,
+    {
+        self.worker.submit_as(label, job);
+        self.verify_pending = true;
+    }
+
+    // Edit a copy of the sessions and show it right away
+    fn update_sessions_locally<F>(&mut self, focus: Focus, update: F)
+    where
+        F: FnOnce(&mut Vec<TmuxSession>),
+    {
+        let mut sessions = self.sessions.clone();
+        update(&mut sessions);
+        self.apply_sessions(sessions, None, focus);
+    }
+
+    fn apply_sessions(
+        &mut self,
+        sessions: Vec<TmuxSession>,
+        current_session: Option<String>,
+        focus: Focus,
+    ) {
+        let previous = self.tree_lines.get(self.selected_index).cloned();
+
+        // Local updates don't know the attached session, keep the last one
+        if current_session.is_some() {
+            self.current_session = current_session.clone();
         }
+        self.sessions = sessions;
+        self.marked_sessions
+            .retain(|name| self.sessions.iter().any(|s| &s.name == name));
+        self.marked_windows.retain(|id| {
+            self.sessions
+                .iter()
+                .any(|s| s.windows.iter().any(|w| &w.id == id))
+        });
+        self.rebuild_tree_view();
 
+        if focus == Focus::Clamp {
+            self.selected_index = self
+                .selected_index
+                .min(self.tree_lines.len().saturating_sub(1));
+        }
         self.ensure_valid_selection();
-        Ok(())
+
+        match focus {
+            Focus::Active => self.position_on_active_window(current_session.as_deref()),
+            Focus::Keep => {
+                // Background refreshes must not make the cursor jump around, so
+                // keep it on the same window (or session) even if lines moved
+                if let Some(line) = previous {
+                    let new_index = match &line.window {
+                        Some(window) => self.find_window_index_by_id(&window.id),
+                        None => self.find_session_index(line.session_name.as_deref()),
+                    };
+                    if let Some(index) = new_index {
+                        self.selected_index = index;
+                    }
+                }
+            }
+            Focus::Window(window_id) => {
+                if let Some(index) = self.find_window_index_by_id(&window_id) {
+                    self.selected_index = index;
+                }
+            }
+            Focus::Session(session_name) => {
+                if let Some(index) = self.find_session_index(Some(&session_name)) {
+                    self.selected_index = index;
+                }
+            }
+            Focus::FirstSession => self.move_to_first_session(),
+            Focus::Clamp => {}
+        }
+    }
+
+    fn find_session_index(&self, session_name: Option<&str>) -> Option<usize> {
+        self.tree_lines.iter().position(|line| {
+            line.line_type == LineType::Session && line.session_name.as_deref() == session_name
+        })
+    }
+
+    /// How long a session has been left alone, against `session_idle_after`
+    /// and `session_stale_after`. Attached sessions are never idle
+    pub fn session_staleness(&self, session_name: &str, now: i64) -> Staleness {
+        let Some(session) = self.sessions.iter().find(|s| s.name == session_name) else {
+            return Staleness::Fresh;
+        };
+        let idle_for = |threshold: &str| {
+            parse_duration(threshold).is_some_and(|seconds| session.is_idle(now, seconds))
+        };
+        if idle_for(&self.config.session_stale_after) {
+            Staleness::Stale
+        } else if idle_for(&self.config.session_idle_after) {
+            Staleness::Idle
+        } else {
+            Staleness::Fresh
+        }
+    }
+
+    // True when the user is just browsing, so a refresh cannot disturb any input
+    pub fn is_idle(&self) -> bool {
+        matches!(self.mode, Mode::Window | Mode::Session | Mode::Watch)
+            && !self.show_popup
+            && !self.is_busy()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.worker.is_busy()
+    }
+
+    /// Spinner and what the worker is doing, once it has been busy long
+    /// enough to look frozen, e.g. "⠹ Deleting session 'work'…"
+    pub fn busy_note(&self) -> Option<String> {
+        let busy_for = self.worker.busy_for().filter(|t| *t >= BUSY_NOTE_DELAY)?;
+        let frame = (busy_for.as_millis() / 80) as usize % SPINNER.len();
+        let label = self.worker.label().unwrap_or("Working");
+        Some(format!("{} {label}…", SPINNER[frame]))
     }
 
     fn rebuild_tree_view(&mut self) {
@@ -149,12 +902,32 @@ impl App {
             return;
         }
 
+        if self.flat {
+            self.tree_lines = self.flat_lines();
+            return;
+        }
+
         // Build individual session trees (each session is a root node)
         let mut all_tree_lines = Vec::new();
+        let view_filter = self.active_view().map(|view| Filter::parse(&view.filter));
+        let now = chrono::Local::now().timestamp();
 
         for session in &self.sessions {
             let mut window_nodes = Vec::new();
 
+            // Most recently active first when sorting by activity, else tmux's order
+            let mut windows: Vec<&TmuxWindow> = session.windows.iter().collect();
+            if self.sort_by_activity {
+                windows.sort_by_key(|w| std::cmp::Reverse(w.activity));
+            }
+            // Sessions without a window in the view disappear with them
+            if let Some(filter) = &view_filter {
+                windows.retain(|w| filter.accepts_window(w, now));
+                if windows.is_empty() {
+                    continue;
+                }
+            }
+
             // Check for duplicate window names in this session (only if config enabled)
             let show_ids = if self.config.show_window_ids {
                 let mut name_counts = std::collections::HashMap::new();
@@ -166,23 +939,14 @@ impl App {
                 false
             };
 
-            for (window_idx, window) in session.windows.iter().enumerate() {
-                let window_display = if show_ids {
-                    // Show ID for disambiguation when there are duplicates
-                    if window.active {
-                        format!("{} [{}] (active)", window.name, window.id)
-                    } else {
-                        format!("{} [{}]", window.name, window.id)
-                    }
+            for (window_idx, window) in windows.iter().enumerate() {
+                let text = window.format(&self.config.window_format);
+                let label = if self.config.show_window_index {
+                    format!("{}: {}", window.index, text)
                 } else {
-                    // Show normally when no duplicates or config disabled
-                    if window.active {
-                        format!("{} (active)", window.name)
-                    } else {
-                        window.name.clone()
-                    }
+                    text
                 };
-                window_nodes.push((window_idx, StringTreeNode::new(window_display)));
+                window_nodes.push((window_idx, StringTreeNode::new(label)));
             }
 
             let session_tree = if window_nodes.is_empty() {
@@ -194,8 +958,7 @@ impl App {
                 )
             };
 
-            // Use box drawing characters
-            let formatting = TreeFormatting::dir_tree(FormatCharacters::box_chars());
+            let formatting = TreeFormatting::dir_tree(self.config.tree_style.format_characters());
             let tree_output = session_tree
                 .to_string_with_format(&formatting)
                 .unwrap_or_else(|_| session_tree.to_string());
@@ -207,17 +970,27 @@ impl App {
                     all_tree_lines.push(TreeLine {
                         line_type: LineType::Session,
                         content: line.to_string(),
+                        suffix: String::new(),
                         session_name: Some(session.name.clone()),
                         window: None,
                     });
                 } else {
                     // This is a window line - use the window index to get the correct window
                     let window_idx = line_idx - 1; // Subtract 1 because line 0 is the session
-                    if window_idx < session.windows.len() {
-                        let window = &session.windows[window_idx];
+                    if window_idx < windows.len() {
+                        let window = windows[window_idx];
+                        // Show ID for disambiguation when there are duplicates
+                        let mut suffix = String::new();
+                        if show_ids {
+                            suffix.push_str(&format!(" [{}]", window.id));
+                        }
+                        if window.active {
+                            suffix.push_str(" (active)");
+                        }
                         all_tree_lines.push(TreeLine {
                             line_type: LineType::Window,
                             content: line.to_string(),
+                            suffix,
                             session_name: Some(window.session_name.clone()),
                             window: Some(window.clone()),
                         });
@@ -229,20 +1002,63 @@ impl App {
         self.tree_lines = all_tree_lines;
     }
 
-    fn position_on_active_window(&mut self) {
-        // Get the current session name from tmux
-        let current_session = match get_current_session_name() {
-            Ok(Some(session_name)) => session_name,
-            _ => {
+    // All windows of the view as `session:window`, most recently active first
+    // when sorting by activity, else by name
+    fn flat_lines(&self) -> Vec<TreeLine> {
+        let view_filter = self.active_view().map(|view| Filter::parse(&view.filter));
+        let now = chrono::Local::now().timestamp();
+
+        let mut windows: Vec<&TmuxWindow> = self
+            .sessions
+            .iter()
+            .flat_map(|s| &s.windows)
+            .filter(|w| {
+                view_filter
+                    .as_ref()
+                    .is_none_or(|f| f.accepts_window(w, now))
+            })
+            .collect();
+        if self.sort_by_activity {
+            windows.sort_by_key(|w| std::cmp::Reverse(w.activity));
+        } else {
+            let key = |w: &TmuxWindow| format!("{}:{}", w.session_name, w.name).to_lowercase();
+            windows.sort_by_cached_key(|w| key(w));
+        }
+
+        windows
+            .into_iter()
+            .map(|window| {
+                let mut suffix = String::new();
+                if window.active {
+                    suffix.push_str(" (active)");
+                }
+                TreeLine {
+                    line_type: LineType::Window,
+                    content: format!(
+                        "{}:{}",
+                        window.session_name,
+                        window.format(&self.config.window_format)
+                    ),
+                    suffix,
+                    session_name: Some(window.session_name.clone()),
+                    window: Some(window.clone()),
+                }
+            })
+            .collect()
+    }
+
+    fn position_on_active_window(&mut self, current_session: Option<&str>) {
+        let current_session = match current_session {
+            Some(session_name) => session_name,
+            None => {
                 // Fallback: find any active window if we can't detect current session
                 for (index, line) in self.tree_lines.iter().enumerate() {
-                    if line.line_type == LineType::Window {
-                        if let Some(window) = &line.window {
-                            if window.active {
-                                self.selected_index = index;
-                                return;
-                            }
-                        }
+                    if line.line_type == LineType::Window
+                        && let Some(window) = &line.window
+                        && window.active
+                    {
+                        self.selected_index = index;
+                        return;
                     }
                 }
                 return;
@@ -251,25 +1067,24 @@ impl App {
 
         // Find the active window within the current session
         for (index, line) in self.tree_lines.iter().enumerate() {
-            if line.line_type == LineType::Window {
-                if let (Some(line_session), Some(window)) = (&line.session_name, &line.window) {
-                    if line_session == &current_session && window.active {
-                        self.selected_index = index;
-                        return;
-                    }
-                }
+            if line.line_type == LineType::Window
+                && let (Some(line_session), Some(window)) = (&line.session_name, &line.window)
+                && line_session == current_session
+                && window.active
+            {
+                self.selected_index = index;
+                return;
             }
         }
 
         // If no active window found in current session, position on the session itself
         for (index, line) in self.tree_lines.iter().enumerate() {
-            if line.line_type == LineType::Session {
-                if let Some(line_session) = &line.session_name {
-                    if line_session == &current_session {
-                        self.selected_index = index;
-                        return;
-                    }
-                }
+            if line.line_type == LineType::Session
+                && let Some(line_session) = &line.session_name
+                && line_session == current_session
+            {
+                self.selected_index = index;
+                return;
             }
         }
     }
@@ -307,113 +1122,606 @@ impl App {
         }
 
         let viewport_height = viewport_height.saturating_sub(1); // Account for borders/padding
-        
+
         // If selected item is above the current viewport, scroll up
         if self.selected_index < self.scroll_offset {
             self.scroll_offset = self.selected_index;
         }
         // If selected item is below the current viewport, scroll down
         else if self.selected_index >= self.scroll_offset + viewport_height {
-            self.scroll_offset = self.selected_index.saturating_sub(viewport_height.saturating_sub(1));
+            self.scroll_offset = self
+                .selected_index
+                .saturating_sub(viewport_height.saturating_sub(1));
+        }
+    }
+
+    // Tree line drawn at a terminal cell
+    fn line_at(&self, column: u16, row: u16) -> Option<usize> {
+        let (area, start) = self.tree_columns.iter().find(|(area, _)| {
+            (area.left()..area.right()).contains(&column)
+                && (area.top()..area.bottom()).contains(&row)
+        })?;
+        let index = start + (row - area.top()) as usize;
+        (index < self.tree_lines.len()).then_some(index)
+    }
+
+    /// Clicks select, the wheel scrolls and dragging a window onto another
+    /// line moves it there; onto a session line makes it that session's first
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if self.mode != Mode::Window || self.show_popup {
+            return;
+        }
+
+        match mouse.kind {
+            MouseEventKind::ScrollDown => self.move_down(),
+            MouseEventKind::ScrollUp => self.move_up(),
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(index) = self.line_at(mouse.column, mouse.row) {
+                    self.selected_index = index;
+                    self.drag = self.tree_lines[index].window.clone();
+                }
+            }
+            // The selection follows the pointer to show where the window lands
+            MouseEventKind::Drag(MouseButton::Left) if self.drag.is_some() => {
+                if let Some(index) = self.line_at(mouse.column, mouse.row) {
+                    self.selected_index = index;
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                let Some(window) = self.drag.take() else {
+                    return;
+                };
+                match self.line_at(mouse.column, mouse.row) {
+                    Some(index) => self.drop_window(window, index),
+                    // Dropped outside the tree, nothing moves
+                    None => {
+                        if let Some(index) = self.find_window_index_by_id(&window.id) {
+                            self.selected_index = index;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn drop_window(&mut self, window: TmuxWindow, index: usize) {
+        let Some(from) = self.find_window_index_by_id(&window.id) else {
+            return;
+        };
+        if index != from
+            && let Some(reason) = self.fixed_order()
+        {
+            self.messages.info(reason);
+            self.selected_index = from;
+            return;
         }
+        let line = &self.tree_lines[index];
+        let (target_window_id, before) = match &line.window {
+            Some(target) if target.id == window.id => return,
+            // Dragged up it goes above the target, dragged down below it
+            Some(target) => (target.id.clone(), index < from),
+            None => {
+                let Some(first) = self
+                    .sessions
+                    .iter()
+                    .find(|s| Some(&s.name) == line.session_name.as_ref())
+                    .and_then(|s| s.windows.first())
+                else {
+                    return;
+                };
+                if first.id == window.id {
+                    self.selected_index = from;
+                    return;
+                }
+                (first.id.clone(), true)
+            }
+        };
+        let target_session = line.session_name.clone().unwrap_or_default();
+
+        self.move_window_to_session(WindowMove {
+            window,
+            target_session,
+            target_window_id,
+            before,
+        });
     }
 
     pub fn handle_key(&mut self, key: KeyEvent) -> Result<bool> {
-        self.error_message = None;
+        if let Some(keys) = &mut self.recording {
+            keys.push(key);
+        }
 
         match self.mode {
             Mode::Window => self.handle_normal_mode(key),
             Mode::Rename => self.handle_insert_mode(key),
+            Mode::Link => self.handle_link_mode(key),
+            Mode::StartDir => self.handle_start_dir_mode(key),
+            Mode::Note => self.handle_note_mode(key),
+            Mode::Shell => self.handle_shell_mode(key),
+            Mode::MoveIndex => self.handle_move_index_mode(key),
             Mode::Search => self.handle_search_input_mode(key),
             Mode::QuickSearch => self.handle_quick_search_mode(key),
             Mode::Session => self.handle_session_mode(key),
             Mode::DeleteConfirm => self.handle_delete_confirm_mode(key),
+            Mode::MoveConfirm => self.handle_move_confirm_mode(key),
+            Mode::CollisionConfirm => self.handle_collision_confirm_mode(key),
+            Mode::Messages => self.handle_messages_mode(key),
+            Mode::Environment => self.handle_environment_mode(key),
+            Mode::Options => self.handle_options_mode(key),
+            Mode::Info => self.handle_info_mode(key),
+            Mode::Archive => self.handle_archive_mode(key),
+            Mode::Stats => self.handle_stats_mode(key),
+            Mode::Watch => self.handle_watch_mode(key),
         }
     }
 
     fn handle_normal_mode(&mut self, key: KeyEvent) -> Result<bool> {
-        match key.code {
-            KeyCode::Esc => return Ok(true), // Quit the app
-            KeyCode::Char('q') => return Ok(true),
-            // Handle Shift+Arrow keys first (for window reordering)
-            KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => self.move_item_up()?,
-            KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
-                self.move_item_down()?
+        if self.index_jump.is_some() {
+            self.handle_index_jump(key);
+            return Ok(false);
+        }
+        if self.tree_search_input.is_some() {
+            self.handle_tree_search_input(key);
+            return Ok(false);
+        }
+        if let Some(result) = self.pending_layout.take() {
+            self.handle_layout_choice(result, key);
+            return Ok(false);
+        }
+        if self.show_pin_overlay {
+            // A digit jumps, anything else just closes the overlay
+            self.show_pin_overlay = false;
+            if let KeyCode::Char(c @ '1'..='9') = key.code {
+                self.jump_to_pin(c as u8 - b'0');
             }
-            // Then handle regular navigation
-            KeyCode::Char('j') | KeyCode::Down => self.move_down(),
-            KeyCode::Char('k') | KeyCode::Up => self.move_up(),
-            KeyCode::Char('g') => {
-                if key.modifiers.contains(KeyModifiers::CONTROL) {
-                    self.move_to_top();
-                }
+            return Ok(false);
+        }
+
+        if let Some(action) = self.keymap.action(Scope::Window, &key) {
+            return self.run_window_action(action);
+        }
+
+        match key.code {
+            // Digit shortcuts for pin navigation
+            KeyCode::Char(c @ '1'..='9') => self.jump_to_pin(c as u8 - b'0'),
+            // Shift+number to toggle pins
+            KeyCode::Char(c) if PIN_TOGGLE_KEYS.contains(c) => {
+                let slot = PIN_TOGGLE_KEYS.find(c).unwrap_or_default() as u8 + 1;
+                self.toggle_pin(slot);
             }
-            KeyCode::Char('G') => self.move_to_bottom(),
-            KeyCode::Enter => {
-                if self.activate_selected()? {
-                    return Ok(true);
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if let Some(action) = self.config.open_actions.iter().find(|a| a.key == c) {
+                    self.run_open_action(action.clone());
+                } else if c.is_alphabetic() {
+                    self.jump_to_letter(c, LineType::Window);
                 }
             }
-            KeyCode::Char('r') | KeyCode::Char(',') => self.start_rename(),
-            KeyCode::Char('x') => self.start_delete_confirm(),
-            KeyCode::Char('R') => {
-                self.auto_position_on_active = true; // Re-enable auto-positioning for manual refresh
-                self.refresh_sessions()?
-            }
-            KeyCode::Char('/') => self.start_quick_search(),
-            KeyCode::Char('F') => self.start_project_search(),
-            KeyCode::Char('S') => self.start_session_mode(),
-            KeyCode::Char('J') => self.move_item_down()?,
-            KeyCode::Char('K') => self.move_item_up()?,
-            KeyCode::Char('C') => self.create_new_window()?,
-            // Digit shortcuts for pin navigation
-            KeyCode::Char('1') => return self.jump_to_pin(1),
-            KeyCode::Char('2') => return self.jump_to_pin(2),
-            KeyCode::Char('3') => return self.jump_to_pin(3),
-            KeyCode::Char('4') => return self.jump_to_pin(4),
-            KeyCode::Char('5') => return self.jump_to_pin(5),
-            KeyCode::Char('6') => return self.jump_to_pin(6),
-            KeyCode::Char('7') => return self.jump_to_pin(7),
-            KeyCode::Char('8') => return self.jump_to_pin(8),
-            KeyCode::Char('9') => return self.jump_to_pin(9),
-            // Shift+number to toggle pins
-            KeyCode::Char('!') => { self.toggle_pin(1); }
-            KeyCode::Char('@') => { self.toggle_pin(2); }
-            KeyCode::Char('#') => { self.toggle_pin(3); }
-            KeyCode::Char('$') => { self.toggle_pin(4); }
-            KeyCode::Char('%') => { self.toggle_pin(5); }
-            KeyCode::Char('^') => { self.toggle_pin(6); }
-            KeyCode::Char('&') => { self.toggle_pin(7); }
-            KeyCode::Char('*') => { self.toggle_pin(8); }
-            KeyCode::Char('(') => { self.toggle_pin(9); }
             _ => {}
         }
 
         Ok(false)
     }
 
-    fn handle_insert_mode(&mut self, key: KeyEvent) -> Result<bool> {
-        match key.code {
-            KeyCode::Esc => {
-                self.mode = Mode::Window;
-                self.show_popup = false;
-                self.popup_input.clear();
+    // Typeahead: select the next line of that type whose name starts with
+    // the letter, wrapping around, so repeating it cycles through them
+    fn jump_to_letter(&mut self, letter: char, line_type: LineType) {
+        let letter: String = letter.to_lowercase().collect();
+        let len = self.tree_lines.len();
+        let next = (1..=len)
+            .map(|offset| (self.selected_index + offset) % len)
+            .find(|&i| {
+                let line = &self.tree_lines[i];
+                let name = match &line.window {
+                    Some(window) => Some(&window.name),
+                    None => line.session_name.as_ref(),
+                };
+                line.line_type == line_type
+                    && name.is_some_and(|name| name.to_lowercase().starts_with(&letter))
+            });
+        if let Some(next) = next {
+            self.selected_index = next;
+        }
+    }
+
+    fn run_window_action(&mut self, action: Action) -> Result<bool> {
+        match action {
+            Action::Back if self.has_marks() => self.clear_marks(),
+            Action::Back if self.tree_search.is_some() => self.tree_search = None,
+            Action::Quit | Action::Back => return Ok(true),
+            Action::Down => self.move_down(),
+            Action::Up => self.move_up(),
+            Action::Top => self.move_to_top(),
+            Action::Bottom => self.move_to_bottom(),
+            Action::NextSession => self.move_to_session(true),
+            Action::PreviousSession => self.move_to_session(false),
+            Action::MoveItemDown => {
+                self.last_change = Some(Change::MoveDown);
+                self.move_item_down()?
             }
-            KeyCode::Enter => {
-                self.confirm_rename()?;
+            Action::MoveItemUp => {
+                self.last_change = Some(Change::MoveUp);
+                self.move_item_up()?
             }
-            KeyCode::Backspace => {
-                self.popup_input.pop();
+            Action::JumpBack => self.jump_back(),
+            Action::JumpForward => self.jump_forward(),
+            Action::RecentInSession => self.cycle_session_recent(),
+            Action::Select => self.activate_selected(false),
+            Action::SelectZoomed => self.activate_selected(true),
+            Action::Rename => self.start_rename(),
+            Action::Mark => {
+                self.toggle_mark();
+                self.move_down();
             }
-            KeyCode::Char(c) => {
-                self.popup_input.push(c);
+            Action::Delete if self.has_marks() => self.start_delete_marked_confirm(),
+            Action::Delete => self.start_delete_confirm(),
+            Action::CloseOthers => self.start_close_others_confirm(),
+            Action::Refresh => self.refresh_sessions(Focus::Active),
+            Action::QuickSearch => self.start_quick_search(),
+            Action::FindProject => self.start_project_search(),
+            Action::SessionMode => self.start_session_mode(),
+            Action::CreateWindow => self.create_new_window(),
+            Action::Messages => self.start_messages_mode(),
+            Action::Environment => self.start_environment_mode(),
+            Action::StartDir => self.start_start_dir(),
+            Action::Note => self.start_note(),
+            Action::Shell => self.start_shell(),
+            Action::MoveToIndex => self.start_move_to_index(),
+            Action::Options => self.start_options_mode(),
+            Action::ServerInfo => self.start_info_mode(),
+            Action::Stats => self.start_stats_mode(),
+            Action::LineNumbers => self.cycle_line_numbers(),
+            Action::TreeSearch => {
+                self.tree_search_input = Some(TextInput::default());
+                self.tree_search_origin = self.selected_index;
+            }
+            Action::SearchNext => self.jump_to_search_match(true),
+            Action::SearchPrevious => self.jump_to_search_match(false),
+            Action::SortByActivity => self.toggle_sort_by_activity(),
+            Action::FlatList => self.toggle_flat(),
+            Action::CycleView => self.cycle_view(),
+            Action::LinkWindow => self.start_link(),
+            Action::UnlinkWindow => self.unlink_selected(),
+            Action::BreakOut => self.break_out_selected(),
+            Action::Scratch => self.show_scratch(),
+            Action::SendToScratch => self.send_to_scratch(),
+            Action::GoToIndex => self.index_jump = Some(String::new()),
+            Action::ShowPins => self.show_pin_overlay = true,
+            Action::CopyTarget => self.copy_target(),
+            Action::CopyPath => self.copy_path(),
+            Action::ExportTree => self.export_tree(),
+            Action::ToggleLogging => self.toggle_logging(),
+            Action::CaptureScrollback => self.capture_scrollback(),
+            Action::CycleColor => self.cycle_window_color(),
+            Action::RecordMacro => self.toggle_recording(),
+            Action::ReplayMacro => self.replay_macro(),
+            Action::Repeat => self.repeat_last_change()?,
+            // Session mode only
+            Action::Prune | Action::Archive | Action::ArchiveBrowser => {}
+            // Project search only
+            Action::NewSession => {}
+        }
+
+        Ok(false)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        !self.replay_queue.is_empty()
+    }
+
+    fn repeat_last_change(&mut self) -> Result<()> {
+        let session_mode = self.mode == Mode::Session;
+        match self.last_change.clone() {
+            None => self.messages.info("Nothing to repeat yet"),
+            Some(Change::Rename(pattern)) => {
+                self.start_rename();
+                if self.mode == Mode::Rename {
+                    self.popup_input
+                        .set(pattern.replace("{name}", &self.popup_input));
+                    self.confirm_rename();
+                }
+            }
+            Some(Change::MoveDown) if session_mode => self.move_session_down()?,
+            Some(Change::MoveUp) if session_mode => self.move_session_up()?,
+            Some(Change::MoveDown) => self.move_item_down()?,
+            Some(Change::MoveUp) => self.move_item_up()?,
+            Some(Change::Delete) => {
+                // Confirmed once already, unless clients are attached to the
+                // session this time: the prompt warns about them
+                if session_mode {
+                    self.start_delete_session_confirm();
+                } else {
+                    self.start_delete_confirm();
+                }
+                let attached = session_mode
+                    && self
+                        .tree_lines
+                        .get(self.selected_index)
+                        .and_then(|line| line.session_name.as_ref())
+                        .and_then(|name| self.sessions.iter().find(|s| &s.name == name))
+                        .is_some_and(|session| session.attached > 0);
+                if self.mode == Mode::DeleteConfirm && !attached {
+                    self.accept_delete();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(mut keys) => {
+                // The key that stopped the recording isn't part of it
+                keys.pop();
+                self.messages
+                    .info(format!("Recorded a macro of {} keys", keys.len()));
+                self.macro_keys = keys;
+            }
+            None if self.is_replaying() => self
+                .messages
+                .info("Can't record while a macro is replaying"),
+            None => {
+                self.recording = Some(Vec::new());
+                self.messages
+                    .info("Recording a macro, stop with the same key");
+            }
+        }
+    }
+
+    fn replay_macro(&mut self) {
+        if let Some(keys) = &mut self.recording {
+            keys.pop();
+            self.messages.info("Can't replay while recording");
+        } else if self.macro_keys.is_empty() {
+            self.messages.info("No macro recorded yet");
+        } else {
+            self.replay_queue.extend(self.macro_keys.iter().copied());
+        }
+    }
+
+    // Replayed keys go in one at a time, each after the tmux commands of the
+    // previous one are done, so a created window is selected before it's renamed
+    fn continue_replay(&mut self) {
+        while !self.is_busy() && !self.verify_pending {
+            let Some(key) = self.replay_queue.pop_front() else {
+                return;
+            };
+            match self.handle_key(key) {
+                Ok(false) => {}
+                Ok(true) => {
+                    self.replay_queue.clear();
+                    self.should_quit = true;
+                }
+                Err(e) => {
+                    self.replay_queue.clear();
+                    self.messages.error(format!("Macro stopped: {e}"));
+                }
+            }
+        }
+    }
+
+    fn handle_index_jump(&mut self, key: KeyEvent) {
+        let Some(input) = &mut self.index_jump else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char(c) if c.is_ascii_digit() => input.push(c),
+            KeyCode::Backspace if input.is_empty() => self.index_jump = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                let input = self.index_jump.take().unwrap_or_default();
+                if let Ok(index) = input.parse() {
+                    self.jump_to_window_index(index);
+                }
+            }
+            KeyCode::Esc => self.index_jump = None,
+            _ => {}
+        }
+    }
+
+    fn handle_tree_search_input(&mut self, key: KeyEvent) {
+        let Some(input) = &mut self.tree_search_input else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.cancel_tree_search(),
+            KeyCode::Backspace if input.is_empty() => self.cancel_tree_search(),
+            // Keep the pattern highlighted for `n`/`N`
+            KeyCode::Enter => {
+                self.tree_search_input = None;
+                self.tree_search = self.tree_search.take().filter(|p| !p.is_empty());
+            }
+            _ => {
+                if !input.handle_key(&key) {
+                    return;
+                }
+                let pattern = input.to_string();
+                self.tree_search = Some(pattern.clone());
+                // Incremental: the first match from where the search started
+                self.selected_index = (0..self.tree_lines.len())
+                    .map(|offset| (self.tree_search_origin + offset) % self.tree_lines.len())
+                    .find(|&i| self.tree_search_match(&self.tree_lines[i]).is_some())
+                    .unwrap_or(self.tree_search_origin);
+            }
+        }
+    }
+
+    fn cancel_tree_search(&mut self) {
+        self.tree_search_input = None;
+        self.tree_search = None;
+        self.selected_index = self.tree_search_origin;
+    }
+
+    /// Character range of the search pattern in the line's window name
+    pub fn tree_search_match(&self, line: &TreeLine) -> Option<std::ops::Range<usize>> {
+        let pattern = self.tree_search.as_deref()?;
+        let start = find_ignore_case(&line.window.as_ref()?.name, pattern)?;
+        Some(start..start + pattern.chars().count())
+    }
+
+    // Next or previous window matching the search, wrapping around
+    fn jump_to_search_match(&mut self, forward: bool) {
+        let Some(pattern) = self.tree_search.clone() else {
+            return;
+        };
+        let len = self.tree_lines.len();
+        let next = (1..=len)
+            .map(|offset| match forward {
+                true => (self.selected_index + offset) % len,
+                false => (self.selected_index + len * 2 - offset) % len,
+            })
+            .find(|&i| self.tree_search_match(&self.tree_lines[i]).is_some());
+        match next {
+            Some(next) => self.selected_index = next,
+            None => self.messages.info(format!("No window matches '{pattern}'")),
+        }
+    }
+
+    // Switch to the window with this tmux index in the selected line's session
+    fn jump_to_window_index(&mut self, index: u32) {
+        let Some(session_name) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.session_name.clone())
+        else {
+            return;
+        };
+
+        let target = self.tree_lines.iter().position(|line| {
+            line.session_name.as_ref() == Some(&session_name)
+                && line.window.as_ref().is_some_and(|w| w.index == index)
+        });
+        match target {
+            Some(line_index) => {
+                self.selected_index = line_index;
+                self.activate_selected(false);
+            }
+            None => self
+                .messages
+                .error(format!("No window {index} in session '{session_name}'")),
+        }
+    }
+
+    fn handle_insert_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = Mode::Window;
+                self.show_popup = false;
+                self.popup_input.clear();
+            }
+            KeyCode::Enter => {
+                self.confirm_rename();
+            }
+            _ => {
+                self.popup_input.handle_key(&key);
+            }
+        }
+        Ok(false)
+    }
+
+    fn handle_link_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = Mode::Window;
+                self.show_popup = false;
+                self.popup_input.clear();
+            }
+            KeyCode::Enter => self.confirm_link(),
+            _ => {
+                self.popup_input.handle_key(&key);
+            }
+        }
+        Ok(false)
+    }
+
+    fn handle_start_dir_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = self.previous_mode.clone();
+                self.show_popup = false;
+                self.popup_input.clear();
+            }
+            KeyCode::Enter => self.confirm_start_dir(),
+            _ => {
+                self.popup_input.handle_key(&key);
+            }
+        }
+        Ok(false)
+    }
+
+    fn handle_note_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = self.previous_mode.clone();
+                self.show_popup = false;
+                self.popup_input.clear();
+            }
+            KeyCode::Enter => self.confirm_note(),
+            _ => {
+                self.popup_input.handle_key(&key);
+            }
+        }
+        Ok(false)
+    }
+
+    fn handle_shell_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = self.previous_mode.clone();
+                self.show_popup = false;
+                self.popup_input.clear();
+            }
+            KeyCode::Enter => self.confirm_shell(),
+            _ => {
+                self.popup_input.handle_key(&key);
+            }
+        }
+        Ok(false)
+    }
+
+    fn handle_move_index_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = self.previous_mode.clone();
+                self.show_popup = false;
+                self.popup_input.clear();
+            }
+            KeyCode::Enter => self.confirm_move_to_index(),
+            _ => {
+                self.popup_input.handle_key(&key);
             }
-            _ => {}
         }
         Ok(false)
     }
 
     fn handle_search_input_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        // Moving through the results follows the keymap preset
+        match self.keymap.action(Scope::Search, &key) {
+            Some(Action::Down) => {
+                if self.search_selected_index < self.search_results.len().saturating_sub(1) {
+                    self.search_selected_index += 1;
+                }
+                return Ok(false);
+            }
+            Some(Action::Up) => {
+                self.search_selected_index = self.search_selected_index.saturating_sub(1);
+                return Ok(false);
+            }
+            Some(Action::NewSession) => {
+                self.create_session_from_query();
+                return Ok(false);
+            }
+            _ => {}
+        }
+
         match key.code {
             KeyCode::Esc => {
                 self.mode = Mode::Window;
@@ -421,40 +1729,60 @@ impl App {
             }
             KeyCode::Enter => {
                 if !self.search_results.is_empty() {
-                    // execute_search_selection returns true if we should exit
-                    self.execute_search_selection()
+                    self.execute_search_selection();
+                } else if !self.search_query.trim().is_empty() {
+                    self.create_session_from_query();
                 } else {
                     self.mode = Mode::Window;
-                    Ok(false)
                 }
+                Ok(false)
             }
-            KeyCode::Char(c) => {
-                self.search_query.push(c);
-                self.update_search_results();
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.rescan_requested = true;
+                self.messages.info("Rescanning projects");
+                self.scan_directories();
                 Ok(false)
             }
-            KeyCode::Backspace => {
-                self.search_query.pop();
-                self.update_search_results();
+            KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.forget_search_result();
                 Ok(false)
             }
-            KeyCode::Up => {
-                if self.search_selected_index > 0 {
-                    self.search_selected_index -= 1;
-                }
+            KeyCode::Tab => {
+                self.toggle_search_group();
                 Ok(false)
             }
-            KeyCode::Down => {
-                if self.search_selected_index < self.search_results.len().saturating_sub(1) {
-                    self.search_selected_index += 1;
+            KeyCode::BackTab => {
+                self.collapsed_groups.clear();
+                self.update_search_results();
+                Ok(false)
+            }
+            _ => {
+                if self.search_query.handle_key(&key) {
+                    self.update_search_results();
                 }
                 Ok(false)
             }
-            _ => Ok(false),
+        }
+    }
+
+    // Why moving windows wouldn't show, if the tree isn't in tmux's order
+    fn fixed_order(&self) -> Option<&'static str> {
+        if self.flat {
+            Some("The flat list is sorted, press f to move windows")
+        } else if self.sort_by_activity {
+            Some("Windows are sorted by activity, press A to move them")
+        } else {
+            None
         }
     }
 
     fn move_item_up(&mut self) -> Result<()> {
+        // Swapping indices wouldn't change what's on screen
+        if let Some(reason) = self.fixed_order() {
+            self.messages.info(reason);
+            return Ok(());
+        }
+
         if self.selected_index == 0 || self.tree_lines.is_empty() {
             return Ok(());
         }
@@ -494,41 +1822,25 @@ impl App {
                 .unwrap()
                 .id
                 .clone();
-            let was_current_active = self.tree_lines[self.selected_index]
-                .window
-                .as_ref()
-                .unwrap()
-                .active;
-
-            // Perform the swap in tmux immediately
-            if let Err(e) =
-                self.swap_windows_in_tmux(current_session, &current_window_id, &prev_window_id)
-            {
-                self.error_message = Some(format!("Failed to swap windows: {e}"));
-                return Ok(());
-            }
+            let current_session = current_session.clone();
 
-            // Refresh to get the updated state from tmux
-            self.refresh_sessions()?;
-
-            // If we moved the active window, find where it ended up and select it
-            if was_current_active {
-                if let Some(new_index) = self.find_window_index_by_id(&current_window_id) {
-                    self.selected_index = new_index;
-                }
-            } else {
-                // If we moved a non-active window, try to maintain selection on it
-                if let Some(new_index) = self.find_window_index_by_id(&current_window_id) {
-                    self.selected_index = new_index;
-                }
-            }
+            // Swap in tmux, then follow the moved window wherever it ended up
+            self.swap_windows_in_tmux(current_session, current_window_id, prev_window_id);
+        } else {
+            self.offer_move_to_adjacent_session(false);
         }
 
         Ok(())
     }
 
     fn move_item_down(&mut self) -> Result<()> {
-        if self.selected_index >= self.tree_lines.len() - 1 {
+        // Swapping indices wouldn't change what's on screen
+        if let Some(reason) = self.fixed_order() {
+            self.messages.info(reason);
+            return Ok(());
+        }
+
+        if self.selected_index + 1 >= self.tree_lines.len() {
             return Ok(());
         }
 
@@ -567,37 +1879,141 @@ impl App {
                 .unwrap()
                 .id
                 .clone();
-            let was_current_active = self.tree_lines[self.selected_index]
-                .window
-                .as_ref()
-                .unwrap()
-                .active;
+            let current_session = current_session.clone();
 
-            // Perform the swap in tmux immediately
-            if let Err(e) =
-                self.swap_windows_in_tmux(current_session, &current_window_id, &next_window_id)
-            {
-                self.error_message = Some(format!("Failed to swap windows: {e}"));
-                return Ok(());
-            }
+            // Swap in tmux, then follow the moved window wherever it ended up
+            self.swap_windows_in_tmux(current_session, current_window_id, next_window_id);
+        } else {
+            self.offer_move_to_adjacent_session(true);
+        }
 
-            // Refresh to get the updated state from tmux
-            self.refresh_sessions()?;
+        Ok(())
+    }
 
-            // If we moved the active window, find where it ended up and select it
-            if was_current_active {
-                if let Some(new_index) = self.find_window_index_by_id(&current_window_id) {
-                    self.selected_index = new_index;
-                }
-            } else {
-                // If we moved a non-active window, try to maintain selection on it
-                if let Some(new_index) = self.find_window_index_by_id(&current_window_id) {
-                    self.selected_index = new_index;
-                }
+    // The selected window is the first/last of its session: ask whether to
+    // move it to the top of the next or the bottom of the previous session
+    fn offer_move_to_adjacent_session(&mut self, down: bool) {
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+
+        let target_session = if down {
+            self.tree_lines[self.selected_index + 1..]
+                .iter()
+                .find(|line| line.line_type == LineType::Session)
+        } else {
+            // The nearest session line above is the window's own
+            self.tree_lines[..self.selected_index]
+                .iter()
+                .rev()
+                .filter(|line| line.line_type == LineType::Session)
+                .nth(1)
+        };
+        let Some(target_session) = target_session.and_then(|line| line.session_name.clone()) else {
+            return;
+        };
+
+        let Some(target) = self.sessions.iter().find(|s| s.name == target_session) else {
+            return;
+        };
+        let target_window = if down {
+            target.windows.first()
+        } else {
+            target.windows.last()
+        };
+        let Some(target_window) = target_window else {
+            return;
+        };
+
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::MoveConfirm;
+        self.show_popup = true;
+        self.pending_move = Some(WindowMove {
+            window,
+            target_session,
+            target_window_id: target_window.id.clone(),
+            before: down,
+        });
+    }
+
+    // Answer of a yes/no confirmation, if this key gives one: y/n directly,
+    // or ←/→/Tab to pick a button and Enter to press it. Focus starts on No
+    fn confirm_answer(&mut self, key: KeyEvent) -> Option<bool> {
+        let answer = match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => false,
+            KeyCode::Enter => self.confirm_button == ConfirmButton::Yes,
+            KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Tab
+            | KeyCode::BackTab
+            | KeyCode::Char('h')
+            | KeyCode::Char('l') => {
+                self.confirm_button = self.confirm_button.toggle();
+                return None;
             }
+            _ => return None,
+        };
+        self.confirm_button = ConfirmButton::default();
+        Some(answer)
+    }
+
+    fn handle_move_confirm_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        let Some(answer) = self.confirm_answer(key) else {
+            return Ok(false);
+        };
+        self.mode = self.previous_mode.clone();
+        self.show_popup = false;
+        let pending = self.pending_move.take();
+        if answer && let Some(pending) = pending {
+            self.move_window_to_session(pending);
         }
+        Ok(false)
+    }
 
-        Ok(())
+    // Yes opens the suffixed session, No the existing one, Esc neither
+    fn handle_collision_confirm_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        let answer = if key.code == KeyCode::Esc {
+            self.confirm_button = ConfirmButton::default();
+            None
+        } else {
+            let Some(answer) = self.confirm_answer(key) else {
+                return Ok(false);
+            };
+            Some(answer)
+        };
+        self.mode = Mode::Window;
+        self.show_popup = false;
+        let pending = self.pending_collision.take();
+        if let (Some(answer), Some((existing, suffixed))) = (answer, pending) {
+            self.switch_to_session_and_window(if answer { suffixed } else { existing });
+        }
+        Ok(false)
+    }
+
+    fn move_window_to_session(&mut self, pending: WindowMove) {
+        let WindowMove {
+            window,
+            target_window_id,
+            before,
+            ..
+        } = pending;
+
+        self.update_sessions_locally(Focus::Window(window.id.clone()), |sessions| {
+            move_window(sessions, &window.id, &target_window_id, before);
+        });
+
+        let tmux = self.tmux.clone();
+        self.submit_optimistic(move || {
+            match tmux.move_window(&window.id, &target_window_id, before) {
+                Ok(_) => TaskOutput::Done,
+                Err(e) => TaskOutput::Failed(format!("Failed to move window: {e}")),
+            }
+        });
     }
 
     fn find_window_index_by_id(&self, window_id: &str) -> Option<usize> {
@@ -609,12 +2025,24 @@ impl App {
 
     // Add a method to handle individual window swaps during J/K operations
     fn swap_windows_in_tmux(
-        &self,
-        session_name: &str,
-        window1_id: &str,
-        window2_id: &str,
-    ) -> Result<()> {
-        crate::tmux::swap_windows_in_tmux(session_name, window1_id, window2_id)
+        &mut self,
+        session_name: String,
+        window1_id: String,
+        window2_id: String,
+    ) {
+        self.update_sessions_locally(Focus::Window(window1_id.clone()), |sessions| {
+            if let Some(session) = sessions.iter_mut().find(|s| s.name == session_name) {
+                session.swap_windows(&window1_id, &window2_id);
+            }
+        });
+
+        let tmux = self.tmux.clone();
+        self.submit_optimistic(move || {
+            match tmux.swap_windows(&session_name, &window1_id, &window2_id) {
+                Ok(_) => TaskOutput::Done,
+                Err(e) => TaskOutput::Failed(format!("Failed to swap windows: {e}")),
+            }
+        });
     }
 
     fn move_down(&mut self) {
@@ -632,6 +2060,31 @@ impl App {
         self.selected_index = next_index;
     }
 
+    // First window of the next or previous session, stays put at either end
+    fn move_to_session(&mut self, forward: bool) {
+        let sessions: Vec<usize> = (0..self.tree_lines.len())
+            .filter(|&i| self.tree_lines[i].line_type == LineType::Session)
+            .collect();
+        // Session line of the selection, the last one at or before it
+        let current = sessions
+            .iter()
+            .rposition(|&i| i <= self.selected_index)
+            .unwrap_or(0);
+        let target = match forward {
+            true => sessions.get(current + 1),
+            false => current.checked_sub(1).and_then(|i| sessions.get(i)),
+        };
+        let first_window = target.and_then(|&session| {
+            self.tree_lines
+                .get(session + 1)
+                .filter(|line| line.line_type == LineType::Window)
+                .map(|_| session + 1)
+        });
+        if let Some(index) = first_window {
+            self.selected_index = index;
+        }
+    }
+
     fn move_up(&mut self) {
         if self.tree_lines.is_empty() || self.selected_index == 0 {
             return;
@@ -665,24 +2118,302 @@ impl App {
         }
     }
 
-    fn activate_selected(&mut self) -> Result<bool> {
-        if let Some(line) = self.tree_lines.get(self.selected_index) {
-            if let Some(window) = &line.window {
-                let session_name = window.session_name.clone();
-                let window_id = window.id.clone();
-                
-                // Add to history before switching
-                self.add_to_history(&session_name, &window_id);
-                
-                match switch_to_window(&session_name, &window_id) {
-                    Ok(_) => return Ok(true), // Exit the app after successful switch
-                    Err(e) => {
-                        self.error_message = Some(format!("Failed to switch: {e}"));
-                    }
+    fn activate_selected(&mut self, zoom: bool) {
+        if let Some(line) = self.tree_lines.get(self.selected_index)
+            && let Some(window) = line.window.clone()
+        {
+            // Add to history before switching
+            self.add_to_history(&window);
+
+            self.switch_to_window(window.session_name, window.id, zoom);
+        }
+    }
+
+    // Decide what happens after a successful switch: quit, or stay open and
+    // refresh so the tree reflects the new active window
+    fn finish_switch(&mut self) {
+        if self.config.exit_on_switch {
+            self.should_quit = true;
+            return;
+        }
+
+        self.refresh_sessions(Focus::Active);
+    }
+
+    // `zoom_on_switch` zooms every time, `zoom` just this once
+    fn switch_to_window(&mut self, session_name: String, window_id: String, zoom: bool) {
+        let zoom = zoom || self.config.zoom_on_switch;
+        let window_name = self
+            .sessions
+            .iter()
+            .filter(|s| s.name == session_name)
+            .flat_map(|s| s.windows.iter())
+            .find(|w| w.id == window_id)
+            .map(|w| w.name.clone())
+            .unwrap_or_default();
+
+        let hooks = self.config.hooks.clone();
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            let mut context = HookContext {
+                session: session_name.clone(),
+                window: window_name,
+                window_id: window_id.clone(),
+                path: String::new(),
+            };
+
+            // Only ask tmux for the path when a hook is going to use it
+            if hooks.pre_switch.is_some() || hooks.post_switch.is_some() {
+                context.path = tmux
+                    .window_path(&session_name, &window_id)
+                    .unwrap_or_default();
+            }
+
+            TaskOutput::Switched(with_switch_hooks(&hooks, &context, || {
+                // Zoomed before switching, so the window shows up fullscreen right away
+                if zoom {
+                    tmux.zoom_window(&window_id)?;
+                }
+                tmux.switch_to_window(&session_name, &window_id)
+            }))
+        });
+    }
+
+    // `session:window` (or just the session) of the selected line, the way
+    // tmux's -t flags take it
+    fn copy_target(&mut self) {
+        if !self.require(Feature::Clipboard) {
+            return;
+        }
+        let Some(line) = self.tree_lines.get(self.selected_index) else {
+            return;
+        };
+        let text = match (&line.session_name, &line.window) {
+            (_, Some(window)) => format!("{}:{}", window.session_name, window.name),
+            (Some(session_name), None) => session_name.clone(),
+            (None, None) => return,
+        };
+
+        let tmux = self.tmux.clone();
+        self.worker
+            .submit(move || TaskOutput::Copied(tmux.copy_to_clipboard(&text).map(|_| text)));
+    }
+
+    // Current directory of the selected window's active pane
+    fn copy_path(&mut self) {
+        if !self.require(Feature::Clipboard) {
+            return;
+        }
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            TaskOutput::Copied(
+                tmux.window_path(&window.session_name, &window.id)
+                    .and_then(|path| tmux.copy_to_clipboard(&path).map(|_| path)),
+            )
+        });
+    }
+
+    // All sessions and windows as a markdown list, e.g. for standup notes
+    fn export_tree(&mut self) {
+        if !self.require(Feature::Clipboard) {
+            return;
+        }
+        let tree = export::render_markdown(&self.sessions, dirs::home_dir().as_deref());
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            TaskOutput::Copied(
+                tmux.copy_to_clipboard(&tree)
+                    .map(|_| "the tree as markdown".to_string()),
+            )
+        });
+    }
+
+    // Start or stop appending the selected window's active pane to `log_path`
+    fn toggle_logging(&mut self) {
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+
+        let piped = !window.piped;
+        let window_id = window.id.clone();
+        self.update_sessions_locally(Focus::Keep, |sessions| {
+            for w in sessions.iter_mut().flat_map(|s| s.windows.iter_mut()) {
+                if w.id == window_id {
+                    w.piped = piped;
                 }
             }
+        });
+
+        let tmux = self.tmux.clone();
+        if !piped {
+            self.messages
+                .info(format!("Stopped logging '{}'", window.name));
+            self.submit_optimistic(move || match tmux.pipe_pane(&window.id, None) {
+                Ok(_) => TaskOutput::Done,
+                Err(e) => TaskOutput::Failed(format!("Failed to stop logging: {e}")),
+            });
+            return;
         }
-        Ok(false)
+
+        // Names may hold slashes, which would point into other directories
+        let context = HookContext {
+            session: window.session_name.replace('/', "_"),
+            window: window.name.replace('/', "_"),
+            window_id: window.id.clone(),
+            path: window.path.clone(),
+        };
+        let time = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let file = expand_path(
+            &context
+                .expand(&self.config.log_path)
+                .replace("{time}", &time),
+        );
+        self.messages
+            .info(format!("Logging '{}' to {}", window.name, file.display()));
+        self.submit_optimistic(move || {
+            let result = match file.parent() {
+                Some(dir) => std::fs::create_dir_all(dir).map_err(anyhow::Error::from),
+                None => Ok(()),
+            }
+            .and_then(|_| tmux.pipe_pane(&window.id, Some(&append_command(&file))));
+            match result {
+                Ok(_) => TaskOutput::Done,
+                Err(e) => TaskOutput::Failed(format!("Failed to start logging: {e}")),
+            }
+        });
+    }
+
+    // Give the selected window the next of `window_colors`, after the last
+    // one it goes back to no color
+    fn cycle_window_color(&mut self) {
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+
+        let colors = &self.config.window_colors;
+        let color = match window
+            .color
+            .as_ref()
+            .and_then(|current| colors.iter().position(|c| c == current))
+        {
+            Some(i) => colors.get(i + 1).cloned(),
+            None if window.color.is_some() => None,
+            None => colors.first().cloned(),
+        };
+
+        let window_id = window.id.clone();
+        let new_color = color.clone();
+        self.update_sessions_locally(Focus::Keep, |sessions| {
+            for w in sessions.iter_mut().flat_map(|s| s.windows.iter_mut()) {
+                if w.id == window_id {
+                    w.color = new_color.clone();
+                }
+            }
+        });
+
+        let tmux = self.tmux.clone();
+        self.submit_optimistic(
+            move || match tmux.set_window_color(&window.id, color.as_deref()) {
+                Ok(_) => TaskOutput::Done,
+                Err(e) => TaskOutput::Failed(format!("Failed to color window: {e}")),
+            },
+        );
+    }
+
+    // Save everything the selected window's pane holds under `capture_dir`
+    fn capture_scrollback(&mut self) {
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+
+        let dir = expand_path(&self.config.capture_dir);
+        let time = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let file = dir.join(format!(
+            "{}-{}-{time}.txt",
+            window.session_name.replace('/', "_"),
+            window.name.replace('/', "_")
+        ));
+        let opened = self.config.capture_pager;
+        let exit = opened && self.config.exit_on_switch;
+        let tmux = self.tmux.clone();
+        self.worker.submit_as("Saving scrollback", move || {
+            let result = tmux.capture_pane(&window.id).and_then(|text| {
+                std::fs::create_dir_all(&dir)?;
+                std::fs::write(&file, text)?;
+                if opened {
+                    tmux.open_window(
+                        &window.session_name,
+                        "scrollback",
+                        &dir.to_string_lossy(),
+                        &pager_command(&file),
+                    )?;
+                }
+                Ok(file)
+            });
+            TaskOutput::Captured {
+                result,
+                opened,
+                exit,
+            }
+        });
+    }
+
+    // Launch a configured tool at the selected window's directory
+    fn run_open_action(&mut self, action: OpenActionConfig) {
+        if action.target == OpenTarget::Popup && !self.require(Feature::Popup) {
+            return;
+        }
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+
+        let exit = action.exit.unwrap_or(self.config.exit_on_switch);
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            let result = tmux
+                .window_path(&window.session_name, &window.id)
+                .and_then(|path| {
+                    let context = HookContext {
+                        session: window.session_name.clone(),
+                        window: window.name.clone(),
+                        window_id: window.id.clone(),
+                        path: path.clone(),
+                    };
+                    let command = context.expand_quoted(&action.command);
+
+                    match action.target {
+                        OpenTarget::Window => {
+                            tmux.open_window(&window.session_name, &action.name, &path, &command)
+                        }
+                        OpenTarget::Popup => tmux.open_popup(&path, &command, 80, 80),
+                    }
+                });
+            TaskOutput::Opened { result, exit }
+        });
     }
 
     fn start_rename(&mut self) {
@@ -693,7 +2424,7 @@ impl App {
                         self.previous_mode = self.mode.clone();
                         self.mode = Mode::Rename;
                         self.show_popup = true;
-                        self.popup_input = window.name.clone();
+                        self.popup_input.set(window.name.clone());
                     }
                 }
                 LineType::Session => {
@@ -701,7 +2432,7 @@ impl App {
                         self.previous_mode = self.mode.clone();
                         self.mode = Mode::Rename;
                         self.show_popup = true;
-                        self.popup_input = session_name.clone();
+                        self.popup_input.set(session_name.clone());
                     }
                 }
             }
@@ -709,738 +2440,4205 @@ impl App {
     }
 
     fn start_delete_confirm(&mut self) {
-        if let Some(line) = self.tree_lines.get(self.selected_index) {
-            if let Some(window) = &line.window {
-                self.previous_mode = self.mode.clone();
-                self.mode = Mode::DeleteConfirm;
-                self.show_popup = true;
-                self.popup_input = format!("Delete window '{}'? (y/N)", window.name);
-            }
+        if let Some(line) = self.tree_lines.get(self.selected_index)
+            && let Some(window) = &line.window
+        {
+            self.previous_mode = self.mode.clone();
+            self.mode = Mode::DeleteConfirm;
+            self.show_popup = true;
+            self.popup_input
+                .set(format!("Delete window '{}'? (y/N)", window.name));
+            self.delete_target = DeleteTarget::Selected;
         }
     }
 
-    fn confirm_rename(&mut self) -> Result<()> {
-        let was_session_mode = self.previous_mode == Mode::Session;
+    pub fn has_marks(&self) -> bool {
+        !self.marked_sessions.is_empty() || !self.marked_windows.is_empty()
+    }
 
-        if let Some(line) = self.tree_lines.get(self.selected_index) {
-            match line.line_type {
-                LineType::Window => {
-                    if let Some(window) = &line.window {
-                        match rename_window(&window.session_name, &window.id, &self.popup_input) {
-                            Ok(_) => {
-                                self.refresh_sessions()?;
-                                self.rebuild_tree_view();
-                                if was_session_mode {
-                                    // In session mode, ensure we're positioned on a session
-                                    self.move_to_first_session();
-                                }
-                            }
-                            Err(e) => {
-                                self.error_message = Some(format!("Failed to rename window: {e}"));
-                            }
-                        }
-                    }
-                }
-                LineType::Session => {
-                    if let Some(session_name) = &line.session_name {
-                        match rename_session(session_name, &self.popup_input) {
-                            Ok(_) => {
-                                self.refresh_sessions()?;
-                                self.rebuild_tree_view();
-                                if was_session_mode {
-                                    // Find the renamed session and position on it
-                                    for (index, line) in self.tree_lines.iter().enumerate() {
-                                        if line.line_type == LineType::Session
-                                            && line.session_name.as_ref() == Some(&self.popup_input)
-                                        {
-                                            self.selected_index = index;
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                self.error_message = Some(format!("Failed to rename session: {e}"));
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    fn clear_marks(&mut self) {
+        self.marked_sessions.clear();
+        self.marked_windows.clear();
+    }
 
-        // Return to the previous mode
-        self.mode = self.previous_mode.clone();
-        self.show_popup = false;
-        self.popup_input.clear();
-        Ok(())
+    // Mark or unmark the window or session under the cursor
+    fn toggle_mark(&mut self) {
+        let Some(line) = self.tree_lines.get(self.selected_index) else {
+            return;
+        };
+        let (marks, key) = match (&line.window, &line.session_name) {
+            (Some(window), _) => (&mut self.marked_windows, window.id.clone()),
+            (None, Some(session_name)) => (&mut self.marked_sessions, session_name.clone()),
+            (None, None) => return,
+        };
+        if !marks.remove(&key) {
+            marks.insert(key);
+        }
     }
 
-    fn confirm_delete(&mut self) -> Result<()> {
-        let was_session_mode = self.previous_mode == Mode::Session;
+    // One confirmation listing everything marked, in tree order. Windows of
+    // marked sessions go with their session
+    fn start_delete_marked_confirm(&mut self) {
+        let mut items = DeleteItems::default();
+        for session in &self.sessions {
+            if self.marked_sessions.contains(&session.name) {
+                items.sessions.push(session.name.clone());
+                continue;
+            }
+            items.windows.extend(
+                session
+                    .windows
+                    .iter()
+                    .filter(|w| self.marked_windows.contains(&w.id))
+                    .cloned(),
+            );
+        }
 
-        if let Some(line) = self.tree_lines.get(self.selected_index) {
-            match line.line_type {
-                LineType::Window => {
-                    if let Some(window) = &line.window {
-                        match delete_window(&window.session_name, &window.id) {
-                            Ok(_) => {
-                                self.refresh_sessions()?;
-                                self.rebuild_tree_view();
-                                if was_session_mode {
-                                    // In session mode, ensure we're positioned on a session
-                                    self.move_to_first_session();
-                                } else {
-                                    // If we deleted the currently selected window, move selection to a safe position
-                                    if self.selected_index >= self.tree_lines.len()
-                                        && self.selected_index > 0
-                                    {
-                                        self.selected_index = self.tree_lines.len() - 1;
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                self.error_message = Some(format!("Failed to delete window: {e}"));
-                            }
-                        }
-                    }
-                }
-                LineType::Session => {
-                    if let Some(session_name) = &line.session_name {
-                        match kill_session(session_name) {
-                            Ok(_) => {
-                                self.refresh_sessions()?;
-                                self.rebuild_tree_view();
-                                if was_session_mode {
-                                    // Position on the first available session
-                                    self.move_to_first_session();
-                                } else {
-                                    self.ensure_valid_selection();
-                                }
-                            }
-                            Err(e) => {
-                                self.error_message = Some(format!("Failed to delete session: {e}"));
-                            }
-                        }
-                    }
-                }
+        let mut counts = Vec::new();
+        for (count, noun) in [
+            (items.sessions.len(), "session"),
+            (items.windows.len(), "window"),
+        ] {
+            match count {
+                0 => {}
+                1 => counts.push(format!("1 {noun}")),
+                _ => counts.push(format!("{count} {noun}s")),
             }
         }
-        Ok(())
-    }
 
-    fn start_project_search(&mut self) {
-        self.mode = Mode::Search;
-        self.search_query.clear();
-        self.search_selected_index = 0;
-        // Perform initial search with empty query (shows all results)
-        self.update_search_results();
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::DeleteConfirm;
+        self.show_popup = true;
+        self.popup_input
+            .set(format!("Delete {}?", counts.join(" and ")));
+        self.delete_target = DeleteTarget::Items(items);
     }
 
-    fn start_session_mode(&mut self) {
-        self.mode = Mode::Session;
-        // In session mode, we show the full tree but navigate between sessions only
-        self.rebuild_tree_view();
-        // Position on the first session
-        self.move_to_first_session();
+    /// Items the delete confirmation is about when it's more than the selection
+    pub fn pending_delete(&self) -> Option<&DeleteItems> {
+        match &self.delete_target {
+            DeleteTarget::Items(items) => Some(items),
+            DeleteTarget::Selected => None,
+        }
     }
 
-    fn move_to_first_session(&mut self) {
-        for (index, line) in self.tree_lines.iter().enumerate() {
-            if line.line_type == LineType::Session {
-                self.selected_index = index;
-                break;
-            }
+    fn start_link(&mut self) {
+        let is_window = self
+            .tree_lines
+            .get(self.selected_index)
+            .is_some_and(|line| line.window.is_some());
+        if !is_window {
+            return;
         }
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::Link;
+        self.show_popup = true;
+        self.popup_input.clear();
     }
 
-    fn start_quick_search(&mut self) {
-        self.mode = Mode::QuickSearch;
-        self.quick_search_query.clear();
-        self.quick_search_selected_index = 0;
-        // Perform initial search with empty query (shows all active sessions/windows)
-        self.update_quick_search_results();
+    fn confirm_link(&mut self) {
+        let target_session = self.popup_input.trim().to_string();
+        self.mode = Mode::Window;
+        self.show_popup = false;
+        self.popup_input.clear();
+
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+        if !self.sessions.iter().any(|s| s.name == target_session) {
+            self.messages
+                .error(format!("No session named '{target_session}'"));
+            return;
+        }
+        let already_there = self
+            .sessions
+            .iter()
+            .filter(|s| s.name == target_session)
+            .flat_map(|s| &s.windows)
+            .any(|w| w.id == window.id);
+        if already_there {
+            self.messages.info(format!(
+                "Window '{}' is already in session '{target_session}'",
+                window.name
+            ));
+            return;
+        }
+
+        self.update_sessions_locally(Focus::Keep, |sessions| {
+            link_window(sessions, &window.id, &target_session);
+        });
+
+        let tmux = self.tmux.clone();
+        self.submit_optimistic(
+            move || match tmux.link_window(&window.id, &target_session) {
+                Ok(_) => TaskOutput::Done,
+                Err(e) => TaskOutput::Failed(format!("Failed to link window: {e}")),
+            },
+        );
     }
 
-    fn update_search_results(&mut self) {
-        self.search_results = self.search_provider.search(&self.search_query);
-        self.search_selected_index = 0; // Reset selection when results change
+    // Only for windows that another session shows too, killing is `x`
+    fn unlink_selected(&mut self) {
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+        if !window.linked {
+            self.messages.info(format!(
+                "Window '{}' is only in session '{}'",
+                window.name, window.session_name
+            ));
+            return;
+        }
+
+        self.update_sessions_locally(Focus::Clamp, |sessions| {
+            unlink_window(sessions, &window.session_name, &window.id);
+        });
+
+        let tmux = self.tmux.clone();
+        self.submit_optimistic(move || {
+            match tmux.unlink_window(&window.session_name, &window.id) {
+                Ok(_) => TaskOutput::Done,
+                Err(e) => TaskOutput::Failed(format!("Failed to unlink window: {e}")),
+            }
+        });
     }
 
-    fn update_quick_search_results(&mut self) {
-        use fuzzy_matcher::FuzzyMatcher;
-        use fuzzy_matcher::skim::SkimMatcherV2;
+    // Prefilled with the current start directory, or the directory of the
+    // session's active window
+    fn start_start_dir(&mut self) {
+        let Some(session) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.session_name.as_ref())
+            .and_then(|name| self.sessions.iter().find(|s| &s.name == name))
+        else {
+            return;
+        };
+        let input = session.start_dir.clone().unwrap_or_else(|| {
+            session
+                .windows
+                .iter()
+                .find(|w| w.active)
+                .map(|w| w.path.clone())
+                .unwrap_or_default()
+        });
+
+        self.popup_input.set(input);
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::StartDir;
+        self.show_popup = true;
+    }
 
-        let matcher = SkimMatcherV2::default().ignore_case();
+    // An empty input forgets the directory
+    fn confirm_start_dir(&mut self) {
+        let input = self.popup_input.trim().to_string();
+        self.mode = self.previous_mode.clone();
+        self.show_popup = false;
+        self.popup_input.clear();
 
-        if self.quick_search_query.is_empty() {
-            // Show all sessions and windows
-            self.quick_search_results = (0..self.tree_lines.len()).collect();
+        let Some(session_name) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.session_name.clone())
+        else {
+            return;
+        };
+        let start_dir = if input.is_empty() {
+            None
         } else {
-            // Fuzzy search through session:window format and sort by score
-            let mut scored_results: Vec<(usize, i64)> = self
-                .tree_lines
-                .iter()
-                .enumerate()
-                .filter_map(|(i, line)| {
-                    let search_text = match line.line_type {
-                        LineType::Session => {
-                            // For sessions, just search the session name
-                            if let Some(ref session_name) = line.session_name {
-                                session_name.clone()
-                            } else {
-                                return None;
-                            }
-                        }
-                        LineType::Window => {
-                            // For windows, search in session:window format
-                            if let Some(window) = &line.window {
-                                if let Some(ref session_name) = line.session_name {
-                                    format!("{}:{}", session_name, window.name)
-                                } else {
-                                    window.name.clone()
-                                }
-                            } else {
-                                return None;
-                            }
-                        }
-                    };
+            let path = expand_path(&input);
+            if !path.is_dir() {
+                self.messages.error(format!("No directory '{input}'"));
+                return;
+            }
+            Some(path.display().to_string())
+        };
 
-                    // Get the fuzzy match score
-                    matcher
-                        .fuzzy_match(&search_text, &self.quick_search_query)
-                        .map(|score| (i, score))
-                })
-                .collect();
+        self.update_sessions_locally(Focus::Keep, |sessions| {
+            if let Some(session) = sessions.iter_mut().find(|s| s.name == session_name) {
+                session.start_dir = start_dir.clone();
+            }
+        });
 
-            // Sort by score (higher scores are better matches)
-            scored_results.sort_by(|a, b| b.1.cmp(&a.1));
+        let tmux = self.tmux.clone();
+        self.submit_optimistic(move || {
+            match tmux.set_start_dir(&session_name, start_dir.as_deref()) {
+                Ok(_) => TaskOutput::Done,
+                Err(e) => TaskOutput::Failed(format!("Failed to set start directory: {e}")),
+            }
+        });
+    }
 
-            // Extract just the indices
-            self.quick_search_results = scored_results.into_iter().map(|(i, _)| i).collect();
-        }
+    // Prefilled with the current note of the selected window or session
+    fn start_note(&mut self) {
+        let Some(line) = self.tree_lines.get(self.selected_index) else {
+            return;
+        };
+        let note = match &line.window {
+            Some(window) => window.note.clone(),
+            None => line
+                .session_name
+                .as_ref()
+                .and_then(|name| self.sessions.iter().find(|s| &s.name == name))
+                .and_then(|session| session.note.clone()),
+        };
 
-        self.quick_search_selected_index = 0; // Reset selection when results change
+        self.popup_input.set(note.unwrap_or_default());
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::Note;
+        self.show_popup = true;
     }
 
-    fn handle_quick_search_mode(&mut self, key: KeyEvent) -> Result<bool> {
-        match key.code {
-            KeyCode::Esc => {
-                self.mode = Mode::Window;
-                Ok(false)
-            }
-            KeyCode::Enter => {
-                if !self.quick_search_results.is_empty() {
-                    // Jump to the selected line in the tree
-                    let selected_tree_index =
-                        self.quick_search_results[self.quick_search_selected_index];
-                    self.selected_index = selected_tree_index;
-                    self.mode = Mode::Window;
+    // An empty input removes the note
+    fn confirm_note(&mut self) {
+        let input = self.popup_input.trim().to_string();
+        self.mode = self.previous_mode.clone();
+        self.show_popup = false;
+        self.popup_input.clear();
 
-                    // Activate the selected item (switch to session/window)
-                    if self.activate_selected()? {
-                        return Ok(true); // Exit if activation was successful
+        let Some(line) = self.tree_lines.get(self.selected_index) else {
+            return;
+        };
+        let Some(session_name) = line.session_name.clone() else {
+            return;
+        };
+        let window_id = line.window.as_ref().map(|w| w.id.clone());
+        let note = Some(input).filter(|note| !note.is_empty());
+
+        self.update_sessions_locally(Focus::Keep, |sessions| match &window_id {
+            // Linked copies are the same tmux window, with the same options
+            Some(window_id) => {
+                for window in sessions.iter_mut().flat_map(|s| s.windows.iter_mut()) {
+                    if &window.id == window_id {
+                        window.note = note.clone();
                     }
-                } else {
-                    self.mode = Mode::Window;
                 }
-                Ok(false)
-            }
-            KeyCode::Char(c) => {
-                self.quick_search_query.push(c);
-                self.update_quick_search_results();
-                Ok(false)
-            }
-            KeyCode::Backspace => {
-                self.quick_search_query.pop();
-                self.update_quick_search_results();
-                Ok(false)
             }
-            KeyCode::Up => {
-                if self.quick_search_selected_index > 0 {
-                    self.quick_search_selected_index -= 1;
+            None => {
+                if let Some(session) = sessions.iter_mut().find(|s| s.name == session_name) {
+                    session.note = note.clone();
                 }
-                Ok(false)
             }
-            KeyCode::Down => {
-                if self.quick_search_selected_index
-                    < self.quick_search_results.len().saturating_sub(1)
-                {
-                    self.quick_search_selected_index += 1;
-                }
-                Ok(false)
+        });
+
+        let tmux = self.tmux.clone();
+        self.submit_optimistic(move || {
+            match tmux.set_note(&session_name, window_id.as_deref(), note.as_deref()) {
+                Ok(_) => TaskOutput::Done,
+                Err(e) => TaskOutput::Failed(format!("Failed to set note: {e}")),
             }
-            _ => Ok(false),
-        }
+        });
     }
 
-    fn execute_search_selection(&mut self) -> Result<bool> {
-        if self.search_selected_index < self.search_results.len() {
-            let selected = &self.search_results[self.search_selected_index];
+    fn start_shell(&mut self) {
+        if self.tree_lines.get(self.selected_index).is_none() {
+            return;
+        }
+        self.popup_input.clear();
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::Shell;
+        self.show_popup = true;
+    }
 
-            // Use the same logic as the bash script
-            self.switch_to_session_and_window(
-                &selected.session_name,
-                &selected.window_name,
-                &selected.full_path,
-            )?;
+    // Runs in the selected window's directory; on a session line in its
+    // active window's, with PORTAL_WINDOW left empty
+    fn confirm_shell(&mut self) {
+        let command = self.popup_input.trim().to_string();
+        self.mode = self.previous_mode.clone();
+        self.show_popup = false;
+        self.popup_input.clear();
 
-            // Return true to indicate the application should exit
-            return Ok(true);
+        let popup = self.config.shell_popup;
+        if command.is_empty() || (popup && !self.require(Feature::Popup)) {
+            return;
         }
-        Ok(false)
+        let Some(line) = self.tree_lines.get(self.selected_index) else {
+            return;
+        };
+        let Some(session_name) = line.session_name.clone() else {
+            return;
+        };
+        let window_name = line.window.as_ref().map(|w| w.name.clone());
+        let Some(window_id) = line.window.as_ref().map(|w| w.id.clone()).or_else(|| {
+            let session = self.sessions.iter().find(|s| s.name == session_name)?;
+            let window = session.windows.iter().find(|w| w.active)?;
+            Some(window.id.clone())
+        }) else {
+            return;
+        };
+
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            let result = tmux
+                .window_path(&session_name, &window_id)
+                .and_then(|path| {
+                    let context = HookContext {
+                        session: session_name,
+                        window: window_name.unwrap_or_default(),
+                        window_id,
+                        path,
+                    };
+                    match popup {
+                        true => tmux
+                            .run_in_popup(&context.path, &command, &context.env())
+                            .map(|status| ShellRun {
+                                status,
+                                output: String::new(),
+                            }),
+                        false => hooks::run_shell(&command, &context),
+                    }
+                });
+            TaskOutput::Ran { command, result }
+        });
     }
 
-    fn switch_to_session_and_window(
-        &self,
-        session_name: &str,
-        window_name: &str,
-        path: &std::path::Path,
-    ) -> Result<()> {
-        crate::tmux::switch_to_session_and_window(session_name, window_name, path)
+    // Prefilled with the window's current index
+    fn start_move_to_index(&mut self) {
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.as_ref())
+        else {
+            return;
+        };
+
+        self.popup_input.set(window.index.to_string());
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::MoveIndex;
+        self.show_popup = true;
     }
 
-    pub fn get_window_line_numbers(&self) -> HashMap<usize, i32> {
-        let mut line_numbers = HashMap::new();
-        let window_indices: Vec<usize> = self
+    // A free index is simply taken; for one another window has,
+    // `occupied_index` decides between shifting that window up and swapping
+    fn confirm_move_to_index(&mut self) {
+        let input = self.popup_input.trim().to_string();
+        self.mode = self.previous_mode.clone();
+        self.show_popup = false;
+        self.popup_input.clear();
+
+        let Some(window) = self
             .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+        let Ok(index) = input.parse::<u32>() else {
+            self.messages
+                .error(format!("Not a window index: '{input}'"));
+            return;
+        };
+        if index == window.index {
+            return;
+        }
+        let session_name = window.session_name.clone();
+        let occupant = self
+            .sessions
             .iter()
-            .enumerate()
-            .filter_map(|(i, line)| {
-                if line.line_type == LineType::Window {
-                    Some(i)
-                } else {
-                    None
-                }
-            })
-            .collect();
+            .find(|s| s.name == session_name)
+            .and_then(|session| session.windows.iter().find(|w| w.index == index))
+            .map(|w| w.id.clone());
+
+        match occupant {
+            None => {
+                self.update_sessions_locally(Focus::Window(window.id.clone()), |sessions| {
+                    if let Some(session) = sessions.iter_mut().find(|s| s.name == session_name) {
+                        session.move_to_index(&window.id, index);
+                    }
+                });
 
-        if let Some(selected_pos) = window_indices
-            .iter()
-            .position(|&i| i == self.selected_index)
-        {
-            for (pos, &line_idx) in window_indices.iter().enumerate() {
-                let relative_num = pos as i32 - selected_pos as i32;
-                line_numbers.insert(line_idx, relative_num);
+                let tmux = self.tmux.clone();
+                self.submit_optimistic(move || {
+                    match tmux.move_window_to_index(&session_name, &window.id, index) {
+                        Ok(_) => TaskOutput::Done,
+                        Err(e) => TaskOutput::Failed(format!("Failed to move window: {e}")),
+                    }
+                });
             }
+            Some(occupant) => match self.config.occupied_index {
+                OccupiedIndex::Swap => self.swap_windows_in_tmux(session_name, window.id, occupant),
+                OccupiedIndex::Shift => self.move_window_to_session(WindowMove {
+                    window,
+                    target_session: session_name,
+                    target_window_id: occupant,
+                    before: true,
+                }),
+            },
         }
+    }
 
-        line_numbers
+    // The inverse of moving a window into another session: give it a session
+    // of its own, named after it, and switch there
+    fn break_out_selected(&mut self) {
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+        let session_name = self.new_session_name(&window.name);
+
+        self.update_sessions_locally(Focus::Window(window.id.clone()), |sessions| {
+            break_window(sessions, &window.session_name, &window.id, &session_name);
+        });
+
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            match tmux.break_window(&window.id, &session_name, &window.path) {
+                Ok(_) => TaskOutput::Switched(tmux.switch_to_window(&session_name, &window.id)),
+                Err(e) => TaskOutput::Failed(format!("Failed to break out window: {e}")),
+            }
+        });
     }
 
-    fn handle_session_mode(&mut self, key: KeyEvent) -> Result<bool> {
-        match key.code {
-            KeyCode::Char('q') => {
-                self.mode = Mode::Window;
-                // Tree view is already built, just ensure valid selection for normal mode
-                self.ensure_valid_selection();
+    // Attach to the scratch session in a popup; detaching hides it again
+    fn show_scratch(&mut self) {
+        if !self.require(Feature::Popup) {
+            return;
+        }
+        let path = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.as_ref())
+            .map(|window| window.path.clone())
+            .or_else(|| {
+                std::env::current_dir()
+                    .ok()
+                    .map(|p| p.display().to_string())
+            })
+            .unwrap_or_default();
+        let scratch = self.config.scratch.clone();
+        let exit = self.config.exit_on_switch;
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            let command = attach_command(&scratch.session);
+            let result = tmux.open_popup(&path, &command, scratch.width, scratch.height);
+            TaskOutput::Opened { result, exit }
+        });
+    }
+
+    // Park the selected window at the end of the scratch session, which is
+    // created from the window if it doesn't exist yet
+    fn send_to_scratch(&mut self) {
+        // Without popups there's no way to look at it
+        if !self.require(Feature::Popup) {
+            return;
+        }
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+        let scratch = self.config.scratch.session.clone();
+        if window.session_name == scratch {
+            self.messages.info(format!(
+                "Window '{}' is already in '{scratch}'",
+                window.name
+            ));
+            return;
+        }
+
+        let last_window_id = self
+            .sessions
+            .iter()
+            .find(|s| s.name == scratch)
+            .and_then(|s| s.windows.last())
+            .map(|w| w.id.clone());
+        let tmux = self.tmux.clone();
+        match last_window_id {
+            Some(target_window_id) => {
+                self.update_sessions_locally(Focus::Clamp, |sessions| {
+                    move_window(sessions, &window.id, &target_window_id, false);
+                });
+                self.submit_optimistic(move || {
+                    match tmux.move_window(&window.id, &target_window_id, false) {
+                        Ok(_) => TaskOutput::Done,
+                        Err(e) => TaskOutput::Failed(format!("Failed to move window: {e}")),
+                    }
+                });
             }
-            KeyCode::Esc => {
-                self.mode = Mode::Window;
-                // Tree view is already built, just ensure valid selection for normal mode
-                self.ensure_valid_selection();
+            None => {
+                self.update_sessions_locally(Focus::Clamp, |sessions| {
+                    break_window(sessions, &window.session_name, &window.id, &scratch);
+                });
+                self.submit_optimistic(move || {
+                    match tmux.break_window(&window.id, &scratch, &window.path) {
+                        Ok(_) => TaskOutput::Done,
+                        Err(e) => TaskOutput::Failed(format!("Failed to move window: {e}")),
+                    }
+                });
             }
-            KeyCode::Char('j') | KeyCode::Down => {
-                self.move_down_session_mode();
+        }
+    }
+
+    // tmux turns '.' and ':' in session names into '_', and a taken name
+    // gets a number
+    fn new_session_name(&self, base: &str) -> String {
+        let base = match base.replace(['.', ':'], "_") {
+            name if name.is_empty() => "window".to_string(),
+            name => name,
+        };
+        let taken = |name: &str| self.sessions.iter().any(|s| s.name == name);
+        if !taken(&base) {
+            return base;
+        }
+        (2..)
+            .map(|n| format!("{base}-{n}"))
+            .find(|name| !taken(name))
+            .unwrap()
+    }
+
+    // Like an editor's "close other tabs", for the selected window's session
+    fn start_close_others_confirm(&mut self) {
+        let Some(window) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.window.clone())
+        else {
+            return;
+        };
+
+        let others: Vec<TmuxWindow> = self
+            .sessions
+            .iter()
+            .filter(|s| s.name == window.session_name)
+            .flat_map(|s| s.windows.iter())
+            .filter(|w| w.id != window.id)
+            .cloned()
+            .collect();
+        if others.is_empty() {
+            self.messages
+                .info(format!("No other windows in '{}'", window.session_name));
+            return;
+        }
+
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::DeleteConfirm;
+        self.show_popup = true;
+        let plural = if others.len() == 1 { "" } else { "s" };
+        self.popup_input.set(format!(
+            "Close {} other window{plural} in '{}'?",
+            others.len(),
+            window.session_name
+        ));
+        self.delete_target = DeleteTarget::Items(DeleteItems {
+            sessions: Vec::new(),
+            windows: others,
+        });
+    }
+
+    fn confirm_rename(&mut self) {
+        let was_session_mode = self.previous_mode == Mode::Session;
+        let new_name = self.popup_input.to_string();
+
+        if let Some(line) = self.tree_lines.get(self.selected_index).cloned() {
+            let old_name = match &line.window {
+                Some(window) => window.name.as_str(),
+                None => line.session_name.as_deref().unwrap_or_default(),
+            };
+            self.last_change = Some(Change::Rename(rename_pattern(old_name, &new_name)));
+
+            match line.line_type {
+                LineType::Window => {
+                    if let Some(window) = line.window {
+                        let session_name = window.session_name;
+                        let window_id = window.id;
+                        self.usage
+                            .rename_window(&session_name, &window.name, &new_name);
+                        self.save_usage();
+                        // In session mode, ensure we're positioned on a session
+                        let focus = if was_session_mode {
+                            Focus::FirstSession
+                        } else {
+                            Focus::Window(window_id.clone())
+                        };
+
+                        // Linked copies in other sessions are the same window
+                        self.update_sessions_locally(focus, |sessions| {
+                            for window in sessions
+                                .iter_mut()
+                                .flat_map(|s| s.windows.iter_mut())
+                                .filter(|w| w.id == window_id)
+                            {
+                                window.name = new_name.clone();
+                            }
+                        });
+
+                        let tmux = self.tmux.clone();
+                        self.submit_optimistic(move || {
+                            match tmux.rename_window(&session_name, &window_id, &new_name) {
+                                Ok(_) => TaskOutput::Done,
+                                Err(e) => {
+                                    TaskOutput::Failed(format!("Failed to rename window: {e}"))
+                                }
+                            }
+                        });
+                    }
+                }
+                LineType::Session => {
+                    if let Some(session_name) = line.session_name {
+                        self.usage.rename_session(&session_name, &new_name);
+                        self.save_usage();
+                        // Stay on the renamed session
+                        let focus = Focus::Session(new_name.clone());
+
+                        self.update_sessions_locally(focus, |sessions| {
+                            if let Some(session) =
+                                sessions.iter_mut().find(|s| s.name == session_name)
+                            {
+                                session.name = new_name.clone();
+                                for window in &mut session.windows {
+                                    window.session_name = new_name.clone();
+                                }
+                            }
+                        });
+
+                        let tmux = self.tmux.clone();
+                        self.submit_optimistic(move || {
+                            match tmux.rename_session(&session_name, &new_name) {
+                                Ok(_) => TaskOutput::Done,
+                                Err(e) => {
+                                    TaskOutput::Failed(format!("Failed to rename session: {e}"))
+                                }
+                            }
+                        });
+                    }
+                }
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                self.move_up_session_mode();
+        }
+
+        // Return to the previous mode
+        self.mode = self.previous_mode.clone();
+        self.show_popup = false;
+        self.popup_input.clear();
+    }
+
+    fn confirm_delete(&mut self) {
+        if let DeleteTarget::Items(items) =
+            std::mem::replace(&mut self.delete_target, DeleteTarget::Selected)
+        {
+            return self.delete_items(items);
+        }
+
+        // Session mode stays on session lines, otherwise keep the cursor where
+        // the deleted item was
+        let focus = if self.previous_mode == Mode::Session {
+            Focus::FirstSession
+        } else {
+            Focus::Clamp
+        };
+
+        if let Some(line) = self.tree_lines.get(self.selected_index) {
+            match line.line_type {
+                LineType::Window => {
+                    if let Some(window) = &line.window {
+                        let session_name = window.session_name.clone();
+                        let window_id = window.id.clone();
+
+                        let tmux = self.tmux.clone();
+                        let label = format!("Deleting window '{}'", window.name);
+                        self.worker.submit_as(label, move || {
+                            match tmux.delete_window(&session_name, &window_id) {
+                                Ok(_) => TaskOutput::refreshed(&*tmux, focus),
+                                Err(e) => {
+                                    TaskOutput::Failed(format!("Failed to delete window: {e}"))
+                                }
+                            }
+                        });
+                    }
+                }
+                LineType::Session => {
+                    if let Some(session_name) = &line.session_name {
+                        let session_name = session_name.clone();
+
+                        let tmux = self.tmux.clone();
+                        let label = format!("Deleting session '{session_name}'");
+                        self.worker.submit_as(label, move || {
+                            match tmux.kill_session(&session_name) {
+                                Ok(_) => TaskOutput::refreshed(&*tmux, focus),
+                                Err(e) => {
+                                    TaskOutput::Failed(format!("Failed to delete session: {e}"))
+                                }
+                            }
+                        });
+                    }
+                }
             }
-            KeyCode::Char('g') => {
-                self.move_to_top_session_mode();
+        }
+    }
+
+    // Kill sessions, then the windows outside of them, one by one. Every
+    // failure is reported on its own
+    fn delete_items(&mut self, items: DeleteItems) {
+        let focus = if self.previous_mode == Mode::Session {
+            Focus::FirstSession
+        } else {
+            Focus::Keep
+        };
+        let DeleteItems { sessions, windows } = items;
+        let window_ids: Vec<&str> = windows.iter().map(|w| w.id.as_str()).collect();
+        self.update_sessions_locally(focus, |all| {
+            all.retain(|s| !sessions.contains(&s.name));
+            for session in all.iter_mut() {
+                session
+                    .windows
+                    .retain(|w| !window_ids.contains(&w.id.as_str()));
+                session.renumber_windows();
             }
-            KeyCode::Char('G') => {
-                self.move_to_bottom_session_mode();
+        });
+
+        let tmux = self.tmux.clone();
+        let label = format!("Deleting {} items", sessions.len() + windows.len());
+        self.submit_optimistic_as(label, move || {
+            let mut failures = Vec::new();
+            for name in &sessions {
+                if let Err(e) = tmux.kill_session(name) {
+                    failures.push(format!("Failed to delete session '{name}': {e}"));
+                }
             }
-            KeyCode::Char('J') => {
-                // Move session down in order
-                self.move_session_down()?;
+            for window in windows
+                .iter()
+                .filter(|w| !sessions.contains(&w.session_name))
+            {
+                if let Err(e) = tmux.delete_window(&window.session_name, &window.id) {
+                    failures.push(format!(
+                        "Failed to delete window '{}:{}': {e}",
+                        window.session_name, window.name
+                    ));
+                }
             }
-            KeyCode::Char('K') => {
-                // Move session up in order
-                self.move_session_up()?;
+            TaskOutput::Deleted { failures }
+        });
+    }
+
+    // Remember the selected session's windows and directories, then kill it
+    fn archive_selected_session(&mut self) {
+        let Some(session) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.session_name.as_ref())
+            .and_then(|name| self.sessions.iter().find(|s| &s.name == name))
+        else {
+            return;
+        };
+        let archived = ArchivedSession::capture(session, chrono::Local::now().timestamp());
+
+        let name = archived.name.clone();
+        self.update_sessions_locally(Focus::FirstSession, |sessions| {
+            sessions.retain(|s| s.name != name);
+        });
+        let tmux = self.tmux.clone();
+        self.submit_optimistic_as(format!("Archiving '{name}'"), move || {
+            let result = tmux.kill_session(&archived.name);
+            TaskOutput::Archived { archived, result }
+        });
+    }
+
+    fn start_archive_mode(&mut self) {
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::Archive;
+        self.archive_selected = 0;
+    }
+
+    fn handle_archive_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        let last = self.archive.len().saturating_sub(1);
+        if let Some(index) = self.move_in_list(&key, self.archive_selected, last) {
+            self.archive_selected = index;
+            return Ok(false);
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('A') => {
+                self.mode = self.previous_mode.clone();
             }
             KeyCode::Enter => {
-                // Switch to selected session
-                if self.activate_selected_session()? {
-                    return Ok(true); // Exit the app after successful switch
+                if let Some(archived) = self.archive.get(self.archive_selected).cloned() {
+                    let tmux = self.tmux.clone();
+                    let label = format!("Restoring '{}'", archived.name);
+                    self.worker.submit_as(label, move || {
+                        let result = tmux.restore_session(&archived);
+                        TaskOutput::Restored { archived, result }
+                    });
                 }
             }
-            KeyCode::Char('r') | KeyCode::Char(',') => {
-                // Rename session
-                self.start_rename();
-            }
-            KeyCode::Char('x') => {
-                // Delete session (with confirmation)
-                self.start_delete_session_confirm();
-            }
-            KeyCode::Char('R') => {
-                // Refresh sessions
-                self.refresh_sessions()?;
-                self.rebuild_tree_view();
-                self.move_to_first_session();
+            // Forget it for good
+            KeyCode::Char('x') if self.archive_selected < self.archive.len() => {
+                let archived = self.archive.remove(self.archive_selected);
+                self.archive_selected = self.archive_selected.min(last.saturating_sub(1));
+                let _ = self.save_archive();
+                self.messages
+                    .info(format!("Removed '{}' from the archive", archived.name));
             }
             _ => {}
         }
         Ok(false)
     }
 
-    fn move_down_session_mode(&mut self) {
-        if self.tree_lines.is_empty() {
+    // Offer to kill detached sessions without activity for `prune_idle`
+    fn start_prune_confirm(&mut self) {
+        let Some(idle_for) = parse_duration(&self.config.prune_idle) else {
+            self.messages.error(format!(
+                "Invalid prune_idle '{}', expected e.g. 7d",
+                self.config.prune_idle
+            ));
             return;
-        }
+        };
 
-        // Find the next session after the current selection
-        for i in (self.selected_index + 1)..self.tree_lines.len() {
-            if self.tree_lines[i].line_type == LineType::Session {
-                self.selected_index = i;
-                return;
-            }
+        let now = chrono::Local::now().timestamp();
+        let idle: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|s| s.is_idle(now, idle_for))
+            .map(|s| s.name.clone())
+            .collect();
+        if idle.is_empty() {
+            self.messages.info(format!(
+                "No detached sessions idle for {}",
+                self.config.prune_idle
+            ));
+            return;
         }
-        // If no session found after current position, stay at current position
+
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::DeleteConfirm;
+        self.show_popup = true;
+        let plural = if idle.len() == 1 { "" } else { "s" };
+        self.popup_input.set(format!(
+            "Kill {} session{plural} idle for {}?",
+            idle.len(),
+            self.config.prune_idle
+        ));
+        self.delete_target = DeleteTarget::Items(DeleteItems {
+            sessions: idle,
+            windows: Vec::new(),
+        });
     }
 
-    fn move_up_session_mode(&mut self) {
-        if self.tree_lines.is_empty() {
+    fn start_project_search(&mut self) {
+        self.scan_lazy_patterns();
+        self.mode = Mode::Search;
+        self.search_query.clear();
+        self.search_selected_index = 0;
+        // Perform initial search with empty query (shows all results)
+        self.update_search_results();
+    }
+
+    fn start_session_mode(&mut self) {
+        if self.flat {
+            self.messages
+                .info("The flat list has no sessions, press f to show them");
             return;
         }
+        self.mode = Mode::Session;
+        // In session mode, we show the full tree but navigate between sessions only
+        self.rebuild_tree_view();
+        // Position on the first session
+        self.move_to_first_session();
+    }
 
-        // Find the previous session before the current selection
+    fn move_to_first_session(&mut self) {
+        for (index, line) in self.tree_lines.iter().enumerate() {
+            if line.line_type == LineType::Session {
+                self.selected_index = index;
+                break;
+            }
+        }
+    }
+
+    fn start_quick_search(&mut self) {
+        self.mode = Mode::QuickSearch;
+        self.quick_search_query.clear();
+        self.quick_search_selected_index = 0;
+        // Perform initial search with empty query (shows all active sessions/windows)
+        self.update_quick_search_results();
+    }
+
+    fn update_search_results(&mut self) {
+        let results = self.search_provider.search(&self.search_query);
+        self.search_groups = group_counts(&results);
+        self.search_results = results
+            .into_iter()
+            .filter(|r| !self.collapsed_groups.contains(&r.group))
+            .collect();
+        self.search_selected_index = 0; // Reset selection when results change
+    }
+
+    fn toggle_search_group(&mut self) {
+        let Some(group) = self
+            .search_results
+            .get(self.search_selected_index)
+            .map(|r| r.group.clone())
+        else {
+            return;
+        };
+        if !self.collapsed_groups.remove(&group) {
+            self.collapsed_groups.insert(group.clone());
+        }
+        self.update_search_results();
+        // Land on the first result after the collapsed group
+        let rank = |g: &str| self.search_groups.iter().position(|(name, _)| name == g);
+        let collapsed = rank(&group);
+        self.search_selected_index = self
+            .search_results
+            .iter()
+            .position(|r| rank(&r.group) > collapsed)
+            .unwrap_or(0);
+    }
+
+    fn update_quick_search_results(&mut self) {
+        use fuzzy_matcher::FuzzyMatcher;
+        use fuzzy_matcher::skim::SkimMatcherV2;
+
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let filter = Filter::parse(&self.quick_search_query);
+        let now = chrono::Local::now().timestamp();
+
+        if filter.is_empty() {
+            // Show all sessions and windows
+            self.quick_search_results = (0..self.tree_lines.len()).collect();
+        } else {
+            // Fuzzy search through session:window format and sort by score
+            let mut scored_results: Vec<(usize, i64)> = self
+                .tree_lines
+                .iter()
+                .enumerate()
+                .filter_map(|(i, line)| {
+                    let search_text = match line.line_type {
+                        LineType::Session => {
+                            // For sessions, just search the session name
+                            let session_name = line.session_name.as_ref()?;
+                            if !filter.matches_session(session_name) {
+                                return None;
+                            }
+                            let note = self
+                                .sessions
+                                .iter()
+                                .find(|s| &s.name == session_name)
+                                .and_then(|s| s.note.as_deref());
+                            match note {
+                                Some(note) => format!("{session_name} {note}"),
+                                None => session_name.clone(),
+                            }
+                        }
+                        LineType::Window => {
+                            // For windows, search in session:window format
+                            let window = line.window.as_ref()?;
+                            if !filter.matches_window(window, now) {
+                                return None;
+                            }
+                            let target = if let Some(ref session_name) = line.session_name {
+                                format!("{}:{}", session_name, window.name)
+                            } else {
+                                window.name.clone()
+                            };
+                            // Notes are searchable too
+                            match &window.note {
+                                Some(note) => format!("{target} {note}"),
+                                None => target,
+                            }
+                        }
+                    };
+
+                    // Only predicates: keep everything they let through
+                    if filter.text.is_empty() {
+                        return Some((i, 0));
+                    }
+
+                    // Get the fuzzy match score
+                    matcher
+                        .fuzzy_match(&search_text, &filter.text)
+                        .map(|score| (i, score))
+                })
+                .collect();
+
+            // Sort by score (higher scores are better matches), ties stay in tree order
+            scored_results.sort_by_key(|r| std::cmp::Reverse(r.1));
+
+            // Extract just the indices
+            self.quick_search_results = scored_results.into_iter().map(|(i, _)| i).collect();
+        }
+
+        self.quick_search_selected_index = 0; // Reset selection when results change
+    }
+
+    fn handle_quick_search_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        match self.keymap.action(Scope::Search, &key) {
+            Some(Action::Down) => {
+                if self.quick_search_selected_index
+                    < self.quick_search_results.len().saturating_sub(1)
+                {
+                    self.quick_search_selected_index += 1;
+                }
+                return Ok(false);
+            }
+            Some(Action::Up) => {
+                self.quick_search_selected_index =
+                    self.quick_search_selected_index.saturating_sub(1);
+                return Ok(false);
+            }
+            _ => {}
+        }
+        // Plain letters go into the query, so only a modified key can cycle
+        let modified = key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if modified && self.keymap.action(Scope::Window, &key) == Some(Action::LineNumbers) {
+            self.cycle_line_numbers();
+            return Ok(false);
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = Mode::Window;
+                Ok(false)
+            }
+            KeyCode::Enter => {
+                if !self.quick_search_results.is_empty() {
+                    // Jump to the selected line in the tree
+                    let selected_tree_index =
+                        self.quick_search_results[self.quick_search_selected_index];
+                    self.selected_index = selected_tree_index;
+                    self.mode = Mode::Window;
+
+                    // Activate the selected item (switch to session/window)
+                    self.activate_selected(false);
+                } else {
+                    self.mode = Mode::Window;
+                }
+                Ok(false)
+            }
+
+            _ => {
+                if self.quick_search_query.handle_key(&key) {
+                    self.update_quick_search_results();
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    fn execute_search_selection(&mut self) {
+        if let Some(selected) = self.search_results.get(self.search_selected_index).cloned() {
+            self.open_search_result(selected);
+        }
+    }
+
+    // Switch to a search result, unless its session already has the window
+    // open in another directory: then `session_collision` decides
+    fn open_search_result(&mut self, result: SearchResult) {
+        self.mode = Mode::Window;
+        if !self.collides(&result) {
+            self.switch_to_session_and_window(result);
+            return;
+        }
+
+        let mut suffixed = result.clone();
+        for n in 2.. {
+            suffixed.session_name = format!("{}-{n}", result.session_name);
+            if !self.collides(&suffixed) {
+                break;
+            }
+        }
+        match self.config.session_collision {
+            SessionCollision::Reuse => self.switch_to_session_and_window(result),
+            SessionCollision::Suffix => self.switch_to_session_and_window(suffixed),
+            SessionCollision::Prompt => {
+                self.mode = Mode::CollisionConfirm;
+                self.show_popup = true;
+                self.pending_collision = Some((result, suffixed));
+            }
+        }
+    }
+
+    // The result's window exists in its session but belongs to another path
+    fn collides(&self, result: &SearchResult) -> bool {
+        self.sessions
+            .iter()
+            .filter(|s| s.name == result.session_name)
+            .flat_map(|s| &s.windows)
+            .any(|w| {
+                w.name == result.window_name && !Path::new(&w.path).starts_with(&result.full_path)
+            })
+    }
+
+    /// The window holding a collided search result's session, for the prompt
+    pub fn collision_window(&self) -> Option<&TmuxWindow> {
+        let (result, _) = self.pending_collision.as_ref()?;
+        self.sessions
+            .iter()
+            .filter(|s| s.name == result.session_name)
+            .flat_map(|s| &s.windows)
+            .find(|w| w.name == result.window_name)
+    }
+
+    fn forget_search_result(&mut self) {
+        let Some(selected) = self.search_results.get(self.search_selected_index).cloned() else {
+            return;
+        };
+        if !self.search_provider.forget(&selected) {
+            self.messages
+                .info("Bookmarks come from the config, remove them there");
+            return;
+        }
+        // Stay on the result that took its place
+        let index = self.search_selected_index;
+        self.update_search_results();
+        self.search_selected_index = index.min(self.search_results.len().saturating_sub(1));
+    }
+
+    // The search box doubles as a launcher for sessions that aren't projects
+    fn create_session_from_query(&mut self) {
+        let dir = expand_path(&self.config.new_session_dir);
+        if let Some(result) = new_session_result(&self.search_query, &dir) {
+            self.open_search_result(result);
+        }
+    }
+
+    /// Layouts `choose_layout` offers, keyed 1 to 9: the result's own, a
+    /// single pane, editor and shell side by side, then `[[layouts]]`
+    pub fn layout_choices(&self, result: &SearchResult) -> Vec<(String, PaneLayout)> {
+        let environment = result.layout.environment;
+        let configured = match result.group.is_empty() {
+            true => "as configured".to_string(),
+            false => format!("as configured ({})", result.group),
+        };
+        let mut choices = vec![
+            (configured, result.layout.clone()),
+            ("single window".to_string(), PaneLayout::default()),
+            (
+                "editor + shell".to_string(),
+                PaneLayout {
+                    panes: vec![String::new()],
+                    layout: Some("main-vertical".to_string()),
+                    ..PaneLayout::default()
+                },
+            ),
+        ];
+        choices.extend(self.config.layouts.iter().map(|l| {
+            let layout = PaneLayout {
+                panes: l.panes.clone(),
+                layout: l.layout.clone(),
+                ..PaneLayout::default()
+            };
+            (l.name.clone(), layout)
+        }));
+        choices.truncate(9);
+        for (_, layout) in &mut choices {
+            layout.environment = environment;
+        }
+        choices
+    }
+
+    // A digit opens with that layout, Enter as configured, anything else cancels
+    fn handle_layout_choice(&mut self, mut result: SearchResult, key: KeyEvent) {
+        let choice = match key.code {
+            KeyCode::Enter => 0,
+            KeyCode::Char(c @ '1'..='9') => (c as u8 - b'1') as usize,
+            _ => return,
+        };
+        let Some((_, layout)) = self.layout_choices(&result).into_iter().nth(choice) else {
+            self.pending_layout = Some(result);
+            return;
+        };
+        result.layout = layout;
+        self.open_with_layout(result);
+    }
+
+    fn switch_to_session_and_window(&mut self, selected: SearchResult) {
+        let creates_window = !self
+            .sessions
+            .iter()
+            .filter(|s| s.name == selected.session_name)
+            .flat_map(|s| &s.windows)
+            .any(|w| w.name == selected.window_name);
+        if self.config.choose_layout && creates_window {
+            self.pending_layout = Some(selected);
+            return;
+        }
+        self.open_with_layout(selected);
+    }
+
+    fn open_with_layout(&mut self, selected: SearchResult) {
+        let hooks = self.config.hooks.clone();
+        let tmux = self.tmux.clone();
+        let label = format!("Opening '{}'", selected.session_name);
+        self.worker.submit_as(label, move || {
+            let context = HookContext {
+                session: selected.session_name.clone(),
+                window: selected.window_name.clone(),
+                window_id: String::new(),
+                path: selected.full_path.to_string_lossy().to_string(),
+            };
+
+            TaskOutput::Switched(with_switch_hooks(&hooks, &context, || {
+                tmux.switch_to_session_and_window(
+                    &selected.session_name,
+                    &selected.window_name,
+                    &selected.full_path,
+                    selected.on_create.as_deref(),
+                    &selected.layout,
+                )
+            }))
+        });
+    }
+
+    /// Number shown in the gutter for each window line, depending on the
+    /// line number mode
+    pub fn get_window_line_numbers(&self) -> HashMap<usize, i32> {
+        let mut line_numbers = HashMap::new();
+        if self.line_number_mode == LineNumberMode::Off {
+            return line_numbers;
+        }
+
+        let window_indices: Vec<usize> = self
+            .tree_lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                if line.line_type == LineType::Window {
+                    Some(i)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        if self.line_number_mode == LineNumberMode::Absolute {
+            for (pos, &line_idx) in window_indices.iter().enumerate() {
+                line_numbers.insert(line_idx, pos as i32 + 1);
+            }
+        } else if let Some(selected_pos) = window_indices
+            .iter()
+            .position(|&i| i == self.selected_index)
+        {
+            for (pos, &line_idx) in window_indices.iter().enumerate() {
+                let relative_num = pos as i32 - selected_pos as i32;
+                line_numbers.insert(line_idx, relative_num);
+            }
+        }
+
+        line_numbers
+    }
+
+    // Relative -> absolute -> off -> relative
+    fn cycle_line_numbers(&mut self) {
+        self.line_number_mode = match self.line_number_mode {
+            LineNumberMode::Relative => LineNumberMode::Absolute,
+            LineNumberMode::Absolute => LineNumberMode::Off,
+            LineNumberMode::Off => LineNumberMode::Relative,
+        };
+        let name = match self.line_number_mode {
+            LineNumberMode::Relative => "relative",
+            LineNumberMode::Absolute => "absolute",
+            LineNumberMode::Off => "off",
+        };
+        self.messages.info(format!("Line numbers: {name}"));
+    }
+
+    pub fn active_view(&self) -> Option<&ViewConfig> {
+        self.active_view.and_then(|i| self.config.views.get(i))
+    }
+
+    // All windows -> first view -> ... -> last view -> all windows
+    fn cycle_view(&mut self) {
+        if self.config.views.is_empty() {
+            self.messages.info("No views configured");
+            return;
+        }
+
+        self.active_view = match self.active_view {
+            None => Some(0),
+            Some(i) if i + 1 < self.config.views.len() => Some(i + 1),
+            Some(_) => None,
+        };
+        if let Some(sort) = self.active_view().and_then(|view| view.sort) {
+            self.sort_by_activity = sort == ViewSort::Activity;
+        }
+        self.apply_sessions(self.sessions.clone(), None, Focus::Keep);
+
+        let name = self.active_view().map_or("all windows", |view| &view.name);
+        self.messages.info(format!("View: {name}"));
+    }
+
+    fn toggle_flat(&mut self) {
+        self.flat = !self.flat;
+        self.apply_sessions(self.sessions.clone(), None, Focus::Keep);
+        let layout = if self.flat { "flat list" } else { "tree" };
+        self.messages.info(format!("Windows shown as {layout}"));
+    }
+
+    fn toggle_sort_by_activity(&mut self) {
+        self.sort_by_activity = !self.sort_by_activity;
+        self.apply_sessions(self.sessions.clone(), None, Focus::Keep);
+        let order = if self.sort_by_activity {
+            "recent activity"
+        } else {
+            "index"
+        };
+        self.messages.info(format!("Windows sorted by {order}"));
+    }
+
+    fn start_messages_mode(&mut self) {
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::Messages;
+        // Everything is in the log now, no need to keep toasts over it
+        self.messages.dismiss_toasts();
+        // Start at the newest message
+        self.messages_scroll = self.messages.entries().len().saturating_sub(1);
+    }
+
+    // Moves in the panels, j/k and g/G unless the keymap preset says otherwise
+    fn move_in_list(&self, key: &KeyEvent, selected: usize, last: usize) -> Option<usize> {
+        match self.keymap.action(Scope::List, key)? {
+            Action::Down => Some((selected + 1).min(last)),
+            Action::Up => Some(selected.saturating_sub(1)),
+            Action::Top => Some(0),
+            Action::Bottom => Some(last),
+            _ => None,
+        }
+    }
+
+    fn handle_messages_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        let last = self.messages.entries().len().saturating_sub(1);
+        if let Some(index) = self.move_in_list(&key, self.messages_scroll, last) {
+            self.messages_scroll = index;
+            return Ok(false);
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('M') => {
+                self.mode = self.previous_mode.clone();
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    // Environment of the selected line's session
+    fn start_environment_mode(&mut self) {
+        let Some(session_name) = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.session_name.clone())
+        else {
+            return;
+        };
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::Environment;
+        self.env_session = session_name;
+        self.env_vars.clear();
+        self.env_selected = 0;
+        self.env_input = None;
+        self.load_environment();
+    }
+
+    fn load_environment(&mut self) {
+        let session_name = self.env_session.clone();
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || TaskOutput::Environment {
+            result: tmux.session_environment(&session_name),
+            session_name,
+        });
+    }
+
+    // Set (or with None unset) a variable, then show the session's new list
+    fn set_environment(&mut self, name: String, value: Option<String>) {
+        let session_name = self.env_session.clone();
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            if let Err(e) = tmux.set_environment(&session_name, &name, value.as_deref()) {
+                return TaskOutput::Failed(format!("Failed to set {name}: {e}"));
+            }
+            TaskOutput::Environment {
+                result: tmux.session_environment(&session_name),
+                session_name,
+            }
+        });
+    }
+
+    fn handle_environment_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        if let Some(input) = &mut self.env_input {
+            match key.code {
+                KeyCode::Esc => self.env_input = None,
+                KeyCode::Enter => {
+                    let input = self.env_input.take().unwrap_or_default();
+                    match input.split_once('=') {
+                        Some((name, value)) if !name.trim().is_empty() => {
+                            self.set_environment(name.trim().to_string(), Some(value.to_string()))
+                        }
+                        _ => self.messages.error("Expected NAME=value"),
+                    }
+                }
+                _ => {
+                    input.handle_key(&key);
+                }
+            }
+            return Ok(false);
+        }
+
+        let last = self.env_vars.len().saturating_sub(1);
+        if let Some(index) = self.move_in_list(&key, self.env_selected, last) {
+            self.env_selected = index;
+            return Ok(false);
+        }
+        let selected = self.env_vars.get(self.env_selected).cloned();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('E') => {
+                self.mode = self.previous_mode.clone();
+            }
+            KeyCode::Char('a') => self.env_input = Some(TextInput::default()),
+            KeyCode::Char('e') | KeyCode::Enter => {
+                if let Some(var) = selected {
+                    let value = var.value.unwrap_or_default();
+                    self.env_input = Some(TextInput::new(format!("{}={value}", var.name)));
+                }
+            }
+            KeyCode::Char('x') => {
+                if let Some(var) = selected {
+                    self.set_environment(var.name, None);
+                }
+            }
+            KeyCode::Char('R') => self.load_environment(),
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    // Options of the selected window, or of a session line's active window
+    fn start_options_mode(&mut self) {
+        let Some(line) = self.tree_lines.get(self.selected_index) else {
+            return;
+        };
+        let window = line.window.clone().or_else(|| {
+            self.sessions
+                .iter()
+                .find(|s| Some(&s.name) == line.session_name.as_ref())
+                .and_then(|s| s.windows.iter().find(|w| w.active).cloned())
+        });
+        let Some(window) = window else {
+            return;
+        };
+
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::Options;
+        self.options_window = Some(window);
+        self.option_values.clear();
+        self.options_selected = 0;
+        self.load_options(None);
+    }
+
+    // Read the values back, after setting one option first if given
+    fn load_options(&mut self, toggle: Option<(OptionToggle, bool)>) {
+        let Some(window) = self.options_window.clone() else {
+            return;
+        };
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            if let Some((toggle, on)) = toggle
+                && let Err(e) = tmux.set_option(&window.session_name, &window.id, &toggle, on)
+            {
+                return TaskOutput::Failed(format!("Failed to set {}: {e}", toggle.name));
+            }
+            TaskOutput::Options {
+                result: tmux.read_options(&window.session_name, &window.id, OPTION_TOGGLES),
+                window_id: window.id,
+            }
+        });
+    }
+
+    fn handle_options_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        let last = OPTION_TOGGLES.len() - 1;
+        if let Some(index) = self.move_in_list(&key, self.options_selected, last) {
+            self.options_selected = index;
+            return Ok(false);
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('O') => {
+                self.mode = self.previous_mode.clone();
+            }
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                // Nothing to flip until the values are read
+                if let Some(value) = self.option_values.get_mut(self.options_selected) {
+                    *value = !*value;
+                    let toggle = OPTION_TOGGLES[self.options_selected];
+                    let on = *value;
+                    self.load_options(Some((toggle, on)));
+                }
+            }
+            KeyCode::Char('R') => self.load_options(None),
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn start_stats_mode(&mut self) {
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::Stats;
+    }
+
+    fn handle_stats_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        if matches!(
+            key.code,
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('T')
+        ) {
+            self.mode = self.previous_mode.clone();
+        }
+        Ok(false)
+    }
+
+    // Nothing to do but leave
+    fn handle_watch_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        let ctrl_c =
+            key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+        Ok(ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc))
+    }
+
+    fn start_info_mode(&mut self) {
+        self.previous_mode = self.mode.clone();
+        self.mode = Mode::Info;
+        self.load_info();
+    }
+
+    fn load_info(&mut self) {
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            TaskOutput::Info(doctor::checks(
+                doctor::find_tmux_binary(),
+                &tmux.server_info(),
+            ))
+        });
+    }
+
+    fn handle_info_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('I') => {
+                self.mode = self.previous_mode.clone();
+            }
+            KeyCode::Char('R') => self.load_info(),
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn handle_session_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        let Some(action) = self.keymap.action(Scope::Session, &key) else {
+            if let KeyCode::Char(c) = key.code
+                && c.is_alphabetic()
+                && !key.modifiers.contains(KeyModifiers::CONTROL)
+            {
+                self.jump_to_letter(c, LineType::Session);
+            }
+            return Ok(false);
+        };
+
+        match action {
+            Action::Quit | Action::Back => {
+                self.mode = Mode::Window;
+                // Tree view is already built, just ensure valid selection for normal mode
+                self.ensure_valid_selection();
+            }
+            Action::Down => self.move_down_session_mode(),
+            Action::Up => self.move_up_session_mode(),
+            Action::Top => self.move_to_top_session_mode(),
+            Action::Bottom => self.move_to_bottom_session_mode(),
+            Action::MoveItemDown => {
+                self.last_change = Some(Change::MoveDown);
+                self.move_session_down()?
+            }
+            Action::MoveItemUp => {
+                self.last_change = Some(Change::MoveUp);
+                self.move_session_up()?
+            }
+            Action::Select => self.activate_selected_session(),
+            Action::Rename => self.start_rename(),
+            Action::Mark => {
+                self.toggle_mark();
+                self.move_down_session_mode();
+            }
+            Action::Delete if self.has_marks() => self.start_delete_marked_confirm(),
+            Action::Delete => self.start_delete_session_confirm(),
+            Action::Prune => self.start_prune_confirm(),
+            Action::Archive => self.archive_selected_session(),
+            Action::ArchiveBrowser => self.start_archive_mode(),
+            Action::Refresh => self.refresh_sessions(Focus::FirstSession),
+            Action::Messages => self.start_messages_mode(),
+            Action::Environment => self.start_environment_mode(),
+            Action::StartDir => self.start_start_dir(),
+            Action::Options => self.start_options_mode(),
+            Action::RecordMacro => self.toggle_recording(),
+            Action::ReplayMacro => self.replay_macro(),
+            Action::Repeat => self.repeat_last_change()?,
+            Action::Stats => self.start_stats_mode(),
+            Action::ExportTree => self.export_tree(),
+            Action::Note => self.start_note(),
+            Action::Shell => self.start_shell(),
+            // Window mode only
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn move_down_session_mode(&mut self) {
+        if self.tree_lines.is_empty() {
+            return;
+        }
+
+        // Find the next session after the current selection
+        for i in (self.selected_index + 1)..self.tree_lines.len() {
+            if self.tree_lines[i].line_type == LineType::Session {
+                self.selected_index = i;
+                return;
+            }
+        }
+        // If no session found after current position, stay at current position
+    }
+
+    fn move_up_session_mode(&mut self) {
+        if self.tree_lines.is_empty() {
+            return;
+        }
+
+        // Find the previous session before the current selection
         for i in (0..self.selected_index).rev() {
             if self.tree_lines[i].line_type == LineType::Session {
                 self.selected_index = i;
                 return;
             }
         }
-        // If no session found before current position, stay at current position
+        // If no session found before current position, stay at current position
+    }
+
+    fn move_to_top_session_mode(&mut self) {
+        // Find the first session
+        for (index, line) in self.tree_lines.iter().enumerate() {
+            if line.line_type == LineType::Session {
+                self.selected_index = index;
+                break;
+            }
+        }
+    }
+
+    fn move_to_bottom_session_mode(&mut self) {
+        // Find the last session
+        for (index, line) in self.tree_lines.iter().enumerate().rev() {
+            if line.line_type == LineType::Session {
+                self.selected_index = index;
+                break;
+            }
+        }
+    }
+
+    fn activate_selected_session(&mut self) {
+        if let Some(line) = self.tree_lines.get(self.selected_index)
+            && let Some(session_name) = &line.session_name
+        {
+            let session_name = session_name.clone();
+            let tmux = self.tmux.clone();
+            self.worker
+                .submit(move || TaskOutput::Switched(tmux.switch_to_session(&session_name)));
+        }
+    }
+
+    fn start_delete_session_confirm(&mut self) {
+        if let Some(line) = self.tree_lines.get(self.selected_index)
+            && let Some(session_name) = &line.session_name
+        {
+            self.previous_mode = self.mode.clone();
+            self.mode = Mode::DeleteConfirm;
+            self.show_popup = true;
+            self.popup_input
+                .set(format!("Delete session '{session_name}'? (y/N)"));
+            self.delete_target = DeleteTarget::Selected;
+        }
+    }
+
+    fn move_session_up(&mut self) -> Result<()> {
+        // Ensure we're on a session line
+        if let Some(current_line) = self.tree_lines.get(self.selected_index) {
+            if current_line.line_type != LineType::Session {
+                return Ok(());
+            }
+        } else {
+            return Ok(());
+        }
+
+        // Find the previous session
+        let mut prev_session_index = None;
+        for i in (0..self.selected_index).rev() {
+            if self.tree_lines[i].line_type == LineType::Session {
+                prev_session_index = Some(i);
+                break;
+            }
+        }
+
+        if let Some(prev_idx) = prev_session_index {
+            // Get session names
+            let current_session = self.tree_lines[self.selected_index]
+                .session_name
+                .as_ref()
+                .unwrap()
+                .clone();
+            let prev_session = self.tree_lines[prev_idx]
+                .session_name
+                .as_ref()
+                .unwrap()
+                .clone();
+
+            // Swap sessions in our local list
+            let current_idx = self
+                .sessions
+                .iter()
+                .position(|s| s.name == current_session)
+                .unwrap();
+            let prev_session_idx = self
+                .sessions
+                .iter()
+                .position(|s| s.name == prev_session)
+                .unwrap();
+
+            self.sessions.swap(current_idx, prev_session_idx);
+
+            // Rebuild tree view and position on the moved session
+            self.rebuild_tree_view();
+
+            // Find the session that was moved and position on it
+            for (index, line) in self.tree_lines.iter().enumerate() {
+                if line.line_type == LineType::Session
+                    && line.session_name.as_ref() == Some(&current_session)
+                {
+                    self.selected_index = index;
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn move_session_down(&mut self) -> Result<()> {
+        // Ensure we're on a session line
+        if let Some(current_line) = self.tree_lines.get(self.selected_index) {
+            if current_line.line_type != LineType::Session {
+                return Ok(());
+            }
+        } else {
+            return Ok(());
+        }
+
+        // Find the next session
+        let mut next_session_index = None;
+        for i in (self.selected_index + 1)..self.tree_lines.len() {
+            if self.tree_lines[i].line_type == LineType::Session {
+                next_session_index = Some(i);
+                break;
+            }
+        }
+
+        if let Some(next_idx) = next_session_index {
+            // Get session names
+            let current_session = self.tree_lines[self.selected_index]
+                .session_name
+                .as_ref()
+                .unwrap()
+                .clone();
+            let next_session = self.tree_lines[next_idx]
+                .session_name
+                .as_ref()
+                .unwrap()
+                .clone();
+
+            // Swap sessions in our local list
+            let current_idx = self
+                .sessions
+                .iter()
+                .position(|s| s.name == current_session)
+                .unwrap();
+            let next_session_idx = self
+                .sessions
+                .iter()
+                .position(|s| s.name == next_session)
+                .unwrap();
+
+            self.sessions.swap(current_idx, next_session_idx);
+
+            // Rebuild tree view and position on the moved session
+            self.rebuild_tree_view();
+
+            // Find the session that was moved and position on it
+            for (index, line) in self.tree_lines.iter().enumerate() {
+                if line.line_type == LineType::Session
+                    && line.session_name.as_ref() == Some(&current_session)
+                {
+                    self.selected_index = index;
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_new_window(&mut self) {
+        // Fall back to the selected session if the attached one can't be found
+        let selected_session = self
+            .tree_lines
+            .get(self.selected_index)
+            .and_then(|line| line.session_name.clone());
+
+        // Sessions with a start directory set with `D`
+        let start_dirs: HashMap<String, String> = self
+            .sessions
+            .iter()
+            .filter_map(|s| Some((s.name.clone(), s.start_dir.clone()?)))
+            .collect();
+
+        let tmux = self.tmux.clone();
+        self.worker.submit(move || {
+            let current_session = match tmux.current_session_name() {
+                Ok(Some(session_name)) => session_name,
+                _ => match selected_session {
+                    Some(session_name) => session_name,
+                    None => return TaskOutput::Failed("No session selected".to_string()),
+                },
+            };
+
+            // Create a new window in the session, it ends up as the last window
+            let start_dir = start_dirs.get(&current_session).map(String::as_str);
+            match tmux.create_new_window(&current_session, start_dir) {
+                Ok(window) => TaskOutput::Created(window),
+                Err(e) => TaskOutput::Failed(format!("Failed to create new window: {e}")),
+            }
+        });
+        // The window list is only fully known once tmux has created it
+        self.verify_pending = true;
+    }
+
+    fn add_to_history(&mut self, window: &TmuxWindow) {
+        // A new jump starts the walk over from the newest entry
+        self.jump_position = None;
+
+        // Remove if already exists
+        self.history.retain(|h| h.window_id != window.id);
+
+        // Add to front
+        self.history.insert(
+            0,
+            HistoryEntry {
+                session_name: window.session_name.clone(),
+                window_id: window.id.clone(),
+                window_name: window.name.clone(),
+            },
+        );
+
+        self.history.truncate(self.config.history_size);
+        self.usage.record_activation(
+            &window.session_name,
+            &window.name,
+            chrono::Local::now().timestamp(),
+        );
+
+        let recent = self
+            .session_history
+            .entry(window.session_name.clone())
+            .or_default();
+        recent.retain(|id| id != &window.id);
+        recent.insert(0, window.id.clone());
+        recent.truncate(self.config.history_size);
+        self.session_cycle = None;
+
+        // Save to disk
+        if self.persist_state {
+            let _ = Self::save_history(&self.history);
+            let _ = Self::save_session_history(&self.session_history);
+        }
+        self.save_usage();
+    }
+
+    // Select the next of the selected session's recent windows, wrapping
+    // around. The first press skips the window the cursor is already on
+    fn cycle_session_recent(&mut self) {
+        let Some(line) = self.tree_lines.get(self.selected_index) else {
+            return;
+        };
+        let Some(session_name) = line.session_name.clone() else {
+            return;
+        };
+        let selected_id = line.window.as_ref().map(|w| w.id.clone());
+
+        let recent: Vec<usize> = self
+            .session_history
+            .get(&session_name)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.find_window_index_by_id(id))
+            .collect();
+        if recent.is_empty() {
+            return;
+        }
+
+        let on_most_recent =
+            self.tree_lines[recent[0]].window.as_ref().map(|w| &w.id) == selected_id.as_ref();
+        let next = match &self.session_cycle {
+            Some((cycled, position)) if cycled == &session_name => position + 1,
+            _ => usize::from(on_most_recent),
+        } % recent.len();
+
+        self.selected_index = recent[next];
+        self.session_cycle = Some((session_name, next));
+    }
+
+    // Ctrl-o: select the next older window from history. The first step skips
+    // the most recent entry if that is where the cursor already is
+    fn jump_back(&mut self) {
+        let next = match self.jump_position {
+            Some(position) => position + 1,
+            None => {
+                let selected_id = self
+                    .tree_lines
+                    .get(self.selected_index)
+                    .and_then(|line| line.window.as_ref())
+                    .map(|w| w.id.as_str());
+                match self.history.first() {
+                    Some(entry) if Some(entry.window_id.as_str()) == selected_id => 1,
+                    _ => 0,
+                }
+            }
+        };
+        self.jump_to_history(next);
+    }
+
+    // Ctrl-i: walk back toward the most recent window
+    fn jump_forward(&mut self) {
+        if let Some(position) = self.jump_position
+            && position > 0
+        {
+            self.jump_to_history(position - 1);
+        }
+    }
+
+    fn jump_to_history(&mut self, position: usize) {
+        let Some(entry) = self.history.get(position) else {
+            return;
+        };
+        if let Some(index) = self.find_window_index_by_id(&entry.window_id) {
+            self.selected_index = index;
+            self.jump_position = Some(position);
+        }
+    }
+
+    // Drop entries for windows that are gone and pick up renames. Window IDs
+    // are unique per server, so a window that moved sessions is still found
+    fn prune_history(&mut self, sessions: &[TmuxSession]) {
+        // No sessions usually means no server, not that every window closed
+        if sessions.is_empty() {
+            return;
+        }
+
+        if self
+            .usage
+            .record_counts(sessions, chrono::Local::now().timestamp())
+        {
+            self.save_usage();
+        }
+
+        let previous = self.history.clone();
+        self.history.retain_mut(|entry| {
+            let Some(window) = sessions
+                .iter()
+                .flat_map(|s| s.windows.iter())
+                .find(|w| w.id == entry.window_id)
+            else {
+                return false;
+            };
+            entry.session_name = window.session_name.clone();
+            entry.window_name = window.name.clone();
+            true
+        });
+
+        if self.persist_state && self.history != previous {
+            let _ = Self::save_history(&self.history);
+        }
+
+        // Regroup by where each window is now, which also follows renames
+        let previous = std::mem::take(&mut self.session_history);
+        for id in previous.values().flatten() {
+            if let Some(window) = sessions
+                .iter()
+                .flat_map(|s| s.windows.iter())
+                .find(|w| &w.id == id)
+            {
+                self.session_history
+                    .entry(window.session_name.clone())
+                    .or_default()
+                    .push(id.clone());
+            }
+        }
+        if self.persist_state && self.session_history != previous {
+            let _ = Self::save_session_history(&self.session_history);
+        }
     }
 
-    fn move_to_top_session_mode(&mut self) {
-        // Find the first session
-        for (index, line) in self.tree_lines.iter().enumerate() {
-            if line.line_type == LineType::Session {
-                self.selected_index = index;
+    pub fn load_history() -> Result<Vec<HistoryEntry>> {
+        let path = get_history_path(socket_name().as_deref())?;
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(path)?;
+        // Older versions stored bare (session_name, window_id) pairs
+        let history = match serde_json::from_str(&content) {
+            Ok(history) => history,
+            Err(_) => serde_json::from_str::<Vec<(String, String)>>(&content)?
+                .into_iter()
+                .map(|(session_name, window_id)| HistoryEntry {
+                    session_name,
+                    window_id,
+                    window_name: String::new(),
+                })
+                .collect(),
+        };
+        Ok(history)
+    }
+
+    fn save_history(history: &[HistoryEntry]) -> Result<()> {
+        let path = get_history_path(socket_name().as_deref())?;
+        let content = serde_json::to_string(history)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn load_session_history() -> Result<HashMap<String, Vec<String>>> {
+        let path = get_session_history_path(socket_name().as_deref())?;
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = std::fs::read_to_string(path)?;
+        let session_history = serde_json::from_str(&content)?;
+        Ok(session_history)
+    }
+
+    fn save_session_history(session_history: &HashMap<String, Vec<String>>) -> Result<()> {
+        let path = get_session_history_path(socket_name().as_deref())?;
+        let content = serde_json::to_string(session_history)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn load_pins() -> Result<HashMap<u8, (String, String)>> {
+        let path = get_pins_path(socket_name().as_deref())?;
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = std::fs::read_to_string(path)?;
+        let pins = serde_json::from_str(&content)?;
+        Ok(pins)
+    }
+
+    fn save_pins(&self) -> Result<()> {
+        if !self.persist_state {
+            return Ok(());
+        }
+        let path = get_pins_path(socket_name().as_deref())?;
+        let content = serde_json::to_string(&self.pins)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn load_archive() -> Result<Vec<ArchivedSession>> {
+        let path = get_archive_path(socket_name().as_deref())?;
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(path)?;
+        let archive = serde_json::from_str(&content)?;
+        Ok(archive)
+    }
+
+    fn save_archive(&self) -> Result<()> {
+        if !self.persist_state {
+            return Ok(());
+        }
+        let path = get_archive_path(socket_name().as_deref())?;
+        let content = serde_json::to_string(&self.archive)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn load_usage() -> Result<Usage> {
+        let path = get_usage_path(socket_name().as_deref())?;
+        if !path.exists() {
+            return Ok(Usage::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        let usage = serde_json::from_str(&content)?;
+        Ok(usage)
+    }
+
+    fn save_usage(&self) {
+        if !self.persist_state {
+            return;
+        }
+        let _ = get_usage_path(socket_name().as_deref()).and_then(|path| {
+            std::fs::write(path, serde_json::to_string(&self.usage)?)?;
+            Ok(())
+        });
+    }
+
+    fn toggle_pin(&mut self, slot: u8) {
+        let Some(line) = self.tree_lines.get(self.selected_index) else {
+            return;
+        };
+        let Some(window) = &line.window else { return };
+
+        let entry = (window.session_name.clone(), window.id.clone());
+
+        // If this window already has this slot, remove it
+        if self.pins.get(&slot) == Some(&entry) {
+            self.pins.remove(&slot);
+            let _ = self.save_pins();
+            return;
+        }
+
+        // Can only assign/steal if current window has no pin
+        if let Some(pinned) = self
+            .pins
+            .iter()
+            .find(|(_, v)| *v == &entry)
+            .map(|(k, _)| *k)
+        {
+            self.messages
+                .info(format!("Window is already pinned to {pinned}"));
+            return;
+        }
+
+        self.pins.insert(slot, entry);
+        let _ = self.save_pins();
+    }
+
+    /// `session:window` of every pin by slot, using the current window names
+    pub fn pin_targets(&self) -> Vec<(u8, String)> {
+        let mut targets: Vec<(u8, String)> = self
+            .pins
+            .iter()
+            .map(|(slot, (session_name, window_id))| {
+                let window = self
+                    .sessions
+                    .iter()
+                    .flat_map(|s| s.windows.iter())
+                    .find(|w| &w.id == window_id);
+                let target = match window {
+                    Some(window) => format!("{}:{}", window.session_name, window.name),
+                    None => format!("{session_name}:{window_id} (closed)"),
+                };
+                (*slot, target)
+            })
+            .collect();
+        targets.sort();
+        targets
+    }
+
+    fn jump_to_pin(&mut self, slot: u8) {
+        if let Some((session_name, window_id)) = self.pins.get(&slot).cloned() {
+            self.switch_to_window(session_name, window_id, false);
+        }
+    }
+
+    fn accept_delete(&mut self) {
+        // Only a single item is repeatable, marks and prunes are one-offs
+        if matches!(self.delete_target, DeleteTarget::Selected) {
+            self.last_change = Some(Change::Delete);
+        }
+        self.confirm_delete();
+        self.mode = self.previous_mode.clone();
+        self.show_popup = false;
+        self.popup_input.clear();
+    }
+
+    fn handle_delete_confirm_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        match self.confirm_answer(key) {
+            Some(true) => self.accept_delete(),
+            Some(false) => {
+                self.mode = self.previous_mode.clone();
+                self.show_popup = false;
+                self.popup_input.clear();
+                self.delete_target = DeleteTarget::Selected;
+            }
+            None => {}
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{
+        BookmarkConfig, KeySpec, KeymapPreset, LayoutConfig, SearchPatternConfig, SessionCollision,
+        TreeStyle,
+    };
+    use crate::messages::Level;
+    use crate::tmux::fake::FakeTmux;
+    use std::thread;
+
+    fn app_with(layout: &[(&str, &[&str])]) -> (App, Arc<FakeTmux>) {
+        let tmux = Arc::new(FakeTmux::with_sessions(layout));
+        let mut app = App::with_backend(Config::default(), tmux.clone());
+        settle(&mut app);
+        (app, tmux)
+    }
+
+    // Apply background results until no job or verify refresh is left
+    fn settle(app: &mut App) {
+        loop {
+            app.process_tasks();
+            if !app.is_busy() && !app.verify_pending && !app.is_replaying() {
                 break;
             }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+        settle(app);
+    }
+
+    fn window_names(sessions: &[TmuxSession]) -> Vec<&str> {
+        sessions[0]
+            .windows
+            .iter()
+            .map(|w| w.name.as_str())
+            .collect()
+    }
+
+    fn selected_window(app: &App) -> &str {
+        &app.tree_lines[app.selected_index]
+            .window
+            .as_ref()
+            .unwrap()
+            .name
+    }
+
+    #[test]
+    fn test_move_window_swaps_in_tmux_and_follows_window() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell", "logs"])]);
+        assert_eq!(selected_window(&app), "editor");
+
+        press(&mut app, KeyCode::Char('J'));
+        press(&mut app, KeyCode::Char('J'));
+
+        assert_eq!(window_names(&tmux.sessions()), ["shell", "logs", "editor"]);
+        assert_eq!(window_names(&app.sessions), ["shell", "logs", "editor"]);
+        assert_eq!(selected_window(&app), "editor");
+    }
+
+    #[test]
+    fn test_delete_window_after_confirm() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell"]), ("work", &["api"])]);
+
+        press(&mut app, KeyCode::Char('x'));
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(window_names(&tmux.sessions()), ["editor", "shell"]);
+
+        press(&mut app, KeyCode::Char('x'));
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(window_names(&tmux.sessions()), ["shell"]);
+        assert_eq!(app.sessions.len(), 2);
+        assert_eq!(selected_window(&app), "shell");
+    }
+
+    #[test]
+    fn test_busy_note_for_slow_jobs() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell"])]);
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        app.handle_key(key('x')).unwrap();
+        app.handle_key(key('y')).unwrap();
+        assert!(app.is_busy());
+        assert_eq!(app.busy_note(), None);
+
+        // Unanswered for a while, it says what it's waiting for
+        std::thread::sleep(BUSY_NOTE_DELAY);
+        let note = app.busy_note().unwrap();
+        assert!(note.ends_with(" Deleting window 'editor'…"), "{note}");
+
+        settle(&mut app);
+        assert_eq!(app.busy_note(), None);
+        assert_eq!(window_names(&tmux.sessions()), ["shell"]);
+    }
+
+    #[test]
+    fn test_usage_stats() {
+        let (mut app, _tmux) = app_with(&[("main", &["editor"]), ("work", &["api", "db"])]);
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Enter);
+
+        let top = app.usage.top_sessions();
+        assert_eq!((top[0].name.as_str(), top[0].activations), ("work", 2));
+        let never: Vec<&str> = app
+            .usage
+            .never_visited(&app.sessions)
+            .iter()
+            .map(|w| w.name.as_str())
+            .collect();
+        assert_eq!(never, ["editor", "api"]);
+        assert_eq!(app.usage.days.last().unwrap().windows, 3);
+
+        press(&mut app, KeyCode::Char('T'));
+        assert_eq!(app.mode, Mode::Stats);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.mode, Mode::Window);
+    }
+
+    #[test]
+    fn test_toggle_logging() {
+        let dir = tempfile::tempdir().unwrap();
+        let tmux = Arc::new(FakeTmux::with_sessions(&[(
+            "main",
+            &["editor", "build/test"],
+        )]));
+        let config = Config {
+            log_path: format!("{}/logs/{{session}}-{{window}}.log", dir.path().display()),
+            ..Config::default()
+        };
+        let mut app = App::with_backend(config, tmux.clone());
+        settle(&mut app);
+
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('P'));
+        let window = &tmux.sessions()[0].windows[1];
+        assert!(window.piped);
+        let file = dir.path().join("logs/main-build_test.log");
+        assert_eq!(tmux.pipe(&window.id), Some(append_command(&file)));
+        assert!(file.parent().unwrap().is_dir());
+        assert!(
+            app.tree_lines[app.selected_index]
+                .window
+                .as_ref()
+                .unwrap()
+                .piped
+        );
+
+        press(&mut app, KeyCode::Char('P'));
+        assert!(!tmux.sessions()[0].windows[1].piped);
+        assert_eq!(tmux.pipe(&window.id), None);
+    }
+
+    #[test]
+    fn test_tree_search() {
+        let (mut app, _tmux) = app_with(&[
+            ("main", &["editor", "api-server", "shell"]),
+            ("work", &["web-server", "db"]),
+        ]);
+        let typed = |app: &mut App, text: &str| {
+            for c in text.chars() {
+                press(app, KeyCode::Char(c));
+            }
+        };
+
+        // Each key moves to the first match while typing
+        press(&mut app, KeyCode::Char('?'));
+        typed(&mut app, "SERV");
+        assert_eq!(selected_window(&app), "api-server");
+        assert_eq!(app.tree_search.as_deref(), Some("SERV"));
+        let line = app.tree_lines[app.selected_index].clone();
+        assert_eq!(app.tree_search_match(&line), Some(4..8));
+        press(&mut app, KeyCode::Enter);
+        assert!(app.tree_search_input.is_none());
+
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(selected_window(&app), "web-server");
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(selected_window(&app), "api-server");
+        press(&mut app, KeyCode::Char('N'));
+        assert_eq!(selected_window(&app), "web-server");
+
+        // Esc in the tree drops the highlight, Esc while typing goes back
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.tree_search, None);
+        press(&mut app, KeyCode::Char('?'));
+        typed(&mut app, "db");
+        assert_eq!(selected_window(&app), "db");
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(selected_window(&app), "web-server");
+        assert_eq!(app.tree_search, None);
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(selected_window(&app), "web-server");
+    }
+
+    #[test]
+    fn test_move_between_sessions() {
+        let (mut app, _tmux) = app_with(&[
+            ("main", &["editor", "shell"]),
+            ("work", &["api", "db"]),
+            ("misc", &["notes"]),
+        ]);
+
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(selected_window(&app), "shell");
+        press(&mut app, KeyCode::Char('l'));
+        assert_eq!(selected_window(&app), "api");
+        press(&mut app, KeyCode::Char('}'));
+        assert_eq!(selected_window(&app), "notes");
+        // Nothing after the last session
+        press(&mut app, KeyCode::Char('l'));
+        assert_eq!(selected_window(&app), "notes");
+
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('h'));
+        assert_eq!(selected_window(&app), "api");
+        press(&mut app, KeyCode::Char('{'));
+        assert_eq!(selected_window(&app), "editor");
+        press(&mut app, KeyCode::Char('h'));
+        assert_eq!(selected_window(&app), "editor");
+    }
+
+    #[test]
+    fn test_flat_list() {
+        let (mut app, tmux) = app_with(&[("work", &["db", "api"]), ("main", &["shell"])]);
+
+        press(&mut app, KeyCode::Char('f'));
+        let contents: Vec<&str> = app.tree_lines.iter().map(|l| l.content.as_str()).collect();
+        assert_eq!(contents, ["main:shell", "work:api", "work:db"]);
+        assert!(
+            app.tree_lines
+                .iter()
+                .all(|l| l.line_type == LineType::Window)
+        );
+
+        // The order is the list's own, windows can't be moved and there are no sessions
+        app.selected_index = 1;
+        press(&mut app, KeyCode::Char('J'));
+        assert_eq!(window_names(&tmux.sessions()), ["db", "api"]);
+        press(&mut app, KeyCode::Char('S'));
+        assert_eq!(app.mode, Mode::Window);
+
+        // Quick search still finds windows by session:window
+        press(&mut app, KeyCode::Char('/'));
+        for c in "wdb".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        let first = app.quick_search_results[0];
+        assert_eq!(app.tree_lines[first].content, "work:db");
+        press(&mut app, KeyCode::Esc);
+
+        press(&mut app, KeyCode::Char('f'));
+        assert_eq!(app.tree_lines[0].line_type, LineType::Session);
+    }
+
+    #[test]
+    fn test_typeahead_jump() {
+        let (mut app, _tmux) = app_with(&[
+            ("main", &["api", "Build", "bench"]),
+            ("work", &["db"]),
+            ("web", &["site"]),
+        ]);
+
+        press(&mut app, KeyCode::Char('b'));
+        assert_eq!(selected_window(&app), "Build");
+        press(&mut app, KeyCode::Char('b'));
+        assert_eq!(selected_window(&app), "bench");
+        // Wraps around, and letters without a match keep the selection
+        press(&mut app, KeyCode::Char('b'));
+        assert_eq!(selected_window(&app), "Build");
+        press(&mut app, KeyCode::Char('v'));
+        assert_eq!(selected_window(&app), "Build");
+
+        press(&mut app, KeyCode::Char('S'));
+        press(&mut app, KeyCode::Char('w'));
+        let session = |app: &App| app.tree_lines[app.selected_index].session_name.clone();
+        assert_eq!(session(&app).as_deref(), Some("work"));
+        press(&mut app, KeyCode::Char('w'));
+        assert_eq!(session(&app).as_deref(), Some("web"));
+        assert_eq!(
+            app.tree_lines[app.selected_index].line_type,
+            LineType::Session
+        );
+    }
+
+    #[test]
+    fn test_cycle_window_color() {
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor"])]));
+        let config = Config {
+            window_colors: vec!["red".to_string(), "colour208".to_string()],
+            ..Config::default()
+        };
+        let mut app = App::with_backend(config, tmux.clone());
+        settle(&mut app);
+
+        let color = |tmux: &FakeTmux| tmux.sessions()[0].windows[0].color.clone();
+        press(&mut app, KeyCode::Char('c'));
+        assert_eq!(color(&tmux).as_deref(), Some("red"));
+        let line = &app.tree_lines[app.selected_index];
+        assert_eq!(line.window.as_ref().unwrap().color.as_deref(), Some("red"));
+        press(&mut app, KeyCode::Char('c'));
+        assert_eq!(color(&tmux).as_deref(), Some("colour208"));
+        press(&mut app, KeyCode::Char('c'));
+        assert_eq!(color(&tmux), None);
+
+        // A color set outside the list is cleared first
+        let id = tmux.sessions()[0].windows[0].id.clone();
+        tmux.set_window_color(&id, Some("white")).unwrap();
+        app.refresh_sessions(Focus::Keep);
+        settle(&mut app);
+        press(&mut app, KeyCode::Char('c'));
+        assert_eq!(color(&tmux), None);
+    }
+
+    #[test]
+    fn test_capture_scrollback() {
+        let dir = tempfile::tempdir().unwrap();
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor", "build"])]));
+        let config = Config {
+            capture_dir: dir.path().join("captures").display().to_string(),
+            capture_pager: true,
+            exit_on_switch: false,
+            ..Config::default()
+        };
+        let mut app = App::with_backend(config, tmux.clone());
+        settle(&mut app);
+        let build = tmux.sessions()[0].windows[1].id.clone();
+        tmux.set_scrollback(&build, "$ make\nok\n");
+
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('H'));
+        let files: Vec<PathBuf> = std::fs::read_dir(dir.path().join("captures"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        let name = files[0].file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("main-build-") && name.ends_with(".txt"));
+        assert_eq!(std::fs::read_to_string(&files[0]).unwrap(), "$ make\nok\n");
+
+        // The pager opens in a new window, the portal stays
+        let pager = tmux.sessions()[0].windows.last().unwrap().clone();
+        assert_eq!(pager.name, "scrollback");
+        assert_eq!(pager.command, pager_command(&files[0]));
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn test_watch_follows_current_window() {
+        let (mut app, tmux) = app_with(&[("main", &["editor"]), ("work", &["api", "db"])]);
+        app.mode = Mode::Watch;
+
+        let db = tmux.sessions()[1].windows[1].id.clone();
+        tmux.switch_to_window("work", &db).unwrap();
+        app.refresh_in_background();
+        settle(&mut app);
+        assert_eq!(selected_window(&app), "db");
+
+        // Read-only: keys don't move or change anything, q quits
+        press(&mut app, KeyCode::Char('k'));
+        press(&mut app, KeyCode::Char('x'));
+        assert_eq!(selected_window(&app), "db");
+        assert_eq!(app.mode, Mode::Watch);
+        assert!(
+            app.handle_key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_switch_records_history() {
+        let (mut app, tmux) = app_with(&[("main", &["editor"]), ("work", &["api", "db"])]);
+
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Enter);
+
+        let db = &tmux.sessions()[1].windows[1];
+        assert_eq!(
+            app.history,
+            vec![HistoryEntry {
+                session_name: "work".to_string(),
+                window_id: db.id.clone(),
+                window_name: "db".to_string(),
+            }]
+        );
+        assert_eq!(tmux.current_session().as_deref(), Some("work"));
+        assert!(db.active);
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn test_tree_style_from_config() {
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor", "shell"])]));
+        let config = Config {
+            tree_style: TreeStyle::Ascii,
+            ..Config::default()
+        };
+        let mut app = App::with_backend(config, tmux);
+        settle(&mut app);
+
+        let lines: Vec<&str> = app.tree_lines.iter().map(|l| l.content.as_str()).collect();
+        assert_eq!(lines, ["main", "+-- 0: editor", "'-- 1: shell"]);
+
+        app.config.tree_style = TreeStyle::None;
+        app.config.show_window_index = false;
+        app.rebuild_tree_view();
+        assert_eq!(app.tree_lines[2].content, "  shell");
+    }
+
+    #[test]
+    fn test_window_format() {
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor", "shell"])]));
+        tmux.set_user_option("@0", "@owner", "alice");
+        let config = Config {
+            window_format: "{name} ({@owner}) {command}".to_string(),
+            ..Config::default()
+        };
+        let mut app = App::with_backend(config, tmux);
+        settle(&mut app);
+
+        assert_eq!(app.tree_lines[1].content, "├── 0: editor (alice) zsh");
+        assert_eq!(app.tree_lines[2].content, "└── 1: shell () zsh");
+    }
+
+    #[test]
+    fn test_sort_windows_by_activity() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell", "logs"])]);
+        for (window, activity) in app.sessions[0].windows.iter_mut().zip([100, 300, 200]) {
+            window.activity = activity;
         }
+        app.rebuild_tree_view();
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(selected_window(&app), "shell");
+
+        press(&mut app, KeyCode::Char('A'));
+        let names: Vec<&str> = app.tree_lines[1..]
+            .iter()
+            .map(|l| l.window.as_ref().unwrap().name.as_str())
+            .collect();
+        assert_eq!(names, ["shell", "logs", "editor"]);
+        assert_eq!(selected_window(&app), "shell");
+
+        // Moving is disabled while the order comes from activity
+        press(&mut app, KeyCode::Char('J'));
+        assert_eq!(window_names(&tmux.sessions()), ["editor", "shell", "logs"]);
+
+        press(&mut app, KeyCode::Char('A'));
+        assert_eq!(app.tree_lines[1].window.as_ref().unwrap().name, "editor");
+    }
+
+    #[test]
+    fn test_quick_search_filters() {
+        let (mut app, _) = app_with(&[("main", &["editor", "shell"]), ("work", &["api"])]);
+        app.sessions[0].windows[0].command = "nvim".to_string();
+        app.sessions[1].windows[0].command = "nvim".to_string();
+        app.rebuild_tree_view();
+
+        press(&mut app, KeyCode::Char('/'));
+        for c in "cmd:vim".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        let names = |app: &App| -> Vec<String> {
+            app.quick_search_results
+                .iter()
+                .map(|&i| app.tree_lines[i].content.clone())
+                .collect()
+        };
+        assert_eq!(names(&app).len(), 2);
+
+        for c in " session:work".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        assert_eq!(names(&app), ["└── 0: api"]);
+
+        // Sessions only show up for session predicates and text
+        app.quick_search_query.set("session:work");
+        app.update_quick_search_results();
+        assert_eq!(names(&app), ["work", "└── 0: api"]);
+    }
+
+    #[test]
+    fn test_cycle_views() {
+        let tmux = Arc::new(FakeTmux::with_sessions(&[
+            ("main", &["editor", "shell"]),
+            ("work", &["api", "db"]),
+        ]));
+        let config = Config {
+            views: vec![
+                ViewConfig {
+                    name: "work only".to_string(),
+                    filter: "session:work".to_string(),
+                    sort: None,
+                },
+                ViewConfig {
+                    name: "shells".to_string(),
+                    filter: "cmd:zsh sh".to_string(),
+                    sort: Some(ViewSort::Activity),
+                },
+            ],
+            ..Config::default()
+        };
+        let mut app = App::with_backend(config, tmux);
+        settle(&mut app);
+        let lines = |app: &App| -> Vec<String> {
+            app.tree_lines.iter().map(|l| l.content.clone()).collect()
+        };
+
+        press(&mut app, KeyCode::Char('V'));
+        assert_eq!(app.active_view().unwrap().name, "work only");
+        assert_eq!(lines(&app), ["work", "├── 0: api", "└── 1: db"]);
+        assert_eq!(selected_window(&app), "api");
+
+        press(&mut app, KeyCode::Char('V'));
+        assert_eq!(lines(&app), ["main", "└── 1: shell"]);
+        assert!(app.sort_by_activity);
+
+        press(&mut app, KeyCode::Char('V'));
+        assert!(app.active_view().is_none());
+        assert_eq!(app.tree_lines.len(), 6);
+    }
+
+    #[test]
+    fn test_close_other_windows() {
+        let (mut app, tmux) =
+            app_with(&[("main", &["editor", "shell", "logs"]), ("work", &["api"])]);
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('X'));
+        assert_eq!(app.mode, Mode::DeleteConfirm);
+        assert_eq!(app.popup_input, "Close 2 other windows in 'main'?");
+
+        press(&mut app, KeyCode::Char('y'));
+        let sessions = tmux.sessions();
+        assert_eq!(window_names(&sessions), ["shell"]);
+        assert_eq!(sessions[1].windows.len(), 1);
+        assert_eq!(selected_window(&app), "shell");
+
+        // Nothing left to close
+        press(&mut app, KeyCode::Char('X'));
+        assert_eq!(app.mode, Mode::Window);
+    }
+
+    #[test]
+    fn test_prune_idle_sessions() {
+        let tmux = Arc::new(FakeTmux::with_sessions(&[
+            ("main", &["editor"]),
+            ("old", &["shell"]),
+            ("fresh", &["shell"]),
+        ]));
+        // Attached sessions are kept no matter how idle
+        tmux.set_session_activity("fresh", chrono::Local::now().timestamp());
+        let mut app = App::with_backend(Config::default(), tmux.clone());
+        settle(&mut app);
+
+        press(&mut app, KeyCode::Char('S'));
+        press(&mut app, KeyCode::Char('P'));
+        assert_eq!(app.popup_input, "Kill 1 session idle for 7d?");
+        assert_eq!(app.pending_delete().unwrap().sessions, ["old"]);
+
+        press(&mut app, KeyCode::Char('y'));
+        let names: Vec<String> = tmux.sessions().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["main", "fresh"]);
+        assert_eq!(app.mode, Mode::Session);
+    }
+
+    #[test]
+    fn test_delete_marked_items() {
+        let (mut app, tmux) = app_with(&[
+            ("main", &["editor", "shell", "logs"]),
+            ("work", &["api"]),
+            ("scratch", &["tmp", "notes"]),
+        ]);
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char(' '));
+        assert_eq!(app.marked_windows.len(), 2);
+
+        // Marks survive switching to session mode, where sessions get marked
+        press(&mut app, KeyCode::Char('S'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char(' '));
+        assert!(app.marked_sessions.contains("scratch"));
+
+        press(&mut app, KeyCode::Char('x'));
+        assert_eq!(app.popup_input, "Delete 1 session and 2 windows?");
+        let items = app.pending_delete().unwrap();
+        assert_eq!(items.sessions, ["scratch"]);
+        assert_eq!(items.windows.len(), 2);
+
+        press(&mut app, KeyCode::Char('y'));
+        let sessions = tmux.sessions();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(window_names(&sessions), ["shell"]);
+        assert!(!app.has_marks());
+    }
+
+    #[test]
+    fn test_delete_marked_reports_each_failure() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell", "logs"])]);
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Char('x'));
+
+        tmux.fail_commands(true);
+        press(&mut app, KeyCode::Char('y'));
+        let errors = app
+            .messages
+            .entries()
+            .iter()
+            .filter(|m| m.level == Level::Error && m.text.starts_with("Failed to delete window"))
+            .count();
+        assert_eq!(errors, 2);
+
+        // Nothing was killed, so they are back once tmux answers again
+        tmux.fail_commands(false);
+        app.refresh_sessions(Focus::Keep);
+        settle(&mut app);
+        assert_eq!(window_names(&app.sessions), ["editor", "shell", "logs"]);
+    }
+
+    #[test]
+    fn test_move_window_across_sessions() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell"]), ("work", &["api", "db"])]);
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('J'));
+        assert_eq!(app.mode, Mode::MoveConfirm);
+        assert_eq!(app.pending_move.as_ref().unwrap().target_session, "work");
+
+        press(&mut app, KeyCode::Char('y'));
+        let sessions = tmux.sessions();
+        assert_eq!(window_names(&sessions), ["editor"]);
+        let work: Vec<(&str, u32)> = sessions[1]
+            .windows
+            .iter()
+            .map(|w| (w.name.as_str(), w.index))
+            .collect();
+        assert_eq!(work, [("shell", 0), ("api", 1), ("db", 2)]);
+        assert_eq!(selected_window(&app), "shell");
+        assert_eq!(
+            app.tree_lines[app.selected_index].session_name.as_deref(),
+            Some("work")
+        );
+
+        // Back up to the end of main, declining first
+        press(&mut app, KeyCode::Char('K'));
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(tmux.sessions()[1].windows.len(), 3);
+        press(&mut app, KeyCode::Char('K'));
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(window_names(&tmux.sessions()), ["editor", "shell"]);
+    }
+
+    #[test]
+    fn test_link_and_unlink_window() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell"]), ("work", &["api"])]);
+        press(&mut app, KeyCode::Char('L'));
+        assert_eq!(app.mode, Mode::Link);
+        for c in "nope".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(tmux.sessions()[1].windows.len(), 1);
+
+        press(&mut app, KeyCode::Char('L'));
+        for c in "work".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::Window);
+        let sessions = tmux.sessions();
+        assert_eq!(sessions[1].windows[1].name, "editor");
+        assert!(sessions[0].windows[0].linked && sessions[1].windows[1].linked);
+        assert!(app.sessions[1].windows[1].linked);
+
+        // Renaming one copy renames the window everywhere
+        press(&mut app, KeyCode::Char('r'));
+        press(&mut app, KeyCode::Backspace);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(tmux.sessions()[1].windows[1].name, "edito");
+
+        // Unlinking from main keeps it in work, unlinking again is refused
+        press(&mut app, KeyCode::Char('U'));
+        let sessions = tmux.sessions();
+        assert_eq!(window_names(&sessions), ["shell"]);
+        assert!(!sessions[1].windows[1].linked);
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('U'));
+        assert_eq!(tmux.sessions()[1].windows.len(), 2);
+    }
+
+    #[test]
+    fn test_break_out_window() {
+        let (mut app, tmux) = app_with(&[
+            ("main", &["editor", "shell"]),
+            ("shell", &["api"]),
+            ("solo", &["notes"]),
+        ]);
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('B'));
+        let sessions = tmux.sessions();
+        assert_eq!(window_names(&sessions), ["editor"]);
+        assert_eq!(sessions[3].name, "shell-2");
+        assert_eq!(sessions[3].windows[0].name, "shell");
+        assert_eq!(tmux.current_session().as_deref(), Some("shell-2"));
+        assert!(app.should_quit);
+
+        // A session's only window takes the session along
+        app.should_quit = false;
+        press(&mut app, KeyCode::Char('G'));
+        assert_eq!(selected_window(&app), "shell");
+        press(&mut app, KeyCode::Char('k'));
+        assert_eq!(selected_window(&app), "notes");
+        press(&mut app, KeyCode::Char('B'));
+        let names: Vec<String> = tmux.sessions().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["main", "shell", "shell-2", "notes"]);
+    }
+
+    #[test]
+    fn test_scratch_session() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell", "notes"])]);
+        app.config.scratch.session = "pad".to_string();
+
+        // The first window sent over creates the scratch session
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('Z'));
+        press(&mut app, KeyCode::Char('Z'));
+        let sessions = tmux.sessions();
+        assert_eq!(window_names(&sessions), ["editor"]);
+        let pad: Vec<&str> = sessions[1]
+            .windows
+            .iter()
+            .map(|w| w.name.as_str())
+            .collect();
+        assert_eq!(
+            (sessions[1].name.as_str(), pad),
+            ("pad", vec!["shell", "notes"])
+        );
+
+        press(&mut app, KeyCode::Char('z'));
+        let popups = tmux.popups();
+        assert_eq!(popups.len(), 1);
+        assert!(popups[0].1.ends_with("new-session -A -s 'pad'"));
+    }
+
+    #[test]
+    fn test_edit_session_environment() {
+        let (mut app, tmux) = app_with(&[("main", &["editor"]), ("work", &["api"])]);
+        tmux.set_environment("work", "DISPLAY", Some(":0")).unwrap();
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('E'));
+        assert_eq!(app.mode, Mode::Environment);
+        assert_eq!(app.env_session, "work");
+        assert_eq!(app.env_vars[0].value.as_deref(), Some(":0"));
+
+        // Edit the selected value, then add one
+        press(&mut app, KeyCode::Char('e'));
+        press(&mut app, KeyCode::Backspace);
+        press(&mut app, KeyCode::Char('1'));
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('a'));
+        for c in "SSH_AUTH_SOCK=/tmp/agent".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        let vars: Vec<(&str, Option<&str>)> = app
+            .env_vars
+            .iter()
+            .map(|v| (v.name.as_str(), v.value.as_deref()))
+            .collect();
+        assert_eq!(
+            vars,
+            [
+                ("DISPLAY", Some(":1")),
+                ("SSH_AUTH_SOCK", Some("/tmp/agent"))
+            ]
+        );
+
+        press(&mut app, KeyCode::Char('x'));
+        assert_eq!(tmux.session_environment("work").unwrap().len(), 1);
+        assert!(tmux.session_environment("main").unwrap().is_empty());
+
+        press(&mut app, KeyCode::Char('q'));
+        assert_eq!(app.mode, Mode::Window);
+    }
+
+    #[test]
+    fn test_shell_command() {
+        let (mut app, tmux) = app_with(&[("main", &["editor"]), ("work", &["api", "db"])]);
+
+        // The selection is in the environment, the status and output end up in the log
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('|'));
+        assert_eq!(app.mode, Mode::Shell);
+        app.popup_input
+            .set("echo $PORTAL_SESSION:$PORTAL_WINDOW in $PORTAL_PATH");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::Window);
+        settle(&mut app);
+        let last = app.messages.entries().last().unwrap();
+        assert_eq!(last.level, Level::Info);
+        assert_eq!(
+            last.text,
+            "`echo $PORTAL_SESSION:$PORTAL_WINDOW in $PORTAL_PATH` exited with 0: work:db in /tmp"
+        );
+
+        press(&mut app, KeyCode::Char('|'));
+        app.popup_input.set("exit 4");
+        press(&mut app, KeyCode::Enter);
+        settle(&mut app);
+        let last = app.messages.entries().last().unwrap();
+        assert_eq!(last.level, Level::Error);
+        assert_eq!(last.text, "`exit 4` exited with 4");
+
+        // Esc runs nothing
+        let logged = app.messages.entries().len();
+        press(&mut app, KeyCode::Char('|'));
+        app.popup_input.set("exit 5");
+        press(&mut app, KeyCode::Esc);
+        settle(&mut app);
+        assert_eq!(app.messages.entries().len(), logged);
+
+        // In a popup on request
+        app.config.shell_popup = true;
+        press(&mut app, KeyCode::Char('|'));
+        app.popup_input.set("make test");
+        press(&mut app, KeyCode::Enter);
+        settle(&mut app);
+        assert_eq!(
+            tmux.popups(),
+            vec![("/tmp".to_string(), "make test".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_notes() {
+        let (mut app, tmux) = app_with(&[("main", &["editor"]), ("work", &["api", "db"])]);
+
+        // Window note on the selected window
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('"'));
+        assert_eq!(app.mode, Mode::Note);
+        app.popup_input.set("migration half done");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            tmux.sessions()[1].windows[1].note.as_deref(),
+            Some("migration half done")
+        );
+        assert_eq!(tmux.sessions()[1].note, None);
+
+        // Session note in session mode, kept apart from window notes
+        press(&mut app, KeyCode::Char('S'));
+        press(&mut app, KeyCode::Char('g'));
+        press(&mut app, KeyCode::Char('"'));
+        assert_eq!(app.popup_input, "");
+        app.popup_input.set("release");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(tmux.sessions()[0].note.as_deref(), Some("release"));
+        assert_eq!(tmux.sessions()[0].windows[0].note, None);
+        assert_eq!(app.mode, Mode::Session);
+        press(&mut app, KeyCode::Esc);
+
+        // Quick search finds windows by their note
+        press(&mut app, KeyCode::Char('/'));
+        for c in "migration".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        let first = app.quick_search_results[0];
+        assert_eq!(app.tree_lines[first].window.as_ref().unwrap().name, "db");
+        press(&mut app, KeyCode::Esc);
+
+        // Empty removes the note again
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('"'));
+        assert_eq!(app.popup_input, "migration half done");
+        app.popup_input.clear();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(tmux.sessions()[1].windows[1].note, None);
+    }
+
+    #[test]
+    fn test_session_start_dir() {
+        let (mut app, tmux) = app_with(&[("main", &["editor"])]);
+        let dir = std::env::temp_dir().display().to_string();
+
+        press(&mut app, KeyCode::Char('D'));
+        assert_eq!(app.mode, Mode::StartDir);
+        assert_eq!(app.popup_input, "/tmp");
+        app.popup_input.set(dir.clone());
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(tmux.sessions()[0].start_dir.as_deref(), Some(dir.as_str()));
+
+        // New windows start there
+        press(&mut app, KeyCode::Char('C'));
+        assert_eq!(tmux.sessions()[0].windows[1].path, dir);
+
+        press(&mut app, KeyCode::Char('D'));
+        app.popup_input.set("/no/such/dir");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(tmux.sessions()[0].start_dir.as_deref(), Some(dir.as_str()));
+        press(&mut app, KeyCode::Char('D'));
+        assert_eq!(app.popup_input, dir);
+        app.popup_input.clear();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(tmux.sessions()[0].start_dir, None);
+    }
+
+    #[test]
+    fn test_toggle_options() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell"])]);
+        // A session line edits its active window
+        press(&mut app, KeyCode::Char('O'));
+        assert_eq!(app.mode, Mode::Options);
+        assert_eq!(app.options_window.as_ref().unwrap().name, "editor");
+        assert_eq!(app.option_values, [false; 5]);
+
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.option_values, [true, false, false, false, true]);
+
+        // Window options are per window, session options shared
+        let window = &tmux.sessions()[0].windows[1];
+        let values = tmux
+            .read_options("main", &window.id, OPTION_TOGGLES)
+            .unwrap();
+        assert_eq!(values, [false, false, false, false, true]);
+
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.mode, Mode::Window);
+    }
+
+    #[test]
+    fn test_info_panel() {
+        let (mut app, tmux) = app_with(&[("main", &["editor"])]);
+        press(&mut app, KeyCode::Char('I'));
+        assert_eq!(app.mode, Mode::Info);
+        let version = app.info_checks.iter().find(|c| c.label == "server version");
+        assert_eq!(version.unwrap().value, "3.4");
+
+        tmux.fail_commands(true);
+        press(&mut app, KeyCode::Char('R'));
+        let server = app.info_checks.last().unwrap();
+        assert_eq!(server.status, doctor::Status::Error);
+
+        press(&mut app, KeyCode::Char('q'));
+        assert_eq!(app.mode, Mode::Window);
+    }
+
+    #[test]
+    fn test_old_tmux_disables_features() {
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor", "shell"])]));
+        tmux.set_version("3.1c");
+        let mut app = App::with_backend(Config::default(), tmux.clone());
+        settle(&mut app);
+        assert_eq!(app.tmux_version, Some((3, 1)));
+        assert!(!app.supports(Feature::Popup));
+
+        press(&mut app, KeyCode::Char('z'));
+        press(&mut app, KeyCode::Char('y'));
+        assert!(tmux.popups().is_empty());
+        assert_eq!(tmux.clipboard(), None);
+        let texts: Vec<&str> = app
+            .messages
+            .entries()
+            .iter()
+            .map(|m| m.text.as_str())
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "Popups need tmux 3.2 or newer",
+                "Copying to the clipboard needs tmux 3.2 or newer"
+            ]
+        );
+
+        // Moving into the scratch session is pointless without the popup
+        press(&mut app, KeyCode::Char('Z'));
+        assert_eq!(window_names(&tmux.sessions()), ["editor", "shell"]);
     }
 
-    fn move_to_bottom_session_mode(&mut self) {
-        // Find the last session
-        for (index, line) in self.tree_lines.iter().enumerate().rev() {
-            if line.line_type == LineType::Session {
-                self.selected_index = index;
-                break;
-            }
-        }
+    #[test]
+    fn test_base_index_one() {
+        let tmux = Arc::new(FakeTmux::with_sessions(&[(
+            "main",
+            &["editor", "shell", "logs"],
+        )]));
+        tmux.set_base_index("main", 1, true);
+        let mut app = App::with_backend(Config::default(), tmux.clone());
+        settle(&mut app);
+
+        // `:2` is the second window, not the third
+        press(&mut app, KeyCode::Char(':'));
+        press(&mut app, KeyCode::Char('2'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(selected_window(&app), "shell");
+
+        // Deleting closes the gap, so `:2` now is logs
+        press(&mut app, KeyCode::Char('k'));
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Char('x'));
+        press(&mut app, KeyCode::Char('y'));
+        let indices: Vec<u32> = app.sessions[0].windows.iter().map(|w| w.index).collect();
+        assert_eq!(indices, [1, 2]);
+        press(&mut app, KeyCode::Char(':'));
+        press(&mut app, KeyCode::Char('2'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(selected_window(&app), "logs");
+
+        press(&mut app, KeyCode::Char('C'));
+        assert_eq!(app.sessions[0].windows[2].index, 3);
     }
 
-    fn activate_selected_session(&mut self) -> Result<bool> {
-        if let Some(line) = self.tree_lines.get(self.selected_index) {
-            if let Some(session_name) = &line.session_name {
-                match switch_to_session(session_name) {
-                    Ok(_) => return Ok(true), // Exit the app after successful switch
-                    Err(e) => {
-                        self.error_message = Some(format!("Failed to switch to session: {e}"));
-                    }
-                }
-            }
-        }
-        Ok(false)
+    #[test]
+    fn test_session_staleness() {
+        let (mut app, tmux) = app_with(&[
+            ("main", &["editor"]),
+            ("work", &["api"]),
+            ("old", &["notes"]),
+        ]);
+        let now = 100 * 86400;
+        tmux.set_session_activity("main", now - 3600);
+        tmux.set_session_activity("work", now - 2 * 86400);
+        tmux.set_session_activity("old", now - 30 * 86400);
+        app.refresh_sessions(Focus::Keep);
+        settle(&mut app);
+
+        assert_eq!(app.session_staleness("main", now), Staleness::Fresh);
+        assert_eq!(app.session_staleness("work", now), Staleness::Idle);
+        assert_eq!(app.session_staleness("old", now), Staleness::Stale);
+
+        app.config.session_stale_after = "off".to_string();
+        assert_eq!(app.session_staleness("old", now), Staleness::Idle);
     }
 
-    fn start_delete_session_confirm(&mut self) {
-        if let Some(line) = self.tree_lines.get(self.selected_index) {
-            if let Some(session_name) = &line.session_name {
-                self.previous_mode = self.mode.clone();
-                self.mode = Mode::DeleteConfirm;
-                self.show_popup = true;
-                self.popup_input = format!("Delete session '{session_name}'? (y/N)");
-            }
-        }
+    #[test]
+    fn test_archive_and_restore_session() {
+        let (mut app, tmux) = app_with(&[("main", &["editor"]), ("work", &["api", "db"])]);
+        tmux.set_start_dir("work", Some("/src/work")).unwrap();
+        app.refresh_sessions(Focus::Keep);
+        settle(&mut app);
+
+        press(&mut app, KeyCode::Char('S'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('a'));
+        settle(&mut app);
+        assert_eq!(tmux.sessions().len(), 1);
+        assert_eq!(app.archive.len(), 1);
+        let archived = &app.archive[0];
+        assert_eq!(archived.name, "work");
+        assert_eq!(archived.start_dir.as_deref(), Some("/src/work"));
+        let names: Vec<&str> = archived.windows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, ["api", "db"]);
+
+        press(&mut app, KeyCode::Char('A'));
+        assert_eq!(app.mode, Mode::Archive);
+        press(&mut app, KeyCode::Enter);
+        settle(&mut app);
+        let sessions = tmux.sessions();
+        let work = sessions.iter().find(|s| s.name == "work").unwrap();
+        let names: Vec<&str> = work.windows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, ["api", "db"]);
+        assert_eq!(work.start_dir.as_deref(), Some("/src/work"));
+        assert!(app.archive.is_empty());
+
+        // A session of the same name blocks restoring, the entry stays
+        press(&mut app, KeyCode::Char('q'));
+        app.archive.push(ArchivedSession::capture(work, 0));
+        press(&mut app, KeyCode::Char('A'));
+        press(&mut app, KeyCode::Enter);
+        settle(&mut app);
+        assert_eq!(app.archive.len(), 1);
+        press(&mut app, KeyCode::Char('x'));
+        assert!(app.archive.is_empty());
     }
 
-    fn move_session_up(&mut self) -> Result<()> {
-        // Ensure we're on a session line
-        if let Some(current_line) = self.tree_lines.get(self.selected_index) {
-            if current_line.line_type != LineType::Session {
-                return Ok(());
-            }
-        } else {
-            return Ok(());
-        }
+    fn mouse(app: &mut App, kind: MouseEventKind, row: u16) {
+        app.handle_mouse(MouseEvent {
+            kind,
+            column: 3,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+        settle(app);
+    }
 
-        // Find the previous session
-        let mut prev_session_index = None;
-        for i in (0..self.selected_index).rev() {
-            if self.tree_lines[i].line_type == LineType::Session {
-                prev_session_index = Some(i);
-                break;
-            }
-        }
+    fn drag(app: &mut App, from: u16, to: u16) {
+        mouse(app, MouseEventKind::Down(MouseButton::Left), from);
+        mouse(app, MouseEventKind::Drag(MouseButton::Left), to);
+        mouse(app, MouseEventKind::Up(MouseButton::Left), to);
+    }
 
-        if let Some(prev_idx) = prev_session_index {
-            // Get session names
-            let current_session = self.tree_lines[self.selected_index]
-                .session_name
-                .as_ref()
-                .unwrap()
-                .clone();
-            let prev_session = self.tree_lines[prev_idx]
-                .session_name
-                .as_ref()
-                .unwrap()
-                .clone();
+    #[test]
+    fn test_drag_windows_with_mouse() {
+        let (mut app, tmux) =
+            app_with(&[("main", &["editor", "shell", "logs"]), ("work", &["api"])]);
+        app.tree_columns = vec![(Rect::new(0, 0, 40, 20), 0)];
+
+        mouse(&mut app, MouseEventKind::Down(MouseButton::Left), 2);
+        mouse(&mut app, MouseEventKind::Up(MouseButton::Left), 2);
+        assert_eq!(selected_window(&app), "shell");
+
+        // logs above editor
+        drag(&mut app, 3, 1);
+        assert_eq!(window_names(&tmux.sessions()), ["logs", "editor", "shell"]);
+        assert_eq!(selected_window(&app), "logs");
+
+        // shell onto the work session line
+        drag(&mut app, 3, 4);
+        let sessions = tmux.sessions();
+        assert_eq!(window_names(&sessions), ["logs", "editor"]);
+        let work: Vec<&str> = sessions[1]
+            .windows
+            .iter()
+            .map(|w| w.name.as_str())
+            .collect();
+        assert_eq!(work, ["shell", "api"]);
 
-            // Swap sessions in our local list
-            let current_idx = self
-                .sessions
-                .iter()
-                .position(|s| s.name == current_session)
-                .unwrap();
-            let prev_session_idx = self
-                .sessions
-                .iter()
-                .position(|s| s.name == prev_session)
-                .unwrap();
+        // Dropped outside the tree, nothing happens
+        drag(&mut app, 1, 30);
+        assert_eq!(window_names(&tmux.sessions()), ["logs", "editor"]);
+        assert_eq!(selected_window(&app), "logs");
+    }
 
-            self.sessions.swap(current_idx, prev_session_idx);
+    #[test]
+    fn test_cycle_line_numbers() {
+        let (mut app, _) = app_with(&[("main", &["editor", "shell"]), ("work", &["api"])]);
+        let api = app.find_session_index(Some("work")).unwrap() + 1;
+        let ctrl_n = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        assert_eq!(app.get_window_line_numbers()[&api], 2);
 
-            // Rebuild tree view and position on the moved session
-            self.rebuild_tree_view();
+        app.handle_key(ctrl_n).unwrap();
+        assert_eq!(app.line_number_mode, LineNumberMode::Absolute);
+        assert_eq!(app.get_window_line_numbers()[&api], 3);
 
-            // Find the session that was moved and position on it
-            for (index, line) in self.tree_lines.iter().enumerate() {
-                if line.line_type == LineType::Session
-                    && line.session_name.as_ref() == Some(&current_session)
-                {
-                    self.selected_index = index;
-                    break;
-                }
-            }
-        }
+        app.handle_key(ctrl_n).unwrap();
+        assert!(app.get_window_line_numbers().is_empty());
 
-        Ok(())
+        app.handle_key(ctrl_n).unwrap();
+        assert_eq!(app.line_number_mode, LineNumberMode::Relative);
     }
 
-    fn move_session_down(&mut self) -> Result<()> {
-        // Ensure we're on a session line
-        if let Some(current_line) = self.tree_lines.get(self.selected_index) {
-            if current_line.line_type != LineType::Session {
-                return Ok(());
-            }
-        } else {
-            return Ok(());
+    #[test]
+    fn test_jump_to_window_index() {
+        let (mut app, tmux) =
+            app_with(&[("main", &["editor", "shell", "logs"]), ("work", &["api"])]);
+
+        // Indices follow the windows' positions, not the order of the swap
+        press(&mut app, KeyCode::Char('J'));
+        for key in [':', '2'] {
+            press(&mut app, KeyCode::Char(key));
         }
+        press(&mut app, KeyCode::Enter);
 
-        // Find the next session
-        let mut next_session_index = None;
-        for i in (self.selected_index + 1)..self.tree_lines.len() {
-            if self.tree_lines[i].line_type == LineType::Session {
-                next_session_index = Some(i);
-                break;
-            }
+        assert_eq!(selected_window(&app), "logs");
+        assert!(tmux.sessions()[0].windows[2].active);
+        assert_eq!(app.index_jump, None);
+
+        for key in [':', '7'] {
+            press(&mut app, KeyCode::Char(key));
         }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            app.messages.entries()[0].text,
+            "No window 7 in session 'main'"
+        );
+    }
 
-        if let Some(next_idx) = next_session_index {
-            // Get session names
-            let current_session = self.tree_lines[self.selected_index]
-                .session_name
-                .as_ref()
-                .unwrap()
-                .clone();
-            let next_session = self.tree_lines[next_idx]
-                .session_name
-                .as_ref()
-                .unwrap()
-                .clone();
+    #[test]
+    fn test_copy_target_and_path() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell"])]);
 
-            // Swap sessions in our local list
-            let current_idx = self
-                .sessions
-                .iter()
-                .position(|s| s.name == current_session)
-                .unwrap();
-            let next_session_idx = self
-                .sessions
-                .iter()
-                .position(|s| s.name == next_session)
-                .unwrap();
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(tmux.clipboard().as_deref(), Some("main:shell"));
 
-            self.sessions.swap(current_idx, next_session_idx);
+        press(&mut app, KeyCode::Char('Y'));
+        assert_eq!(tmux.clipboard().as_deref(), Some("/tmp"));
+        assert_eq!(app.messages.entries()[1].text, "Copied /tmp");
+    }
 
-            // Rebuild tree view and position on the moved session
-            self.rebuild_tree_view();
+    #[test]
+    fn test_open_action() {
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor"])]));
+        let config = Config {
+            open_actions: vec![
+                OpenActionConfig {
+                    key: 'e',
+                    name: "lazygit".to_string(),
+                    command: "lazygit".to_string(),
+                    target: OpenTarget::Window,
+                    exit: Some(false),
+                },
+                OpenActionConfig {
+                    key: 'o',
+                    name: "files".to_string(),
+                    command: "yazi {path}".to_string(),
+                    target: OpenTarget::Popup,
+                    exit: None,
+                },
+            ],
+            ..Config::default()
+        };
+        let mut app = App::with_backend(config, tmux.clone());
+        settle(&mut app);
+
+        press(&mut app, KeyCode::Char('e'));
+        assert_eq!(window_names(&tmux.sessions()), ["editor", "lazygit"]);
+        assert_eq!(window_names(&app.sessions), ["editor", "lazygit"]);
+        assert!(!app.should_quit);
+
+        press(&mut app, KeyCode::Char('o'));
+        assert_eq!(
+            tmux.popups(),
+            [("/tmp".to_string(), "yazi '/tmp'".to_string())]
+        );
+        assert!(app.should_quit);
+    }
 
-            // Find the session that was moved and position on it
-            for (index, line) in self.tree_lines.iter().enumerate() {
-                if line.line_type == LineType::Session
-                    && line.session_name.as_ref() == Some(&current_session)
-                {
-                    self.selected_index = index;
-                    break;
-                }
-            }
+    #[test]
+    fn test_history_size_from_config() {
+        let (mut app, _) = app_with(&[("main", &["editor", "shell", "logs"])]);
+        app.config.exit_on_switch = false;
+        app.config.history_size = 2;
+
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Enter);
+            press(&mut app, KeyCode::Char('j'));
         }
 
-        Ok(())
+        let names: Vec<&str> = app.history.iter().map(|h| h.window_name.as_str()).collect();
+        assert_eq!(names, ["logs", "shell"]);
     }
 
-    fn create_new_window(&mut self) -> Result<()> {
-        // Get the current session name
-        let current_session = match get_current_session_name() {
-            Ok(Some(session_name)) => session_name,
-            _ => {
-                // If we can't get the current session, check if there's a selected session
-                if let Some(line) = self.tree_lines.get(self.selected_index) {
-                    if let Some(session_name) = &line.session_name {
-                        session_name.clone()
-                    } else {
-                        self.error_message = Some("No session selected".to_string());
-                        return Ok(());
-                    }
-                } else {
-                    self.error_message = Some("No session selected".to_string());
-                    return Ok(());
-                }
-            }
+    #[test]
+    fn test_jumplist_walks_history() {
+        let (mut app, _) = app_with(&[("main", &["editor", "shell"]), ("work", &["api"])]);
+        app.config.exit_on_switch = false;
+
+        // Visit editor, then api, then shell
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Enter);
+        app.selected_index = app
+            .find_window_index_by_id(&app.sessions[0].windows[1].id)
+            .unwrap();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(selected_window(&app), "shell");
+
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        app.handle_key(ctrl('o')).unwrap();
+        assert_eq!(selected_window(&app), "api");
+        app.handle_key(ctrl('o')).unwrap();
+        assert_eq!(selected_window(&app), "editor");
+        app.handle_key(ctrl('o')).unwrap();
+        assert_eq!(selected_window(&app), "editor");
+
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(selected_window(&app), "api");
+        app.handle_key(ctrl('i')).unwrap();
+        assert_eq!(selected_window(&app), "shell");
+    }
+
+    #[test]
+    fn test_switch_and_zoom() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "logs"])]);
+
+        // Enter leaves the pane as it is, Ctrl-f zooms it
+        press(&mut app, KeyCode::Enter);
+        settle(&mut app);
+        assert!(!tmux.sessions()[0].windows[0].zoomed);
+        press(&mut app, KeyCode::Char('j'));
+        app.handle_key(KeyEvent::new(KeyCode::Char('f'), KeyModifiers::CONTROL))
+            .unwrap();
+        settle(&mut app);
+        let windows = &tmux.sessions()[0].windows;
+        assert!(windows[1].active && windows[1].zoomed);
+
+        // With zoom_on_switch every switch zooms
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor", "logs"])]));
+        let config = Config {
+            zoom_on_switch: true,
+            ..Config::default()
         };
+        let mut app = App::with_backend(config, tmux.clone());
+        settle(&mut app);
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Enter);
+        settle(&mut app);
+        assert!(tmux.sessions()[0].windows[1].zoomed);
+    }
 
-        // Create a new window in the session
-        if let Err(e) = crate::tmux::create_new_window(&current_session) {
-            self.error_message = Some(format!("Failed to create new window: {e}"));
-            return Ok(());
-        }
+    #[test]
+    fn test_move_to_index() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell", "logs"])]);
+        let indices = |tmux: &FakeTmux| -> Vec<(String, u32)> {
+            tmux.sessions()[0]
+                .windows
+                .iter()
+                .map(|w| (w.name.clone(), w.index))
+                .collect()
+        };
 
-        // Refresh sessions to get the new window
-        self.refresh_sessions()?;
+        // A free index is taken as is
+        press(&mut app, KeyCode::Char('='));
+        assert_eq!(app.mode, Mode::MoveIndex);
+        assert_eq!(&*app.popup_input, "0");
+        app.popup_input.set("7");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            indices(&tmux),
+            [
+                ("shell".into(), 1),
+                ("logs".into(), 2),
+                ("editor".into(), 7)
+            ]
+        );
+        assert_eq!(selected_window(&app), "editor");
+
+        // An occupied one shifts its window and the later ones up
+        press(&mut app, KeyCode::Char('='));
+        app.popup_input.set("1");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            indices(&tmux),
+            [
+                ("editor".into(), 1),
+                ("shell".into(), 2),
+                ("logs".into(), 3)
+            ]
+        );
+
+        press(&mut app, KeyCode::Char('='));
+        app.popup_input.set("first");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::Window);
+        let last = app.messages.entries().last().unwrap();
+        assert!(last.text.contains("Not a window index"));
+
+        // Or trades places with it
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor", "shell"])]));
+        let config = Config {
+            occupied_index: OccupiedIndex::Swap,
+            ..Config::default()
+        };
+        let mut app = App::with_backend(config, tmux.clone());
+        settle(&mut app);
+        press(&mut app, KeyCode::Char('='));
+        app.popup_input.set("1");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(indices(&tmux), [("shell".into(), 0), ("editor".into(), 1)]);
+    }
 
-        // Find the newly created window (should be the last window in the session)
-        // and position the cursor on it
-        let mut last_window_index = None;
-        for (i, line) in self.tree_lines.iter().enumerate().rev() {
-            if line.line_type == LineType::Window
-                && line.session_name.as_ref() == Some(&current_session)
-            {
-                last_window_index = Some(i);
-                break;
-            }
-        }
+    #[test]
+    fn test_pin_overlay() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell"])]);
+
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('@'));
+        assert_eq!(app.pin_targets(), [(2, "main:shell".to_string())]);
+
+        press(&mut app, KeyCode::Char('\''));
+        assert!(app.show_pin_overlay);
+        press(&mut app, KeyCode::Char('k'));
+        assert!(!app.show_pin_overlay);
+        assert_eq!(selected_window(&app), "shell");
+
+        press(&mut app, KeyCode::Char('\''));
+        press(&mut app, KeyCode::Char('2'));
+        assert!(!app.show_pin_overlay);
+        assert!(tmux.sessions()[0].windows[1].active);
+
+        // Every Shift+digit pins, none is taken by a binding
+        press(&mut app, KeyCode::Char('k'));
+        press(&mut app, KeyCode::Char('#'));
+        assert_eq!(
+            app.pin_targets(),
+            [
+                (2, "main:shell".to_string()),
+                (3, "main:editor".to_string())
+            ]
+        );
+    }
 
-        if let Some(index) = last_window_index {
-            self.selected_index = index;
+    #[test]
+    fn test_cycle_session_recent_windows() {
+        let (mut app, _) = app_with(&[("main", &["editor", "shell", "logs"]), ("work", &["api"])]);
+        app.config.exit_on_switch = false;
+
+        // Visit logs, api, then editor
+        for (session, window) in [(0, 2), (1, 0), (0, 0)] {
+            let id = app.sessions[session].windows[window].id.clone();
+            app.selected_index = app.find_window_index_by_id(&id).unwrap();
+            press(&mut app, KeyCode::Enter);
         }
+        assert_eq!(selected_window(&app), "editor");
 
-        Ok(())
+        press(&mut app, KeyCode::Char('w'));
+        assert_eq!(selected_window(&app), "logs");
+        press(&mut app, KeyCode::Char('w'));
+        assert_eq!(selected_window(&app), "editor");
     }
 
-    fn add_to_history(&mut self, session_name: &str, window_id: &str) {
-        let entry = (session_name.to_string(), window_id.to_string());
-        
-        // Remove if already exists
-        self.history.retain(|h| h != &entry);
-        
-        // Add to front
-        self.history.insert(0, entry);
-        
-        // Keep only last 10
-        self.history.truncate(10);
-        
-        // Save to disk
-        let _ = Self::save_history(&self.history);
+    #[test]
+    fn test_history_drops_closed_windows() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell"])]);
+        app.config.exit_on_switch = false;
+
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.history.len(), 2);
+
+        // Closed and renamed outside the portal
+        let editor = tmux.sessions()[0].windows[0].id.clone();
+        let shell = tmux.sessions()[0].windows[1].id.clone();
+        tmux.delete_window("main", &editor).unwrap();
+        tmux.rename_window("main", &shell, "build").unwrap();
+        press(&mut app, KeyCode::Char('R'));
+
+        assert_eq!(app.history.len(), 1);
+        assert_eq!(app.history[0].window_name, "build");
     }
 
-    fn load_history() -> Result<Vec<(String, String)>> {
-        let path = get_history_path()?;
-        if !path.exists() {
-            return Ok(Vec::new());
-        }
-        let content = std::fs::read_to_string(path)?;
-        let history = serde_json::from_str(&content)?;
-        Ok(history)
+    #[test]
+    fn test_failed_command_is_reported() {
+        let (mut app, tmux) = app_with(&[("main", &["editor", "shell"])]);
+        tmux.fail_commands(true);
+
+        press(&mut app, KeyCode::Char('x'));
+        press(&mut app, KeyCode::Char('y'));
+
+        let errors: Vec<&str> = app
+            .messages
+            .entries()
+            .iter()
+            .filter(|m| m.level == Level::Error)
+            .map(|m| m.text.as_str())
+            .collect();
+        assert!(errors[0].starts_with("Failed to delete window"));
+        assert_eq!(app.sessions[0].windows.len(), 2);
     }
 
-    fn save_history(history: &[(String, String)]) -> Result<()> {
-        let path = get_history_path()?;
-        let content = serde_json::to_string(history)?;
-        std::fs::write(path, content)?;
-        Ok(())
+    #[test]
+    fn test_rebound_keys() {
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor", "shell"])]));
+        let mut config = Config::default();
+        config
+            .keys
+            .window
+            .insert("down".to_string(), KeySpec::One("n".to_string()));
+        config
+            .keys
+            .session
+            .insert("nope".to_string(), KeySpec::One("n".to_string()));
+        let mut app = App::with_backend(config, tmux);
+        settle(&mut app);
+        assert_eq!(
+            app.messages.entries()[0].text,
+            "Unknown action 'nope' in keys.session"
+        );
+
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(selected_window(&app), "editor");
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(selected_window(&app), "shell");
     }
 
-    fn load_pins() -> Result<HashMap<u8, (String, String)>> {
-        let path = get_pins_path()?;
-        if !path.exists() {
-            return Ok(HashMap::new());
+    #[test]
+    fn test_record_and_replay_macro() {
+        let (mut app, _tmux) = app_with(&[("main", &["editor"])]);
+
+        // Create a window and rename it
+        press(&mut app, KeyCode::Char('Q'));
+        assert!(app.is_recording());
+        press(&mut app, KeyCode::Char('C'));
+        press(&mut app, KeyCode::Char('r'));
+        press(&mut app, KeyCode::Char('!'));
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('Q'));
+        assert!(!app.is_recording());
+        let first = window_names(&app.sessions).last().unwrap().to_string();
+        assert!(first.ends_with('!'));
+
+        // The rename waits for the replayed window to exist
+        app.handle_key(KeyEvent::new(KeyCode::Char('m'), KeyModifiers::NONE))
+            .unwrap();
+        assert!(app.is_replaying());
+        settle(&mut app);
+        assert_eq!(app.sessions[0].windows.len(), 3);
+        assert_eq!(window_names(&app.sessions)[1], first);
+        assert!(window_names(&app.sessions)[2].ends_with('!'));
+        assert_eq!(app.macro_keys.len(), 4);
+    }
+
+    #[test]
+    fn test_repeat_last_change() {
+        let (mut app, _tmux) = app_with(&[("main", &["a", "b", "c", "d", "e"])]);
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(app.messages.entries()[0].text, "Nothing to repeat yet");
+
+        // Renames repeat as a pattern of the old name
+        press(&mut app, KeyCode::Char('r'));
+        for c in "-old".chars() {
+            press(&mut app, KeyCode::Char(c));
         }
-        let content = std::fs::read_to_string(path)?;
-        let pins = serde_json::from_str(&content)?;
-        Ok(pins)
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(
+            window_names(&app.sessions),
+            ["a-old", "b-old", "c", "d", "e"]
+        );
+
+        // Moves go the same direction again
+        press(&mut app, KeyCode::Char('J'));
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(
+            window_names(&app.sessions),
+            ["a-old", "c", "d", "b-old", "e"]
+        );
+        assert_eq!(selected_window(&app), "b-old");
+
+        // Deletes skip the confirmation the second time
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('x'));
+        press(&mut app, KeyCode::Char('y'));
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(window_names(&app.sessions), ["a-old", "c", "d"]);
+        assert_eq!(app.mode, Mode::Window);
     }
 
-    fn save_pins(&self) -> Result<()> {
-        let path = get_pins_path()?;
-        let content = serde_json::to_string(&self.pins)?;
-        std::fs::write(path, content)?;
-        Ok(())
+    #[test]
+    fn test_repeat_delete_attached_session() {
+        let (mut app, tmux) = app_with(&[("main", &["a"]), ("work", &["b"]), ("old", &["c"])]);
+        let session_names = |tmux: &FakeTmux| {
+            tmux.list_sessions()
+                .unwrap()
+                .into_iter()
+                .map(|s| s.name)
+                .collect::<Vec<_>>()
+        };
+        press(&mut app, KeyCode::Char('S'));
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('x'));
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(session_names(&tmux), ["main", "work"]);
+
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(session_names(&tmux), ["main"]);
+
+        // The attached session asks again instead
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(app.mode, Mode::DeleteConfirm);
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(session_names(&tmux), ["main"]);
+        assert_eq!(app.mode, Mode::Session);
     }
 
-    fn toggle_pin(&mut self, slot: u8) {
-        let Some(line) = self.tree_lines.get(self.selected_index) else { return };
-        let Some(window) = &line.window else { return };
+    #[test]
+    fn test_confirm_buttons() {
+        let (mut app, _tmux) = app_with(&[("main", &["a", "b", "c"])]);
+
+        // Enter presses the focused button, No at first
+        press(&mut app, KeyCode::Char('x'));
+        assert_eq!(app.confirm_button, ConfirmButton::No);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::Window);
+        assert_eq!(window_names(&app.sessions), ["a", "b", "c"]);
+
+        press(&mut app, KeyCode::Char('x'));
+        press(&mut app, KeyCode::Left);
+        assert_eq!(app.confirm_button, ConfirmButton::Yes);
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(window_names(&app.sessions), ["b", "c"]);
+
+        // The next prompt starts on No again
+        press(&mut app, KeyCode::Char('x'));
+        assert_eq!(app.confirm_button, ConfirmButton::No);
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(window_names(&app.sessions), ["c"]);
+    }
 
-        let entry = (window.session_name.clone(), window.id.clone());
+    #[test]
+    fn test_create_session_from_search_query() {
+        let (mut app, tmux) = app_with(&[("main", &["editor"])]);
 
-        // If this window already has this slot, remove it
-        if self.pins.get(&slot) == Some(&entry) {
-            self.pins.remove(&slot);
-            let _ = self.save_pins();
-            return;
+        press(&mut app, KeyCode::Char('F'));
+        for c in "notes.d".chars() {
+            press(&mut app, KeyCode::Char(c));
         }
+        assert!(app.search_results.is_empty());
+        press(&mut app, KeyCode::Enter);
+
+        let sessions = tmux.list_sessions().unwrap();
+        let created = sessions.iter().find(|s| s.name == "notes_d").unwrap();
+        assert_eq!(window_names(std::slice::from_ref(created)), ["notes_d"]);
+        assert_eq!(app.mode, Mode::Window);
+
+        // An empty query just closes the search
+        press(&mut app, KeyCode::Char('F'));
+        app.handle_key(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL))
+            .unwrap();
+        assert_eq!(app.mode, Mode::Search);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::Window);
+        assert_eq!(tmux.list_sessions().unwrap().len(), 2);
+    }
 
-        // Can only assign/steal if current window has no pin
-        if self.pins.values().any(|v| v == &entry) {
-            return;
+    #[test]
+    fn test_search_keys_follow_preset() {
+        let bookmark = |name: &str| BookmarkConfig {
+            name: name.to_string(),
+            path: "/tmp".to_string(),
+            session: None,
+            window: None,
+        };
+        let config = Config {
+            keymap: KeymapPreset::Emacs,
+            bookmarks: vec![bookmark("dotfiles"), bookmark("notes")],
+            ..Config::default()
+        };
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor", "shell"])]));
+        let mut app = App::with_backend(config, tmux.clone());
+        settle(&mut app);
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+
+        // C-n/C-p move through project search results, M-Enter creates a session
+        press(&mut app, KeyCode::Char('F'));
+        assert_eq!(app.search_results.len(), 2);
+        app.handle_key(ctrl('n')).unwrap();
+        assert_eq!(app.search_selected_index, 1);
+        app.handle_key(ctrl('p')).unwrap();
+        assert_eq!(app.search_selected_index, 0);
+        assert_eq!(tmux.sessions().len(), 1);
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT))
+            .unwrap();
+        assert_eq!(tmux.sessions().len(), 1); // Nothing typed to name it after
+        press(&mut app, KeyCode::Esc);
+
+        // ... and quick search results, where M-l cycles line numbers
+        press(&mut app, KeyCode::Char('/'));
+        press(&mut app, KeyCode::Char('e'));
+        assert_eq!(app.quick_search_results.len(), 2);
+        app.handle_key(ctrl('n')).unwrap();
+        assert_eq!(app.quick_search_selected_index, 1);
+        assert_eq!(app.quick_search_query, "e");
+        let numbers = app.line_number_mode;
+        app.handle_key(KeyEvent::new(KeyCode::Char('l'), KeyModifiers::ALT))
+            .unwrap();
+        assert_ne!(app.line_number_mode, numbers);
+    }
+
+    #[test]
+    fn test_group_search_results() {
+        let dir = tempfile::tempdir().unwrap();
+        for project in ["oss/ripgrep", "work/api", "work/web"] {
+            std::fs::create_dir_all(dir.path().join(project)).unwrap();
         }
+        let pattern = |name: &str| SearchPatternConfig {
+            name: name.to_string(),
+            paths: vec![dir.path().join(name).to_string_lossy().to_string()],
+            pattern: "{window}".to_string(),
+            ..SearchPatternConfig::default()
+        };
+        let config = Config {
+            search_patterns: vec![pattern("work"), pattern("oss")],
+            bookmarks: vec![BookmarkConfig {
+                name: "dotfiles".to_string(),
+                path: dir.path().to_string_lossy().to_string(),
+                session: None,
+                window: None,
+            }],
+            ..Config::default()
+        };
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor"])]));
+        let mut app = App::with_backend(config, tmux);
+        settle(&mut app);
+        press(&mut app, KeyCode::Char('F'));
+        let groups = |app: &App| {
+            app.search_results
+                .iter()
+                .map(|r| r.group.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(groups(&app), ["bookmarks", "work", "work", "oss"]);
+
+        // Tab folds the selected result's group and moves past it
+        app.search_selected_index = 1;
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(groups(&app), ["bookmarks", "oss"]);
+        assert_eq!(app.search_selected_index, 1);
+        assert_eq!(
+            app.search_groups,
+            [
+                ("bookmarks".to_string(), 1),
+                ("work".to_string(), 2),
+                ("oss".to_string(), 1)
+            ]
+        );
+
+        // Folded groups stay folded while typing, BackTab unfolds them
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::Backspace);
+        assert!(!groups(&app).contains(&"work".to_string()));
+        press(&mut app, KeyCode::BackTab);
+        assert!(groups(&app).contains(&"work".to_string()));
+    }
 
-        self.pins.insert(slot, entry);
-        let _ = self.save_pins();
+    #[test]
+    fn test_pattern_window_template() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("work/api")).unwrap();
+        let config = Config {
+            search_patterns: vec![SearchPatternConfig {
+                name: "editor".to_string(),
+                paths: vec![dir.path().to_string_lossy().to_string()],
+                pattern: "{session}/{window}".to_string(),
+                window_name: Some("edit".to_string()),
+                on_create: Some("nvim .".to_string()),
+                panes: vec!["cargo watch".to_string(), String::new()],
+                layout: Some("main-vertical".to_string()),
+                ..SearchPatternConfig::default()
+            }],
+            ..Config::default()
+        };
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor"])]));
+        let mut app = App::with_backend(config, tmux.clone());
+        settle(&mut app);
+        press(&mut app, KeyCode::Char('F'));
+        press(&mut app, KeyCode::Enter);
+        settle(&mut app);
+
+        let sessions = tmux.list_sessions().unwrap();
+        let work = sessions.iter().find(|s| s.name == "work").unwrap();
+        let window = &work.windows[0];
+        assert_eq!(window.name, "edit");
+        assert_eq!(window.command, "nvim .");
+        assert_eq!(window.panes, 3);
+        assert!(window.path.ends_with("work/api"));
     }
 
-    fn jump_to_pin(&mut self, slot: u8) -> Result<bool> {
-        if let Some((session_name, window_id)) = self.pins.get(&slot).cloned() {
-            match switch_to_window(&session_name, &window_id) {
-                Ok(_) => return Ok(true),
-                Err(e) => {
-                    self.error_message = Some(format!("Failed to switch: {e}"));
-                }
-            }
+    #[test]
+    fn test_choose_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        for project in ["api", "web", "cli"] {
+            std::fs::create_dir_all(dir.path().join("work").join(project)).unwrap();
         }
-        Ok(false)
+        let config = Config {
+            search_patterns: vec![SearchPatternConfig {
+                name: "work".to_string(),
+                paths: vec![dir.path().to_string_lossy().to_string()],
+                pattern: "{session}/{window}".to_string(),
+                panes: vec!["cargo watch".to_string()],
+                ..SearchPatternConfig::default()
+            }],
+            choose_layout: true,
+            exit_on_switch: false,
+            layouts: vec![LayoutConfig {
+                name: "three shells".to_string(),
+                panes: vec![String::new(), String::new()],
+                layout: Some("even-horizontal".to_string()),
+            }],
+            ..Config::default()
+        };
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor"])]));
+        let mut app = App::with_backend(config, tmux.clone());
+        settle(&mut app);
+        let ask = |app: &mut App, project: &str| {
+            press(app, KeyCode::Char('F'));
+            for c in project.chars() {
+                press(app, KeyCode::Char(c));
+            }
+            press(app, KeyCode::Enter);
+            app.pending_layout.clone()
+        };
+        let panes = |project: &str| {
+            let sessions = tmux.list_sessions().unwrap();
+            let work = sessions.iter().find(|s| s.name == "work")?;
+            work.windows
+                .iter()
+                .find(|w| w.name == project)
+                .map(|w| w.panes)
+        };
+
+        // Enter keeps the pattern's panes, digits pick one of the others
+        let result = ask(&mut app, "api").unwrap();
+        let choices = app.layout_choices(&result);
+        let names: Vec<&str> = choices.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "as configured (work)",
+                "single window",
+                "editor + shell",
+                "three shells"
+            ]
+        );
+        press(&mut app, KeyCode::Enter);
+        settle(&mut app);
+        assert_eq!(panes("api"), Some(2));
+
+        ask(&mut app, "web").unwrap();
+        press(&mut app, KeyCode::Char('2'));
+        settle(&mut app);
+        assert_eq!(panes("web"), Some(1));
+
+        // Esc opens nothing, a digit without a layout keeps asking
+        ask(&mut app, "cli").unwrap();
+        press(&mut app, KeyCode::Char('7'));
+        assert!(app.pending_layout.is_some());
+        press(&mut app, KeyCode::Esc);
+        settle(&mut app);
+        assert!(app.pending_layout.is_none());
+        assert_eq!(panes("cli"), None);
+
+        ask(&mut app, "cli").unwrap();
+        press(&mut app, KeyCode::Char('4'));
+        settle(&mut app);
+        assert_eq!(panes("cli"), Some(3));
+
+        // Existing windows open without asking
+        assert!(ask(&mut app, "api").is_none());
+        settle(&mut app);
     }
 
-    fn handle_delete_confirm_mode(&mut self, key: KeyEvent) -> Result<bool> {
-        match key.code {
-            KeyCode::Esc => {
-                self.mode = self.previous_mode.clone();
-                self.show_popup = false;
-                self.popup_input.clear();
-            }
-            KeyCode::Char('y') | KeyCode::Char('Y') => {
-                // Confirm deletion
-                if let Err(e) = self.confirm_delete() {
-                    self.error_message = Some(format!("Failed to delete: {e}"));
-                }
-                self.mode = self.previous_mode.clone();
-                self.show_popup = false;
-                self.popup_input.clear();
-            }
-            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Enter => {
-                // Cancel deletion
-                self.mode = self.previous_mode.clone();
-                self.show_popup = false;
-                self.popup_input.clear();
-            }
-            _ => {}
+    #[test]
+    fn test_session_collision() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = |session_collision| Config {
+            bookmarks: vec![BookmarkConfig {
+                name: "api".to_string(),
+                path: dir.path().join("api").to_string_lossy().to_string(),
+                session: None,
+                window: None,
+            }],
+            session_collision,
+            ..Config::default()
+        };
+        let session_names = |tmux: &FakeTmux| {
+            tmux.list_sessions()
+                .unwrap()
+                .into_iter()
+                .map(|s| s.name)
+                .collect::<Vec<_>>()
+        };
+
+        // Session "api" already has an "api" window, but in another directory
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("api", &["api"])]));
+        let mut app = App::with_backend(config(SessionCollision::Prompt), tmux.clone());
+        settle(&mut app);
+        press(&mut app, KeyCode::Char('F'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::CollisionConfirm);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.mode, Mode::Window);
+        assert!(app.pending_collision.is_none());
+
+        press(&mut app, KeyCode::Char('F'));
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('y'));
+        settle(&mut app);
+        assert_eq!(session_names(&tmux), ["api", "api-2"]);
+
+        // The suffixed session is the project's from now on
+        let mut app = App::with_backend(config(SessionCollision::Suffix), tmux.clone());
+        settle(&mut app);
+        press(&mut app, KeyCode::Char('F'));
+        press(&mut app, KeyCode::Enter);
+        settle(&mut app);
+        assert_eq!(session_names(&tmux), ["api", "api-2"]);
+        assert_eq!(
+            tmux.current_session_name().unwrap().as_deref(),
+            Some("api-2")
+        );
+
+        let mut app = App::with_backend(config(SessionCollision::Reuse), tmux.clone());
+        settle(&mut app);
+        press(&mut app, KeyCode::Char('F'));
+        press(&mut app, KeyCode::Enter);
+        settle(&mut app);
+        assert_eq!(tmux.current_session_name().unwrap().as_deref(), Some("api"));
+    }
+
+    #[test]
+    fn test_lazy_pattern_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        for project in ["cheap/api", "heavy/monorepo", "archive/old"] {
+            std::fs::create_dir_all(dir.path().join(project)).unwrap();
         }
-        Ok(false)
+        let pattern = |name: &str, scan_on_startup| SearchPatternConfig {
+            name: name.to_string(),
+            paths: vec![dir.path().join(name).to_string_lossy().to_string()],
+            pattern: "{window}".to_string(),
+            scan_on_startup,
+            ..SearchPatternConfig::default()
+        };
+        let config = Config {
+            search_patterns: vec![
+                pattern("cheap", Some(true)),
+                pattern("heavy", None),
+                pattern("archive", Some(false)),
+            ],
+            scan_on_startup: false,
+            ..Config::default()
+        };
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor"])]));
+        let mut app = App::with_backend(config, tmux);
+        settle(&mut app);
+        assert_eq!(app.search_provider.project_count(), 1);
+
+        // The first search scans the rest, once
+        assert!(app.lazy_scan_pending);
+        press(&mut app, KeyCode::Char('F'));
+        assert!(!app.lazy_scan_pending);
+        assert_eq!(app.search_provider.project_count(), 3);
+        assert_eq!(app.search_results.len(), 3);
+    }
+
+    #[test]
+    fn test_rescan_and_forget_search_results() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("work/api")).unwrap();
+        let config = Config {
+            search_patterns: vec![SearchPatternConfig {
+                name: "projects".to_string(),
+                paths: vec![dir.path().to_string_lossy().to_string()],
+                pattern: "{session}/{window}".to_string(),
+                ..SearchPatternConfig::default()
+            }],
+            ..Config::default()
+        };
+        let tmux = Arc::new(FakeTmux::with_sessions(&[("main", &["editor"])]));
+        let mut app = App::with_backend(config, tmux);
+        settle(&mut app);
+        press(&mut app, KeyCode::Char('F'));
+        assert_eq!(app.search_results.len(), 1);
+
+        // A fresh clone shows up after Ctrl-r
+        std::fs::create_dir_all(dir.path().join("work/web")).unwrap();
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        app.handle_key(ctrl('r')).unwrap();
+        settle(&mut app);
+        assert_eq!(app.search_results.len(), 2);
+        assert_eq!(
+            app.messages.entries().last().unwrap().text,
+            "Found 2 projects"
+        );
+
+        app.search_selected_index = 1;
+        let forgotten = app.search_results[1].window_name.clone();
+        app.handle_key(ctrl('x')).unwrap();
+        assert_eq!(app.search_results.len(), 1);
+        assert_ne!(app.search_results[0].window_name, forgotten);
+        assert_eq!(app.search_selected_index, 0);
     }
 }
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use crate::tmux::TmuxSession;
+
+/// Layout of a session that was archived and killed, enough to bring it back:
+/// its windows with their names and directories. Running programs are gone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedSession {
+    pub name: String,
+    pub archived_at: i64, // Unix time
+    #[serde(default)]
+    pub start_dir: Option<String>,
+    pub windows: Vec<ArchivedWindow>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedWindow {
+    pub name: String,
+    pub path: String, // Active pane's directory at the time
+}
+
+impl ArchivedSession {
+    pub fn capture(session: &TmuxSession, now: i64) -> Self {
+        Self {
+            name: session.name.clone(),
+            archived_at: now,
+            start_dir: session.start_dir.clone(),
+            windows: session
+                .windows
+                .iter()
+                .map(|w| ArchivedWindow {
+                    name: w.name.clone(),
+                    path: w.path.clone(),
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
+use clap_complete::Shell;
+use std::path::PathBuf;
+
+use crate::filter::parse_duration;
+
+/// A TUI session manager for tmux
+#[derive(Debug, Parser)]
+#[command(name = "tmux-portal", version, about)]
+pub struct Cli {
+    /// Keep the portal open after switching (overrides `exit_on_switch`)
+    #[arg(long)]
+    pub stay_open: bool,
+
+    /// Log tmux commands to debug.log in the config dir (`RUST_LOG` also works)
+    #[arg(long, global = true)]
+    pub debug: bool,
+
+    /// Use modifiers (bold, reverse, ...) instead of colors, same as NO_COLOR
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Use this config file instead of the default one
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath, conflicts_with = "profile")]
+    pub config: Option<PathBuf>,
+
+    /// Use the named profile, stored as profiles/<NAME>.toml in the config dir
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Install tmux hooks that tell a running portal to refresh
+    InstallHooks,
+    /// Check the tmux setup: binary, server version, socket and format fields
+    Doctor,
+    /// Kill detached sessions without activity for a while
+    Prune {
+        /// How long a session has to be idle, e.g. 12h, 7d or 2w
+        #[arg(long, value_name = "DURATION", value_parser = parse_idle)]
+        idle: i64,
+
+        /// Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Print a completion script, e.g. `tmux-portal completions zsh > _tmux-portal`
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the window and session mode keys, including the ones from `[keys]`
+    Keys {
+        #[arg(long, value_enum, default_value_t = KeysFormat::Table)]
+        format: KeysFormat,
+    },
+    /// Show a read-only tree that keeps itself up to date, e.g. in a side pane
+    Watch {
+        /// How often to refresh, e.g. 2s or 1m. Refresh hooks update it right away
+        #[arg(long, value_name = "DURATION", value_parser = parse_interval, default_value = "2s")]
+        interval: i64,
+    },
+    /// Print the most recent portal targets, e.g. for tmux's `status-right`
+    Status {
+        /// `#{mru1}`..`#{mru9}` for `session:window`, or their
+        /// `#{mruN_session}` and `#{mruN_window}` parts
+        #[arg(long, value_name = "FORMAT", default_value = "#{mru1} #{mru2}")]
+        format: String,
+    },
+    /// Print the session and window tree, with flags and paths
+    Tree {
+        #[arg(long, value_enum, default_value_t = TreeFormat::Txt)]
+        format: TreeFormat,
+
+        /// Write the tree to this file instead of stdout
+        #[arg(long, short, value_name = "PATH", value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
+    /// Open, locate or validate the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Open the config file in $VISUAL or $EDITOR, then check it
+    Edit,
+    /// Print the path of the config file
+    Path,
+    /// Report syntax errors, wrong types and unknown keys with their line
+    Check,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeysFormat {
+    /// Markdown tables, one per mode
+    Md,
+    /// Aligned plain text
+    Table,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TreeFormat {
+    /// A markdown list
+    Md,
+    /// Indented plain text
+    Txt,
+}
+
+/// Completion script for the subcommands and flags above
+pub fn write_completions(shell: Shell, out: &mut dyn std::io::Write) {
+    clap_complete::generate(shell, &mut Cli::command(), "tmux-portal", out);
+}
+
+fn parse_idle(value: &str) -> Result<i64, String> {
+    parse_duration(value).ok_or_else(|| format!("invalid duration '{value}', expected e.g. 7d"))
+}
+
+fn parse_interval(value: &str) -> Result<i64, String> {
+    parse_duration(value)
+        .filter(|&seconds| seconds > 0)
+        .ok_or_else(|| format!("invalid interval '{value}', expected e.g. 2s"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completions() {
+        Cli::command().debug_assert();
+
+        let mut script = Vec::new();
+        write_completions(Shell::Bash, &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("install-hooks"));
+        assert!(script.contains("--idle"));
+    }
+}
@@ -1,11 +1,17 @@
 use anyhow::Result;
+use ratatui::layout::Rect;
 use ratatui::style::{Color, Modifier, Style};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use text_trees::FormatCharacters;
+use toml::Spanned;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default)]
     pub colors: ColorConfig,
     pub session_order: Option<Vec<String>>,
     #[serde(default)]
@@ -15,14 +21,361 @@ pub struct Config {
     #[serde(default)]
     pub search_patterns: Vec<SearchPatternConfig>,
     #[serde(default)]
+    pub bookmarks: Vec<BookmarkConfig>, // Fixed paths listed in project search
+    #[serde(default)]
+    pub choose_layout: bool, // Ask for a pane layout when project search creates a window
+    #[serde(default)]
+    pub layouts: Vec<LayoutConfig>, // Choices offered next to the built-in ones
+    #[serde(default = "default_new_session_dir")]
+    pub new_session_dir: String, // Where sessions created from a search query start
+    #[serde(default = "default_true")]
+    pub scan_on_startup: bool, // Scan search patterns at startup instead of on first search
+    #[serde(default)]
+    pub session_collision: SessionCollision, // A search result's session holds another project
+    #[serde(default)]
     pub show_window_ids: bool, // Show window IDs when names are ambiguous
+    #[serde(default = "default_true")]
+    pub show_window_index: bool, // Prefix windows with their tmux index, e.g. "1: editor"
+    #[serde(default = "default_window_format")]
+    pub window_format: String, // Window lines; {name}, {command}, ..., and {@option} are expanded
+    #[serde(default)]
+    pub show_activity: bool, // Show how long ago each window was last active
+    #[serde(default)]
+    pub sort_by_activity: bool, // Start with windows ordered by recent activity
+    #[serde(default)]
+    pub flat_list: bool, // Start with windows as a flat `session:window` list
+    #[serde(default)]
+    pub theme: Theme, // Built-in preset used instead of [colors]
+    #[serde(default)]
+    pub tree_style: TreeStyle, // Characters connecting windows to their session
+    #[serde(default)]
+    pub two_column_min_width: Option<u16>, // Flow the tree into two columns from this width on
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub open_actions: Vec<OpenActionConfig>, // Tools launched at the selected window's directory
+    #[serde(default = "default_prune_idle")]
+    pub prune_idle: String, // `P` in session mode offers sessions idle this long, e.g. "7d"
+    #[serde(default = "default_session_idle_after")]
+    pub session_idle_after: String, // Detached sessions this quiet get `colors.session_idle`
+    #[serde(default = "default_session_stale_after")]
+    pub session_stale_after: String, // ... and this quiet `colors.session_stale`
+    #[serde(default = "default_log_path")]
+    pub log_path: String, // File `P` logs a pane to; {session}, {window}, {window_id}, {time} are expanded
+    #[serde(default = "default_capture_dir")]
+    pub capture_dir: String, // Where `H` saves a window's scrollback
+    #[serde(default)]
+    pub capture_pager: bool, // Open saved scrollback in $PAGER in a new window
+    #[serde(default)]
+    pub shell_popup: bool, // Run `|` commands in a tmux popup instead of in the background
+    #[serde(default = "default_window_colors")]
+    pub window_colors: Vec<String>, // Colors `c` cycles a window through, in tmux's names
+    #[serde(default)]
+    pub views: Vec<ViewConfig>, // Named filters cycled through with `V`
+    #[serde(default)]
+    pub scratch: ScratchConfig, // Session shown in a popup with `z`
+    #[serde(default = "default_true")]
+    pub mouse: bool, // Click to select, wheel to scroll, drag windows to move them
+    #[serde(default)]
+    pub zoom_on_switch: bool, // Zoom the active pane of windows switched to, `C-f` does it once
+    #[serde(default)]
+    pub occupied_index: OccupiedIndex, // What `=` does when another window has the index
+    #[serde(default = "default_true")]
+    pub exit_on_switch: bool, // Quit after switching; disable when running in a side pane
+    #[serde(default)]
+    pub refresh_interval_ms: Option<u64>, // Re-read tmux state periodically while idle
+    #[serde(default)]
+    pub status_bar: StatusBarConfig,
+    #[serde(default)]
+    pub mode_labels: ModeLabelsConfig,
+    #[serde(default)]
+    pub popup: PopupConfig, // Where prompts show up and how much room they and search bars take
+    #[serde(default = "default_toast_duration_ms")]
+    pub toast_duration_ms: u64, // How long messages stay on screen before moving to the log
+    #[serde(default = "default_history_size")]
+    pub history_size: usize, // Recently visited windows to remember
+    #[serde(default)]
+    pub keymap: KeymapPreset, // Navigation keys `[keys]` builds on: vim, emacs or plain
+    #[serde(default)]
+    pub keys: KeysConfig, // Rebind window and session mode actions, see `tmux-portal keys`
+    #[serde(skip)]
+    pub monochrome: bool, // Set by `make_monochrome`, window colors from tmux are ignored
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModeLabelsConfig {
+    // Text of the mode indicator in the status bar
+    pub window: String,
+    pub rename: String,
+    pub link: String,
+    pub start_dir: String,
+    pub search: String,
+    pub quick_search: String,
+    pub session: String,
+    pub confirm: String,
+    pub messages: String,
+    pub environment: String,
+    pub options: String,
+    pub info: String,
+    pub archive: String,
+    pub stats: String,
+    pub note: String,
+    pub shell: String,
+    pub move_index: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusBarConfig {
+    // Segments from left to right
+    pub segments: Vec<StatusSegment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusSegment {
+    pub kind: StatusSegmentKind,
+    #[serde(default)]
+    pub width: Option<u16>, // Fixed width in columns; unset fits the mode labels or shares the rest
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusSegmentKind {
+    Mode,    // -- WINDOW -- etc.
+    Help,    // Key hints for the current mode
+    Counts,  // Session/window counts, result count while searching
+    Session, // Attached session
+    Clock,   // Local time
+    History, // Recently visited windows, most recent first
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PopupConfig {
+    pub anchor: PopupAnchor, // Prompts and the pin list at the top, center or bottom
+    pub max_width: u16,      // Percent of the screen width prompts grow to before wrapping
+    pub search_bar_height: u16, // 3 draws search inputs in a box, 1 as a bare line
+}
+
+impl Default for PopupConfig {
+    fn default() -> Self {
+        Self {
+            anchor: PopupAnchor::default(),
+            max_width: 80,
+            search_bar_height: 3,
+        }
+    }
+}
+
+impl PopupConfig {
+    /// Whether search inputs get a box, which takes three rows
+    pub fn boxed_search_bar(&self) -> bool {
+        self.search_bar_height >= 3
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PopupAnchor {
+    Top,
+    #[default]
+    Center,
+    Bottom,
+}
+
+impl PopupAnchor {
+    /// Row a popup this tall starts at. At the bottom it stays clear of the
+    /// status bar
+    pub fn y(self, area: Rect, height: u16) -> u16 {
+        let free = area.height.saturating_sub(height);
+        match self {
+            PopupAnchor::Top => area.y,
+            PopupAnchor::Center => area.y + free / 2,
+            PopupAnchor::Bottom => area.y + free.saturating_sub(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    Custom, // Whatever [colors] says
+    HighContrast, // Bright text on the terminal background, black on yellow selections
+    BoldOnly,     // No colors at all, bold and reverse video only
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TreeStyle {
+    #[default]
+    Box, // ├── └──
+    Ascii,   // +-- '--
+    Rounded, // ├── ╰──
+    None,    // Plain indentation, for fonts without line drawing characters
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HooksConfig {
+    // Shell commands run around a switch; {session}, {window}, {window_id}, {path} are expanded quoted
+    pub pre_switch: Option<String>,
+    pub post_switch: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    // Action name to its keys, e.g. `rename = ["r", "F2"]`; replaces the default keys
+    pub window: BTreeMap<String, KeySpec>,
+    pub session: BTreeMap<String, KeySpec>,
+}
+
+/// Which keys move around the tree and the panels
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeymapPreset {
+    #[default]
+    Vim, // j/k, g/G, h/l and J/K
+    Emacs, // C-n/C-p, M-</M->, M-n/M-p, M-Up/M-Down and C-g
+    Plain, // Arrows, Home/End, PageUp/PageDown and S-Up/S-Down
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeySpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeySpec {
+    pub fn keys(&self) -> &[String] {
+        match self {
+            KeySpec::One(key) => std::slice::from_ref(key),
+            KeySpec::Many(keys) => keys,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenActionConfig {
+    pub key: char, // Key in window mode, built-in bindings take precedence
+    pub name: String,
+    pub command: String, // {session}, {window}, {window_id}, {path} are expanded
+    #[serde(default)]
+    pub target: OpenTarget,
+    #[serde(default)]
+    pub exit: Option<bool>, // Quit afterwards; unset follows exit_on_switch
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenTarget {
+    #[default]
+    Window, // New window in the selected session, named after the action
+    Popup, // tmux popup; needs the portal itself to run outside a popup
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewConfig {
+    pub name: String,
+    #[serde(default)]
+    pub filter: String, // Quick search syntax, e.g. "session:work active:no"
+    #[serde(default)]
+    pub sort: Option<ViewSort>, // Unset keeps the current order
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewSort {
+    Index,
+    Activity, // Most recently active first
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchPatternConfig {
     pub name: String,
     pub paths: Vec<String>,
-    pub pattern: String,
+    #[serde(default, rename = "type")]
+    pub kind: PatternKind,
+    #[serde(default = "default_pattern")]
+    pub pattern: String, // Only for directory patterns
+    #[serde(default)]
+    pub window_name: Option<String>, // Template for created windows, e.g. "{window}-edit"
+    #[serde(default)]
+    pub on_create: Option<String>, // Command run in newly created windows, e.g. "nvim ."
+    #[serde(default)]
+    pub style: Option<StyleConfig>, // Header style of the pattern's group in project search
+    #[serde(default)]
+    pub panes: Vec<String>, // Commands of panes split off newly created windows, "" for a shell
+    #[serde(default)]
+    pub layout: Option<String>, // tmux layout applied after splitting, e.g. "main-vertical"
+    #[serde(default)]
+    pub environment: ProjectEnv, // Load the project's .envrc or virtualenv in created windows
+    #[serde(default)]
+    pub scan_on_startup: Option<bool>, // Defaults to the global `scan_on_startup`
+    #[serde(default = "default_weight")]
+    pub weight: f64, // Multiplies match scores, e.g. 2.0 ranks a work root above an archive
+}
+
+/// Pane layout offered by `choose_layout`, like a pattern's `panes` and `layout`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutConfig {
+    pub name: String,
+    #[serde(default)]
+    pub panes: Vec<String>, // Commands of panes split off the window, "" for a shell
+    #[serde(default)]
+    pub layout: Option<String>, // tmux layout applied after splitting
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookmarkConfig {
+    pub name: String,
+    pub path: String,
+    #[serde(default)]
+    pub session: Option<String>, // Defaults to the bookmark's name
+    #[serde(default)]
+    pub window: Option<String>, // Defaults to the directory's name
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternKind {
+    #[default]
+    Directory, // Walk `paths` along `pattern`
+    File, // Read projects listed in the files at `paths`
+}
+
+/// Environment the shells of windows created from project search start in
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectEnv {
+    #[default]
+    None,
+    Direnv, // `direnv exec` when the project has an .envrc
+    Venv,   // Activate .venv, venv or env when one of them is a virtualenv
+    Auto,   // direnv if there's an .envrc, else a virtualenv
+}
+
+/// What opening a search result does when its session already exists and
+/// has the window open in another directory
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionCollision {
+    Reuse,  // Switch to the existing window anyway
+    Suffix, // Use the first free name of `name-2`, `name-3`, ...
+    #[default]
+    Prompt, // Ask which of the two
+}
+
+/// What moving a window to an index another window has does
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OccupiedIndex {
+    #[default]
+    Shift, // Take the index, that window and the ones after it move up
+    Swap, // Trade indices with that window
 }
 
 impl Default for SearchPatternConfig {
@@ -30,7 +383,34 @@ impl Default for SearchPatternConfig {
         Self {
             name: "git-style".to_string(),
             paths: Vec::new(),
-            pattern: "{session}/{window}".to_string(),
+            kind: PatternKind::default(),
+            pattern: default_pattern(),
+            window_name: None,
+            on_create: None,
+            style: None,
+            panes: Vec::new(),
+            layout: None,
+            environment: ProjectEnv::default(),
+            scan_on_startup: None,
+            weight: default_weight(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScratchConfig {
+    pub session: String, // Created on first use
+    pub width: u16,      // Popup size in percent of the terminal
+    pub height: u16,
+}
+
+impl Default for ScratchConfig {
+    fn default() -> Self {
+        Self {
+            session: "scratch".to_string(),
+            width: 80,
+            height: 80,
         }
     }
 }
@@ -38,12 +418,23 @@ impl Default for SearchPatternConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct LineNumberConfig {
+    pub mode: LineNumberMode, // Starting mode, `N` cycles through them at runtime
     pub padding: usize,
     pub current_line_color: StyleConfig,
     pub other_lines_color: StyleConfig,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineNumberMode {
+    #[default]
+    Relative, // Distance from the selected window
+    Absolute, // Position of the window in the tree, counting from 1
+    Off,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ColorConfig {
     pub session: StyleConfig,
     pub window_active: StyleConfig,
@@ -72,6 +463,34 @@ pub struct ColorConfig {
     pub quick_search_selected: StyleConfig,
     #[serde(default = "default_list_highlight")]
     pub list_highlight: StyleConfig,
+    #[serde(default = "default_border_session")]
+    pub border_session: StyleConfig, // Mode indicator in session mode
+    #[serde(default = "default_session_mode_selected")]
+    pub session_mode_selected: StyleConfig, // Selected session line in session mode
+    #[serde(default = "default_pane_count")]
+    pub pane_count: StyleConfig, // "(3 panes)" on windows with more than one pane
+    #[serde(default = "default_zoomed")]
+    pub zoomed: StyleConfig, // "(zoomed)" on windows with a zoomed pane
+    #[serde(default = "default_linked")]
+    pub linked: StyleConfig, // "(linked)" on windows shown in several sessions
+    #[serde(default = "default_logging")]
+    pub logging: StyleConfig, // "(logging)" on windows whose pane is piped to a log
+    #[serde(default = "default_note")]
+    pub note: StyleConfig, // "# note" after windows and sessions with a note
+    #[serde(default = "default_attached")]
+    pub attached: StyleConfig, // "● 2" after sessions with clients attached
+    #[serde(default = "default_activity")]
+    pub activity: StyleConfig, // Time since the last activity, e.g. "5m"
+    #[serde(default = "default_marked")]
+    pub marked: StyleConfig, // Lines marked with Space for deleting together
+    #[serde(default = "default_session_idle")]
+    pub session_idle: StyleConfig, // On top of `session` past `session_idle_after`
+    #[serde(default = "default_session_stale")]
+    pub session_stale: StyleConfig, // On top of `session` past `session_stale_after`
+    #[serde(default = "default_session_current")]
+    pub session_current: StyleConfig, // On top of `session` for the session this client is in
+    #[serde(default = "default_window_current")]
+    pub window_current: StyleConfig, // On top of `window_active` for that session's active window
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,6 +504,11 @@ pub struct StyleConfig {
     pub underline: Option<bool>,
     pub dim: Option<bool>,
     pub reversed: Option<bool>,
+    pub strikethrough: Option<bool>, // Same as crossed_out
+    pub crossed_out: Option<bool>,
+    pub slow_blink: Option<bool>,
+    pub rapid_blink: Option<bool>,
+    pub underline_color: Option<String>, // Needs terminal support for colored underlines
 }
 
 impl Default for Config {
@@ -95,7 +519,391 @@ impl Default for Config {
             line_numbers: LineNumberConfig::default(),
             search_paths: Vec::new(),
             search_patterns: Vec::new(),
+            bookmarks: Vec::new(),
+            choose_layout: false,
+            layouts: Vec::new(),
+            new_session_dir: default_new_session_dir(),
+            scan_on_startup: true,
+            session_collision: SessionCollision::default(),
             show_window_ids: true, // Default to showing IDs for disambiguation
+            show_window_index: true,
+            window_format: default_window_format(),
+            show_activity: false,
+            sort_by_activity: false,
+            flat_list: false,
+            theme: Theme::default(),
+            tree_style: TreeStyle::default(),
+            two_column_min_width: None,
+            hooks: HooksConfig::default(),
+            open_actions: Vec::new(),
+            prune_idle: default_prune_idle(),
+            log_path: default_log_path(),
+            capture_dir: default_capture_dir(),
+            capture_pager: false,
+            shell_popup: false,
+            window_colors: default_window_colors(),
+            session_idle_after: default_session_idle_after(),
+            session_stale_after: default_session_stale_after(),
+            views: Vec::new(),
+            scratch: ScratchConfig::default(),
+            mouse: true,
+            zoom_on_switch: false,
+            occupied_index: OccupiedIndex::default(),
+            exit_on_switch: true,
+            refresh_interval_ms: None,
+            status_bar: StatusBarConfig::default(),
+            mode_labels: ModeLabelsConfig::default(),
+            popup: PopupConfig::default(),
+            toast_duration_ms: default_toast_duration_ms(),
+            history_size: default_history_size(),
+            keymap: KeymapPreset::default(),
+            keys: KeysConfig::default(),
+            monochrome: false,
+        }
+    }
+}
+
+impl Default for ModeLabelsConfig {
+    fn default() -> Self {
+        Self {
+            window: "-- WINDOW --".to_string(),
+            rename: "-- RENAME --".to_string(),
+            link: "-- LINK --".to_string(),
+            start_dir: "-- DIR --".to_string(),
+            search: "-- SEARCH --".to_string(),
+            quick_search: "-- QUICK --".to_string(),
+            session: "-- SESSION --".to_string(),
+            confirm: "-- CONFIRM --".to_string(),
+            messages: "-- MESSAGES --".to_string(),
+            environment: "-- ENV --".to_string(),
+            options: "-- OPTIONS --".to_string(),
+            info: "-- INFO --".to_string(),
+            archive: "-- ARCHIVE --".to_string(),
+            stats: "-- STATS --".to_string(),
+            note: "-- NOTE --".to_string(),
+            shell: "-- SHELL --".to_string(),
+            move_index: "-- MOVE --".to_string(),
+        }
+    }
+}
+
+impl ModeLabelsConfig {
+    // Widest label, so the mode column doesn't jump around between modes
+    pub fn max_width(&self) -> usize {
+        [
+            &self.window,
+            &self.rename,
+            &self.link,
+            &self.start_dir,
+            &self.search,
+            &self.quick_search,
+            &self.session,
+            &self.confirm,
+            &self.messages,
+            &self.environment,
+            &self.options,
+            &self.info,
+            &self.archive,
+            &self.stats,
+            &self.note,
+            &self.shell,
+            &self.move_index,
+        ]
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or(0)
+    }
+}
+
+impl Default for StatusBarConfig {
+    fn default() -> Self {
+        Self {
+            segments: vec![
+                StatusSegment {
+                    kind: StatusSegmentKind::Mode,
+                    width: None,
+                },
+                StatusSegment {
+                    kind: StatusSegmentKind::Help,
+                    width: None,
+                },
+                StatusSegment {
+                    kind: StatusSegmentKind::Counts,
+                    width: Some(30),
+                },
+            ],
+        }
+    }
+}
+
+impl StatusBarConfig {
+    pub fn has_clock(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|s| s.kind == StatusSegmentKind::Clock)
+    }
+}
+
+impl Config {
+    /// Drop every color and fall back to modifiers, for `NO_COLOR` and
+    /// `--no-color`
+    pub fn make_monochrome(&mut self) {
+        self.monochrome = true;
+        self.colors.make_monochrome();
+        self.line_numbers.current_line_color.strip_colors();
+        self.line_numbers.other_lines_color.strip_colors();
+    }
+
+    /// Replace [colors] with the configured built-in theme, if any
+    pub fn apply_theme(&mut self) {
+        match self.theme {
+            Theme::Custom => {}
+            Theme::HighContrast => {
+                self.colors = ColorConfig::high_contrast();
+                self.line_numbers.current_line_color = themed(Some("yellow"), None);
+                self.line_numbers.current_line_color.bold = Some(true);
+                self.line_numbers.other_lines_color = themed(Some("white"), None);
+            }
+            Theme::BoldOnly => {
+                self.monochrome = true;
+                self.colors = ColorConfig::bold_only();
+                self.line_numbers.current_line_color = StyleConfig {
+                    bold: Some(true),
+                    ..Default::default()
+                };
+                self.line_numbers.other_lines_color = StyleConfig::default();
+            }
+        }
+    }
+
+    /// Foreground of a window colored with `c`, nothing in monochrome mode
+    pub fn window_color_style(&self, color: &str) -> Style {
+        match self.monochrome {
+            true => Style::default(),
+            false => Style::default().fg(parse_color(color)),
+        }
+    }
+}
+
+impl ColorConfig {
+    // Every entry, so presets and monochrome can't miss one
+    fn styles_mut(&mut self) -> [&mut StyleConfig; 34] {
+        [
+            &mut self.session,
+            &mut self.window_active,
+            &mut self.window_inactive,
+            &mut self.window_selected,
+            &mut self.session_selected,
+            &mut self.border_list,
+            &mut self.border_prompt,
+            &mut self.border_search,
+            &mut self.help_key,
+            &mut self.help_text,
+            &mut self.status_text,
+            &mut self.error_text,
+            &mut self.popup_border,
+            &mut self.popup_input,
+            &mut self.popup_text,
+            &mut self.numeric_buffer,
+            &mut self.search_highlight,
+            &mut self.quick_search_match,
+            &mut self.quick_search_selected,
+            &mut self.list_highlight,
+            &mut self.border_session,
+            &mut self.session_mode_selected,
+            &mut self.pane_count,
+            &mut self.zoomed,
+            &mut self.linked,
+            &mut self.logging,
+            &mut self.note,
+            &mut self.attached,
+            &mut self.activity,
+            &mut self.marked,
+            &mut self.session_idle,
+            &mut self.session_stale,
+            &mut self.session_current,
+            &mut self.window_current,
+        ]
+    }
+
+    fn high_contrast() -> Self {
+        let mut colors = Self::default();
+        for style in colors.styles_mut() {
+            *style = themed(Some("white"), None);
+        }
+
+        for style in [
+            &mut colors.window_selected,
+            &mut colors.session_selected,
+            &mut colors.session_mode_selected,
+            &mut colors.quick_search_selected,
+            &mut colors.list_highlight,
+        ] {
+            *style = themed(Some("black"), Some("yellow"));
+        }
+        colors.search_highlight = themed(Some("black"), Some("light_cyan"));
+        colors.quick_search_match = themed(Some("black"), Some("light_cyan"));
+        colors.error_text = themed(Some("white"), Some("red"));
+        for style in [
+            &mut colors.border_session,
+            &mut colors.popup_border,
+            &mut colors.help_key,
+            &mut colors.numeric_buffer,
+        ] {
+            *style = themed(Some("yellow"), None);
+        }
+        for style in [
+            &mut colors.attached,
+            &mut colors.zoomed,
+            &mut colors.logging,
+            &mut colors.note,
+        ] {
+            *style = themed(Some("light_cyan"), None);
+        }
+        colors.marked = themed(Some("light_magenta"), None);
+        colors.marked.underline = Some(true);
+        colors.session_stale = themed(Some("light_red"), None);
+        colors.session_current = themed(Some("light_green"), None);
+        colors.window_current = themed(Some("light_green"), None);
+
+        // Bold wherever the default theme leans on color alone
+        for style in [
+            &mut colors.session,
+            &mut colors.window_active,
+            &mut colors.window_selected,
+            &mut colors.session_selected,
+            &mut colors.session_mode_selected,
+            &mut colors.quick_search_selected,
+            &mut colors.list_highlight,
+            &mut colors.search_highlight,
+            &mut colors.quick_search_match,
+            &mut colors.error_text,
+            &mut colors.border_session,
+            &mut colors.help_key,
+            &mut colors.numeric_buffer,
+            &mut colors.attached,
+            &mut colors.zoomed,
+            &mut colors.logging,
+            &mut colors.marked,
+            &mut colors.session_current,
+            &mut colors.window_current,
+        ] {
+            style.bold = Some(true);
+        }
+        colors.session_idle.italic = Some(true);
+        colors.session_stale.italic = Some(true);
+        colors.session_current.underline = Some(true);
+        colors.window_current.underline = Some(true);
+        colors
+    }
+
+    fn bold_only() -> Self {
+        let mut colors = Self::default();
+        for style in colors.styles_mut() {
+            *style = StyleConfig::default();
+        }
+
+        // Selections are reversed but not bold, so matches inside them still show
+        for style in [
+            &mut colors.window_selected,
+            &mut colors.session_selected,
+            &mut colors.session_mode_selected,
+            &mut colors.quick_search_selected,
+            &mut colors.list_highlight,
+            &mut colors.search_highlight,
+            &mut colors.quick_search_match,
+        ] {
+            style.reversed = Some(true);
+        }
+        for style in [
+            &mut colors.session,
+            &mut colors.window_active,
+            &mut colors.search_highlight,
+            &mut colors.quick_search_match,
+            &mut colors.error_text,
+            &mut colors.border_session,
+            &mut colors.popup_input,
+            &mut colors.help_key,
+            &mut colors.numeric_buffer,
+            &mut colors.attached,
+            &mut colors.zoomed,
+            &mut colors.logging,
+            &mut colors.marked,
+            &mut colors.session_current,
+            &mut colors.window_current,
+        ] {
+            style.bold = Some(true);
+        }
+        colors
+    }
+
+    fn make_monochrome(&mut self) {
+        for style in self.styles_mut() {
+            style.strip_colors();
+        }
+
+        // Without colors, selections and matches need modifiers to stand out
+        for style in [
+            &mut self.window_selected,
+            &mut self.session_selected,
+            &mut self.session_mode_selected,
+            &mut self.quick_search_selected,
+        ] {
+            style.reversed = Some(true);
+        }
+        self.session_mode_selected.bold = Some(true);
+        self.search_highlight.underline = Some(true);
+        self.quick_search_match.underline = Some(true);
+        self.error_text.bold = Some(true);
+        self.help_key.bold = Some(true);
+        self.zoomed.bold = Some(true);
+        self.logging.bold = Some(true);
+        self.attached.bold = Some(true);
+        self.marked.underline = Some(true);
+        self.session_idle.italic = Some(true);
+        self.session_current.underline = Some(true);
+        self.window_current.underline = Some(true);
+    }
+}
+
+// Preset entry: these colors, no modifiers yet
+fn themed(fg: Option<&str>, bg: Option<&str>) -> StyleConfig {
+    StyleConfig {
+        fg: fg.map(str::to_string),
+        bg: bg.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+impl StyleConfig {
+    fn strip_colors(&mut self) {
+        self.fg = None;
+        self.bg = None;
+        self.underline_color = None;
+    }
+}
+
+impl TreeStyle {
+    pub fn format_characters(&self) -> FormatCharacters {
+        match self {
+            TreeStyle::Box => FormatCharacters::box_chars(),
+            TreeStyle::Ascii => FormatCharacters::ascii(),
+            TreeStyle::Rounded => FormatCharacters {
+                right_facing_angle: '╰',
+                ..FormatCharacters::box_chars()
+            },
+            TreeStyle::None => FormatCharacters {
+                down_facing_angle: ' ',
+                down_facing_tee: ' ',
+                vertical_line: ' ',
+                horizontal_line: ' ',
+                horizontal_line_count: 1,
+                right_facing_tee: ' ',
+                right_facing_angle: ' ',
+                label_space_count: 0,
+                ..FormatCharacters::ascii()
+            },
         }
     }
 }
@@ -103,6 +911,7 @@ impl Default for Config {
 impl Default for LineNumberConfig {
     fn default() -> Self {
         Self {
+            mode: LineNumberMode::default(),
             padding: 5,
             current_line_color: StyleConfig {
                 fg: Some("dark_gray".to_string()),
@@ -112,6 +921,7 @@ impl Default for LineNumberConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             other_lines_color: StyleConfig {
                 fg: Some("dark_gray".to_string()),
@@ -121,11 +931,62 @@ impl Default for LineNumberConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
         }
     }
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_toast_duration_ms() -> u64 {
+    4000
+}
+
+fn default_prune_idle() -> String {
+    "7d".to_string()
+}
+
+fn default_window_format() -> String {
+    "{name}".to_string()
+}
+
+fn default_log_path() -> String {
+    "~/tmux-logs/{session}-{window}-{time}.log".to_string()
+}
+
+fn default_capture_dir() -> String {
+    "~/tmux-captures".to_string()
+}
+
+fn default_window_colors() -> Vec<String> {
+    ["red", "yellow", "green", "blue", "magenta", "cyan"]
+        .map(String::from)
+        .to_vec()
+}
+
+fn default_pattern() -> String {
+    "{session}/{window}".to_string()
+}
+
+fn default_session_idle_after() -> String {
+    "1d".to_string()
+}
+
+fn default_session_stale_after() -> String {
+    "7d".to_string()
+}
+
+fn default_new_session_dir() -> String {
+    "~".to_string()
+}
+
+fn default_history_size() -> usize {
+    10
+}
+
 // Default functions for new color configurations
 fn default_numeric_buffer() -> StyleConfig {
     StyleConfig {
@@ -136,6 +997,7 @@ fn default_numeric_buffer() -> StyleConfig {
         underline: None,
         dim: None,
         reversed: None,
+        ..Default::default()
     }
 }
 
@@ -148,9 +1010,14 @@ fn default_popup_text() -> StyleConfig {
         underline: None,
         dim: None,
         reversed: None,
+        ..Default::default()
     }
 }
 
+fn default_weight() -> f64 {
+    1.0
+}
+
 fn default_search_highlight() -> StyleConfig {
     StyleConfig {
         fg: Some("cyan".to_string()),
@@ -160,6 +1027,7 @@ fn default_search_highlight() -> StyleConfig {
         underline: None,
         dim: None,
         reversed: None,
+        ..Default::default()
     }
 }
 
@@ -172,6 +1040,7 @@ fn default_quick_search_match() -> StyleConfig {
         underline: None,
         dim: None,
         reversed: None,
+        ..Default::default()
     }
 }
 
@@ -184,6 +1053,7 @@ fn default_quick_search_selected() -> StyleConfig {
         underline: None,
         dim: None,
         reversed: None,
+        ..Default::default()
     }
 }
 
@@ -196,6 +1066,119 @@ fn default_list_highlight() -> StyleConfig {
         underline: None,
         dim: None,
         reversed: Some(true),
+        ..Default::default()
+    }
+}
+
+fn default_border_session() -> StyleConfig {
+    StyleConfig {
+        fg: Some("magenta".to_string()),
+        bg: None,
+        bold: None,
+        italic: None,
+        underline: None,
+        dim: None,
+        reversed: None,
+        ..Default::default()
+    }
+}
+
+fn default_session_mode_selected() -> StyleConfig {
+    StyleConfig {
+        fg: Some("black".to_string()),
+        bg: Some("magenta".to_string()),
+        bold: Some(true),
+        italic: None,
+        underline: None,
+        dim: None,
+        reversed: None,
+        ..Default::default()
+    }
+}
+
+fn default_pane_count() -> StyleConfig {
+    StyleConfig {
+        fg: Some("dark_gray".to_string()),
+        ..Default::default()
+    }
+}
+
+fn default_marked() -> StyleConfig {
+    StyleConfig {
+        fg: Some("magenta".to_string()),
+        ..Default::default()
+    }
+}
+
+fn default_activity() -> StyleConfig {
+    StyleConfig {
+        fg: Some("dark_gray".to_string()),
+        ..Default::default()
+    }
+}
+
+fn default_session_idle() -> StyleConfig {
+    StyleConfig {
+        fg: Some("yellow".to_string()),
+        ..Default::default()
+    }
+}
+
+fn default_session_stale() -> StyleConfig {
+    StyleConfig {
+        fg: Some("red".to_string()),
+        dim: Some(true),
+        ..Default::default()
+    }
+}
+
+fn default_zoomed() -> StyleConfig {
+    StyleConfig {
+        fg: Some("yellow".to_string()),
+        ..Default::default()
+    }
+}
+
+fn default_logging() -> StyleConfig {
+    StyleConfig {
+        fg: Some("red".to_string()),
+        ..Default::default()
+    }
+}
+
+fn default_session_current() -> StyleConfig {
+    StyleConfig {
+        fg: Some("green".to_string()),
+        ..Default::default()
+    }
+}
+
+fn default_window_current() -> StyleConfig {
+    StyleConfig {
+        fg: Some("green".to_string()),
+        ..Default::default()
+    }
+}
+
+fn default_attached() -> StyleConfig {
+    StyleConfig {
+        fg: Some("light_blue".to_string()),
+        ..Default::default()
+    }
+}
+
+fn default_note() -> StyleConfig {
+    StyleConfig {
+        fg: Some("dark_gray".to_string()),
+        italic: Some(true),
+        ..Default::default()
+    }
+}
+
+fn default_linked() -> StyleConfig {
+    StyleConfig {
+        fg: Some("cyan".to_string()),
+        ..Default::default()
     }
 }
 
@@ -210,6 +1193,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             window_active: StyleConfig {
                 fg: None,
@@ -219,6 +1203,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             window_inactive: StyleConfig {
                 fg: None,
@@ -228,6 +1213,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             window_selected: StyleConfig {
                 fg: Some("yellow".to_string()),
@@ -237,6 +1223,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             session_selected: StyleConfig {
                 fg: None,
@@ -246,6 +1233,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             border_list: StyleConfig {
                 fg: Some("white".to_string()),
@@ -255,6 +1243,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             border_prompt: StyleConfig {
                 fg: Some("yellow".to_string()),
@@ -264,6 +1253,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             border_search: StyleConfig {
                 fg: Some("cyan".to_string()),
@@ -273,6 +1263,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             help_key: StyleConfig {
                 fg: Some("yellow".to_string()),
@@ -282,6 +1273,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             help_text: StyleConfig {
                 fg: Some("white".to_string()),
@@ -291,6 +1283,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             status_text: StyleConfig {
                 fg: Some("green".to_string()),
@@ -300,6 +1293,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             error_text: StyleConfig {
                 fg: Some("red".to_string()),
@@ -309,6 +1303,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             popup_border: StyleConfig {
                 fg: Some("yellow".to_string()),
@@ -318,6 +1313,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             popup_input: StyleConfig {
                 fg: Some("white".to_string()),
@@ -327,6 +1323,7 @@ impl Default for ColorConfig {
                 underline: None,
                 dim: None,
                 reversed: None,
+                ..Default::default()
             },
             popup_text: default_popup_text(),
             // New configurable colors using default functions
@@ -335,6 +1332,20 @@ impl Default for ColorConfig {
             quick_search_match: default_quick_search_match(),
             quick_search_selected: default_quick_search_selected(),
             list_highlight: default_list_highlight(),
+            border_session: default_border_session(),
+            session_mode_selected: default_session_mode_selected(),
+            pane_count: default_pane_count(),
+            zoomed: default_zoomed(),
+            linked: default_linked(),
+            logging: default_logging(),
+            note: default_note(),
+            attached: default_attached(),
+            activity: default_activity(),
+            marked: default_marked(),
+            session_idle: default_session_idle(),
+            session_stale: default_session_stale(),
+            session_current: default_session_current(),
+            window_current: default_window_current(),
         }
     }
 }
@@ -351,6 +1362,10 @@ impl StyleConfig {
             style = style.bg(parse_color(bg_str));
         }
 
+        if let Some(underline_str) = &self.underline_color {
+            style = style.underline_color(parse_color(underline_str));
+        }
+
         let mut modifiers = Modifier::empty();
 
         if self.bold.unwrap_or(false) {
@@ -368,6 +1383,15 @@ impl StyleConfig {
         if self.reversed.unwrap_or(false) {
             modifiers |= Modifier::REVERSED;
         }
+        if self.strikethrough.unwrap_or(false) || self.crossed_out.unwrap_or(false) {
+            modifiers |= Modifier::CROSSED_OUT;
+        }
+        if self.slow_blink.unwrap_or(false) {
+            modifiers |= Modifier::SLOW_BLINK;
+        }
+        if self.rapid_blink.unwrap_or(false) {
+            modifiers |= Modifier::RAPID_BLINK;
+        }
 
         if !modifiers.is_empty() {
             style = style.add_modifier(modifiers);
@@ -378,101 +1402,416 @@ impl StyleConfig {
 }
 
 fn parse_color(color_str: &str) -> Color {
+    try_parse_color(color_str).unwrap_or(Color::White)
+}
+
+fn try_parse_color(color_str: &str) -> Option<Color> {
+    let rgb = |hex: &str| {
+        let rgb = u32::from_str_radix(hex, 16).ok()?;
+        Some(Color::Rgb(
+            ((rgb >> 16) & 0xFF) as u8,
+            ((rgb >> 8) & 0xFF) as u8,
+            (rgb & 0xFF) as u8,
+        ))
+    };
     match color_str.to_lowercase().as_str() {
-        "black" => Color::Black,
-        "red" => Color::Red,
-        "green" => Color::Green,
-        "yellow" => Color::Yellow,
-        "blue" => Color::Blue,
-        "magenta" => Color::Magenta,
-        "cyan" => Color::Cyan,
-        "gray" | "grey" => Color::Gray,
-        "dark_gray" | "dark_grey" => Color::DarkGray,
-        "light_red" => Color::LightRed,
-        "light_green" => Color::LightGreen,
-        "light_yellow" => Color::LightYellow,
-        "light_blue" => Color::LightBlue,
-        "light_magenta" => Color::LightMagenta,
-        "light_cyan" => Color::LightCyan,
-        "white" => Color::White,
+        "black" => Some(Color::Black),
+        "red" => Some(Color::Red),
+        "green" => Some(Color::Green),
+        "yellow" => Some(Color::Yellow),
+        "blue" => Some(Color::Blue),
+        "magenta" => Some(Color::Magenta),
+        "cyan" => Some(Color::Cyan),
+        "gray" | "grey" => Some(Color::Gray),
+        "dark_gray" | "dark_grey" => Some(Color::DarkGray),
+        "light_red" => Some(Color::LightRed),
+        "light_green" => Some(Color::LightGreen),
+        "light_yellow" => Some(Color::LightYellow),
+        "light_blue" => Some(Color::LightBlue),
+        "light_magenta" => Some(Color::LightMagenta),
+        "light_cyan" => Some(Color::LightCyan),
+        "white" => Some(Color::White),
+        // The terminal's own fg/bg, keeps transparent backgrounds transparent
+        "default" | "none" | "reset" => Some(Color::Reset),
         // Try to parse as RGB hex (e.g., "#FF0000", "FF0000", or "0xFF0000")
-        hex if hex.starts_with('#') && hex.len() == 7 => {
-            if let Ok(rgb) = u32::from_str_radix(&hex[1..], 16) {
-                Color::Rgb(
-                    ((rgb >> 16) & 0xFF) as u8,
-                    ((rgb >> 8) & 0xFF) as u8,
-                    (rgb & 0xFF) as u8,
-                )
-            } else {
-                Color::White
+        hex if hex.starts_with('#') && hex.len() == 7 => rgb(&hex[1..]),
+        hex if hex.starts_with("0x") && hex.len() == 8 => rgb(&hex[2..]),
+        hex if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => rgb(hex),
+        // Try to parse as 256-color index, also in tmux's "colour208" spelling
+        num_str => {
+            let num_str = num_str
+                .strip_prefix("colour")
+                .or_else(|| num_str.strip_prefix("color"))
+                .unwrap_or(num_str);
+            num_str.parse::<u8>().ok().map(Color::Indexed)
+        }
+    }
+}
+
+/// Load the config, creating it with the defaults on first use. An existing
+/// file is never written to. What can't be used in it is skipped and
+/// returned as warnings; only broken TOML syntax fails
+pub fn load_config(config_path: &PathBuf) -> Result<(Config, Vec<ConfigIssue>)> {
+    if !config_path.exists() {
+        let default_config = Config::default();
+        write_config(config_path, &default_config)?;
+        return Ok((default_config, Vec::new()));
+    }
+
+    let config_str = fs::read_to_string(config_path)?;
+    parse_config(&config_str).map_err(|issue| anyhow::anyhow!("{}:{issue}", config_path.display()))
+}
+
+fn write_config(path: &PathBuf, config: &Config) -> Result<()> {
+    let config_str = toml::to_string(config)?;
+    fs::write(path, config_str)?;
+    Ok(())
+}
+
+/// Something wrong in the config file, at a 1-based line and column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn at(text: &str, offset: usize, message: String) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Result of `config check`: an error if the config can't be loaded at all,
+/// warnings for what loads but is ignored
+#[derive(Debug, Default)]
+pub struct ConfigReport {
+    pub error: Option<ConfigIssue>,
+    pub warnings: Vec<ConfigIssue>,
+}
+
+pub fn check_config(text: &str) -> ConfigReport {
+    match parse_config(text) {
+        Ok((_, warnings)) => ConfigReport {
+            error: None,
+            warnings,
+        },
+        Err(error) => ConfigReport {
+            error: Some(error),
+            warnings: Vec::new(),
+        },
+    }
+}
+
+// Style keys holding a color, see `try_parse_color`
+const COLOR_KEYS: [&str; 3] = ["fg", "bg", "underline_color"];
+
+// One step from a table to a value inside it
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// Apply everything that can be used: invalid colors and values serde
+/// rejects are removed one by one, so their defaults apply, and reported
+/// with unknown keys. Fails only on broken syntax
+fn parse_config(text: &str) -> std::result::Result<(Config, Vec<ConfigIssue>), ConfigIssue> {
+    let syntax_error = |e: toml::de::Error| {
+        let offset = e.span().map_or(0, |span| span.start);
+        ConfigIssue::at(text, offset, e.message().trim().to_string())
+    };
+    let original = toml::de::DeTable::parse(text).map_err(syntax_error)?;
+    let mut value = toml::Value::Table(toml::from_str(text).map_err(syntax_error)?);
+
+    let mut issues = Vec::new();
+    let mut report = |path: &[Step], message: String| {
+        let offset = locate(original.get_ref(), path).unwrap_or(0);
+        issues.push(ConfigIssue::at(text, offset, message));
+    };
+
+    let mut colors = Vec::new();
+    invalid_colors(&value, &mut Vec::new(), &mut colors);
+    for (path, color) in colors {
+        remove_value(&mut value, &path);
+        let name = path_name(&path);
+        report(
+            &path,
+            format!("invalid color `{color}` for `{name}`, ignored"),
+        );
+    }
+
+    // Each round removes a value, so this ends
+    let config = loop {
+        let current = toml::to_string(&value).unwrap_or_default();
+        let error = match toml::from_str::<Config>(&current) {
+            Ok(config) => break config,
+            Err(e) => e,
+        };
+        let path = match (toml::de::DeTable::parse(&current), error.span()) {
+            (Ok(table), Some(span)) => path_at(table.get_ref(), &span),
+            _ => Vec::new(),
+        };
+        let message = error.message().trim().to_string();
+        if !remove_value(&mut value, &path) {
+            let offset = locate(original.get_ref(), &path).unwrap_or(0);
+            return Err(ConfigIssue::at(text, offset, message));
+        }
+        report(&path, format!("`{}`: {message}, ignored", path_name(&path)));
+    };
+
+    // Everything serde understood comes back out, so what doesn't was ignored
+    if let Ok(known) = toml::Value::try_from(&config) {
+        unknown_keys(&value, &known, &mut Vec::new(), &mut |path| {
+            report(path, format!("unknown key `{}`", path_name(path)));
+        });
+    }
+
+    issues.sort_by_key(|issue| (issue.line, issue.column));
+    Ok((config, issues))
+}
+
+// Dotted keys like `colors.session.fg`, array indices left out
+fn path_name(path: &[Step]) -> String {
+    let keys: Vec<&str> = path
+        .iter()
+        .filter_map(|step| match step {
+            Step::Key(key) => Some(key.as_str()),
+            Step::Index(_) => None,
+        })
+        .collect();
+    keys.join(".")
+}
+
+// Where a value starts in the file: its key, or the array element
+fn locate(table: &toml::de::DeTable, path: &[Step]) -> Option<usize> {
+    let (Step::Key(name), rest) = path.split_first()? else {
+        return None;
+    };
+    let (key, value) = table.iter().find(|(key, _)| key.get_ref() == name)?;
+    locate_in(value, rest).or(Some(key.span().start))
+}
+
+fn locate_in(value: &Spanned<toml::de::DeValue>, path: &[Step]) -> Option<usize> {
+    use toml::de::DeValue;
+
+    match (path.first()?, value.get_ref()) {
+        (Step::Key(_), DeValue::Table(table)) => locate(table, path),
+        (Step::Index(index), DeValue::Array(items)) => {
+            let item = items.get(*index)?;
+            locate_in(item, &path[1..]).or(Some(item.span().start))
+        }
+        _ => None,
+    }
+}
+
+// The innermost value containing all of `span`
+fn path_at(table: &toml::de::DeTable, span: &Range<usize>) -> Vec<Step> {
+    let contains = |outer: Range<usize>| outer.start <= span.start && span.end <= outer.end;
+    for (key, value) in table {
+        if contains(value.span()) {
+            let mut path = vec![Step::Key(key.get_ref().to_string())];
+            match value.get_ref() {
+                toml::de::DeValue::Table(table) => path.extend(path_at(table, span)),
+                toml::de::DeValue::Array(items) => {
+                    if let Some(index) = items.iter().position(|item| contains(item.span())) {
+                        path.push(Step::Index(index));
+                        if let toml::de::DeValue::Table(table) = items[index].get_ref() {
+                            path.extend(path_at(table, span));
+                        }
+                    }
+                }
+                _ => {}
+            }
+            return path;
+        }
+    }
+    Vec::new()
+}
+
+fn remove_value(value: &mut toml::Value, path: &[Step]) -> bool {
+    use toml::Value;
+
+    match (value, path) {
+        (Value::Table(table), [Step::Key(key)]) => table.remove(key).is_some(),
+        (Value::Array(items), [Step::Index(index)]) if *index < items.len() => {
+            items.remove(*index);
+            true
+        }
+        (Value::Table(table), [Step::Key(key), rest @ ..]) => table
+            .get_mut(key)
+            .is_some_and(|value| remove_value(value, rest)),
+        (Value::Array(items), [Step::Index(index), rest @ ..]) => items
+            .get_mut(*index)
+            .is_some_and(|value| remove_value(value, rest)),
+        _ => false,
+    }
+}
+
+fn invalid_colors(value: &toml::Value, path: &mut Vec<Step>, found: &mut Vec<(Vec<Step>, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                path.push(Step::Key(key.clone()));
+                match value {
+                    toml::Value::String(color)
+                        if COLOR_KEYS.contains(&key.as_str())
+                            && try_parse_color(color).is_none() =>
+                    {
+                        found.push((path.clone(), color.clone()));
+                    }
+                    _ => invalid_colors(value, path, found),
+                }
+                path.pop();
             }
         }
-        hex if hex.starts_with("0x") && hex.len() == 8 => {
-            if let Ok(rgb) = u32::from_str_radix(&hex[2..], 16) {
-                Color::Rgb(
-                    ((rgb >> 16) & 0xFF) as u8,
-                    ((rgb >> 8) & 0xFF) as u8,
-                    (rgb & 0xFF) as u8,
-                )
-            } else {
-                Color::White
+        toml::Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                path.push(Step::Index(index));
+                invalid_colors(item, path, found);
+                path.pop();
             }
         }
-        hex if hex.len() == 6 => {
-            if let Ok(rgb) = u32::from_str_radix(hex, 16) {
-                Color::Rgb(
-                    ((rgb >> 16) & 0xFF) as u8,
-                    ((rgb >> 8) & 0xFF) as u8,
-                    (rgb & 0xFF) as u8,
-                )
-            } else {
-                Color::White
+        _ => {}
+    }
+}
+
+fn unknown_keys(
+    value: &toml::Value,
+    known: &toml::Value,
+    path: &mut Vec<Step>,
+    found: &mut dyn FnMut(&[Step]),
+) {
+    use toml::Value;
+
+    match (value, known) {
+        (Value::Table(table), Value::Table(known)) => {
+            for (key, value) in table {
+                path.push(Step::Key(key.clone()));
+                match known.get(key) {
+                    Some(known) => unknown_keys(value, known, path, found),
+                    None => found(path),
+                }
+                path.pop();
             }
         }
-        // Try to parse as 256-color index
-        num_str => {
-            if let Ok(index) = num_str.parse::<u8>() {
-                Color::Indexed(index)
-            } else {
-                Color::White
+        (Value::Array(items), Value::Array(known)) => {
+            for (index, (item, known)) in items.iter().zip(known).enumerate() {
+                path.push(Step::Index(index));
+                unknown_keys(item, known, path, found);
+                path.pop();
             }
         }
+        _ => {}
     }
 }
 
-pub fn load_config() -> Result<Config> {
-    let config_path = get_config_path()?;
+/// Expand `~`, `$VAR` and `${VAR}` in a configured path so one config works
+/// across machines. Unknown variables are left as they are
+pub fn expand_path(path: &str) -> PathBuf {
+    let home = dirs::home_dir().map(|h| h.to_string_lossy().to_string());
+    PathBuf::from(expand_vars(path, home.as_deref(), |name| {
+        std::env::var(name).ok()
+    }))
+}
 
-    let config = if config_path.exists() {
-        // Load existing config
-        let config_str = fs::read_to_string(&config_path)?;
-        let config: Config = toml::from_str(&config_str)?;
+fn expand_vars(input: &str, home: Option<&str>, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::new();
+    let mut rest = input;
 
-        // Check if we need to update the config with new defaults
-        let default_config = Config::default();
-        let serialized_config = toml::to_string(&config)?;
-        let serialized_default = toml::to_string(&default_config)?;
+    if let Some(home) = home
+        && (rest == "~" || rest.starts_with("~/"))
+    {
+        output.push_str(home);
+        rest = &rest[1..];
+    }
+
+    while let Some(dollar) = rest.find('$') {
+        output.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
 
-        // Only write back if the config would be different (missing fields that need defaults)
-        if serialized_config != serialized_default && !has_all_fields(&config, &default_config) {
-            write_config(&config_path, &config)?;
+        // ${VAR} or $VAR, where VAR is made of letters, digits and underscores
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        match lookup(name).filter(|_| !name.is_empty()) {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&rest[dollar..dollar + 1 + consumed]),
         }
+        rest = &after[consumed..];
+    }
+    output.push_str(rest);
 
-        config
-    } else {
-        // Create default config for first time use
-        let default_config = Config::default();
-        write_config(&config_path, &default_config)?;
-        default_config
-    };
+    output
+}
 
-    Ok(config)
+/// Pick the config file: an explicit `--config` path, a named profile under
+/// `profiles/` in the config dir, or the default `config.toml`. Only
+/// `create_profile` (for `config edit`) lets a missing profile through
+pub fn resolve_config_path(
+    path: Option<PathBuf>,
+    profile: Option<&str>,
+    create_profile: bool,
+) -> Result<PathBuf> {
+    if let Some(path) = path {
+        // Don't silently create a default config for a mistyped path
+        if !path.exists() {
+            return Err(anyhow::anyhow!("Config file not found: {}", path.display()));
+        }
+        return Ok(path);
+    }
+
+    match profile {
+        Some(profile) => {
+            let config_dir = dirs::config_dir()
+                .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+            get_profile_path(&config_dir, profile, create_profile)
+        }
+        None => get_config_path(),
+    }
 }
 
-fn write_config(path: &PathBuf, config: &Config) -> Result<()> {
-    let config_str = toml::to_string(config)?;
-    fs::write(path, config_str)?;
-    Ok(())
+fn get_profile_path(config_dir: &Path, profile: &str, create: bool) -> Result<PathBuf> {
+    if profile.is_empty() || profile.contains(['/', '\\']) || profile.starts_with('.') {
+        return Err(anyhow::anyhow!("Invalid profile name: '{}'", profile));
+    }
+
+    let profiles_dir = config_dir.join("tmux_portal").join("profiles");
+    let path = profiles_dir.join(format!("{profile}.toml"));
+    // Like `--config`, a mistyped profile shouldn't become a fresh default one
+    if !create && !path.exists() {
+        return Err(anyhow::anyhow!(
+            "Profile '{}' not found (expected {})",
+            profile,
+            path.display()
+        ));
+    }
+    if !profiles_dir.exists() {
+        fs::create_dir_all(&profiles_dir)?;
+    }
+
+    Ok(path)
 }
 
 fn get_config_path() -> Result<PathBuf> {
@@ -487,7 +1826,10 @@ fn get_config_path() -> Result<PathBuf> {
     Ok(tmux_portal_config_dir.join("config.toml"))
 }
 
-pub fn get_history_path() -> Result<PathBuf> {
+/// `<stem>.json` in the config directory for the default tmux server, and
+/// `<stem>-<name>.json` for others (`tmux -L <name>`), since the files hold
+/// server-specific window IDs
+fn data_file(stem: &str, socket_name: Option<&str>) -> Result<PathBuf> {
     let config_dir =
         dirs::config_dir().ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
 
@@ -496,65 +1838,48 @@ pub fn get_history_path() -> Result<PathBuf> {
         fs::create_dir_all(&tmux_portal_config_dir)?;
     }
 
-    Ok(tmux_portal_config_dir.join("history.json"))
+    let file_name = match socket_name {
+        Some(name) if name != "default" => format!("{stem}-{name}.json"),
+        _ => format!("{stem}.json"),
+    };
+    Ok(tmux_portal_config_dir.join(file_name))
 }
 
-pub fn get_pins_path() -> Result<PathBuf> {
-    let config_dir =
-        dirs::config_dir().ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
-
-    let tmux_portal_config_dir = config_dir.join("tmux_portal");
-    if !tmux_portal_config_dir.exists() {
-        fs::create_dir_all(&tmux_portal_config_dir)?;
-    }
+/// Recently visited windows of one tmux server
+pub fn get_history_path(socket_name: Option<&str>) -> Result<PathBuf> {
+    data_file("history", socket_name)
+}
 
-    Ok(tmux_portal_config_dir.join("pins.json"))
+/// Per-session recent windows of one tmux server
+pub fn get_session_history_path(socket_name: Option<&str>) -> Result<PathBuf> {
+    data_file("session-history", socket_name)
 }
 
-// Check if the loaded config has all fields from the default config
-// This is a simple check to determine if we need to write back the config
-fn has_all_fields(config: &Config, default_config: &Config) -> bool {
-    // Convert both configs to toml::Value for easier comparison
-    let config_value = match toml::to_string(config) {
-        Ok(s) => match toml::from_str::<toml::Value>(&s) {
-            Ok(v) => v,
-            Err(_) => return false,
-        },
-        Err(_) => return false,
-    };
+/// Usage statistics of one tmux server
+pub fn get_usage_path(socket_name: Option<&str>) -> Result<PathBuf> {
+    data_file("usage", socket_name)
+}
 
-    let default_value = match toml::to_string(default_config) {
-        Ok(s) => match toml::from_str::<toml::Value>(&s) {
-            Ok(v) => v,
-            Err(_) => return false,
-        },
-        Err(_) => return false,
-    };
+/// Archived sessions of one tmux server
+pub fn get_archive_path(socket_name: Option<&str>) -> Result<PathBuf> {
+    data_file("archive", socket_name)
+}
 
-    // Check if all fields in default are present in config
-    has_all_fields_recursive(&config_value, &default_value)
+/// Pins of one tmux server
+pub fn get_pins_path(socket_name: Option<&str>) -> Result<PathBuf> {
+    data_file("pins", socket_name)
 }
 
-fn has_all_fields_recursive(config: &toml::Value, default: &toml::Value) -> bool {
-    match (config, default) {
-        (toml::Value::Table(config_map), toml::Value::Table(default_map)) => {
-            // Check if all keys in default_map exist in config_map
-            for (key, default_value) in default_map {
-                match config_map.get(key) {
-                    Some(config_value) => {
-                        // Recursively check nested structures
-                        if !has_all_fields_recursive(config_value, default_value) {
-                            return false;
-                        }
-                    }
-                    None => return false, // Missing key
-                }
-            }
-            true
-        }
-        // For non-table types, just check if they're the same type
-        _ => std::mem::discriminant(config) == std::mem::discriminant(default),
+pub fn get_log_path() -> Result<PathBuf> {
+    let config_dir =
+        dirs::config_dir().ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    let tmux_portal_config_dir = config_dir.join("tmux_portal");
+    if !tmux_portal_config_dir.exists() {
+        fs::create_dir_all(&tmux_portal_config_dir)?;
     }
+
+    Ok(tmux_portal_config_dir.join("debug.log"))
 }
 
 #[cfg(test)]
@@ -571,5 +1896,208 @@ mod tests {
         assert_eq!(parse_color("0x11161f"), Color::Rgb(17, 22, 31));
         assert_eq!(parse_color("42"), Color::Indexed(42));
         assert_eq!(parse_color("invalid"), Color::White);
+        assert_eq!(parse_color("default"), Color::Reset);
+        assert_eq!(parse_color("None"), Color::Reset);
+    }
+
+    #[test]
+    fn test_popup_anchor() {
+        let area = Rect::new(0, 2, 80, 20);
+        assert_eq!(PopupAnchor::Top.y(area, 5), 2);
+        assert_eq!(PopupAnchor::Center.y(area, 5), 9);
+        // One row stays free for the status bar
+        assert_eq!(PopupAnchor::Bottom.y(area, 5), 16);
+        assert_eq!(PopupAnchor::Bottom.y(area, 30), 2);
+
+        let popup: PopupConfig = toml::from_str("anchor = \"bottom\"").unwrap();
+        assert_eq!(popup.anchor, PopupAnchor::Bottom);
+        assert_eq!(popup.max_width, 80);
+        assert!(popup.boxed_search_bar());
+    }
+
+    #[test]
+    fn test_style_modifiers() {
+        let style = StyleConfig {
+            strikethrough: Some(true),
+            slow_blink: Some(true),
+            underline_color: Some("red".to_string()),
+            ..Default::default()
+        }
+        .to_ratatui_style();
+
+        assert!(style.add_modifier.contains(Modifier::CROSSED_OUT));
+        assert!(style.add_modifier.contains(Modifier::SLOW_BLINK));
+        assert!(!style.add_modifier.contains(Modifier::RAPID_BLINK));
+        assert_eq!(style.underline_color, Some(Color::Red));
+    }
+
+    #[test]
+    fn test_monochrome_keeps_selection_visible() {
+        let mut config = Config::default();
+        config.make_monochrome();
+
+        let selected = config.colors.window_selected.to_ratatui_style();
+        assert_eq!(selected.fg, None);
+        assert_eq!(selected.bg, None);
+        assert!(selected.add_modifier.contains(Modifier::REVERSED));
+        assert_eq!(config.colors.error_text.to_ratatui_style().fg, None);
+        // The session this client is in still stands out
+        let current = config.colors.session_current.to_ratatui_style();
+        assert_eq!(current.fg, None);
+        assert!(current.add_modifier.contains(Modifier::UNDERLINED));
+    }
+
+    #[test]
+    fn test_check_config() {
+        let defaults = toml::to_string(&Config::default()).unwrap();
+        let report = check_config(&defaults);
+        assert!(report.error.is_none());
+        assert!(report.warnings.is_empty());
+
+        let text = defaults
+            .replace("search_patterns = []\n", "")
+            .replace("[colors.session]", "[colors.session]\nbgg = \"red\"")
+            + "\n[[search_patterns]]\nname = \"code\"\npaths = []\nnmae = \"typo\"\n";
+        let report = check_config(&text);
+        assert!(report.error.is_none());
+        let warnings: Vec<&str> = report.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            warnings,
+            [
+                "unknown key `colors.session.bgg`",
+                "unknown key `search_patterns.nmae`"
+            ]
+        );
+        let line = text.lines().position(|l| l.starts_with("nmae")).unwrap() + 1;
+        assert_eq!(
+            (report.warnings[1].line, report.warnings[1].column),
+            (line, 1)
+        );
+
+        let error = check_config("mouse = [\n").error.unwrap();
+        assert_eq!(error.line, 2);
+    }
+
+    #[test]
+    fn test_lenient_config() {
+        let (config, issues) = parse_config("").unwrap();
+        assert!(issues.is_empty());
+        assert!(config.mouse);
+
+        let text = "\
+mouse = \"yes\"
+exit_on_switch = false
+window_colors = [\"red\", 5]
+
+[colors.session]
+fg = \"blu\"
+bold = true
+
+[[search_patterns]]
+name = \"code\"
+";
+        let (config, issues) = parse_config(text).unwrap();
+        // What's wrong falls back to its default, the rest applies
+        assert!(config.mouse);
+        assert!(!config.exit_on_switch);
+        assert_eq!(config.window_colors, ["red"]);
+        assert_eq!(config.colors.session.fg, None);
+        assert_eq!(config.colors.session.bold, Some(true));
+        assert!(config.search_patterns.is_empty());
+        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            issues,
+            [
+                "1:1: `mouse`: invalid type: string \"yes\", expected a boolean, ignored",
+                "3:25: `window_colors`: invalid type: integer `5`, expected a string, ignored",
+                "6:1: invalid color `blu` for `colors.session.fg`, ignored",
+                "9:1: `search_patterns`: missing field `paths`, ignored",
+            ]
+        );
+
+        // Only broken syntax is an error, and the file is left as it is
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, text).unwrap();
+        load_config(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+        fs::write(&path, "mouse = [\n").unwrap();
+        let error = load_config(&path).unwrap_err().to_string();
+        assert!(error.starts_with(&format!("{}:2:1: ", path.display())));
+    }
+
+    #[test]
+    fn test_missing_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tmux_portal/profiles/work.toml");
+        let error = get_profile_path(dir.path(), "work", false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Profile 'work' not found (expected {})", path.display())
+        );
+        assert!(!path.parent().unwrap().exists());
+
+        // `config edit` may start a new one
+        assert_eq!(get_profile_path(dir.path(), "work", true).unwrap(), path);
+        fs::write(&path, "").unwrap();
+        assert_eq!(get_profile_path(dir.path(), "work", false).unwrap(), path);
+        assert!(get_profile_path(dir.path(), "../work", true).is_err());
+    }
+
+    #[test]
+    fn test_themes() {
+        let defaults = toml::to_string(&Config::default()).unwrap();
+        let mut config: Config =
+            toml::from_str(&defaults.replace("theme = \"custom\"", "theme = \"bold-only\""))
+                .unwrap();
+        assert_eq!(config.theme, Theme::BoldOnly);
+        config.apply_theme();
+        assert!(config.monochrome);
+        for style in config.colors.styles_mut() {
+            let style = style.to_ratatui_style();
+            assert_eq!((style.fg, style.bg), (None, None));
+        }
+        let selected = config.colors.window_selected.to_ratatui_style();
+        assert!(selected.add_modifier.contains(Modifier::REVERSED));
+        let highlight = config.colors.search_highlight.to_ratatui_style();
+        assert!(highlight.add_modifier.contains(Modifier::BOLD));
+
+        config.theme = Theme::HighContrast;
+        config.apply_theme();
+        let selected = config.colors.window_selected.to_ratatui_style();
+        assert_eq!(selected.fg, Some(Color::Black));
+        assert_eq!(selected.bg, Some(Color::Yellow));
+        // Nothing is left dim or gray
+        for style in config.colors.styles_mut() {
+            assert_ne!(style.dim, Some(true));
+            assert_ne!(style.fg.as_deref(), Some("dark_gray"));
+        }
+
+        // [colors] wins without a theme
+        config.theme = Theme::Custom;
+        config.colors = ColorConfig::default();
+        config.apply_theme();
+        assert_eq!(
+            config.colors.window_selected.fg,
+            ColorConfig::default().window_selected.fg
+        );
+    }
+
+    #[test]
+    fn test_expand_vars() {
+        let lookup = |name: &str| match name {
+            "HOME" => Some("/home/me".to_string()),
+            "WORK" => Some("/srv/work".to_string()),
+            _ => None,
+        };
+        let expand = |input| expand_vars(input, Some("/home/me"), lookup);
+
+        assert_eq!(expand("~"), "/home/me");
+        assert_eq!(expand("~/git"), "/home/me/git");
+        assert_eq!(expand("$HOME/git"), "/home/me/git");
+        assert_eq!(expand("${WORK}/repos"), "/srv/work/repos");
+        assert_eq!(expand("$WORK_DIR/x"), "$WORK_DIR/x");
+        assert_eq!(expand("/a/~b/$"), "/a/~b/$");
+        assert_eq!(expand("${UNCLOSED"), "${UNCLOSED");
     }
 }
@@ -0,0 +1,223 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::tmux::ServerInfo;
+
+// Oldest tmux the portal is tested against
+pub const MIN_VERSION: (u32, u32) = (3, 0);
+
+/// Portal features built on commands or flags newer than MIN_VERSION
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feature {
+    Popup,     // display-popup, for the scratch session and popup actions
+    Clipboard, // set-buffer -w
+}
+
+impl Feature {
+    pub const ALL: [Feature; 2] = [Feature::Popup, Feature::Clipboard];
+
+    pub fn min_version(self) -> (u32, u32) {
+        match self {
+            Feature::Popup | Feature::Clipboard => (3, 2),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Popup => "popups",
+            Feature::Clipboard => "clipboard",
+        }
+    }
+
+    // Servers we can't tell the version of get the benefit of the doubt
+    pub fn supported_by(self, version: Option<(u32, u32)>) -> bool {
+        version.is_none_or(|version| version >= self.min_version())
+    }
+
+    /// Message for when the feature is used anyway
+    pub fn unsupported_message(self) -> String {
+        let (major, minor) = self.min_version();
+        let what = match self {
+            Feature::Popup => "Popups need",
+            Feature::Clipboard => "Copying to the clipboard needs",
+        };
+        format!("{what} tmux {major}.{minor} or newer")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Ok,
+    Warning, // Works, but something may be off
+    Error,   // The portal can't work like this
+}
+
+/// One line of the health report
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub label: &'static str,
+    pub value: String,
+    pub status: Status,
+}
+
+impl Check {
+    fn new(label: &'static str, value: impl Into<String>, status: Status) -> Self {
+        Self {
+            label,
+            value: value.into(),
+            status,
+        }
+    }
+}
+
+/// Major and minor of versions like "3.3a", "3.2-rc2" or "next-3.5"
+pub fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let version = version.strip_prefix("next-").unwrap_or(version);
+    let (major, rest) = version.split_once('.')?;
+    let minor: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// The first `tmux` in `$PATH`, which is what every command runs
+pub fn find_tmux_binary() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join("tmux"))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Everything worth knowing about the tmux setup, worst problems flagged
+pub fn checks(binary: Option<PathBuf>, info: &Result<ServerInfo>) -> Vec<Check> {
+    let mut checks = vec![match binary {
+        Some(path) => Check::new("tmux binary", path.display().to_string(), Status::Ok),
+        None => Check::new("tmux binary", "not found in $PATH", Status::Error),
+    }];
+
+    let info = match info {
+        Ok(info) => info,
+        Err(e) => {
+            checks.push(Check::new(
+                "server",
+                format!("not reachable: {e}"),
+                Status::Error,
+            ));
+            return checks;
+        }
+    };
+
+    let version = parse_version(&info.version);
+    let (min_major, min_minor) = MIN_VERSION;
+    checks.push(match version {
+        Some(version) if version >= MIN_VERSION => {
+            Check::new("server version", info.version.clone(), Status::Ok)
+        }
+        Some(_) => Check::new(
+            "server version",
+            format!("{}, need at least {min_major}.{min_minor}", info.version),
+            Status::Error,
+        ),
+        None => Check::new(
+            "server version",
+            format!("{} (unknown format)", info.version),
+            Status::Warning,
+        ),
+    });
+    let missing: Vec<String> = Feature::ALL
+        .iter()
+        .filter(|feature| !feature.supported_by(version))
+        .map(|feature| {
+            let (major, minor) = feature.min_version();
+            format!("{} (needs {major}.{minor})", feature.name())
+        })
+        .collect();
+    if !missing.is_empty() {
+        checks.push(Check::new(
+            "features",
+            format!("disabled {}", missing.join(", ")),
+            Status::Warning,
+        ));
+    }
+    checks.push(Check::new("socket", info.socket_path.clone(), Status::Ok));
+    checks.push(Check::new("server pid", info.pid.clone(), Status::Ok));
+    checks.push(Check::new("clients", info.clients.to_string(), Status::Ok));
+    checks.push(if info.missing_formats.is_empty() {
+        Check::new("format fields", "all available", Status::Ok)
+    } else {
+        Check::new(
+            "format fields",
+            format!("missing {}", info.missing_formats.join(", ")),
+            Status::Warning,
+        )
+    });
+    checks
+}
+
+/// Report for `tmux-portal doctor`, false if anything is an error
+pub fn print_report(checks: &[Check]) -> bool {
+    let width = checks.iter().map(|c| c.label.len()).max().unwrap_or(0);
+    for check in checks {
+        let status = match check.status {
+            Status::Ok => "ok",
+            Status::Warning => "warn",
+            Status::Error => "FAIL",
+        };
+        println!("{status:<4}  {:<width$}  {}", check.label, check.value);
+    }
+    checks.iter().all(|c| c.status != Status::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn info(version: &str, missing_formats: &[&str]) -> ServerInfo {
+        ServerInfo {
+            version: version.to_string(),
+            socket_path: "/tmp/tmux-1000/default".to_string(),
+            pid: "42".to_string(),
+            clients: 1,
+            missing_formats: missing_formats.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    fn statuses(checks: &[Check]) -> Vec<(&str, Status)> {
+        checks.iter().map(|c| (c.label, c.status)).collect()
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("3.3a"), Some((3, 3)));
+        assert_eq!(parse_version("3.2-rc2"), Some((3, 2)));
+        assert_eq!(parse_version("next-3.5"), Some((3, 5)));
+        assert_eq!(parse_version("2.9"), Some((2, 9)));
+        assert_eq!(parse_version("master"), None);
+
+        assert!(Feature::Popup.supported_by(Some((3, 2))));
+        assert!(!Feature::Popup.supported_by(Some((3, 1))));
+        assert!(Feature::Clipboard.supported_by(None));
+    }
+
+    #[test]
+    fn test_checks() {
+        let healthy = checks(Some(PathBuf::from("/usr/bin/tmux")), &Ok(info("3.3a", &[])));
+        assert!(healthy.iter().all(|c| c.status == Status::Ok));
+
+        let old = checks(None, &Ok(info("2.9a", &["window_linked"])));
+        let statuses = statuses(&old);
+        assert!(statuses.contains(&("tmux binary", Status::Error)));
+        assert!(statuses.contains(&("server version", Status::Error)));
+        assert!(statuses.contains(&("format fields", Status::Warning)));
+
+        let no_popups = checks(None, &Ok(info("3.1c", &[])));
+        let features = no_popups.iter().find(|c| c.label == "features").unwrap();
+        assert_eq!(
+            features.value,
+            "disabled popups (needs 3.2), clipboard (needs 3.2)"
+        );
+
+        let down = checks(None, &Err(anyhow!("no server running")));
+        assert_eq!(down.len(), 2);
+        assert_eq!(down[1].value, "not reachable: no server running");
+    }
+}
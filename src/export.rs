@@ -0,0 +1,162 @@
+use std::path::Path;
+
+use crate::tmux::{TmuxSession, TmuxWindow};
+
+/// The session/window tree as a markdown list, for standup notes or docs
+pub fn render_markdown(sessions: &[TmuxSession], home: Option<&Path>) -> String {
+    let mut out = String::new();
+    for session in sessions {
+        out.push_str(&format!(
+            "- **{}**{}\n",
+            session.name,
+            session_flags(session)
+        ));
+        for window in &session.windows {
+            out.push_str(&format!(
+                "  - {}: {} `{}` in `{}`{}\n",
+                window.index,
+                window.name,
+                window.command,
+                short_path(&window.path, home),
+                window_flags(window)
+            ));
+        }
+    }
+    out
+}
+
+/// The session/window tree as indented plain text
+pub fn render_text(sessions: &[TmuxSession], home: Option<&Path>) -> String {
+    let mut out = String::new();
+    for session in sessions {
+        out.push_str(&format!("{}{}\n", session.name, session_flags(session)));
+        let name_width = session
+            .windows
+            .iter()
+            .map(|w| format!("{}: {}", w.index, w.name).chars().count())
+            .max()
+            .unwrap_or(0);
+        let command_width = session
+            .windows
+            .iter()
+            .map(|w| w.command.chars().count())
+            .max()
+            .unwrap_or(0);
+        for window in &session.windows {
+            let name = format!("{}: {}", window.index, window.name);
+            let line = format!(
+                "  {name:<name_width$}  {:<command_width$}  {}{}",
+                window.command,
+                short_path(&window.path, home),
+                window_flags(window)
+            );
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn session_flags(session: &TmuxSession) -> String {
+    match session.attached {
+        0 => String::new(),
+        _ => " (attached)".to_string(),
+    }
+}
+
+// Active, pane count, zoomed, linked and dead, as far as they apply
+fn window_flags(window: &TmuxWindow) -> String {
+    let mut flags = Vec::new();
+    if window.active {
+        flags.push("active".to_string());
+    }
+    if window.panes > 1 {
+        flags.push(format!("{} panes", window.panes));
+    }
+    if window.zoomed {
+        flags.push("zoomed".to_string());
+    }
+    if window.linked {
+        flags.push("linked".to_string());
+    }
+    if window.dead {
+        flags.push("dead".to_string());
+    }
+    match flags.is_empty() {
+        true => String::new(),
+        false => format!(" ({})", flags.join(", ")),
+    }
+}
+
+// `/home/me/src` reads better as `~/src`
+fn short_path(path: &str, home: Option<&Path>) -> String {
+    match home.and_then(|home| Path::new(path).strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(index: u32, name: &str, path: &str) -> TmuxWindow {
+        TmuxWindow {
+            id: format!("@{index}"),
+            index,
+            name: name.to_string(),
+            session_name: "main".to_string(),
+            active: false,
+            panes: 1,
+            zoomed: false,
+            activity: 0,
+            command: "zsh".to_string(),
+            path: path.to_string(),
+            dead: false,
+            linked: false,
+            piped: false,
+            note: None,
+            color: None,
+            user_options: Default::default(),
+        }
+    }
+
+    fn sessions() -> Vec<TmuxSession> {
+        let mut editor = window(1, "editor", "/home/me/src/app");
+        editor.active = true;
+        editor.panes = 2;
+        editor.command = "nvim".to_string();
+        vec![TmuxSession {
+            name: "main".to_string(),
+            windows: vec![editor, window(2, "shell", "/home/me")],
+            attached: 1,
+            activity: 0,
+            start_dir: None,
+            base_index: 1,
+            renumber: false,
+            note: None,
+        }]
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let home = Some(Path::new("/home/me"));
+        assert_eq!(
+            render_markdown(&sessions(), home),
+            concat!(
+                "- **main** (attached)\n",
+                "  - 1: editor `nvim` in `~/src/app` (active, 2 panes)\n",
+                "  - 2: shell `zsh` in `~`\n",
+            )
+        );
+        assert_eq!(
+            render_text(&sessions(), None),
+            concat!(
+                "main (attached)\n",
+                "  1: editor  nvim  /home/me/src/app (active, 2 panes)\n",
+                "  2: shell   zsh   /home/me\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+
+use crate::tmux::TmuxWindow;
+
+/// Quick search input split into `key:value` predicates and the remaining
+/// fuzzy text, e.g. `cmd:vim path:api main` or `session:work idle:7d`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    pub predicates: Vec<Predicate>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Command(String),   // Substring of the active pane's command
+    Path(String),      // Substring of the active pane's directory
+    Session(String),   // Substring of the session name
+    Active(bool),      // Window is (not) the active one of its session
+    Dead(bool),        // Active pane's process has (not) exited
+    ActiveWithin(i64), // Output within this many seconds
+    IdleFor(i64),      // No output for at least this many seconds
+}
+
+impl Filter {
+    /// Words with an unknown key or bad value stay part of the fuzzy text, so
+    /// `main:editor` still searches session:window names
+    pub fn parse(input: &str) -> Self {
+        let mut predicates = Vec::new();
+        let mut words = Vec::new();
+
+        for word in input.split_whitespace() {
+            match parse_predicate(word) {
+                Some(predicate) => predicates.push(predicate),
+                None => words.push(word),
+            }
+        }
+
+        Self {
+            predicates,
+            text: words.join(" "),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.predicates.is_empty() && self.text.is_empty()
+    }
+
+    // Predicates that only windows can satisfy hide session lines
+    pub fn matches_session(&self, session_name: &str) -> bool {
+        self.predicates.iter().all(|predicate| match predicate {
+            Predicate::Session(value) => contains_ignore_case(session_name, value),
+            _ => false,
+        })
+    }
+
+    // `now` is unix time, for the activity predicates
+    pub fn matches_window(&self, window: &TmuxWindow, now: i64) -> bool {
+        self.predicates.iter().all(|predicate| match predicate {
+            Predicate::Command(value) => contains_ignore_case(&window.command, value),
+            Predicate::Path(value) => contains_ignore_case(&window.path, value),
+            Predicate::Session(value) => contains_ignore_case(&window.session_name, value),
+            Predicate::Active(active) => window.active == *active,
+            Predicate::Dead(dead) => window.dead == *dead,
+            Predicate::ActiveWithin(seconds) => now - window.activity <= *seconds,
+            Predicate::IdleFor(seconds) => now - window.activity >= *seconds,
+        })
+    }
+
+    /// Predicates and a fuzzy match of the text against `session:window`
+    pub fn accepts_window(&self, window: &TmuxWindow, now: i64) -> bool {
+        if !self.matches_window(window, now) {
+            return false;
+        }
+        self.text.is_empty()
+            || SkimMatcherV2::default()
+                .ignore_case()
+                .fuzzy_match(
+                    &format!("{}:{}", window.session_name, window.name),
+                    &self.text,
+                )
+                .is_some()
+    }
+}
+
+/// Durations like "30s", "5m", "2h", "7d" or "2w" in seconds
+pub fn parse_duration(input: &str) -> Option<i64> {
+    let unit = input.chars().last()?;
+    let amount: i64 = input[..input.len() - unit.len_utf8()].parse().ok()?;
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 7 * 86400,
+        _ => return None,
+    };
+    amount.checked_mul(seconds)
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match value {
+        "yes" | "true" | "1" => Some(true),
+        "no" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_predicate(word: &str) -> Option<Predicate> {
+    let (key, value) = word.split_once(':')?;
+    if value.is_empty() {
+        return None;
+    }
+
+    match key {
+        "cmd" => Some(Predicate::Command(value.to_string())),
+        "path" => Some(Predicate::Path(value.to_string())),
+        "session" => Some(Predicate::Session(value.to_string())),
+        "active" => parse_flag(value).map(Predicate::Active),
+        "dead" => parse_flag(value).map(Predicate::Dead),
+        "activity" => parse_duration(value).map(Predicate::ActiveWithin),
+        "idle" => parse_duration(value).map(Predicate::IdleFor),
+        _ => None,
+    }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(session_name: &str, command: &str, path: &str, active: bool) -> TmuxWindow {
+        TmuxWindow {
+            id: "@1".to_string(),
+            index: 0,
+            name: "editor".to_string(),
+            session_name: session_name.to_string(),
+            active,
+            panes: 1,
+            zoomed: false,
+            activity: 1000,
+            command: command.to_string(),
+            path: path.to_string(),
+            dead: false,
+            linked: false,
+            piped: false,
+            note: None,
+            color: None,
+            user_options: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        let filter = Filter::parse("cmd:vim  main:editor active:yes path: active:maybe");
+        assert_eq!(
+            filter.predicates,
+            [
+                Predicate::Command("vim".to_string()),
+                Predicate::Active(true)
+            ]
+        );
+        assert_eq!(filter.text, "main:editor path: active:maybe");
+        assert!(Filter::parse("  ").is_empty());
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let filter = Filter::parse("cmd:VIM path:api session:work");
+        assert!(filter.matches_window(&window("work", "nvim", "/src/api", false), 1000));
+        assert!(!filter.matches_window(&window("work", "zsh", "/src/api", false), 1000));
+        assert!(!filter.matches_window(&window("home", "nvim", "/src/api", false), 1000));
+        assert!(!filter.matches_session("work"));
+
+        assert!(Filter::parse("session:wo").matches_session("work"));
+        assert!(!Filter::parse("active:no").matches_window(&window("work", "", "", true), 1000));
+    }
+
+    #[test]
+    fn test_activity_predicates() {
+        assert_eq!(parse_duration("7d"), Some(7 * 86400));
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("5y"), None);
+
+        let window = window("work", "", "", false);
+        assert!(Filter::parse("activity:1h").matches_window(&window, 1000 + 3600));
+        assert!(!Filter::parse("activity:1h").matches_window(&window, 1001 + 3600));
+        assert!(Filter::parse("idle:2h").matches_window(&window, 1000 + 7200));
+        assert!(!Filter::parse("idle:2h").matches_window(&window, 1000));
+        assert!(Filter::parse("work:ed idle:0s").accepts_window(&window, 1000));
+        assert!(!Filter::parse("work:xyz").accepts_window(&window, 1000));
+    }
+}
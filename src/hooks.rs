@@ -0,0 +1,212 @@
+use anyhow::{Result, anyhow};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::config::HooksConfig;
+use crate::tmux::shell_quote;
+
+/// Values substituted into hook command templates
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    pub session: String,
+    pub window: String,
+    pub window_id: String,
+    pub path: String,
+}
+
+impl HookContext {
+    /// Replace `{session}`, `{window}`, `{window_id}` and `{path}` in a template
+    pub fn expand(&self, template: &str) -> String {
+        template
+            .replace("{session}", &self.session)
+            .replace("{window_id}", &self.window_id)
+            .replace("{window}", &self.window)
+            .replace("{path}", &self.path)
+    }
+
+    /// Like `expand`, but with every value shell-quoted, for templates run
+    /// through `sh -c`. Tmux names are user-controlled, so a window called
+    /// `a'; rm -rf ~ #` must stay a single argument
+    pub fn expand_quoted(&self, template: &str) -> String {
+        HookContext {
+            session: shell_quote(&self.session),
+            window: shell_quote(&self.window),
+            window_id: shell_quote(&self.window_id),
+            path: shell_quote(&self.path),
+        }
+        .expand(template)
+    }
+
+    /// The selection as environment variables for commands run with `|`
+    pub fn env(&self) -> Vec<(String, String)> {
+        vec![
+            ("PORTAL_SESSION".to_string(), self.session.clone()),
+            ("PORTAL_WINDOW".to_string(), self.window.clone()),
+            ("PORTAL_PATH".to_string(), self.path.clone()),
+        ]
+    }
+}
+
+/// How a shell command run with `|` ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellRun {
+    pub status: Option<i32>, // None when killed by a signal
+    pub output: String,      // Last line of stdout, or stderr when that's empty
+}
+
+/// Run a command typed at the `|` prompt in `context.path`, with the
+/// selection in `PORTAL_*` variables
+pub fn run_shell(command: &str, context: &HookContext) -> Result<ShellRun> {
+    let mut shell = Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(command)
+        .envs(context.env())
+        .stdin(Stdio::null());
+    // A pane whose directory is unknown or was deleted would fail the spawn,
+    // so the command runs in $HOME then
+    let path = Path::new(&context.path);
+    if !context.path.is_empty() && path.is_dir() {
+        shell.current_dir(path);
+    } else if let Some(home) = dirs::home_dir() {
+        shell.current_dir(home);
+    }
+    let output = shell.output()?;
+
+    let last_line = |bytes: &[u8]| {
+        String::from_utf8_lossy(bytes)
+            .lines()
+            .rfind(|line| !line.trim().is_empty())
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    };
+    let mut text = last_line(&output.stdout);
+    if text.is_empty() {
+        text = last_line(&output.stderr);
+    }
+
+    Ok(ShellRun {
+        status: output.status.code(),
+        output: text,
+    })
+}
+
+/// Run a hook command through `sh -c` after quoted placeholder expansion
+pub fn run_hook(command: &str, context: &HookContext) -> Result<()> {
+    let expanded = context.expand_quoted(command);
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(&expanded)
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Hook '{}' failed: {}", expanded, stderr.trim()));
+    }
+
+    Ok(())
+}
+
+/// Run the configured pre/post switch hooks around a switch operation
+pub fn with_switch_hooks<F>(hooks: &HooksConfig, context: &HookContext, switch: F) -> Result<()>
+where
+    F: FnOnce() -> Result<()>,
+{
+    if let Some(command) = &hooks.pre_switch {
+        run_hook(command, context)?;
+    }
+
+    switch()?;
+
+    if let Some(command) = &hooks.post_switch {
+        // The switch already happened, a failing post hook must not undo it
+        let _ = run_hook(command, context);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_placeholders() {
+        let context = HookContext {
+            session: "work".to_string(),
+            window: "api".to_string(),
+            window_id: "@3".to_string(),
+            path: "/home/user/work/api".to_string(),
+        };
+
+        assert_eq!(
+            context.expand("echo {session}:{window} {window_id} {path}"),
+            "echo work:api @3 /home/user/work/api"
+        );
+        assert_eq!(context.expand("no placeholders"), "no placeholders");
+        assert_eq!(
+            context.expand_quoted("echo {session}:{window}"),
+            "echo 'work':'api'"
+        );
+    }
+
+    #[test]
+    fn test_hook_quotes_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = HookContext {
+            session: "work $(touch pwned)".to_string(),
+            window: "a'; touch pwned #".to_string(),
+            window_id: "@3".to_string(),
+            path: dir.path().to_string_lossy().to_string(),
+        };
+
+        run_hook("cd {path} && echo {session}/{window} > out", &context).unwrap();
+
+        assert!(!dir.path().join("pwned").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("out")).unwrap(),
+            "work $(touch pwned)/a'; touch pwned #\n"
+        );
+    }
+
+    #[test]
+    fn test_run_shell() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = HookContext {
+            session: "work".to_string(),
+            window: "api".to_string(),
+            window_id: "@3".to_string(),
+            path: dir.path().to_string_lossy().to_string(),
+        };
+
+        let run = run_shell("echo $PORTAL_SESSION:$PORTAL_WINDOW; pwd", &context).unwrap();
+        assert_eq!(run.status, Some(0));
+        assert_eq!(
+            run.output,
+            dir.path().canonicalize().unwrap().to_string_lossy()
+        );
+
+        // Without its directory the command still runs, in $HOME
+        let home = dirs::home_dir().unwrap().canonicalize().unwrap();
+        for path in ["", "/nonexistent/portal-test"] {
+            let context = HookContext {
+                path: path.to_string(),
+                ..context.clone()
+            };
+            let run = run_shell("pwd -P", &context).unwrap();
+            assert_eq!(run.output, home.to_string_lossy());
+        }
+
+        let run = run_shell("echo oops >&2; exit 3", &context).unwrap();
+        assert_eq!(
+            run,
+            ShellRun {
+                status: Some(3),
+                output: "oops".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+use std::ops::Deref;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// A line of text typed into a prompt or search box. The cursor moves and
+/// deletes by grapheme, so an emoji or a letter with combining accents is a
+/// single step
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextInput {
+    text: String,
+    cursor: usize, // Byte offset, always on a grapheme boundary
+}
+
+impl TextInput {
+    /// Input holding `text`, with the cursor at its end
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let cursor = text.len();
+        Self { text, cursor }
+    }
+
+    pub fn set(&mut self, text: impl Into<String>) {
+        *self = Self::new(text);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Columns before the cursor, where the terminal cursor goes
+    pub fn cursor_column(&self) -> usize {
+        self.text[..self.cursor].width()
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        // A combining character joins the grapheme before it
+        self.cursor = self.boundary_at_or_after(self.cursor);
+    }
+
+    pub fn backspace(&mut self) {
+        let start = self.previous_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn delete(&mut self) {
+        let end = self.next_boundary();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.previous_boundary();
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    /// Apply an editing key: characters, Backspace/Delete, ←/→, Home/End and
+    /// the readline Ctrl-a/e/b/f/d/u/k. Returns false for keys that aren't
+    /// editing, e.g. Enter
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.text.len(),
+            KeyCode::Char('b') if ctrl => self.left(),
+            KeyCode::Char('f') if ctrl => self.right(),
+            KeyCode::Char('d') if ctrl => self.delete(),
+            KeyCode::Char('u') if ctrl => {
+                self.text.replace_range(..self.cursor, "");
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if ctrl => self.text.truncate(self.cursor),
+            KeyCode::Char(c) if !ctrl => self.insert(c),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.left(),
+            KeyCode::Right => self.right(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |g| self.cursor + g.len())
+    }
+
+    fn boundary_at_or_after(&self, offset: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .map(|(i, g)| i + g.len())
+            .find(|&end| end >= offset)
+            .unwrap_or(self.text.len())
+    }
+}
+
+impl Deref for TextInput {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for TextInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl PartialEq<&str> for TextInput {
+    fn eq(&self, other: &&str) -> bool {
+        self.text == *other
+    }
+}
+
+impl PartialEq<String> for TextInput {
+    fn eq(&self, other: &String) -> bool {
+        &self.text == other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_edit_by_grapheme() {
+        let mut input = TextInput::new("日本");
+        assert_eq!(input.cursor_column(), 4);
+        input.left();
+        assert_eq!(input.cursor_column(), 2);
+        input.insert('語');
+        assert_eq!(input, "日語本");
+        input.backspace();
+        input.backspace();
+        assert_eq!(input, "本");
+        assert_eq!(input.cursor_column(), 0);
+
+        // A combining accent belongs to its letter
+        let mut input = TextInput::new("cafe");
+        input.insert('\u{301}');
+        assert_eq!(input.cursor_column(), 4);
+        input.left();
+        input.handle_key(&key(KeyCode::Delete));
+        assert_eq!(input, "caf");
+
+        let mut input = TextInput::new("x👍🏽");
+        input.backspace();
+        assert_eq!(input, "x");
+    }
+
+    #[test]
+    fn test_editing_keys() {
+        let mut input = TextInput::default();
+        for c in "shell".chars() {
+            assert!(input.handle_key(&key(KeyCode::Char(c))));
+        }
+        input.handle_key(&key(KeyCode::Home));
+        input.handle_key(&key(KeyCode::Char('z')));
+        input.handle_key(&key(KeyCode::End));
+        input.handle_key(&key(KeyCode::Char('!')));
+        assert_eq!(input, "zshell!");
+        assert!(!input.handle_key(&key(KeyCode::Enter)));
+
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        input.handle_key(&ctrl('a'));
+        input.handle_key(&ctrl('f'));
+        input.handle_key(&ctrl('d'));
+        assert_eq!(input, "zhell!");
+        input.handle_key(&ctrl('f'));
+        input.handle_key(&ctrl('k'));
+        assert_eq!(input, "zh");
+        input.handle_key(&ctrl('b'));
+        input.handle_key(&ctrl('u'));
+        assert_eq!(input, "h");
+        input.set("!");
+        assert!(!input.handle_key(&KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL)));
+        assert_eq!(input, "!");
+    }
+}
@@ -0,0 +1,1030 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+
+use crate::config::{KeySpec, KeymapPreset, KeysConfig, OpenActionConfig};
+
+/// A key as written in the config, e.g. `x`, `C-o`, `S-Up` or `Enter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    ctrl: bool,
+    alt: bool,
+    // Only for non-character keys, `J` already carries its shift
+    shift: bool,
+}
+
+impl Key {
+    pub fn from_event(event: &KeyEvent) -> Self {
+        Self {
+            code: event.code,
+            ctrl: event.modifiers.contains(KeyModifiers::CONTROL),
+            alt: event.modifiers.contains(KeyModifiers::ALT),
+            shift: event.modifiers.contains(KeyModifiers::SHIFT)
+                && !matches!(event.code, KeyCode::Char(_)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut key = Self {
+            code: KeyCode::Null,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        let mut rest = text;
+        // A lone "-" or "C--" still names the minus key
+        while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+            match &rest[..1] {
+                "C" => key.ctrl = true,
+                "M" => key.alt = true,
+                "S" => key.shift = true,
+                _ => return None,
+            }
+            rest = &rest[2..];
+        }
+
+        key.code = match rest {
+            "Enter" => KeyCode::Enter,
+            "Esc" => KeyCode::Esc,
+            "Tab" => KeyCode::Tab,
+            "Space" => KeyCode::Char(' '),
+            "Backspace" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            _ if rest.len() > 1 && rest.starts_with('F') => KeyCode::F(rest[1..].parse().ok()?),
+            _ => {
+                let mut chars = rest.chars();
+                let (Some(c), None) = (chars.next(), chars.next()) else {
+                    return None;
+                };
+                KeyCode::Char(c)
+            }
+        };
+
+        if let KeyCode::Char(c) = key.code
+            && key.shift
+        {
+            key.code = KeyCode::Char(c.to_ascii_uppercase());
+            key.shift = false;
+        }
+        Some(key)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "C-")?;
+        }
+        if self.alt {
+            write!(f, "M-")?;
+        }
+        if self.shift {
+            write!(f, "S-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Delete"),
+            KeyCode::Up => write!(f, "Up"),
+            KeyCode::Down => write!(f, "Down"),
+            KeyCode::Left => write!(f, "Left"),
+            KeyCode::Right => write!(f, "Right"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+/// Shift+1 to Shift+9 toggle the pins, on a US layout
+pub const PIN_TOGGLE_KEYS: &str = "!@#$%^&*(";
+
+/// Something a key can be bound to in window or session mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Back,
+    Down,
+    Up,
+    Top,
+    Bottom,
+    MoveItemDown,
+    MoveItemUp,
+    JumpBack,
+    JumpForward,
+    RecentInSession,
+    Select,
+    Rename,
+    Mark,
+    Delete,
+    CloseOthers,
+    Refresh,
+    QuickSearch,
+    FindProject,
+    SessionMode,
+    CreateWindow,
+    Messages,
+    Environment,
+    StartDir,
+    Options,
+    ServerInfo,
+    LineNumbers,
+    SortByActivity,
+    CycleView,
+    LinkWindow,
+    UnlinkWindow,
+    BreakOut,
+    Scratch,
+    SendToScratch,
+    GoToIndex,
+    ShowPins,
+    CopyTarget,
+    CopyPath,
+    Prune,
+    Archive,
+    ArchiveBrowser,
+    RecordMacro,
+    ReplayMacro,
+    Repeat,
+    Stats,
+    ExportTree,
+    ToggleLogging,
+    CaptureScrollback,
+    Note,
+    CycleColor,
+    TreeSearch,
+    SearchNext,
+    SearchPrevious,
+    NextSession,
+    PreviousSession,
+    FlatList,
+    Shell,
+    SelectZoomed,
+    MoveToIndex,
+    NewSession,
+}
+
+impl Action {
+    pub const ALL: &[Action] = &[
+        Action::Quit,
+        Action::Back,
+        Action::Down,
+        Action::Up,
+        Action::Top,
+        Action::Bottom,
+        Action::MoveItemDown,
+        Action::MoveItemUp,
+        Action::JumpBack,
+        Action::JumpForward,
+        Action::RecentInSession,
+        Action::Select,
+        Action::Rename,
+        Action::Mark,
+        Action::Delete,
+        Action::CloseOthers,
+        Action::Refresh,
+        Action::QuickSearch,
+        Action::FindProject,
+        Action::SessionMode,
+        Action::CreateWindow,
+        Action::Messages,
+        Action::Environment,
+        Action::StartDir,
+        Action::Options,
+        Action::ServerInfo,
+        Action::LineNumbers,
+        Action::SortByActivity,
+        Action::CycleView,
+        Action::LinkWindow,
+        Action::UnlinkWindow,
+        Action::BreakOut,
+        Action::Scratch,
+        Action::SendToScratch,
+        Action::GoToIndex,
+        Action::ShowPins,
+        Action::CopyTarget,
+        Action::CopyPath,
+        Action::Prune,
+        Action::Archive,
+        Action::ArchiveBrowser,
+        Action::RecordMacro,
+        Action::ReplayMacro,
+        Action::Repeat,
+        Action::Stats,
+        Action::ExportTree,
+        Action::ToggleLogging,
+        Action::CaptureScrollback,
+        Action::Note,
+        Action::CycleColor,
+        Action::TreeSearch,
+        Action::SearchNext,
+        Action::SearchPrevious,
+        Action::NextSession,
+        Action::PreviousSession,
+        Action::FlatList,
+        Action::Shell,
+        Action::SelectZoomed,
+        Action::MoveToIndex,
+        Action::NewSession,
+    ];
+
+    /// Name used in the `[keys]` config section
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Back => "back",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::MoveItemDown => "move_item_down",
+            Action::MoveItemUp => "move_item_up",
+            Action::JumpBack => "jump_back",
+            Action::JumpForward => "jump_forward",
+            Action::RecentInSession => "recent_in_session",
+            Action::Select => "select",
+            Action::Rename => "rename",
+            Action::Mark => "mark",
+            Action::Delete => "delete",
+            Action::CloseOthers => "close_others",
+            Action::Refresh => "refresh",
+            Action::QuickSearch => "quick_search",
+            Action::FindProject => "find_project",
+            Action::SessionMode => "session_mode",
+            Action::CreateWindow => "create_window",
+            Action::Messages => "messages",
+            Action::Environment => "environment",
+            Action::StartDir => "start_dir",
+            Action::Options => "options",
+            Action::ServerInfo => "server_info",
+            Action::LineNumbers => "line_numbers",
+            Action::SortByActivity => "sort_by_activity",
+            Action::CycleView => "cycle_view",
+            Action::LinkWindow => "link_window",
+            Action::UnlinkWindow => "unlink_window",
+            Action::BreakOut => "break_out",
+            Action::Scratch => "scratch",
+            Action::SendToScratch => "send_to_scratch",
+            Action::GoToIndex => "go_to_index",
+            Action::ShowPins => "show_pins",
+            Action::CopyTarget => "copy_target",
+            Action::CopyPath => "copy_path",
+            Action::Prune => "prune",
+            Action::Archive => "archive",
+            Action::ArchiveBrowser => "archive_browser",
+            Action::RecordMacro => "record_macro",
+            Action::ReplayMacro => "replay_macro",
+            Action::Repeat => "repeat",
+            Action::Stats => "stats",
+            Action::ExportTree => "export_tree",
+            Action::ToggleLogging => "toggle_logging",
+            Action::CaptureScrollback => "capture_scrollback",
+            Action::Note => "note",
+            Action::CycleColor => "cycle_color",
+            Action::TreeSearch => "tree_search",
+            Action::SearchNext => "search_next",
+            Action::SearchPrevious => "search_previous",
+            Action::NextSession => "next_session",
+            Action::PreviousSession => "previous_session",
+            Action::FlatList => "flat_list",
+            Action::Shell => "shell",
+            Action::SelectZoomed => "select_zoomed",
+            Action::MoveToIndex => "move_to_index",
+            Action::NewSession => "new_session",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Back => "clear marks or quit; in session mode, go back",
+            Action::Down => "move down",
+            Action::Up => "move up",
+            Action::Top => "go to the top",
+            Action::Bottom => "go to the bottom",
+            Action::MoveItemDown => "move the selection down",
+            Action::MoveItemUp => "move the selection up",
+            Action::JumpBack => "jump back in history",
+            Action::JumpForward => "jump forward in history",
+            Action::RecentInSession => "cycle recent windows of the session",
+            Action::Select => "switch to the selection",
+            Action::Rename => "rename",
+            Action::Mark => "mark and move down",
+            Action::Delete => "delete the selection or the marked items",
+            Action::CloseOthers => "close the other windows",
+            Action::Refresh => "refresh",
+            Action::QuickSearch => "search windows",
+            Action::FindProject => "find a project",
+            Action::SessionMode => "session mode",
+            Action::CreateWindow => "create a window",
+            Action::Messages => "show messages",
+            Action::Environment => "edit the environment",
+            Action::StartDir => "set the start directory",
+            Action::Options => "edit options",
+            Action::ServerInfo => "server info",
+            Action::LineNumbers => "cycle line numbers",
+            Action::SortByActivity => "toggle sorting by activity",
+            Action::CycleView => "cycle views",
+            Action::LinkWindow => "link the window into a session",
+            Action::UnlinkWindow => "unlink the window",
+            Action::BreakOut => "break the window out to a session",
+            Action::Scratch => "show the scratch popup",
+            Action::SendToScratch => "send the window to the scratch session",
+            Action::GoToIndex => "go to a window index",
+            Action::ShowPins => "show pins",
+            Action::CopyTarget => "copy the tmux target",
+            Action::CopyPath => "copy the path",
+            Action::Prune => "prune idle sessions",
+            Action::Archive => "archive the session",
+            Action::ArchiveBrowser => "browse archived sessions",
+            Action::RecordMacro => "start or stop recording a macro",
+            Action::ReplayMacro => "replay the macro on the selection",
+            Action::Repeat => "repeat the last rename, move or delete",
+            Action::Stats => "usage statistics",
+            Action::ExportTree => "copy the tree as markdown",
+            Action::ToggleLogging => "start/stop logging the pane",
+            Action::CaptureScrollback => "save the scrollback to a file",
+            Action::Note => "edit the note",
+            Action::CycleColor => "cycle the window's color, also in tmux",
+            Action::TreeSearch => "highlight windows matching a search",
+            Action::SearchNext => "next search match",
+            Action::SearchPrevious => "previous search match",
+            Action::NextSession => "first window of the next session",
+            Action::PreviousSession => "first window of the previous session",
+            Action::FlatList => "flat session:window list",
+            Action::Shell => "run a shell command on the selection",
+            Action::SelectZoomed => "switch and zoom the active pane",
+            Action::MoveToIndex => "move the window to an index",
+            Action::NewSession => "create a session named after the query",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+}
+
+/// The modes whose keys can be rebound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Window,
+    Session,
+    // Messages, environment, options and archive panels; only moves
+    List,
+    // Results of project and quick search, where letters type into the query
+    Search,
+}
+
+impl Scope {
+    fn defaults(self) -> &'static [(&'static str, Action)] {
+        match self {
+            Scope::Window => WINDOW_DEFAULTS,
+            Scope::Session => SESSION_DEFAULTS,
+            Scope::List => LIST_DEFAULTS,
+            Scope::Search => SEARCH_DEFAULTS,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Scope::Window => "window",
+            Scope::Session => "session",
+            Scope::List => "list",
+            Scope::Search => "search",
+        }
+    }
+
+    // What a preset rebinds on top of the defaults, the way `[keys]` does.
+    // The defaults are the vim ones
+    fn preset(self, preset: KeymapPreset) -> &'static [(Action, &'static [&'static str])] {
+        match (preset, self) {
+            (KeymapPreset::Vim, _) => &[],
+            (KeymapPreset::Emacs, Scope::Window) => EMACS_WINDOW,
+            (KeymapPreset::Emacs, Scope::Session) => EMACS_SESSION,
+            (KeymapPreset::Emacs, Scope::List) => EMACS_LIST,
+            (KeymapPreset::Emacs, Scope::Search) => EMACS_SEARCH,
+            (KeymapPreset::Plain, Scope::Window) => PLAIN_WINDOW,
+            (KeymapPreset::Plain, Scope::Session) => PLAIN_SESSION,
+            (KeymapPreset::Plain, Scope::List) => PLAIN_LIST,
+            // Arrows are all the search lists have
+            (KeymapPreset::Plain, Scope::Search) => &[],
+        }
+    }
+}
+
+const WINDOW_DEFAULTS: &[(&str, Action)] = &[
+    ("q", Action::Quit),
+    ("Esc", Action::Back),
+    ("j", Action::Down),
+    ("Down", Action::Down),
+    ("k", Action::Up),
+    ("Up", Action::Up),
+    ("C-g", Action::Top),
+    ("G", Action::Bottom),
+    ("l", Action::NextSession),
+    ("}", Action::NextSession),
+    ("h", Action::PreviousSession),
+    ("{", Action::PreviousSession),
+    ("J", Action::MoveItemDown),
+    ("S-Down", Action::MoveItemDown),
+    ("K", Action::MoveItemUp),
+    ("S-Up", Action::MoveItemUp),
+    ("C-o", Action::JumpBack),
+    // Terminals send Ctrl-i as Tab
+    ("C-i", Action::JumpForward),
+    ("Tab", Action::JumpForward),
+    ("w", Action::RecentInSession),
+    ("Enter", Action::Select),
+    ("r", Action::Rename),
+    (",", Action::Rename),
+    ("Space", Action::Mark),
+    ("x", Action::Delete),
+    ("X", Action::CloseOthers),
+    ("R", Action::Refresh),
+    ("/", Action::QuickSearch),
+    ("F", Action::FindProject),
+    ("S", Action::SessionMode),
+    ("C", Action::CreateWindow),
+    ("M", Action::Messages),
+    ("E", Action::Environment),
+    ("D", Action::StartDir),
+    ("O", Action::Options),
+    ("I", Action::ServerInfo),
+    ("C-n", Action::LineNumbers),
+    ("A", Action::SortByActivity),
+    ("f", Action::FlatList),
+    ("V", Action::CycleView),
+    ("L", Action::LinkWindow),
+    ("U", Action::UnlinkWindow),
+    ("B", Action::BreakOut),
+    ("z", Action::Scratch),
+    ("Z", Action::SendToScratch),
+    (":", Action::GoToIndex),
+    ("'", Action::ShowPins),
+    ("y", Action::CopyTarget),
+    ("Y", Action::CopyPath),
+    ("Q", Action::RecordMacro),
+    ("m", Action::ReplayMacro),
+    (".", Action::Repeat),
+    ("T", Action::Stats),
+    ("W", Action::ExportTree),
+    ("P", Action::ToggleLogging),
+    ("H", Action::CaptureScrollback),
+    ("\"", Action::Note),
+    ("c", Action::CycleColor),
+    ("?", Action::TreeSearch),
+    ("n", Action::SearchNext),
+    ("N", Action::SearchPrevious),
+    ("|", Action::Shell),
+    ("C-f", Action::SelectZoomed),
+    ("=", Action::MoveToIndex),
+];
+
+const SESSION_DEFAULTS: &[(&str, Action)] = &[
+    ("q", Action::Back),
+    ("Esc", Action::Back),
+    ("j", Action::Down),
+    ("Down", Action::Down),
+    ("k", Action::Up),
+    ("Up", Action::Up),
+    ("g", Action::Top),
+    ("G", Action::Bottom),
+    ("J", Action::MoveItemDown),
+    ("K", Action::MoveItemUp),
+    ("Enter", Action::Select),
+    ("r", Action::Rename),
+    (",", Action::Rename),
+    ("Space", Action::Mark),
+    ("x", Action::Delete),
+    ("P", Action::Prune),
+    ("a", Action::Archive),
+    ("A", Action::ArchiveBrowser),
+    ("R", Action::Refresh),
+    ("M", Action::Messages),
+    ("E", Action::Environment),
+    ("D", Action::StartDir),
+    ("O", Action::Options),
+    ("Q", Action::RecordMacro),
+    ("m", Action::ReplayMacro),
+    (".", Action::Repeat),
+    ("T", Action::Stats),
+    ("W", Action::ExportTree),
+    ("\"", Action::Note),
+    ("|", Action::Shell),
+];
+
+const LIST_DEFAULTS: &[(&str, Action)] = &[
+    ("j", Action::Down),
+    ("Down", Action::Down),
+    ("k", Action::Up),
+    ("Up", Action::Up),
+    ("g", Action::Top),
+    ("Home", Action::Top),
+    ("G", Action::Bottom),
+    ("End", Action::Bottom),
+];
+
+const SEARCH_DEFAULTS: &[(&str, Action)] = &[
+    ("Down", Action::Down),
+    ("Up", Action::Up),
+    // Only project search, quick search cycles line numbers with the window key
+    ("C-n", Action::NewSession),
+];
+
+// Letters left free by the presets jump by first letter
+const EMACS_WINDOW: &[(Action, &[&str])] = &[
+    (Action::Down, &["C-n", "Down"]),
+    (Action::Up, &["C-p", "Up"]),
+    (Action::Top, &["M-<", "Home"]),
+    (Action::Bottom, &["M->", "End"]),
+    (Action::NextSession, &["M-n", "}"]),
+    (Action::PreviousSession, &["M-p", "{"]),
+    (Action::MoveItemDown, &["M-Down", "S-Down"]),
+    (Action::MoveItemUp, &["M-Up", "S-Up"]),
+    (Action::Back, &["C-g", "Esc"]),
+    (Action::TreeSearch, &["C-s", "?"]),
+    // C-n moves down now
+    (Action::LineNumbers, &["M-l"]),
+];
+
+const EMACS_SESSION: &[(Action, &[&str])] = &[
+    (Action::Down, &["C-n", "Down"]),
+    (Action::Up, &["C-p", "Up"]),
+    (Action::Top, &["M-<", "Home"]),
+    (Action::Bottom, &["M->", "End"]),
+    (Action::MoveItemDown, &["M-Down"]),
+    (Action::MoveItemUp, &["M-Up"]),
+    (Action::Back, &["C-g", "Esc", "q"]),
+];
+
+const EMACS_LIST: &[(Action, &[&str])] = &[
+    (Action::Down, &["C-n", "Down"]),
+    (Action::Up, &["C-p", "Up"]),
+    (Action::Top, &["M-<", "Home"]),
+    (Action::Bottom, &["M->", "End"]),
+];
+
+const EMACS_SEARCH: &[(Action, &[&str])] = &[
+    (Action::Down, &["C-n", "Down"]),
+    (Action::Up, &["C-p", "Up"]),
+    // C-n moves down now
+    (Action::NewSession, &["M-Enter"]),
+];
+
+const PLAIN_WINDOW: &[(Action, &[&str])] = &[
+    (Action::Down, &["Down"]),
+    (Action::Up, &["Up"]),
+    (Action::Top, &["Home"]),
+    (Action::Bottom, &["End"]),
+    (Action::NextSession, &["PageDown"]),
+    (Action::PreviousSession, &["PageUp"]),
+    (Action::MoveItemDown, &["S-Down"]),
+    (Action::MoveItemUp, &["S-Up"]),
+];
+
+const PLAIN_SESSION: &[(Action, &[&str])] = &[
+    (Action::Down, &["Down"]),
+    (Action::Up, &["Up"]),
+    (Action::Top, &["Home"]),
+    (Action::Bottom, &["End"]),
+    (Action::MoveItemDown, &["S-Down"]),
+    (Action::MoveItemUp, &["S-Up"]),
+];
+
+const PLAIN_LIST: &[(Action, &[&str])] = &[
+    (Action::Down, &["Down"]),
+    (Action::Up, &["Up"]),
+    (Action::Top, &["Home"]),
+    (Action::Bottom, &["End"]),
+];
+
+/// Keys of the window and session modes and the panels: the defaults,
+/// patched by the preset and then by `[keys]`
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    window: Vec<(Key, Action)>,
+    session: Vec<(Key, Action)>,
+    list: Vec<(Key, Action)>,
+    search: Vec<(Key, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(KeymapPreset::default(), &KeysConfig::default()).0
+    }
+}
+
+impl Keymap {
+    /// Build the keymap, returning problems with the config next to it
+    pub fn new(preset: KeymapPreset, config: &KeysConfig) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+        let window = Self::bindings(Scope::Window, preset, &config.window, &mut errors);
+        let session = Self::bindings(Scope::Session, preset, &config.session, &mut errors);
+        let list = Self::bindings(Scope::List, preset, &Default::default(), &mut errors);
+        let search = Self::bindings(Scope::Search, preset, &Default::default(), &mut errors);
+        (
+            Self {
+                window,
+                session,
+                list,
+                search,
+            },
+            errors,
+        )
+    }
+
+    fn bindings(
+        scope: Scope,
+        preset: KeymapPreset,
+        overrides: &std::collections::BTreeMap<String, KeySpec>,
+        errors: &mut Vec<String>,
+    ) -> Vec<(Key, Action)> {
+        let mut bindings: Vec<(Key, Action)> = scope
+            .defaults()
+            .iter()
+            .filter_map(|(key, action)| Some((Key::parse(key)?, *action)))
+            .collect();
+
+        for (action, keys) in scope.preset(preset) {
+            let keys: Vec<Key> = keys.iter().filter_map(|key| Key::parse(key)).collect();
+            Self::rebind(&mut bindings, *action, keys);
+        }
+
+        for (name, spec) in overrides {
+            let Some(action) = Action::from_name(name) else {
+                errors.push(format!("Unknown action '{name}' in keys.{}", scope.name()));
+                continue;
+            };
+            let keys: Vec<Key> = spec
+                .keys()
+                .iter()
+                .filter_map(|text| {
+                    let key = Key::parse(text);
+                    if key.is_none() {
+                        errors.push(format!("Unknown key '{text}' for {name}"));
+                    }
+                    key
+                })
+                .collect();
+
+            Self::rebind(&mut bindings, action, keys);
+        }
+        bindings
+    }
+
+    // The new keys replace the action's defaults and whatever else they were bound to
+    fn rebind(bindings: &mut Vec<(Key, Action)>, action: Action, keys: Vec<Key>) {
+        bindings.retain(|(key, bound)| *bound != action && !keys.contains(key));
+        bindings.extend(keys.into_iter().map(|key| (key, action)));
+    }
+
+    fn scope(&self, scope: Scope) -> &[(Key, Action)] {
+        match scope {
+            Scope::Window => &self.window,
+            Scope::Session => &self.session,
+            Scope::List => &self.list,
+            Scope::Search => &self.search,
+        }
+    }
+
+    pub fn action(&self, scope: Scope, event: &KeyEvent) -> Option<Action> {
+        let key = Key::from_event(event);
+        self.scope(scope)
+            .iter()
+            .find(|(bound, _)| *bound == key)
+            .map(|(_, action)| *action)
+    }
+
+    /// The key an action is shown with in the help bar
+    pub fn first_key(&self, scope: Scope, action: Action) -> Option<Key> {
+        self.scope(scope)
+            .iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|(key, _)| *key)
+    }
+
+    /// Bound actions in order of their first key, each with all its keys
+    pub fn actions(&self, scope: Scope) -> Vec<(Action, Vec<Key>)> {
+        let mut actions: Vec<(Action, Vec<Key>)> = Vec::new();
+        for (key, action) in self.scope(scope) {
+            match actions.iter_mut().find(|(a, _)| a == action) {
+                Some((_, keys)) => keys.push(*key),
+                None => actions.push((*action, vec![*key])),
+            }
+        }
+        actions
+    }
+}
+
+/// One line of the cheatsheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheatsheetRow {
+    pub keys: String,
+    pub action: String, // Config name, empty for fixed keys
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheatsheetSection {
+    pub title: String,
+    pub rows: Vec<CheatsheetRow>,
+}
+
+/// The effective keys of the window and session modes, including pins and open actions
+pub fn cheatsheet(keymap: &Keymap, open_actions: &[OpenActionConfig]) -> Vec<CheatsheetSection> {
+    let rows = |scope| {
+        keymap
+            .actions(scope)
+            .into_iter()
+            .map(|(action, keys)| CheatsheetRow {
+                keys: keys
+                    .iter()
+                    .map(|k| k.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                action: action.name().to_string(),
+                description: action.description().to_string(),
+            })
+            .collect::<Vec<_>>()
+    };
+    let fixed = |keys: &str, description: &str| CheatsheetRow {
+        keys: keys.to_string(),
+        action: String::new(),
+        description: description.to_string(),
+    };
+
+    let mut window = rows(Scope::Window);
+    window.push(fixed("1-9", "jump to a pin"));
+    window.push(fixed(PIN_TOGGLE_KEYS, "toggle pin 1-9"));
+    for open in open_actions {
+        window.push(fixed(&open.key.to_string(), &format!("open {}", open.name)));
+    }
+
+    vec![
+        CheatsheetSection {
+            title: "Window mode".to_string(),
+            rows: window,
+        },
+        CheatsheetSection {
+            title: "Session mode".to_string(),
+            rows: rows(Scope::Session),
+        },
+    ]
+}
+
+pub fn render_markdown(sections: &[CheatsheetSection]) -> String {
+    let mut out = String::new();
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("## {}\n\n", section.title));
+        out.push_str("| Keys | Action | Description |\n|---|---|---|\n");
+        for row in &section.rows {
+            let keys = row
+                .keys
+                .split(", ")
+                .map(|k| format!("`{}`", k.replace('|', "\\|")))
+                .collect::<Vec<_>>()
+                .join(", ");
+            out.push_str(&format!(
+                "| {} | {} | {} |\n",
+                keys, row.action, row.description
+            ));
+        }
+    }
+    out
+}
+
+pub fn render_table(sections: &[CheatsheetSection]) -> String {
+    let key_width = sections
+        .iter()
+        .flat_map(|s| &s.rows)
+        .map(|r| r.keys.chars().count())
+        .max()
+        .unwrap_or(0);
+    let action_width = sections
+        .iter()
+        .flat_map(|s| &s.rows)
+        .map(|r| r.action.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("{}\n", section.title.to_uppercase()));
+        for row in &section.rows {
+            let line = format!(
+                "  {:key_width$}  {:action_width$}  {}",
+                row.keys, row.action, row.description
+            );
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn event(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_parse_and_display_keys() {
+        for text in [
+            "x", "J", "C-o", "S-Up", "Enter", "Space", "-", "C--", "F2", ",",
+        ] {
+            assert_eq!(Key::parse(text).unwrap().to_string(), text);
+        }
+        assert_eq!(Key::parse("S-j").unwrap().to_string(), "J");
+        assert!(Key::parse("Hyper").is_none());
+        assert!(Key::parse("X-a").is_none());
+
+        // Terminals report the shift of uppercase letters, or not
+        let k = Key::parse("K").unwrap();
+        assert_eq!(
+            Key::from_event(&event(KeyCode::Char('K'), KeyModifiers::SHIFT)),
+            k
+        );
+        assert_eq!(
+            Key::from_event(&event(KeyCode::Char('K'), KeyModifiers::NONE)),
+            k
+        );
+        assert_ne!(
+            Key::from_event(&event(KeyCode::Up, KeyModifiers::SHIFT)),
+            Key::parse("Up").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut window = BTreeMap::new();
+        window.insert("rename".to_string(), KeySpec::One("F2".to_string()));
+        window.insert(
+            "quit".to_string(),
+            KeySpec::Many(vec!["Q".into(), "C-c".into()]),
+        );
+        window.insert("delete".to_string(), KeySpec::Many(vec![]));
+        window.insert("fly".to_string(), KeySpec::One("f".to_string()));
+        window.insert("scratch".to_string(), KeySpec::One("Hyper".to_string()));
+        let config = KeysConfig {
+            window,
+            session: BTreeMap::new(),
+        };
+        let (keymap, errors) = Keymap::new(KeymapPreset::Vim, &config);
+        assert_eq!(errors.len(), 2);
+
+        let none = KeyModifiers::NONE;
+        let action = |code, modifiers| keymap.action(Scope::Window, &event(code, modifiers));
+        assert_eq!(action(KeyCode::F(2), none), Some(Action::Rename));
+        assert_eq!(action(KeyCode::Char('r'), none), None);
+        assert_eq!(action(KeyCode::Char('q'), none), None);
+        assert_eq!(
+            action(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            Some(Action::Quit)
+        );
+        assert_eq!(action(KeyCode::Char('x'), none), None);
+        assert_eq!(action(KeyCode::Char('z'), none), None);
+        // Session mode keeps its defaults
+        assert_eq!(
+            keymap.action(Scope::Session, &event(KeyCode::Char('r'), none)),
+            Some(Action::Rename)
+        );
+
+        // Taking a key from another action
+        let mut window = BTreeMap::new();
+        window.insert("select".to_string(), KeySpec::One("l".to_string()));
+        window.insert("scratch".to_string(), KeySpec::One("L".to_string()));
+        let (keymap, _) = Keymap::new(
+            KeymapPreset::Vim,
+            &KeysConfig {
+                window,
+                session: BTreeMap::new(),
+            },
+        );
+        let actions = keymap.actions(Scope::Window);
+        assert!(!actions.iter().any(|(a, _)| *a == Action::LinkWindow));
+        assert!(actions.iter().any(|(a, _)| *a == Action::Select));
+    }
+
+    #[test]
+    fn test_presets() {
+        let none = KeyModifiers::NONE;
+        let ctrl = KeyModifiers::CONTROL;
+        let alt = KeyModifiers::ALT;
+
+        let (keymap, errors) = Keymap::new(KeymapPreset::Emacs, &KeysConfig::default());
+        assert!(errors.is_empty());
+        let action = |scope, code, modifiers| keymap.action(scope, &event(code, modifiers));
+        assert_eq!(
+            action(Scope::Window, KeyCode::Char('n'), ctrl),
+            Some(Action::Down)
+        );
+        assert_eq!(
+            action(Scope::Window, KeyCode::Char('l'), alt),
+            Some(Action::LineNumbers)
+        );
+        assert_eq!(
+            action(Scope::Window, KeyCode::Char('>'), alt),
+            Some(Action::Bottom)
+        );
+        assert_eq!(
+            action(Scope::Window, KeyCode::Char('g'), ctrl),
+            Some(Action::Back)
+        );
+        assert_eq!(
+            action(Scope::Session, KeyCode::Char('p'), ctrl),
+            Some(Action::Up)
+        );
+        assert_eq!(
+            action(Scope::List, KeyCode::Char('n'), ctrl),
+            Some(Action::Down)
+        );
+        // hjkl are free for jumping by first letter
+        for c in ['h', 'j', 'k', 'l', 'J', 'K', 'G'] {
+            assert_eq!(action(Scope::Window, KeyCode::Char(c), none), None);
+        }
+        assert_eq!(action(Scope::List, KeyCode::Char('j'), none), None);
+
+        let (keymap, _) = Keymap::new(KeymapPreset::Plain, &KeysConfig::default());
+        let action = |scope, code| keymap.action(scope, &event(code, none));
+        assert_eq!(action(Scope::Window, KeyCode::Char('j')), None);
+        assert_eq!(action(Scope::Window, KeyCode::Down), Some(Action::Down));
+        assert_eq!(
+            action(Scope::Window, KeyCode::PageDown),
+            Some(Action::NextSession)
+        );
+        assert_eq!(action(Scope::List, KeyCode::End), Some(Action::Bottom));
+
+        // `[keys]` still has the last word
+        let mut window = BTreeMap::new();
+        window.insert("down".to_string(), KeySpec::One("j".to_string()));
+        let (keymap, _) = Keymap::new(
+            KeymapPreset::Emacs,
+            &KeysConfig {
+                window,
+                session: BTreeMap::new(),
+            },
+        );
+        let down = keymap.action(Scope::Window, &event(KeyCode::Char('j'), none));
+        assert_eq!(down, Some(Action::Down));
+        let down = keymap.action(Scope::Window, &event(KeyCode::Char('n'), ctrl));
+        assert_eq!(down, None);
+    }
+
+    #[test]
+    fn test_cheatsheet() {
+        let open = OpenActionConfig {
+            key: 'e',
+            name: "editor".to_string(),
+            command: "nvim".to_string(),
+            target: Default::default(),
+            exit: None,
+        };
+        let sections = cheatsheet(&Keymap::default(), &[open]);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(
+            sections[0].rows[2],
+            CheatsheetRow {
+                keys: "j, Down".to_string(),
+                action: "down".to_string(),
+                description: "move down".to_string(),
+            }
+        );
+        assert_eq!(sections[0].rows.last().unwrap().description, "open editor");
+
+        let md = render_markdown(&sections);
+        assert!(md.contains("## Session mode"));
+        assert!(md.contains("| `r`, `,` | rename | rename |"));
+
+        let table = render_table(&sections);
+        assert!(table.starts_with("WINDOW MODE\n  q"));
+        assert!(table.lines().all(|l| l == l.trim_end()));
+    }
+}
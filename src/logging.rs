@@ -0,0 +1,29 @@
+use anyhow::Result;
+use std::fs::OpenOptions;
+use std::sync::Mutex;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::get_log_path;
+
+/// Log to `debug.log` in the config dir when `--debug` is passed or
+/// `RUST_LOG` is set. The TUI owns the terminal, so nothing goes to stderr.
+pub fn init(debug: bool) -> Result<()> {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) if debug => EnvFilter::new("tmux_portal=debug"),
+        Err(_) => return Ok(()),
+    };
+
+    let path = get_log_path()?;
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_thread_ids(true)
+        .init();
+
+    tracing::info!("tmux-portal {} started", env!("CARGO_PKG_VERSION"));
+    Ok(())
+}
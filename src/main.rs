@@ -1,64 +1,483 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use clap::Parser;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind},
+    cursor,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{Terminal, backend::CrosstermBackend};
-use std::io;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM, SIGTSTP, SIGWINCH};
+use signal_hook::iterator::Signals;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 mod app;
+mod archive;
+mod cli;
 mod config;
+mod doctor;
+mod export;
+mod filter;
+mod hooks;
+mod input;
+mod keymap;
+mod logging;
+mod messages;
 mod search;
+mod status;
 mod tmux;
 mod ui;
+mod usage;
+mod watcher;
 mod widgets;
+mod worker;
 
 use app::App;
+use cli::{Cli, Command, ConfigCommand, KeysFormat, TreeFormat};
+use config::Config;
+use keymap::Keymap;
+use tmux::{RealTmux, TmuxBackend, TmuxSession};
+use ui::text::format_age;
+use watcher::RefreshWatcher;
+
+// How often the event loop checks for background results and hook signals
+const TICK: Duration = Duration::from_millis(50);
+
+// Redraw often enough to keep a status bar clock current
+const CLOCK_TICK: Duration = Duration::from_secs(1);
+
+// Whether mouse capture has to be turned back on after a suspend
+static MOUSE: AtomicBool = AtomicBool::new(false);
+
+// Set after coming back from a suspend, the screen needs a full redraw
+static RESUMED: AtomicBool = AtomicBool::new(false);
 
 fn main() -> Result<()> {
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let mut cli = Cli::parse();
+    logging::init(cli.debug)?;
+
+    if let Some(command) = cli.command.take() {
+        return run_command(command, &cli);
+    }
+
+    // Load the config before taking over the terminal so errors stay readable
+    let (config, warnings) = load_config(&cli)?;
+    let mouse = config.mouse;
+    let mut terminal = enter_terminal(mouse)?;
 
     // Create app and run
-    let mut app = App::new()?;
+    let mut app = App::new(config);
+    for warning in warnings {
+        app.messages.error(warning);
+    }
+    if cli.stay_open {
+        app.config.exit_on_switch = false;
+    }
     let res = run_app(&mut terminal, &mut app);
+    leave_terminal(&mut terminal, mouse)?;
+
+    if let Err(err) = res {
+        println!("{err:?}");
+    }
 
-    // Restore terminal
+    Ok(())
+}
+
+// Problems in the config come back as `path:line:column: message` lines
+fn load_config(cli: &Cli) -> Result<(Config, Vec<String>)> {
+    let config_path =
+        config::resolve_config_path(cli.config.clone(), cli.profile.as_deref(), false)?;
+    let (mut config, issues) = config::load_config(&config_path)?;
+    let warnings = issues
+        .iter()
+        .map(|issue| format!("{}:{issue}", config_path.display()))
+        .collect();
+    config.apply_theme();
+
+    // https://no-color.org: any non-empty value disables colors
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    if cli.no_color || no_color_env {
+        config.make_monochrome();
+    }
+    Ok((config, warnings))
+}
+
+fn enter_terminal(mouse: bool) -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
+    install_restore_handlers()?;
+    MOUSE.store(mouse, Ordering::SeqCst);
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    // Without capture the terminal keeps its own text selection
+    if mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
+    Ok(Terminal::new(CrosstermBackend::new(stdout))?)
+}
+
+fn leave_terminal(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    mouse: bool,
+) -> Result<()> {
     disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
+    if mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+// Give the terminal back even when we don't get to `leave_terminal`: a panic
+// or being killed, e.g. when the popup is closed under us. Errors are
+// ignored, there is nothing left to report them to
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        DisableMouseCapture,
         LeaveAlternateScreen,
-        DisableMouseCapture
+        cursor::Show
+    );
+}
+
+// Stop like any other job (`fg` brings us back), then take the terminal again.
+// The next draw starts from scratch since the shell scribbled over the screen
+fn suspend() {
+    restore_terminal();
+    let _ = signal_hook::low_level::emulate_default_handler(SIGTSTP);
+
+    let _ = enable_raw_mode();
+    let _ = execute!(io::stdout(), EnterAlternateScreen, cursor::Hide);
+    if MOUSE.load(Ordering::SeqCst) {
+        let _ = execute!(io::stdout(), EnableMouseCapture);
+    }
+    RESUMED.store(true, Ordering::SeqCst);
+}
+
+fn install_restore_handlers() -> Result<()> {
+    // Worker threads that panic don't take the UI down, so leave it alone then
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().name() == Some("main") {
+            restore_terminal();
+        }
+        default_hook(info);
+    }));
+
+    let mut signals = Signals::new([SIGTERM, SIGHUP, SIGINT, SIGTSTP])
+        .context("Failed to install signal handlers")?;
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            if signal != SIGTSTP {
+                restore_terminal();
+                std::process::exit(128 + signal);
+            }
+            suspend();
+            // Wake the event loop, it may be blocked waiting for a key
+            let _ = signal_hook::low_level::raise(SIGWINCH);
+        }
+    });
+    Ok(())
+}
+
+fn watch(cli: &Cli, interval: i64) -> Result<()> {
+    let (mut config, warnings) = load_config(cli)?;
+    config.refresh_interval_ms = Some(interval as u64 * 1000);
+    let mut terminal = enter_terminal(false)?;
+
+    let mut app = App::watch(config);
+    for warning in warnings {
+        app.messages.error(warning);
+    }
+    let res = run_app(&mut terminal, &mut app);
+    leave_terminal(&mut terminal, false)?;
+    res
+}
+
+fn run_command(command: Command, cli: &Cli) -> Result<()> {
+    match command {
+        Command::InstallHooks => {
+            tmux::install_refresh_hooks()?;
+
+            // Hooks set on a running server are lost on restart
+            println!("Installed refresh hooks. To keep them, add to ~/.tmux.conf:");
+            let hook_command = tmux::refresh_hook_command();
+            for hook in tmux::REFRESH_HOOKS {
+                println!(
+                    "set-hook -g '{}' '{}'",
+                    tmux::refresh_hook_name(hook),
+                    hook_command
+                );
+            }
+            Ok(())
+        }
+        Command::Doctor => {
+            let checks = doctor::checks(
+                doctor::find_tmux_binary(),
+                &RealTmux::default().server_info(),
+            );
+            if !doctor::print_report(&checks) {
+                anyhow::bail!("tmux-portal can't work with this tmux setup");
+            }
+            Ok(())
+        }
+        Command::Prune { idle, yes } => prune_sessions(idle, yes),
+        Command::Completions { shell } => {
+            cli::write_completions(shell, &mut std::io::stdout());
+            Ok(())
+        }
+        Command::Keys { format } => print_keys(cli, format),
+        Command::Watch { interval } => watch(cli, interval),
+        Command::Status { format } => {
+            let history = App::load_history()?;
+            let sessions = RealTmux::default().list_sessions()?;
+            // Missing entries would leave trailing spaces
+            println!(
+                "{}",
+                status::render(&format, &history, &sessions).trim_end()
+            );
+            Ok(())
+        }
+        Command::Tree { format, output } => print_tree(format, output),
+        Command::Config { command } => config_command(cli, command),
+    }
+}
+
+fn config_command(cli: &Cli, command: ConfigCommand) -> Result<()> {
+    let config_path = config::resolve_config_path(
+        cli.config.clone(),
+        cli.profile.as_deref(),
+        matches!(command, ConfigCommand::Edit),
     )?;
-    terminal.show_cursor()?;
+    match command {
+        ConfigCommand::Path => println!("{}", config_path.display()),
+        ConfigCommand::Edit => {
+            // Start from the defaults rather than an empty file
+            if !config_path.exists() {
+                config::load_config(&config_path)?;
+            }
+            let editor = std::env::var("VISUAL")
+                .or_else(|_| std::env::var("EDITOR"))
+                .unwrap_or_else(|_| "vi".to_string());
+            // Through sh, so an editor with arguments like `code -w` works
+            let status = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("{editor} \"$1\""))
+                .arg("sh")
+                .arg(&config_path)
+                .status()
+                .with_context(|| format!("Failed to run {editor}"))?;
+            if !status.success() {
+                anyhow::bail!("{editor} exited with {status}");
+            }
+            check_config_file(&config_path)?;
+        }
+        ConfigCommand::Check => check_config_file(&config_path)?,
+    }
+    Ok(())
+}
 
-    if let Err(err) = res {
-        println!("{err:?}");
+// Compiler style `path:line:column: message`, so editors can jump there
+fn check_config_file(path: &std::path::Path) -> Result<()> {
+    if !path.exists() {
+        println!("{}: not created yet, the defaults apply", path.display());
+        return Ok(());
+    }
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let report = config::check_config(&text);
+
+    let issues = report.error.iter().map(|issue| ("error", issue));
+    let issues = issues.chain(report.warnings.iter().map(|issue| ("warning", issue)));
+    for (level, issue) in issues {
+        println!(
+            "{}:{}:{}: {level}: {}",
+            path.display(),
+            issue.line,
+            issue.column,
+            issue.message
+        );
+    }
+
+    if report.error.is_some() {
+        anyhow::bail!("{} can't be loaded", path.display());
+    }
+    if report.warnings.is_empty() {
+        println!("{}: ok", path.display());
+    }
+    Ok(())
+}
+
+fn print_tree(format: TreeFormat, output: Option<PathBuf>) -> Result<()> {
+    let sessions = RealTmux::default().list_sessions()?;
+    let home = dirs::home_dir();
+    let tree = match format {
+        TreeFormat::Md => export::render_markdown(&sessions, home.as_deref()),
+        TreeFormat::Txt => export::render_text(&sessions, home.as_deref()),
+    };
+    match output {
+        Some(path) => std::fs::write(&path, tree)
+            .with_context(|| format!("Failed to write {}", path.display()))?,
+        None => print!("{tree}"),
+    }
+    Ok(())
+}
+
+fn print_keys(cli: &Cli, format: KeysFormat) -> Result<()> {
+    let (config, warnings) = load_config(cli)?;
+    for warning in warnings {
+        eprintln!("{warning}");
+    }
+    let (keymap, errors) = Keymap::new(config.keymap, &config.keys);
+    for error in errors {
+        eprintln!("{error}");
+    }
+
+    let sections = keymap::cheatsheet(&keymap, &config.open_actions);
+    match format {
+        KeysFormat::Md => print!("{}", keymap::render_markdown(&sections)),
+        KeysFormat::Table => print!("{}", keymap::render_table(&sections)),
+    }
+    Ok(())
+}
+
+fn prune_sessions(idle_for: i64, yes: bool) -> Result<()> {
+    let tmux = RealTmux::default();
+    let now = chrono::Local::now().timestamp();
+    let idle: Vec<TmuxSession> = tmux
+        .list_sessions()?
+        .into_iter()
+        .filter(|s| s.is_idle(now, idle_for))
+        .collect();
+
+    if idle.is_empty() {
+        println!("No detached sessions idle for {}", format_age(idle_for));
+        return Ok(());
+    }
+
+    println!("Detached sessions idle for {}:", format_age(idle_for));
+    for session in &idle {
+        println!(
+            "  {} ({} windows, last active {} ago)",
+            session.name,
+            session.windows.len(),
+            format_age(now - session.activity)
+        );
+    }
+
+    if !yes {
+        print!("Kill {} sessions? [y/N] ", idle.len());
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Nothing killed");
+            return Ok(());
+        }
     }
 
+    // Keep going after a failure so one stuck session doesn't block the rest
+    let mut failed = 0;
+    for session in &idle {
+        match tmux.kill_session(&session.name) {
+            Ok(()) => println!("Killed {}", session.name),
+            Err(e) => {
+                eprintln!("Failed to kill {}: {e}", session.name);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{failed} of {} sessions could not be killed", idle.len());
+    }
     Ok(())
 }
 
 fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+    let refresh_interval = app
+        .config
+        .refresh_interval_ms
+        .filter(|&ms| ms > 0)
+        .map(Duration::from_millis);
+    let mut last_refresh = Instant::now();
+
+    // Only listen for hook signals when `install-hooks` was run
+    let watcher = tmux::refresh_hooks_installed().then(RefreshWatcher::spawn);
+
     loop {
+        app.process_tasks();
+        if app.should_quit {
+            return Ok(());
+        }
+
+        if RESUMED.swap(false, Ordering::SeqCst) {
+            terminal.clear()?;
+        }
         terminal.draw(|f| ui::draw(f, app))?;
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
-                match app.handle_key(key) {
-                    Ok(should_quit) => {
-                        if should_quit {
-                            return Ok(());
-                        }
-                    }
-                    Err(err) => return Err(err),
+        // Keep pending signals queued until the user is back to browsing
+        if let Some(watcher) = &watcher
+            && app.is_idle()
+            && watcher.changed()
+        {
+            app.refresh_in_background();
+            last_refresh = Instant::now();
+            continue;
+        }
+
+        // Block until the next event unless something needs a wake-up: a
+        // background job or macro, hook signals, expiring toasts or the refresh interval
+        let mut timeout = None;
+        if app.is_busy() || app.is_replaying() || watcher.is_some() || app.messages.has_toasts() {
+            timeout = Some(TICK);
+        }
+        if app.config.status_bar.has_clock() {
+            timeout = Some(timeout.map_or(CLOCK_TICK, |t: Duration| t.min(CLOCK_TICK)));
+        }
+        if let Some(interval) = refresh_interval {
+            let remaining = interval.saturating_sub(last_refresh.elapsed());
+            timeout = Some(timeout.map_or(remaining, |t: Duration| t.min(remaining)));
+        }
+
+        if let Some(timeout) = timeout
+            && !event::poll(timeout)?
+        {
+            if let Some(interval) = refresh_interval
+                && last_refresh.elapsed() >= interval
+            {
+                if app.is_idle() {
+                    app.refresh_in_background();
                 }
+                last_refresh = Instant::now();
             }
+            continue;
+        }
+
+        match event::read()? {
+            // Raw mode turns Ctrl-Z into a key, so suspend by hand
+            Event::Key(key)
+                if key.kind == KeyEventKind::Press
+                    && key.code == KeyCode::Char('z')
+                    && key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                suspend();
+            }
+            Event::Key(key) if key.kind == KeyEventKind::Press => match app.handle_key(key) {
+                Ok(should_quit) => {
+                    if should_quit {
+                        return Ok(());
+                    }
+                }
+                Err(err) => return Err(err),
+            },
+            Event::Mouse(mouse) => app.handle_mouse(mouse),
+            _ => {}
         }
     }
 }
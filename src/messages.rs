@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+
+// Oldest entries are dropped once the log grows past this
+const MAX_MESSAGES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub level: Level,
+    pub text: String,
+    pub created: Instant,
+}
+
+/// Every message shown during the session; recent ones double as toasts
+pub struct MessageLog {
+    entries: Vec<Message>,
+    toast_duration: Duration,
+}
+
+impl MessageLog {
+    pub fn new(toast_duration: Duration) -> Self {
+        Self {
+            entries: Vec::new(),
+            toast_duration,
+        }
+    }
+
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(Level::Info, text.into());
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        tracing::error!("{text}");
+        self.push(Level::Error, text);
+    }
+
+    fn push(&mut self, level: Level, text: String) {
+        self.entries.push(Message {
+            level,
+            text,
+            created: Instant::now(),
+        });
+        if self.entries.len() > MAX_MESSAGES {
+            self.entries.remove(0);
+        }
+    }
+
+    /// All messages, oldest first
+    pub fn entries(&self) -> &[Message] {
+        &self.entries
+    }
+
+    /// Messages young enough to still be shown as toasts, oldest first
+    pub fn toasts(&self, now: Instant) -> Vec<&Message> {
+        self.entries
+            .iter()
+            .filter(|m| now.duration_since(m.created) < self.toast_duration)
+            .collect()
+    }
+
+    pub fn has_toasts(&self) -> bool {
+        !self.toasts(Instant::now()).is_empty()
+    }
+
+    /// Hide all current toasts, they stay in the log
+    pub fn dismiss_toasts(&mut self) {
+        let expired = Instant::now()
+            .checked_sub(self.toast_duration)
+            .unwrap_or_else(Instant::now);
+        for message in &mut self.entries {
+            message.created = message.created.min(expired);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toasts_expire_but_stay_in_log() {
+        let mut log = MessageLog::new(Duration::from_secs(5));
+        log.error("first");
+        log.info("second");
+
+        let now = Instant::now();
+        assert_eq!(log.toasts(now).len(), 2);
+        assert!(log.toasts(now + Duration::from_secs(6)).is_empty());
+        assert_eq!(log.entries().len(), 2);
+        assert_eq!(log.entries()[0].level, Level::Error);
+
+        log.dismiss_toasts();
+        assert!(!log.has_toasts());
+        assert_eq!(log.entries()[1].text, "second");
+    }
+}
@@ -1,8 +1,15 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
+use serde::Deserialize;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::config::{PatternKind, expand_path};
+use crate::tmux::PaneLayout;
 
 #[derive(Debug, Clone)]
 pub struct SearchResult {
@@ -12,6 +19,9 @@ pub struct SearchResult {
     pub full_path: PathBuf,
     pub score: i64,
     pub match_indices: Vec<usize>, // Indices of characters that matched the query
+    pub on_create: Option<String>, // Command run in the window when it gets created
+    pub layout: PaneLayout,        // Panes split off the window when it gets created
+    pub group: String,             // Name of the pattern that found it, or "bookmarks"
 }
 
 #[derive(Debug, Clone)]
@@ -19,6 +29,12 @@ pub struct SearchPattern {
     pub name: String,
     pub base_paths: Vec<PathBuf>,
     pub pattern: String, // e.g., "{session}/{window}" or "{session}/src/{window}"
+    pub window_name: Option<String>, // e.g., "{window}-edit"
+    pub on_create: Option<String>, // e.g., "nvim ."
+    pub layout: PaneLayout,
+    pub kind: PatternKind,
+    pub weight: f64,           // Multiplies the scores of the pattern's matches
+    pub scan_on_startup: bool, // Otherwise scanned when project search first opens
 }
 
 impl SearchPattern {
@@ -27,6 +43,58 @@ impl SearchPattern {
             name,
             base_paths,
             pattern,
+            window_name: None,
+            on_create: None,
+            layout: PaneLayout::default(),
+            kind: PatternKind::default(),
+            weight: 1.0,
+            scan_on_startup: true,
+        }
+    }
+
+    /// Read project lists from the base paths instead of walking them
+    pub fn kind(mut self, kind: PatternKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Set the template used to name created windows
+    pub fn window_name(mut self, template: Option<String>) -> Self {
+        self.window_name = template;
+        self
+    }
+
+    /// Set the command run in newly created windows
+    pub fn on_create(mut self, command: Option<String>) -> Self {
+        self.on_create = command;
+        self
+    }
+
+    /// Set the panes split off newly created windows
+    pub fn layout(mut self, layout: PaneLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Leave a heavy pattern for when project search first opens
+    pub fn scan_on_startup(mut self, eager: bool) -> Self {
+        self.scan_on_startup = eager;
+        self
+    }
+
+    /// Rank the pattern's matches above (> 1.0) or below (< 1.0) others
+    pub fn weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    // Expand the window name template with the captured session and window
+    fn format_window_name(&self, session_name: &str, window_name: &str) -> String {
+        match &self.window_name {
+            Some(template) => template
+                .replace("{session}", session_name)
+                .replace("{window}", window_name),
+            None => window_name.to_string(),
         }
     }
 
@@ -62,6 +130,100 @@ impl SearchPattern {
     }
 }
 
+/// A fixed path that shows up in project search without any scanning
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub name: String,
+    pub path: PathBuf,
+    pub session_name: Option<String>,
+    pub window_name: Option<String>,
+}
+
+impl Bookmark {
+    fn to_result(&self) -> SearchResult {
+        let window_name = self.window_name.clone().unwrap_or_else(|| {
+            self.path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| self.name.clone())
+        });
+        SearchResult {
+            display_text: self.name.clone(),
+            session_name: self
+                .session_name
+                .clone()
+                .unwrap_or_else(|| self.name.clone()),
+            window_name,
+            full_path: self.path.clone(),
+            score: 0,
+            match_indices: Vec::new(),
+            on_create: None,
+            layout: PaneLayout::default(),
+            group: BOOKMARKS_GROUP.to_string(),
+        }
+    }
+}
+
+/// A session named after a typed query that matched nothing, started in
+/// `dir`. tmux turns `.` and `:` into `_` in session names, so do it here to
+/// be able to switch to it
+pub fn new_session_result(query: &str, dir: &Path) -> Option<SearchResult> {
+    let name = query.trim().replace(['.', ':'], "_");
+    if name.is_empty() {
+        return None;
+    }
+    Some(SearchResult {
+        display_text: format!("Create session '{name}'"),
+        session_name: name.clone(),
+        window_name: name,
+        full_path: dir.to_path_buf(),
+        score: 0,
+        match_indices: Vec::new(),
+        on_create: None,
+        layout: PaneLayout::default(),
+        group: String::new(),
+    })
+}
+
+/// A project in a list file. Plain text files have one path per line, TOML
+/// files `[[projects]]` tables with these fields
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct ListedProject {
+    #[serde(default)]
+    name: String, // Defaults to the directory's name
+    path: String,
+    #[serde(default)]
+    session: Option<String>, // Defaults to the pattern's name
+    #[serde(default)]
+    window: Option<String>, // Defaults to the project's name
+}
+
+#[derive(Deserialize)]
+struct ProjectList {
+    #[serde(default)]
+    projects: Vec<ListedProject>,
+}
+
+fn parse_project_list(file: &Path, content: &str) -> Result<Vec<ListedProject>> {
+    if file.extension().is_some_and(|ext| ext == "toml") {
+        let list: ProjectList =
+            toml::from_str(content).map_err(|e| anyhow!("{}: {e}", file.display()))?;
+        return Ok(list.projects);
+    }
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| ListedProject {
+            name: String::new(),
+            path: line.to_string(),
+            session: None,
+            window: None,
+        })
+        .collect())
+}
+
 #[derive(Debug, Clone)]
 enum PatternComponent {
     Session,
@@ -70,10 +232,30 @@ enum PatternComponent {
     FixedSession(String), // New component for fixed session names
 }
 
+/// Group of the configured bookmarks, listed before the patterns
+pub const BOOKMARKS_GROUP: &str = "bookmarks";
+
+/// How long the last scan took and when it finished
+#[derive(Debug, Clone, Copy)]
+pub struct ScanStats {
+    pub duration: Duration,
+    pub finished: Instant,
+}
+
+/// Projects found on the worker thread, handed to `set_results`
+pub struct Scan {
+    pub patterns: Vec<String>, // Names of the scanned patterns
+    pub results: Vec<SearchResult>,
+    pub stats: ScanStats,
+}
+
 pub struct SearchProvider {
     matcher: SkimMatcherV2,
     patterns: Vec<SearchPattern>,
+    bookmarks: Vec<SearchResult>, // Listed before scanned results, even mid-scan
     cached_results: Vec<SearchResult>,
+    scan_stats: Option<ScanStats>, // None until the first scan is done
+    match_time: Cell<Duration>,    // Spent in the matcher by the last search
 }
 
 impl SearchProvider {
@@ -81,13 +263,70 @@ impl SearchProvider {
         Self {
             matcher: SkimMatcherV2::default(),
             patterns,
+            bookmarks: Vec::new(),
             cached_results: Vec::new(),
+            scan_stats: None,
+            match_time: Cell::new(Duration::ZERO),
         }
     }
 
-    // Legacy constructor for backward compatibility
+    /// Add fixed paths that don't depend on the directory scan
+    pub fn bookmarks(mut self, bookmarks: &[Bookmark]) -> Self {
+        self.bookmarks = bookmarks.iter().map(Bookmark::to_result).collect();
+        self
+    }
+
+    pub fn patterns(&self) -> &[SearchPattern] {
+        &self.patterns
+    }
+
+    // Scan without a provider so it can run on the worker thread
+    pub fn scan(patterns: Vec<SearchPattern>) -> Result<Scan> {
+        let names = patterns.iter().map(|p| p.name.clone()).collect();
+        let mut provider = Self::new(patterns);
+        let stats = provider.scan_directories()?;
+        Ok(Scan {
+            patterns: names,
+            results: provider.cached_results,
+            stats,
+        })
+    }
+
+    /// Replace the results of the scanned patterns, others keep theirs
+    pub fn set_results(&mut self, scan: Scan) {
+        self.cached_results
+            .retain(|result| !scan.patterns.contains(&result.group));
+        self.cached_results.extend(scan.results);
+        self.scan_stats = Some(scan.stats);
+    }
+
+    /// Projects found by the last scan, minus forgotten ones
+    pub fn project_count(&self) -> usize {
+        self.cached_results.len()
+    }
+
+    pub fn scan_stats(&self) -> Option<ScanStats> {
+        self.scan_stats
+    }
 
-    pub fn scan_directories(&mut self) -> Result<()> {
+    /// Time the last search spent matching, to tell a slow matcher from a
+    /// slow scan
+    pub fn match_time(&self) -> Duration {
+        self.match_time.get()
+    }
+
+    /// Drop a scanned result until the next scan, e.g. a deleted checkout.
+    /// Returns false for bookmarks, which come from the config
+    pub fn forget(&mut self, result: &SearchResult) -> bool {
+        let count = self.cached_results.len();
+        self.cached_results.retain(|cached| {
+            cached.full_path != result.full_path || cached.session_name != result.session_name
+        });
+        self.cached_results.len() < count
+    }
+
+    pub fn scan_directories(&mut self) -> Result<ScanStats> {
+        let start = Instant::now();
         self.cached_results.clear();
 
         let patterns = self.patterns.clone(); // Clone to avoid borrowing issues
@@ -95,10 +334,19 @@ impl SearchProvider {
             self.scan_pattern(pattern)?;
         }
 
-        Ok(())
+        let stats = ScanStats {
+            duration: start.elapsed(),
+            finished: Instant::now(),
+        };
+        self.scan_stats = Some(stats);
+        Ok(stats)
     }
 
     fn scan_pattern(&mut self, pattern: &SearchPattern) -> Result<()> {
+        if pattern.kind == PatternKind::File {
+            return self.read_project_lists(pattern);
+        }
+
         let components = pattern.parse_pattern();
 
         for base_path in &pattern.base_paths {
@@ -106,7 +354,46 @@ impl SearchProvider {
                 continue;
             }
 
-            self.scan_with_pattern(base_path, &components, &mut Vec::new())?;
+            self.scan_with_pattern(pattern, base_path, &components, &mut Vec::new())?;
+        }
+
+        Ok(())
+    }
+
+    // Missing list files are skipped like missing directories, broken ones
+    // are errors
+    fn read_project_lists(&mut self, pattern: &SearchPattern) -> Result<()> {
+        for file in &pattern.base_paths {
+            if !file.is_file() {
+                continue;
+            }
+            let content = fs::read_to_string(file)?;
+
+            for project in parse_project_list(file, &content)? {
+                let full_path = expand_path(&project.path);
+                let name = if project.name.is_empty() {
+                    full_path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| project.path.clone())
+                } else {
+                    project.name
+                };
+                let session_name = project.session.unwrap_or_else(|| pattern.name.clone());
+                let window_name = project.window.unwrap_or(name);
+
+                self.cached_results.push(SearchResult {
+                    display_text: format!("{session_name}/{window_name}"),
+                    window_name: pattern.format_window_name(&session_name, &window_name),
+                    session_name,
+                    full_path,
+                    score: 0,
+                    match_indices: Vec::new(),
+                    on_create: pattern.on_create.clone(),
+                    layout: pattern.layout.clone(),
+                    group: pattern.name.clone(),
+                });
+            }
         }
 
         Ok(())
@@ -114,6 +401,7 @@ impl SearchProvider {
 
     fn scan_with_pattern(
         &mut self,
+        pattern: &SearchPattern,
         current_path: &Path,
         remaining_components: &[PatternComponent],
         captured_values: &mut Vec<(PatternComponent, String)>,
@@ -134,6 +422,7 @@ impl SearchProvider {
 
             if !session_name.is_empty() && !window_name.is_empty() {
                 let display_text = format!("{session_name}/{window_name}");
+                let window_name = pattern.format_window_name(&session_name, &window_name);
 
                 self.cached_results.push(SearchResult {
                     display_text,
@@ -142,6 +431,9 @@ impl SearchProvider {
                     full_path: current_path.to_path_buf(),
                     score: 0,
                     match_indices: Vec::new(), // Empty for cached results
+                    on_create: pattern.on_create.clone(),
+                    layout: pattern.layout.clone(),
+                    group: pattern.name.clone(),
                 });
             }
 
@@ -156,7 +448,7 @@ impl SearchProvider {
                 // Must match this literal directory name
                 let next_path = current_path.join(literal);
                 if next_path.exists() && next_path.is_dir() {
-                    self.scan_with_pattern(&next_path, remaining, captured_values)?;
+                    self.scan_with_pattern(pattern, &next_path, remaining, captured_values)?;
                 }
             }
             PatternComponent::Session | PatternComponent::Window => {
@@ -172,7 +464,12 @@ impl SearchProvider {
 
                         if let Some(dir_name) = entry_path.file_name().and_then(|n| n.to_str()) {
                             captured_values.push((current_component.clone(), dir_name.to_string()));
-                            self.scan_with_pattern(&entry_path, remaining, captured_values)?;
+                            self.scan_with_pattern(
+                                pattern,
+                                &entry_path,
+                                remaining,
+                                captured_values,
+                            )?;
                             captured_values.pop();
                         }
                     }
@@ -181,7 +478,7 @@ impl SearchProvider {
             PatternComponent::FixedSession(name) => {
                 // Add the fixed session name to captured values and continue
                 captured_values.push((current_component.clone(), name.clone()));
-                self.scan_with_pattern(current_path, remaining, captured_values)?;
+                self.scan_with_pattern(pattern, current_path, remaining, captured_values)?;
                 captured_values.pop();
             }
         }
@@ -190,30 +487,225 @@ impl SearchProvider {
     }
 
     pub fn search(&self, query: &str) -> Vec<SearchResult> {
-        if query.is_empty() {
-            return self.cached_results.clone();
-        }
-
-        let mut results: Vec<SearchResult> = self
-            .cached_results
-            .iter()
-            .filter_map(|result| {
+        let start = Instant::now();
+        let all = self.bookmarks.iter().chain(&self.cached_results);
+        let mut results: Vec<SearchResult> = if query.is_empty() {
+            all.cloned().collect()
+        } else {
+            all.filter_map(|result| {
                 if let Some((score, indices)) =
                     self.matcher.fuzzy_indices(&result.display_text, query)
                 {
                     let mut scored_result = result.clone();
-                    scored_result.score = score;
+                    scored_result.score =
+                        (score as f64 * self.weight(&result.group)).round() as i64;
                     scored_result.match_indices = indices;
                     Some(scored_result)
                 } else {
                     None
                 }
             })
+            .collect()
+        };
+
+        // Keep each pattern's results together. The group with the best match
+        // comes first, on a tie the heavier pattern, then config order
+        let mut best: HashMap<String, i64> = HashMap::new();
+        for result in &results {
+            let score = best.entry(result.group.clone()).or_insert(result.score);
+            *score = (*score).max(result.score);
+        }
+        results.sort_by(|a, b| {
+            best[&b.group]
+                .cmp(&best[&a.group])
+                .then(self.weight(&b.group).total_cmp(&self.weight(&a.group)))
+                .then(self.group_rank(&a.group).cmp(&self.group_rank(&b.group)))
+                .then(b.score.cmp(&a.score))
+        });
+
+        self.match_time.set(start.elapsed());
+        results
+    }
+
+    fn weight(&self, group: &str) -> f64 {
+        self.patterns
+            .iter()
+            .find(|p| p.name == group)
+            .map_or(1.0, |p| p.weight)
+    }
+
+    // Bookmarks first, then the patterns in config order
+    fn group_rank(&self, group: &str) -> usize {
+        if group == BOOKMARKS_GROUP {
+            return 0;
+        }
+        self.patterns
+            .iter()
+            .position(|p| p.name == group)
+            .map_or(usize::MAX, |i| i + 1)
+    }
+}
+
+/// Groups in the order they appear in `results`, with how many results each has
+pub fn group_counts(results: &[SearchResult]) -> Vec<(String, usize)> {
+    let mut groups: Vec<(String, usize)> = Vec::new();
+    for result in results {
+        match groups.last_mut() {
+            Some((group, count)) if *group == result.group => *count += 1,
+            _ => groups.push((result.group.clone(), 1)),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_name_template_and_on_create() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("work/api")).unwrap();
+
+        let pattern = SearchPattern::new(
+            "editor".to_string(),
+            vec![dir.path().to_path_buf()],
+            "{session}/{window}".to_string(),
+        )
+        .window_name(Some("{window}-edit".to_string()))
+        .on_create(Some("nvim .".to_string()));
+
+        let mut provider = SearchProvider::new(vec![pattern]);
+        provider.scan_directories().unwrap();
+
+        let results = provider.search("");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].display_text, "work/api");
+        assert_eq!(results[0].session_name, "work");
+        assert_eq!(results[0].window_name, "api-edit");
+        assert_eq!(results[0].on_create.as_deref(), Some("nvim ."));
+    }
+
+    #[test]
+    fn test_project_list_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("projects.txt");
+        fs::write(&text, "# generated nightly\n/src/api\n\n  /src/web  \n").unwrap();
+        let toml = dir.path().join("projects.toml");
+        fs::write(
+            &toml,
+            concat!(
+                "[[projects]]\n",
+                "path = \"/srv/db\"\n",
+                "[[projects]]\n",
+                "name = \"docs\"\n",
+                "path = \"/srv/site/docs\"\n",
+                "session = \"site\"\n",
+            ),
+        )
+        .unwrap();
+
+        let pattern = SearchPattern::new(
+            "nightly".to_string(),
+            vec![text, toml, dir.path().join("missing.txt")],
+            String::new(),
+        )
+        .kind(PatternKind::File)
+        .window_name(Some("{window}-edit".to_string()));
+        let results = SearchProvider::scan(vec![pattern]).unwrap().results;
+
+        let found: Vec<(&str, &str, &Path)> = results
+            .iter()
+            .map(|r| {
+                (
+                    r.display_text.as_str(),
+                    r.window_name.as_str(),
+                    r.full_path.as_path(),
+                )
+            })
             .collect();
+        assert_eq!(
+            found,
+            [
+                ("nightly/api", "api-edit", Path::new("/src/api")),
+                ("nightly/web", "web-edit", Path::new("/src/web")),
+                ("nightly/db", "db-edit", Path::new("/srv/db")),
+                ("site/docs", "docs-edit", Path::new("/srv/site/docs")),
+            ]
+        );
+
+        let broken = dir.path().join("broken.toml");
+        fs::write(&broken, "[[projects]]\nname = 1\n").unwrap();
+        let pattern = SearchPattern::new("nightly".to_string(), vec![broken], String::new())
+            .kind(PatternKind::File);
+        assert!(SearchProvider::scan(vec![pattern]).is_err());
+    }
 
-        // Sort by score (higher is better)
-        results.sort_by(|a, b| b.score.cmp(&a.score));
+    #[test]
+    fn test_pattern_weight() {
+        let dir = tempfile::tempdir().unwrap();
+        for project in ["archive/api", "archive/apiary", "work/api"] {
+            fs::create_dir_all(dir.path().join(project)).unwrap();
+        }
+        let pattern = |name: &str| {
+            SearchPattern::new(
+                name.to_string(),
+                vec![dir.path().join(name)],
+                "{window}".to_string(),
+            )
+        };
+        let mut provider =
+            SearchProvider::new(vec![pattern("archive"), pattern("work").weight(2.0)]);
+        assert!(provider.scan_stats().is_none());
+        provider.scan_directories().unwrap();
+        assert!(provider.scan_stats().is_some());
+        assert_eq!(provider.project_count(), 3);
+        let groups = |query: &str| {
+            provider
+                .search(query)
+                .into_iter()
+                .map(|r| r.group)
+                .collect::<Vec<_>>()
+        };
+
+        // Equal matches rank the heavier pattern first, listed or searched
+        assert_eq!(groups(""), ["work", "archive", "archive"]);
+        assert_eq!(groups("api"), ["work", "archive", "archive"]);
+        let results = provider.search("api");
+        assert_eq!(results[0].score, 2 * results[1].score);
+
+        // A match only the lighter pattern has still shows up
+        assert_eq!(groups("apiary"), ["archive"]);
+    }
 
-        results
+    #[test]
+    fn test_bookmarks() {
+        let bookmarks = [
+            Bookmark {
+                name: "dotfiles".to_string(),
+                path: PathBuf::from("/home/me/.dotfiles"),
+                session_name: None,
+                window_name: None,
+            },
+            Bookmark {
+                name: "notes".to_string(),
+                path: PathBuf::from("/srv/notes"),
+                session_name: Some("misc".to_string()),
+                window_name: Some("journal".to_string()),
+            },
+        ];
+        // Listed without any scan, and the paths don't need to exist
+        let provider = SearchProvider::new(Vec::new()).bookmarks(&bookmarks);
+
+        let results = provider.search("");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].session_name, "dotfiles");
+        assert_eq!(results[0].window_name, ".dotfiles");
+
+        let results = provider.search("nots");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].session_name, "misc");
+        assert_eq!(results[0].window_name, "journal");
+        assert_eq!(results[0].full_path, PathBuf::from("/srv/notes"));
     }
 }
@@ -0,0 +1,127 @@
+use crate::app::HistoryEntry;
+use crate::tmux::{TmuxSession, TmuxWindow};
+
+/// Fill in `#{mru1}`..`#{mru9}` (`session:window`), `#{mruN_session}` and
+/// `#{mruN_window}` with the most recent portal targets that still exist.
+/// Missing entries become empty, anything else is left for tmux
+pub fn render(format: &str, history: &[HistoryEntry], sessions: &[TmuxSession]) -> String {
+    let recent: Vec<&TmuxWindow> = history
+        .iter()
+        .filter_map(|entry| {
+            sessions
+                .iter()
+                .find(|s| s.name == entry.session_name)
+                .and_then(|s| s.windows.iter().find(|w| w.id == entry.window_id))
+        })
+        .collect();
+
+    let mut out = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find("#{mru") {
+        out.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let Some(end) = placeholder.find('}') else {
+            rest = placeholder;
+            break;
+        };
+        match expand(&placeholder[5..end], &recent) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&placeholder[..=end]),
+        }
+        rest = &placeholder[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// `1`, `2_session` or `3_window` after `#{mru`
+fn expand(field: &str, recent: &[&TmuxWindow]) -> Option<String> {
+    let (index, part) = field.split_once('_').unwrap_or((field, ""));
+    let index: usize = index.parse().ok().filter(|&i| (1..=9).contains(&i))?;
+    let window = recent.get(index - 1);
+    let value = match part {
+        "" => window.map(|w| format!("{}:{}", w.session_name, w.name)),
+        "session" => window.map(|w| w.session_name.clone()),
+        "window" => window.map(|w| w.name.clone()),
+        _ => return None,
+    };
+    Some(value.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str, windows: &[(&str, &str)]) -> TmuxSession {
+        TmuxSession {
+            name: name.to_string(),
+            windows: windows
+                .iter()
+                .enumerate()
+                .map(|(i, (id, window))| TmuxWindow {
+                    id: id.to_string(),
+                    index: i as u32,
+                    name: window.to_string(),
+                    session_name: name.to_string(),
+                    active: false,
+                    panes: 1,
+                    zoomed: false,
+                    activity: 0,
+                    command: "zsh".to_string(),
+                    path: "/tmp".to_string(),
+                    dead: false,
+                    linked: false,
+                    piped: false,
+                    note: None,
+                    color: None,
+                    user_options: Default::default(),
+                })
+                .collect(),
+            attached: 0,
+            activity: 0,
+            start_dir: None,
+            base_index: 0,
+            renumber: false,
+            note: None,
+        }
+    }
+
+    fn entry(session_name: &str, window_id: &str) -> HistoryEntry {
+        HistoryEntry {
+            session_name: session_name.to_string(),
+            window_id: window_id.to_string(),
+            window_name: "stale".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_recent_targets() {
+        let sessions = [
+            session("main", &[("@1", "editor")]),
+            session("work", &[("@2", "api"), ("@3", "db")]),
+        ];
+        // The gone window is skipped, live names win over stored ones
+        let history = [
+            entry("work", "@3"),
+            entry("work", "@9"),
+            entry("main", "@1"),
+        ];
+
+        assert_eq!(
+            render("#{mru1} #{mru2}", &history, &sessions),
+            "work:db main:editor"
+        );
+        assert_eq!(
+            render(
+                "[#{mru2_window}@#{mru2_session}] #{mru3}|",
+                &history,
+                &sessions
+            ),
+            "[editor@main] |"
+        );
+        assert_eq!(
+            render("#{mru} #{mru1_pane} #{host} #{mru1", &history, &sessions),
+            "#{mru} #{mru1_pane} #{host} #{mru1"
+        );
+    }
+}
@@ -1,31 +1,857 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Instant;
 use tmux_interface::{
     KillSession, ListSessions, ListWindows, RenameWindow, SelectWindow, SwitchClient, Tmux,
+    TmuxCommand, TmuxOutput,
 };
 
+use crate::archive::ArchivedSession;
+use crate::config::ProjectEnv;
+
+#[cfg(test)]
+pub mod fake;
+
+// tmux wait-for channel signalled by the hooks from `install-hooks`
+pub const REFRESH_CHANNEL: &str = "tmux-portal-refresh";
+
+// Hooks that change what the tree shows
+pub const REFRESH_HOOKS: &[&str] = &[
+    "session-created",
+    "session-closed",
+    "session-renamed",
+    "window-linked",
+    "window-unlinked",
+    "window-renamed",
+    "session-window-changed",
+    "window-layout-changed", // Panes split, closed or zoomed
+];
+
+// Fixed array index so installing twice replaces our hooks instead of
+// appending duplicates, and user hooks at other indices stay untouched
+const REFRESH_HOOK_INDEX: usize = 77;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TmuxSession {
     pub name: String,
     pub windows: Vec<TmuxWindow>,
+    pub attached: u32,             // #{session_attached}, number of clients
+    pub activity: i64,             // #{session_activity}, unix time of the last input
+    pub start_dir: Option<String>, // Where `C` starts new windows, see START_DIR_OPTION
+    pub base_index: u32,           // #{base-index}, where window numbering starts
+    pub renumber: bool,            // #{renumber-windows}, closing a window closes the gap
+    #[serde(default)]
+    pub note: Option<String>, // See SESSION_NOTE_OPTION
+}
+
+impl TmuxSession {
+    /// Nobody attached and no activity for at least `idle_for` seconds
+    pub fn is_idle(&self, now: i64, idle_for: i64) -> bool {
+        self.attached == 0 && now - self.activity >= idle_for
+    }
+
+    /// Swap two windows the way `swap-window` does: they trade places and
+    /// indices, so the list stays ordered by index. False if either is missing
+    pub fn swap_windows(&mut self, window1_id: &str, window2_id: &str) -> bool {
+        let first = self.windows.iter().position(|w| w.id == window1_id);
+        let second = self.windows.iter().position(|w| w.id == window2_id);
+        let (Some(first), Some(second)) = (first, second) else {
+            return false;
+        };
+
+        self.windows.swap(first, second);
+        let first_index = self.windows[first].index;
+        self.windows[first].index = self.windows[second].index;
+        self.windows[second].index = first_index;
+        true
+    }
+
+    /// The index tmux gives a window added without one: the first free one
+    /// from base-index on
+    pub fn next_free_index(&self) -> u32 {
+        (self.base_index..)
+            .find(|index| self.windows.iter().all(|w| w.index != *index))
+            .unwrap_or(self.base_index)
+    }
+
+    /// Close the gaps left by a removed window when renumber-windows is on
+    pub fn renumber_windows(&mut self) {
+        if !self.renumber {
+            return;
+        }
+        for (index, window) in (self.base_index..).zip(&mut self.windows) {
+            window.index = index;
+        }
+    }
+
+    /// Give a window a free index, the way `move-window -t session:index`
+    /// does. With renumber-windows on the gaps close again right away.
+    /// False if the window is missing or another one has the index
+    pub fn move_to_index(&mut self, window_id: &str, index: u32) -> bool {
+        let Some(position) = self.windows.iter().position(|w| w.id == window_id) else {
+            return false;
+        };
+        if self
+            .windows
+            .iter()
+            .any(|w| w.index == index && w.id != window_id)
+        {
+            return false;
+        }
+
+        let mut window = self.windows.remove(position);
+        window.index = index;
+        let position = self
+            .windows
+            .iter()
+            .position(|w| w.index > index)
+            .unwrap_or(self.windows.len());
+        self.windows.insert(position, window);
+        self.renumber_windows();
+        true
+    }
+}
+
+/// Move a window next to another one, possibly in another session, the way
+/// `move-window -b`/`-a` does: it takes the target's index (before) or the
+/// one after it, and later windows shift up to make room. A session left
+/// without windows is gone. False if either window is missing
+pub fn move_window(
+    sessions: &mut Vec<TmuxSession>,
+    window_id: &str,
+    target_window_id: &str,
+    before: bool,
+) -> bool {
+    let find = |sessions: &[TmuxSession], id: &str| {
+        sessions.iter().enumerate().find_map(|(s, session)| {
+            let w = session.windows.iter().position(|w| w.id == id)?;
+            Some((s, w))
+        })
+    };
+    if window_id == target_window_id || find(sessions, target_window_id).is_none() {
+        return false;
+    }
+    let Some((source, position)) = find(sessions, window_id) else {
+        return false;
+    };
+
+    let mut window = sessions[source].windows.remove(position);
+    sessions[source].renumber_windows();
+    let (target, target_position) = find(sessions, target_window_id).unwrap();
+    let target_session = &mut sessions[target];
+    let target_index = target_session.windows[target_position].index;
+    let (position, index) = if before {
+        (target_position, target_index)
+    } else {
+        (target_position + 1, target_index + 1)
+    };
+    window.index = index;
+    window.session_name = target_session.name.clone();
+    window.active = false;
+    target_session.windows.insert(position, window);
+    for i in position + 1..target_session.windows.len() {
+        let previous = target_session.windows[i - 1].index;
+        if target_session.windows[i].index <= previous {
+            target_session.windows[i].index = previous + 1;
+        }
+    }
+
+    sessions.retain(|s| !s.windows.is_empty());
+    true
+}
+
+/// Show a window in another session too, the way `link-window` does: it
+/// takes the next free index there and all its copies count as linked.
+/// False if either is missing or the session already shows the window
+pub fn link_window(sessions: &mut [TmuxSession], window_id: &str, target_session: &str) -> bool {
+    let Some(window) = sessions
+        .iter()
+        .flat_map(|s| &s.windows)
+        .find(|w| w.id == window_id)
+        .cloned()
+    else {
+        return false;
+    };
+    let Some(target) = sessions.iter_mut().find(|s| s.name == target_session) else {
+        return false;
+    };
+    if target.windows.iter().any(|w| w.id == window_id) {
+        return false;
+    }
+
+    let index = target.next_free_index();
+    let position = target.windows.partition_point(|w| w.index < index);
+    target.windows.insert(
+        position,
+        TmuxWindow {
+            index,
+            session_name: target_session.to_string(),
+            active: false,
+            ..window
+        },
+    );
+    update_linked(sessions, window_id);
+    true
+}
+
+/// Remove a window from one session, the way `unlink-window` does. A session
+/// left without windows is gone. False unless another session shows it too
+pub fn unlink_window(sessions: &mut Vec<TmuxSession>, session_name: &str, window_id: &str) -> bool {
+    let copies = sessions
+        .iter()
+        .flat_map(|s| &s.windows)
+        .filter(|w| w.id == window_id)
+        .count();
+    let Some(session) = sessions.iter_mut().find(|s| s.name == session_name) else {
+        return false;
+    };
+    if copies < 2 || !session.windows.iter().any(|w| w.id == window_id) {
+        return false;
+    }
+
+    session.windows.retain(|w| w.id != window_id);
+    session.renumber_windows();
+    sessions.retain(|s| !s.windows.is_empty());
+    update_linked(sessions, window_id);
+    true
+}
+
+/// Take a window out of its session into a new one, as its only window. A
+/// session left without windows is gone. False if the window is missing or
+/// the new session's name is taken
+pub fn break_window(
+    sessions: &mut Vec<TmuxSession>,
+    session_name: &str,
+    window_id: &str,
+    new_session: &str,
+) -> bool {
+    if sessions.iter().any(|s| s.name == new_session) {
+        return false;
+    }
+    let Some(session) = sessions.iter_mut().find(|s| s.name == session_name) else {
+        return false;
+    };
+    let Some(position) = session.windows.iter().position(|w| w.id == window_id) else {
+        return false;
+    };
+
+    let window = session.windows.remove(position);
+    session.renumber_windows();
+    // The new session gets the global settings, which are most likely what
+    // this session has too
+    let (base_index, renumber) = (session.base_index, session.renumber);
+    let activity = window.activity;
+    sessions.retain(|s| !s.windows.is_empty());
+    sessions.push(TmuxSession {
+        name: new_session.to_string(),
+        windows: vec![TmuxWindow {
+            index: base_index,
+            session_name: new_session.to_string(),
+            active: true,
+            ..window
+        }],
+        attached: 0,
+        activity,
+        start_dir: None,
+        base_index,
+        renumber,
+        note: None,
+    });
+    update_linked(sessions, window_id);
+    true
+}
+
+fn update_linked(sessions: &mut [TmuxSession], window_id: &str) {
+    let copies = sessions
+        .iter()
+        .flat_map(|s| &s.windows)
+        .filter(|w| w.id == window_id)
+        .count();
+    for window in sessions.iter_mut().flat_map(|s| s.windows.iter_mut()) {
+        if window.id == window_id {
+            window.linked = copies > 1;
+        }
+    }
+}
+
+/// Extra panes split off a window created from project search
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaneLayout {
+    pub panes: Vec<String>, // Command typed into each extra pane, "" for a shell
+    pub layout: Option<String>, // Applied after splitting, e.g. "main-vertical"
+    pub environment: ProjectEnv, // What the shells of all panes start with
+}
+
+/// Shell command loading the project's environment before handing over to
+/// the user's shell, None when there's nothing to load
+pub fn project_shell(environment: ProjectEnv, path: &Path) -> Option<String> {
+    let shell = "\"${SHELL:-sh}\"";
+    let direnv = || {
+        path.join(".envrc").is_file().then(|| {
+            // Without direnv installed the pane would die right away
+            format!(
+                "if command -v direnv >/dev/null; then exec direnv exec {} {shell}; else exec {shell}; fi",
+                shell_quote(&path.to_string_lossy())
+            )
+        })
+    };
+    // What `activate` does, for any shell
+    let venv = || {
+        [".venv", "venv", "env"]
+            .iter()
+            .map(|name| path.join(name))
+            .find(|dir| dir.join("bin/activate").is_file())
+            .map(|dir| {
+                let dir = dir.to_string_lossy();
+                format!(
+                    "VIRTUAL_ENV={} PATH={}:\"$PATH\" exec {shell}",
+                    shell_quote(&dir),
+                    shell_quote(&format!("{dir}/bin"))
+                )
+            })
+    };
+
+    match environment {
+        ProjectEnv::None => None,
+        ProjectEnv::Direnv => direnv(),
+        ProjectEnv::Venv => venv(),
+        ProjectEnv::Auto => direnv().or_else(venv),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TmuxWindow {
     pub id: String,
+    pub index: u32, // #{window_index}, what `select-window -t :N` addresses
     pub name: String,
     pub session_name: String,
     pub active: bool,
+    pub panes: u32,
+    pub zoomed: bool,
+    pub activity: i64,   // #{window_activity}, unix time of the last output
+    pub command: String, // Command running in the active pane
+    pub path: String,    // Working directory of the active pane
+    pub dead: bool,      // Active pane's process exited (remain-on-exit)
+    pub linked: bool,    // #{window_linked}, also shown in another session
+    #[serde(default)]
+    pub piped: bool, // #{pane_pipe}, active pane's output goes to `pipe-pane`
+    #[serde(default)]
+    pub note: Option<String>, // See WINDOW_NOTE_OPTION
+    #[serde(default)]
+    pub color: Option<String>, // See WINDOW_COLOR_OPTION
+    #[serde(default)]
+    pub user_options: BTreeMap<String, String>, // `@options` that `window_format` shows
 }
 
-pub fn get_current_session_name() -> Result<Option<String>> {
-    use tmux_interface::{ListSessions, Tmux};
+impl TmuxWindow {
+    /// Expand a `window_format` template. Unknown placeholders stay as they
+    /// are, user options that weren't read are empty
+    pub fn format(&self, template: &str) -> String {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let placeholder = &rest[start..=start + len];
+            match self.field(&placeholder[1..len]) {
+                Some(value) => out.push_str(&value),
+                None => out.push_str(placeholder),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
 
-    // Try to get the current session name from tmux
-    let output = Tmux::with_command(
-        ListSessions::new().format("#{session_name}:#{?session_attached,attached,not_attached}"),
+    fn field(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "name" => self.name.clone(),
+            "index" => self.index.to_string(),
+            "command" => self.command.clone(),
+            "path" => self.path.clone(),
+            "panes" => self.panes.to_string(),
+            "note" => self.note.clone().unwrap_or_default(),
+            _ if name.starts_with('@') => self.user_options.get(name).cloned().unwrap_or_default(),
+            _ => return None,
+        })
+    }
+}
+
+/// User options a `window_format` template shows, e.g. `@owner` for
+/// `{@owner}`. Only plain names, they end up in a tmux format
+pub fn format_user_options(template: &str) -> Vec<String> {
+    let mut options = Vec::new();
+    for (start, _) in template.match_indices("{@") {
+        let Some(len) = template[start..].find('}') else {
+            continue;
+        };
+        let name = &template[start + 1..start + len];
+        let plain = name[1..]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if name.len() > 1 && plain && !options.iter().any(|o| o == name) {
+            options.push(name.to_string());
+        }
+    }
+    options
+}
+
+/// An on/off tmux option offered in the options panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OptionToggle {
+    pub name: &'static str,
+    pub window: bool, // Window option (`set-option -w`), else a session option
+}
+
+pub const OPTION_TOGGLES: &[OptionToggle] = &[
+    OptionToggle {
+        name: "automatic-rename",
+        window: true,
+    },
+    OptionToggle {
+        name: "monitor-activity",
+        window: true,
+    },
+    OptionToggle {
+        name: "remain-on-exit",
+        window: true,
+    },
+    OptionToggle {
+        name: "aggressive-resize",
+        window: true,
+    },
+    OptionToggle {
+        name: "renumber-windows",
+        window: false,
+    },
+];
+
+// Flags format as "1"/"0", choices like remain-on-exit as "on"/"off"
+fn parse_option_values(output: &str, count: usize) -> Option<Vec<bool>> {
+    let values: Vec<bool> = output
+        .trim_end()
+        .split('|')
+        .map(|value| value == "1" || value == "on")
+        .collect();
+    (values.len() == count).then_some(values)
+}
+
+/// What `doctor` and the info panel report about the running server
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServerInfo {
+    pub version: String, // #{version}, e.g. "3.3a"
+    pub socket_path: String,
+    pub pid: String,
+    pub clients: usize,
+    pub missing_formats: Vec<String>, // FORMAT_FIELDS that came back empty
+}
+
+// Fields are separated by \x1f, which can't show up in paths or names
+fn parse_server_info(output: &str, clients: usize) -> Option<ServerInfo> {
+    let mut parts = output.trim_end_matches('\n').split('\x1f');
+    let version = parts.next()?.to_string();
+    let socket_path = parts.next()?.to_string();
+    let pid = parts.next()?.to_string();
+    let values: Vec<&str> = parts.collect();
+    if values.len() != FORMAT_FIELDS.len() {
+        return None;
+    }
+
+    let missing_formats = FORMAT_FIELDS
+        .iter()
+        .zip(values)
+        .filter(|(_, value)| value.is_empty())
+        .map(|(field, _)| field.to_string())
+        .collect();
+    Some(ServerInfo {
+        version,
+        socket_path,
+        pid,
+        clients,
+        missing_formats,
+    })
+}
+
+/// One line of `show-environment`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: Option<String>, // None: removed from new processes ("-NAME")
+}
+
+// `show-environment` prints "NAME=value" or "-NAME", sorted by name
+fn parse_environment(output: &str) -> Vec<EnvVar> {
+    output
+        .lines()
+        .filter_map(|line| match line.strip_prefix('-') {
+            Some(name) => Some(EnvVar {
+                name: name.to_string(),
+                value: None,
+            }),
+            None => line.split_once('=').map(|(name, value)| EnvVar {
+                name: name.to_string(),
+                value: Some(value.to_string()),
+            }),
+        })
+        .collect()
+}
+
+/// Name of the socket of the tmux server we run under, from `$TMUX`
+pub fn socket_name() -> Option<String> {
+    std::env::var("TMUX")
+        .ok()
+        .and_then(|value| parse_socket_name(&value))
+}
+
+// `$TMUX` is "<socket path>,<server pid>,<session id>"
+fn parse_socket_name(tmux_env: &str) -> Option<String> {
+    let socket_path = tmux_env.split(',').next()?;
+    Path::new(socket_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+/// Shell command that attaches to a session from inside a popup, creating it
+/// if needed. It talks to our server even when that isn't the default one
+pub fn attach_command(session_name: &str) -> String {
+    let socket = std::env::var("TMUX")
+        .ok()
+        .and_then(|value| value.split(',').next().map(str::to_string))
+        .filter(|path| !path.is_empty());
+    attach_command_for(session_name, socket.as_deref())
+}
+
+// Without unsetting $TMUX, tmux refuses to attach from inside a pane
+fn attach_command_for(session_name: &str, socket_path: Option<&str>) -> String {
+    let socket = socket_path
+        .map(|path| format!(" -S {}", shell_quote(path)))
+        .unwrap_or_default();
+    format!(
+        "env -u TMUX tmux{socket} new-session -A -s {}",
+        shell_quote(session_name)
     )
-    .output();
+}
+
+/// `pipe-pane` command appending the pane's output to `file`. tmux expands
+/// formats in it, so `#` is doubled
+pub fn append_command(file: &Path) -> String {
+    format!("cat >> {}", shell_quote(&file.to_string_lossy())).replace('#', "##")
+}
+
+/// Shell command showing `file` in `$PAGER`, or less without one
+pub fn pager_command(file: &Path) -> String {
+    format!("${{PAGER:-less}} {}", shell_quote(&file.to_string_lossy()))
+}
+
+/// Quote `value` as a single `sh` word
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Everything the app asks of tmux. `RealTmux` talks to the tmux server,
+/// tests use the in-memory `fake::FakeTmux`
+pub trait TmuxBackend: Send + Sync {
+    fn list_sessions(&self) -> Result<Vec<TmuxSession>>;
+    fn current_session_name(&self) -> Result<Option<String>>;
+    fn window_path(&self, session_name: &str, window_id: &str) -> Result<String>;
+    fn switch_to_window(&self, session_name: &str, window_id: &str) -> Result<()>;
+    fn switch_to_session(&self, session_name: &str) -> Result<()>;
+    // Switch to session:window, creating either one in `path` if missing.
+    // A created window runs `on_create` and gets split into `layout`
+    fn switch_to_session_and_window(
+        &self,
+        session_name: &str,
+        window_name: &str,
+        path: &Path,
+        on_create: Option<&str>,
+        layout: &PaneLayout,
+    ) -> Result<()>;
+    fn rename_window(&self, session_name: &str, window_id: &str, new_name: &str) -> Result<()>;
+    fn rename_session(&self, old_name: &str, new_name: &str) -> Result<()>;
+    fn delete_window(&self, session_name: &str, window_id: &str) -> Result<()>;
+    fn kill_session(&self, session_name: &str) -> Result<()>;
+    // In `path` if given, else wherever tmux starts new windows
+    fn create_new_window(&self, session_name: &str, path: Option<&str>) -> Result<TmuxWindow>;
+    // Remember where `C` starts windows in the session, None forgets it
+    fn set_start_dir(&self, session_name: &str, path: Option<&str>) -> Result<()>;
+    // Note of a window, or of the session without one; None removes it
+    fn set_note(
+        &self,
+        session_name: &str,
+        window_id: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<()>;
+    // Color the window in the tree and in tmux's own status bar (through
+    // `window-status-style`), None goes back to the default style
+    fn set_window_color(&self, window_id: &str, color: Option<&str>) -> Result<()>;
+    fn swap_windows(&self, session_name: &str, window1_id: &str, window2_id: &str) -> Result<()>;
+    // Move a window right before or after another one, also across sessions
+    fn move_window(&self, window_id: &str, target_window_id: &str, before: bool) -> Result<()>;
+    // Move a window to a free index of its session
+    fn move_window_to_index(&self, session_name: &str, window_id: &str, index: u32) -> Result<()>;
+    // Show a window in another session too, at that session's next free index
+    fn link_window(&self, window_id: &str, target_session: &str) -> Result<()>;
+    // Remove a linked window from one session, it stays in the others
+    fn unlink_window(&self, session_name: &str, window_id: &str) -> Result<()>;
+    // Current values with inheritance, window options of this window and
+    // session options of its session
+    fn read_options(
+        &self,
+        session_name: &str,
+        window_id: &str,
+        toggles: &[OptionToggle],
+    ) -> Result<Vec<bool>>;
+    fn set_option(
+        &self,
+        session_name: &str,
+        window_id: &str,
+        toggle: &OptionToggle,
+        on: bool,
+    ) -> Result<()>;
+    fn server_info(&self) -> Result<ServerInfo>;
+    // The session's own environment, without the global one
+    fn session_environment(&self, session_name: &str) -> Result<Vec<EnvVar>>;
+    // Set a variable for processes started in the session from now on, or
+    // drop the session's own value (None)
+    fn set_environment(&self, session_name: &str, name: &str, value: Option<&str>) -> Result<()>;
+    // Move a window into a new session of its own, with `path` as its directory
+    fn break_window(&self, window_id: &str, session_name: &str, path: &str) -> Result<()>;
+    // Pipe the output of the window's active pane into a shell command, or
+    // stop piping (None)
+    fn pipe_pane(&self, window_id: &str, command: Option<&str>) -> Result<()>;
+    // Everything the window's active pane holds, scrollback included
+    fn capture_pane(&self, window_id: &str) -> Result<String>;
+    // Into a tmux buffer and, via OSC 52, the system clipboard
+    fn copy_to_clipboard(&self, text: &str) -> Result<()>;
+    // Run a command in a new, focused window or in a popup, starting in `path`.
+    // Popup width and height are in percent of the terminal
+    fn open_window(&self, session_name: &str, name: &str, path: &str, command: &str) -> Result<()>;
+    fn open_popup(&self, path: &str, command: &str, width: u16, height: u16) -> Result<()>;
+    // Zoom the window's active pane, leaving it be when it's zoomed already
+    fn zoom_window(&self, window_id: &str) -> Result<()>;
+    // Run `command` in a popup with `env` set and wait for it to close, the
+    // exit status is the command's
+    fn run_in_popup(
+        &self,
+        path: &str,
+        command: &str,
+        env: &[(String, String)],
+    ) -> Result<Option<i32>>;
+    // Recreate an archived session's windows, detached. Fails if the name is
+    // taken
+    fn restore_session(&self, archived: &ArchivedSession) -> Result<()>;
+}
+
+/// The tmux server we run under. `user_options` are read along with every
+/// window, see `format_user_options`
+#[derive(Default)]
+pub struct RealTmux {
+    user_options: Vec<String>,
+}
+
+impl RealTmux {
+    pub fn new(user_options: Vec<String>) -> Self {
+        Self { user_options }
+    }
+}
+
+impl TmuxBackend for RealTmux {
+    fn list_sessions(&self) -> Result<Vec<TmuxSession>> {
+        get_tmux_sessions(&self.user_options)
+    }
+
+    fn current_session_name(&self) -> Result<Option<String>> {
+        get_current_session_name()
+    }
+
+    fn window_path(&self, session_name: &str, window_id: &str) -> Result<String> {
+        get_window_path(session_name, window_id)
+    }
+
+    fn switch_to_window(&self, session_name: &str, window_id: &str) -> Result<()> {
+        switch_to_window(session_name, window_id)
+    }
+
+    fn switch_to_session(&self, session_name: &str) -> Result<()> {
+        switch_to_session(session_name)
+    }
+
+    fn switch_to_session_and_window(
+        &self,
+        session_name: &str,
+        window_name: &str,
+        path: &Path,
+        on_create: Option<&str>,
+        layout: &PaneLayout,
+    ) -> Result<()> {
+        switch_to_session_and_window(session_name, window_name, path, on_create, layout)
+    }
+
+    fn rename_window(&self, session_name: &str, window_id: &str, new_name: &str) -> Result<()> {
+        rename_window(session_name, window_id, new_name)
+    }
+
+    fn rename_session(&self, old_name: &str, new_name: &str) -> Result<()> {
+        rename_session(old_name, new_name)
+    }
+
+    fn delete_window(&self, session_name: &str, window_id: &str) -> Result<()> {
+        delete_window(session_name, window_id)
+    }
+
+    fn kill_session(&self, session_name: &str) -> Result<()> {
+        kill_session(session_name)
+    }
+
+    fn create_new_window(&self, session_name: &str, path: Option<&str>) -> Result<TmuxWindow> {
+        create_new_window(session_name, path, &self.user_options)
+    }
+
+    fn set_start_dir(&self, session_name: &str, path: Option<&str>) -> Result<()> {
+        set_start_dir(session_name, path)
+    }
+
+    fn set_note(
+        &self,
+        session_name: &str,
+        window_id: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<()> {
+        set_note(session_name, window_id, note)
+    }
+
+    fn set_window_color(&self, window_id: &str, color: Option<&str>) -> Result<()> {
+        set_window_color(window_id, color)
+    }
+
+    fn swap_windows(&self, session_name: &str, window1_id: &str, window2_id: &str) -> Result<()> {
+        swap_windows_in_tmux(session_name, window1_id, window2_id)
+    }
+
+    fn move_window(&self, window_id: &str, target_window_id: &str, before: bool) -> Result<()> {
+        move_window_in_tmux(window_id, target_window_id, before)
+    }
+
+    fn move_window_to_index(&self, session_name: &str, window_id: &str, index: u32) -> Result<()> {
+        move_window_to_index_in_tmux(session_name, window_id, index)
+    }
+
+    fn link_window(&self, window_id: &str, target_session: &str) -> Result<()> {
+        link_window_in_tmux(window_id, target_session)
+    }
+
+    fn unlink_window(&self, session_name: &str, window_id: &str) -> Result<()> {
+        unlink_window_in_tmux(session_name, window_id)
+    }
+
+    fn read_options(
+        &self,
+        session_name: &str,
+        window_id: &str,
+        toggles: &[OptionToggle],
+    ) -> Result<Vec<bool>> {
+        read_options(session_name, window_id, toggles)
+    }
+
+    fn set_option(
+        &self,
+        session_name: &str,
+        window_id: &str,
+        toggle: &OptionToggle,
+        on: bool,
+    ) -> Result<()> {
+        set_option(session_name, window_id, toggle, on)
+    }
+
+    fn server_info(&self) -> Result<ServerInfo> {
+        get_server_info()
+    }
+
+    fn session_environment(&self, session_name: &str) -> Result<Vec<EnvVar>> {
+        session_environment(session_name)
+    }
+
+    fn set_environment(&self, session_name: &str, name: &str, value: Option<&str>) -> Result<()> {
+        set_environment(session_name, name, value)
+    }
+
+    fn break_window(&self, window_id: &str, session_name: &str, path: &str) -> Result<()> {
+        break_window_in_tmux(window_id, session_name, path)
+    }
+
+    fn pipe_pane(&self, window_id: &str, command: Option<&str>) -> Result<()> {
+        pipe_pane(window_id, command)
+    }
+
+    fn capture_pane(&self, window_id: &str) -> Result<String> {
+        capture_pane(window_id)
+    }
+
+    fn copy_to_clipboard(&self, text: &str) -> Result<()> {
+        copy_to_clipboard(text)
+    }
+
+    fn open_window(&self, session_name: &str, name: &str, path: &str, command: &str) -> Result<()> {
+        open_window(session_name, name, path, command)
+    }
+
+    fn open_popup(&self, path: &str, command: &str, width: u16, height: u16) -> Result<()> {
+        open_popup(path, command, width, height)
+    }
+
+    fn run_in_popup(
+        &self,
+        path: &str,
+        command: &str,
+        env: &[(String, String)],
+    ) -> Result<Option<i32>> {
+        run_in_popup(path, command, env)
+    }
+
+    fn zoom_window(&self, window_id: &str) -> Result<()> {
+        zoom_window(window_id)
+    }
+
+    fn restore_session(&self, archived: &ArchivedSession) -> Result<()> {
+        restore_session(archived)
+    }
+}
+
+// Every tmux invocation goes through here so `--debug` can log it
+fn run<'a>(command: impl Into<TmuxCommand<'a>>) -> Result<TmuxOutput, tmux_interface::Error> {
+    let command = command.into();
+    let line = command.to_string();
+    let start = Instant::now();
+
+    let output = Tmux::with_command(command).output();
+
+    let elapsed = start.elapsed();
+    match &output {
+        Ok(output) => tracing::debug!(
+            command = %line,
+            ?elapsed,
+            status = %output.0.status,
+            stdout = ?String::from_utf8_lossy(&output.0.stdout).trim_end(),
+            stderr = ?String::from_utf8_lossy(&output.0.stderr).trim_end(),
+            "tmux"
+        ),
+        Err(err) => tracing::warn!(command = %line, ?elapsed, error = %err, "tmux failed to run"),
+    }
+
+    output
+}
+
+fn get_current_session_name() -> Result<Option<String>> {
+    use tmux_interface::ListSessions;
+
+    // Try to get the current session name from tmux
+    let output =
+        run(ListSessions::new()
+            .format("#{session_name}\x1f#{?session_attached,attached,not_attached}"));
 
     match output {
         Ok(output) if output.status().success() => {
@@ -34,8 +860,8 @@ pub fn get_current_session_name() -> Result<Option<String>> {
 
             // Find the attached session
             for line in stdout_str.lines() {
-                if line.ends_with(":attached") {
-                    let session_name = line.trim_end_matches(":attached").to_string();
+                if let Some(session_name) = line.strip_suffix("\x1fattached") {
+                    let session_name = session_name.to_string();
                     if !session_name.is_empty() {
                         return Ok(Some(session_name));
                     }
@@ -48,9 +874,104 @@ pub fn get_current_session_name() -> Result<Option<String>> {
     }
 }
 
-pub fn get_tmux_sessions() -> Result<Vec<TmuxSession>> {
-    // Check if tmux server is running
-    let sessions_output = match Tmux::with_command(ListSessions::new()).output() {
+// Separates fields in -F formats. Unlike ':' or '|' it can't be typed into a
+// session name, window name or path
+const FIELD_SEPARATOR: char = '\x1f';
+
+// Fixed fields of a window as `parse_window` reads them
+const WINDOW_FIELDS: &str = concat!(
+    "#{window_id}\x1f#{window_index}\x1f#{window_active}\x1f",
+    "#{window_panes}\x1f#{window_zoomed_flag}\x1f#{window_activity}\x1f#{window_linked}\x1f",
+    "#{pane_dead}\x1f#{pane_pipe}\x1f#{pane_current_command}\x1f#{pane_current_path}\x1f",
+    "#{@portal_note}\x1f#{@portal_color}\x1f"
+);
+
+// The fixed fields, the values of `user_options` and the name, which goes
+// last and takes the rest of the line: a separator in it, or in an option
+// value, ends up in the name instead of dropping the window
+fn window_format(user_options: &[String]) -> String {
+    let options: String = user_options
+        .iter()
+        .map(|name| format!("#{{{name}}}{FIELD_SEPARATOR}"))
+        .collect();
+    format!("{WINDOW_FIELDS}{options}#{{window_name}}")
+}
+
+// Format variables the tree reads. tmux expands ones it doesn't know to
+// nothing, so an old server shows up as empty fields
+pub const FORMAT_FIELDS: &[&str] = &[
+    "session_attached",
+    "session_activity",
+    "window_id",
+    "window_index",
+    "window_active",
+    "window_panes",
+    "window_zoomed_flag",
+    "window_activity",
+    "window_linked",
+    "pane_dead",
+    "pane_pipe",
+    "pane_current_command",
+    "pane_current_path",
+];
+
+// Session user option with the directory new windows start in, so it lives
+// and dies with the session
+const START_DIR_OPTION: &str = "@portal_dir";
+
+// User options holding the notes typed with `"`. A window without its own
+// option would show its session's in formats, so sessions use another name
+const WINDOW_NOTE_OPTION: &str = "@portal_note";
+const SESSION_NOTE_OPTION: &str = "@portal_session_note";
+
+// The color given with `c`, also written to the window's
+// `window-status-style`. Reading that back instead would show a theme's
+// global style as a color on every window
+const WINDOW_COLOR_OPTION: &str = "@portal_color";
+
+// Prefix of `list-windows -a` lines
+const SESSION_FORMAT: &str = concat!(
+    "#{session_name}\x1f#{session_attached}\x1f#{session_activity}\x1f#{@portal_dir}\x1f",
+    "#{base-index}\x1f#{renumber-windows}\x1f#{@portal_session_note}\x1f"
+);
+
+fn parse_window(session_name: &str, line: &str, user_options: &[String]) -> Option<TmuxWindow> {
+    let fields = 14 + user_options.len();
+    let parts: Vec<&str> = line.splitn(fields, FIELD_SEPARATOR).collect();
+    if parts.len() != fields {
+        return None;
+    }
+
+    Some(TmuxWindow {
+        id: parts[0].to_string(),
+        index: parts[1].parse().ok()?,
+        name: parts[parts.len() - 1].to_string(),
+        session_name: session_name.to_string(),
+        active: parts[2] == "1",
+        panes: parts[3].parse().ok()?,
+        zoomed: parts[4] == "1",
+        activity: parts[5].parse().ok()?,
+        linked: parts[6] == "1",
+        dead: parts[7] == "1",
+        piped: parts[8] == "1",
+        command: parts[9].to_string(),
+        path: parts[10].to_string(),
+        note: Some(parts[11].to_string()).filter(|note| !note.is_empty()),
+        color: Some(parts[12].to_string()).filter(|color| !color.is_empty()),
+        user_options: user_options
+            .iter()
+            .zip(&parts[13..])
+            .map(|(name, value)| (name.clone(), value.to_string()))
+            .collect(),
+    })
+}
+
+fn get_tmux_sessions(user_options: &[String]) -> Result<Vec<TmuxSession>> {
+    // One `list-windows -a` for all sessions instead of one call per session
+    let windows_output = match run(ListWindows::new()
+        .all()
+        .format(format!("{SESSION_FORMAT}{}", window_format(user_options))))
+    {
         Ok(output) => output,
         Err(_) => {
             // No tmux server running or tmux not available
@@ -58,41 +979,62 @@ pub fn get_tmux_sessions() -> Result<Vec<TmuxSession>> {
         }
     };
 
-    if !sessions_output.status().success() {
+    if !windows_output.status().success() {
         return Ok(vec![]);
     }
 
-    let stdout_data = sessions_output.stdout();
-    let sessions_str = String::from_utf8_lossy(&stdout_data);
-    let mut sessions = Vec::new();
+    let stdout_data = windows_output.stdout();
+    Ok(parse_all_windows(
+        &String::from_utf8_lossy(&stdout_data),
+        user_options,
+    ))
+}
+
+// Group `list-windows -a` lines into sessions, keeping tmux's order
+fn parse_all_windows(output: &str, user_options: &[String]) -> Vec<TmuxSession> {
+    let mut sessions: Vec<TmuxSession> = Vec::new();
 
-    for line in sessions_str.lines() {
-        if let Some(session_name) = parse_session_name(line) {
-            let windows = get_session_windows(&session_name)?;
-            sessions.push(TmuxSession {
-                name: session_name,
-                windows,
-            });
+    for line in output.lines() {
+        let parts: Vec<&str> = line.splitn(8, FIELD_SEPARATOR).collect();
+        let [
+            session_name,
+            attached,
+            activity,
+            start_dir,
+            base_index,
+            renumber,
+            note,
+            rest,
+        ] = parts[..]
+        else {
+            continue;
+        };
+        let Some(window) = parse_window(session_name, rest, user_options) else {
+            continue;
+        };
+
+        match sessions.last_mut() {
+            Some(session) if session.name == session_name => session.windows.push(window),
+            _ => sessions.push(TmuxSession {
+                name: session_name.to_string(),
+                windows: vec![window],
+                attached: attached.parse().unwrap_or(0),
+                activity: activity.parse().unwrap_or(0),
+                start_dir: Some(start_dir.to_string()).filter(|dir| !dir.is_empty()),
+                base_index: base_index.parse().unwrap_or(0),
+                renumber: renumber == "1",
+                note: Some(note.to_string()).filter(|note| !note.is_empty()),
+            }),
         }
     }
 
-    Ok(sessions)
-}
-
-fn parse_session_name(line: &str) -> Option<String> {
-    // Parse session name from tmux list-sessions output
-    // Format: "session_name: 1 windows (created ...)"
-    line.find(':')
-        .map(|colon_pos| line[..colon_pos].trim().to_string())
+    sessions
 }
 
 fn get_session_windows(session_name: &str) -> Result<Vec<TmuxWindow>> {
-    let windows_output = Tmux::with_command(
-        ListWindows::new()
-            .target_session(session_name)
-            .format("#{window_id}|#{window_name}|#{window_active}"),
-    )
-    .output()?;
+    let windows_output = run(ListWindows::new()
+        .target_session(session_name)
+        .format(window_format(&[])))?;
 
     if !windows_output.status().success() {
         return Err(anyhow!(
@@ -103,29 +1045,17 @@ fn get_session_windows(session_name: &str) -> Result<Vec<TmuxWindow>> {
 
     let stdout_data = windows_output.stdout();
     let windows_str = String::from_utf8(stdout_data)?;
-    let mut windows = Vec::new();
-
-    for line in windows_str.lines() {
-        let parts: Vec<&str> = line.split('|').collect();
-        if parts.len() == 3 {
-            windows.push(TmuxWindow {
-                id: parts[0].to_string(),
-                name: parts[1].to_string(),
-                session_name: session_name.to_string(),
-                active: parts[2] == "1",
-            });
-        }
-    }
 
-    Ok(windows)
+    Ok(windows_str
+        .lines()
+        .filter_map(|line| parse_window(session_name, line, &[]))
+        .collect())
 }
 
-pub fn switch_to_window(session_name: &str, window_id: &str) -> Result<()> {
+fn switch_to_window(session_name: &str, window_id: &str) -> Result<()> {
     // First select the window using window ID for unique identification
-    let select_output = Tmux::with_command(
-        SelectWindow::new().target_window(format!("{session_name}:{window_id}")),
-    )
-    .output()?;
+    let select_output =
+        run(SelectWindow::new().target_window(format!("{session_name}:{window_id}")))?;
 
     if !select_output.status().success() {
         return Err(anyhow!(
@@ -136,8 +1066,7 @@ pub fn switch_to_window(session_name: &str, window_id: &str) -> Result<()> {
     }
 
     // Then switch to the session
-    let switch_output =
-        Tmux::with_command(SwitchClient::new().target_session(session_name)).output()?;
+    let switch_output = run(SwitchClient::new().target_session(session_name))?;
 
     if !switch_output.status().success() {
         return Err(anyhow!("Failed to switch to session: {}", session_name));
@@ -146,13 +1075,30 @@ pub fn switch_to_window(session_name: &str, window_id: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn rename_window(session_name: &str, window_id: &str, new_name: &str) -> Result<()> {
-    let output = Tmux::with_command(
-        RenameWindow::new()
-            .target_window(format!("{session_name}:{window_id}"))
-            .new_name(new_name),
-    )
-    .output()?;
+fn get_window_path(session_name: &str, window_id: &str) -> Result<String> {
+    use tmux_interface::DisplayMessage;
+
+    let output = run(DisplayMessage::new()
+        .print()
+        .target_pane(format!("{session_name}:{window_id}"))
+        .message("#{pane_current_path}"))?;
+
+    if !output.status().success() {
+        return Err(anyhow!(
+            "Failed to get path of window: {}:{}",
+            session_name,
+            window_id
+        ));
+    }
+
+    let stdout_data = output.stdout();
+    Ok(String::from_utf8_lossy(&stdout_data).trim().to_string())
+}
+
+fn rename_window(session_name: &str, window_id: &str, new_name: &str) -> Result<()> {
+    let output = run(RenameWindow::new()
+        .target_window(format!("{session_name}:{window_id}"))
+        .new_name(new_name))?;
 
     if !output.status().success() {
         return Err(anyhow!(
@@ -165,11 +1111,11 @@ pub fn rename_window(session_name: &str, window_id: &str, new_name: &str) -> Res
     Ok(())
 }
 
-pub fn delete_window(session_name: &str, window_id: &str) -> Result<()> {
-    use tmux_interface::{KillWindow, Tmux};
+fn delete_window(session_name: &str, window_id: &str) -> Result<()> {
+    use tmux_interface::KillWindow;
 
     let target = format!("{session_name}:{window_id}");
-    let output = Tmux::with_command(KillWindow::new().target_window(&target)).output()?;
+    let output = run(KillWindow::new().target_window(&target))?;
 
     if !output.status().success() {
         let stderr = output.stderr();
@@ -180,9 +1126,8 @@ pub fn delete_window(session_name: &str, window_id: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn switch_to_session(session_name: &str) -> Result<()> {
-    let switch_output =
-        Tmux::with_command(SwitchClient::new().target_session(session_name)).output()?;
+fn switch_to_session(session_name: &str) -> Result<()> {
+    let switch_output = run(SwitchClient::new().target_session(session_name))?;
 
     if !switch_output.status().success() {
         return Err(anyhow!("Failed to switch to session: {}", session_name));
@@ -191,8 +1136,8 @@ pub fn switch_to_session(session_name: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn kill_session(session_name: &str) -> Result<()> {
-    let output = Tmux::with_command(KillSession::new().target_session(session_name)).output()?;
+fn kill_session(session_name: &str) -> Result<()> {
+    let output = run(KillSession::new().target_session(session_name))?;
 
     if !output.status().success() {
         return Err(anyhow!("Failed to kill session '{}'", session_name));
@@ -201,15 +1146,12 @@ pub fn kill_session(session_name: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn rename_session(old_name: &str, new_name: &str) -> Result<()> {
-    use tmux_interface::{RenameSession, Tmux};
+fn rename_session(old_name: &str, new_name: &str) -> Result<()> {
+    use tmux_interface::RenameSession;
 
-    let output = Tmux::with_command(
-        RenameSession::new()
-            .target_session(old_name)
-            .new_name(new_name),
-    )
-    .output()?;
+    let output = run(RenameSession::new()
+        .target_session(old_name)
+        .new_name(new_name))?;
 
     if !output.status().success() {
         let stderr = output.stderr();
@@ -224,20 +1166,13 @@ pub fn rename_session(old_name: &str, new_name: &str) -> Result<()> {
 
     Ok(())
 }
-pub fn find_window_in_session(session_name: &str, window_name: &str) -> Result<Option<TmuxWindow>> {
+fn find_window_in_session(session_name: &str, window_name: &str) -> Result<Option<TmuxWindow>> {
     // First check if the session exists
-    let session_exists = Tmux::with_command(ListSessions::new())
-        .output()
+    let session_exists = run(ListSessions::new().format("#{session_name}"))
         .map(|output| {
             let stdout_data = output.stdout();
             let sessions_str = String::from_utf8_lossy(&stdout_data);
-            sessions_str.lines().any(|line| {
-                if let Some(name) = parse_session_name(line) {
-                    name == session_name
-                } else {
-                    false
-                }
-            })
+            sessions_str.lines().any(|name| name == session_name)
         })
         .unwrap_or(false);
 
@@ -254,16 +1189,25 @@ pub fn find_window_in_session(session_name: &str, window_name: &str) -> Result<O
     Ok(window)
 }
 
-pub fn create_new_window(session_name: &str) -> Result<()> {
-    use tmux_interface::{NewWindow, Tmux};
-
-    // Use the -d flag to create the window without attaching to it
-    let output = Tmux::with_command(
-        NewWindow::new()
-            .detached() // -d flag
-            .target_window(session_name),
-    )
-    .output()?;
+fn create_new_window(
+    session_name: &str,
+    path: Option<&str>,
+    user_options: &[String],
+) -> Result<TmuxWindow> {
+    use tmux_interface::NewWindow;
+
+    // Use the -d flag to create the window without attaching to it, and print
+    // the new window so the caller doesn't have to list everything again
+    let command = NewWindow::new()
+        .detached() // -d flag
+        .print()
+        .format(window_format(user_options))
+        .target_window(session_name);
+    let command = match path {
+        Some(path) => command.start_directory(path),
+        None => command,
+    };
+    let output = run(command)?;
 
     if !output.status().success() {
         let stderr = output.stderr();
@@ -271,58 +1215,348 @@ pub fn create_new_window(session_name: &str) -> Result<()> {
         return Err(anyhow::anyhow!("Failed to create new window: {}", error));
     }
 
-    Ok(())
+    let stdout_data = output.stdout();
+    let stdout_str = String::from_utf8_lossy(&stdout_data);
+    parse_window(session_name, stdout_str.trim_end(), user_options)
+        .ok_or_else(|| anyhow!("Unexpected new-window output: {}", stdout_str))
 }
 
-pub fn swap_windows_in_tmux(session_name: &str, window1_id: &str, window2_id: &str) -> Result<()> {
-    use tmux_interface::{ListWindows, SelectWindow, SwapWindow, Tmux};
+fn set_start_dir(session_name: &str, path: Option<&str>) -> Result<()> {
+    use tmux_interface::SetOption;
 
-    // First, check which window is currently active
-    let active_window_output = Tmux::with_command(
-        ListWindows::new()
-            .target_session(session_name)
-            .format("#{window_id}:#{window_active}"),
-    )
-    .output()?;
-
-    let mut active_window_id = String::new();
-    if active_window_output.status().success() {
-        let stdout_data = active_window_output.stdout();
-        let stdout_str = String::from_utf8_lossy(&stdout_data);
+    // Without -w/-p the option is set on the session that -t resolves to
+    let command = SetOption::new()
+        .target_pane(session_name)
+        .option(START_DIR_OPTION);
+    let command = match path {
+        Some(path) => command.value(path),
+        None => command.unset(),
+    };
+    let output = run(command)?;
 
-        // Find the active window
-        for line in stdout_str.lines() {
-            if line.ends_with(":1") {
-                // Active window has window_active=1
-                let parts: Vec<&str> = line.split(':').collect();
-                if parts.len() >= 2 {
-                    active_window_id = parts[0].to_string();
-                    break;
-                }
-            }
-        }
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to set start directory: {}", error.trim()));
     }
+    Ok(())
+}
 
-    // Perform the swap
-    let output = Tmux::with_command(
-        SwapWindow::new()
-            .src_window(window1_id)
-            .dst_window(window2_id),
-    )
-    .output()?;
+fn set_note(session_name: &str, window_id: Option<&str>, note: Option<&str>) -> Result<()> {
+    use tmux_interface::SetOption;
+
+    let command = match window_id {
+        Some(window_id) => SetOption::new()
+            .window()
+            .target_pane(window_id)
+            .option(WINDOW_NOTE_OPTION),
+        None => SetOption::new()
+            .target_pane(session_name)
+            .option(SESSION_NOTE_OPTION),
+    };
+    let command = match note {
+        Some(note) => command.value(note),
+        None => command.unset(),
+    };
+    let output = run(command)?;
 
     if !output.status().success() {
         let stderr = output.stderr();
         let error = String::from_utf8_lossy(&stderr);
-        return Err(anyhow::anyhow!("Failed to swap windows: {}", error));
+        return Err(anyhow!("Failed to set note: {}", error.trim()));
     }
+    Ok(())
+}
 
-    // If one of the swapped windows was active, make sure it stays active
+fn set_window_color(window_id: &str, color: Option<&str>) -> Result<()> {
+    use tmux_interface::SetOption;
+
+    let options = [
+        (
+            "window-status-style",
+            color.map(|color| format!("fg={color}")),
+        ),
+        (WINDOW_COLOR_OPTION, color.map(str::to_string)),
+    ];
+    for (option, value) in options {
+        let command = SetOption::new()
+            .window()
+            .target_pane(window_id)
+            .option(option);
+        let command = match value {
+            Some(value) => command.value(value),
+            None => command.unset(),
+        };
+        let output = run(command)?;
+
+        if !output.status().success() {
+            let stderr = output.stderr();
+            let error = String::from_utf8_lossy(&stderr);
+            return Err(anyhow!("Failed to set window color: {}", error.trim()));
+        }
+    }
+    Ok(())
+}
+
+fn move_window_in_tmux(window_id: &str, target_window_id: &str, before: bool) -> Result<()> {
+    use tmux_interface::MoveWindow;
+
+    // -d keeps the current window of the target session
+    let command = MoveWindow::new()
+        .detached()
+        .src_window(window_id)
+        .dst_window(target_window_id);
+    let command = if before {
+        command.before()
+    } else {
+        command.after()
+    };
+    let output = run(command)?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to move window: {}", error.trim()));
+    }
+    Ok(())
+}
+
+fn move_window_to_index_in_tmux(session_name: &str, window_id: &str, index: u32) -> Result<()> {
+    use tmux_interface::MoveWindow;
+
+    let target = format!("{session_name}:{index}");
+    let output = run(MoveWindow::new()
+        .detached()
+        .src_window(window_id)
+        .dst_window(target.as_str()))?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to move window: {}", error.trim()));
+    }
+    Ok(())
+}
+
+fn link_window_in_tmux(window_id: &str, target_session: &str) -> Result<()> {
+    use tmux_interface::LinkWindow;
+
+    // "session:" without an index takes the next free one
+    let target = format!("{target_session}:");
+    let output = run(LinkWindow::new()
+        .detached()
+        .src_window(window_id)
+        .dst_window(target.as_str()))?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to link window: {}", error.trim()));
+    }
+    Ok(())
+}
+
+fn unlink_window_in_tmux(session_name: &str, window_id: &str) -> Result<()> {
+    use tmux_interface::UnlinkWindow;
+
+    // A linked window has the same id in every session, the session picks one
+    let target = format!("{session_name}:{window_id}");
+    let output = run(UnlinkWindow::new().target_window(target.as_str()))?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to unlink window: {}", error.trim()));
+    }
+    Ok(())
+}
+
+fn read_options(
+    session_name: &str,
+    window_id: &str,
+    toggles: &[OptionToggle],
+) -> Result<Vec<bool>> {
+    use tmux_interface::DisplayMessage;
+
+    // Options are format variables too, so one call reads them all
+    let message = toggles
+        .iter()
+        .map(|toggle| format!("#{{{}}}", toggle.name))
+        .collect::<Vec<_>>()
+        .join("|");
+    let output = run(DisplayMessage::new()
+        .print()
+        .target_pane(format!("{session_name}:{window_id}"))
+        .message(message))?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to read options: {}", error.trim()));
+    }
+
+    let stdout = output.stdout();
+    let stdout = String::from_utf8_lossy(&stdout);
+    parse_option_values(&stdout, toggles.len())
+        .ok_or_else(|| anyhow!("Unexpected option values: {}", stdout.trim()))
+}
+
+fn set_option(session_name: &str, window_id: &str, toggle: &OptionToggle, on: bool) -> Result<()> {
+    use tmux_interface::SetOption;
+
+    let value = if on { "on" } else { "off" };
+    let command = SetOption::new().option(toggle.name).value(value);
+    let command = if toggle.window {
+        command
+            .window()
+            .target_pane(format!("{session_name}:{window_id}"))
+    } else {
+        command.target_pane(session_name)
+    };
+    let output = run(command)?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to set {}: {}", toggle.name, error.trim()));
+    }
+    Ok(())
+}
+
+fn get_server_info() -> Result<ServerInfo> {
+    use tmux_interface::{DisplayMessage, ListClients};
+
+    let message = ["version", "socket_path", "pid"]
+        .iter()
+        .chain(FORMAT_FIELDS)
+        .map(|field| format!("#{{{field}}}"))
+        .collect::<Vec<_>>()
+        .join("\x1f");
+    let output = run(DisplayMessage::new().print().message(message))?;
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("{}", error.trim()));
+    }
+    let stdout = output.stdout();
+    let stdout = String::from_utf8_lossy(&stdout).to_string();
+
+    let clients = run(ListClients::new().format("#{client_name}"))?;
+    let clients = String::from_utf8_lossy(&clients.stdout()).lines().count();
+
+    parse_server_info(&stdout, clients)
+        .ok_or_else(|| anyhow!("Unexpected server info: {}", stdout.trim()))
+}
+
+fn session_environment(session_name: &str) -> Result<Vec<EnvVar>> {
+    use tmux_interface::ShowEnvironment;
+
+    let output = run(ShowEnvironment::new().target_session(session_name))?;
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to show environment: {}", error.trim()));
+    }
+
+    let stdout = output.stdout();
+    Ok(parse_environment(&String::from_utf8_lossy(&stdout)))
+}
+
+fn set_environment(session_name: &str, name: &str, value: Option<&str>) -> Result<()> {
+    use tmux_interface::SetEnvironment;
+
+    let command = SetEnvironment::new()
+        .target_session(session_name)
+        .name(name);
+    let command = match value {
+        Some(value) => command.value(value),
+        None => command.unset(),
+    };
+    let output = run(command)?;
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to set environment: {}", error.trim()));
+    }
+    Ok(())
+}
+
+fn break_window_in_tmux(window_id: &str, session_name: &str, path: &str) -> Result<()> {
+    use tmux_interface::{KillWindow, NewSession, SwapWindow};
+
+    // A new session always comes with a window: swap ours in for it, then
+    // kill that placeholder where our window used to be
+    let output = run(NewSession::new()
+        .detached()
+        .print()
+        .format("#{window_id}")
+        .session_name(session_name)
+        .start_directory(path))?;
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to create session: {}", error.trim()));
+    }
+    let stdout = output.stdout();
+    let placeholder = String::from_utf8_lossy(&stdout).trim().to_string();
+
+    let output = run(SwapWindow::new()
+        .detached()
+        .src_window(window_id)
+        .dst_window(placeholder.as_str()))?;
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        let _ = run(KillSession::new().target_session(session_name));
+        return Err(anyhow!("Failed to move window: {}", error.trim()));
+    }
+
+    run(KillWindow::new().target_window(placeholder.as_str()))?;
+    Ok(())
+}
+
+fn swap_windows_in_tmux(session_name: &str, window1_id: &str, window2_id: &str) -> Result<()> {
+    use tmux_interface::{ListWindows, SelectWindow, SwapWindow};
+
+    // First, check which window is currently active
+    let active_window_output = run(ListWindows::new()
+        .target_session(session_name)
+        .format("#{window_id}:#{window_active}"))?;
+
+    let mut active_window_id = String::new();
+    if active_window_output.status().success() {
+        let stdout_data = active_window_output.stdout();
+        let stdout_str = String::from_utf8_lossy(&stdout_data);
+
+        // Find the active window
+        for line in stdout_str.lines() {
+            if line.ends_with(":1") {
+                // Active window has window_active=1
+                let parts: Vec<&str> = line.split(':').collect();
+                if parts.len() >= 2 {
+                    active_window_id = parts[0].to_string();
+                    break;
+                }
+            }
+        }
+    }
+
+    // Perform the swap
+    let output = run(SwapWindow::new()
+        .src_window(window1_id)
+        .dst_window(window2_id))?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow::anyhow!("Failed to swap windows: {}", error));
+    }
+
+    // If one of the swapped windows was active, make sure it stays active
     if !active_window_id.is_empty()
         && (active_window_id == window1_id || active_window_id == window2_id)
     {
-        let select_output =
-            Tmux::with_command(SelectWindow::new().target_window(&active_window_id)).output()?;
+        let select_output = run(SelectWindow::new().target_window(&active_window_id))?;
 
         if !select_output.status().success() {
             // Don't fail the whole operation if select-window fails
@@ -333,12 +1567,203 @@ pub fn swap_windows_in_tmux(session_name: &str, window1_id: &str, window2_id: &s
     Ok(())
 }
 
-pub fn switch_to_session_and_window(
+fn pipe_pane(window_id: &str, command: Option<&str>) -> Result<()> {
+    use tmux_interface::PipePane;
+
+    // Without a command tmux closes the pipe. -o keeps a second pipe from
+    // replacing one that is already open
+    let mut pipe = PipePane::new().target_pane(window_id);
+    if let Some(command) = command {
+        pipe = pipe.open().shell_command(command);
+    }
+    let output = run(pipe)?;
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to pipe pane: {}", error.trim()));
+    }
+    Ok(())
+}
+
+fn capture_pane(window_id: &str) -> Result<String> {
+    use tmux_interface::CapturePane;
+
+    // -J joins wrapped lines, so a long command reads as one line
+    let output = run(CapturePane::new()
+        .stdout()
+        .join()
+        .start_line("-")
+        .target_pane(window_id))?;
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to capture pane: {}", error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout()).to_string())
+}
+
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    use tmux_interface::SetBuffer;
+
+    // -w also hands the buffer to the terminal's clipboard when tmux has
+    // set-clipboard enabled
+    let output = run(SetBuffer::new().send_to_clipboard().data(text))?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to set buffer: {}", error));
+    }
+
+    Ok(())
+}
+
+fn open_window(session_name: &str, name: &str, path: &str, command: &str) -> Result<()> {
+    use tmux_interface::NewWindow;
+
+    let output = run(NewWindow::new()
+        .target_window(format!("{session_name}:"))
+        .window_name(name)
+        .start_directory(path)
+        .print()
+        .format("#{window_id}")
+        .shell_command(command))?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to open window: {}", error));
+    }
+
+    // new-window only selects it within its session
+    let window_id = String::from_utf8_lossy(&output.stdout()).trim().to_string();
+    switch_to_window(session_name, &window_id)
+}
+
+fn restore_session(archived: &ArchivedSession) -> Result<()> {
+    use tmux_interface::{NewSession, NewWindow};
+
+    let name = &archived.name;
+    let Some((first, rest)) = archived.windows.split_first() else {
+        return Err(anyhow!("Archived session '{}' has no windows", name));
+    };
+    // tmux refuses a name that's taken
+    let output = run(NewSession::new()
+        .detached()
+        .session_name(name)
+        .window_name(&first.name)
+        .start_directory(&first.path))?;
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to create session '{}': {}", name, error));
+    }
+
+    // Directories that are gone by now fall back to tmux's default
+    for window in rest {
+        let output = run(NewWindow::new()
+            .detached()
+            .target_window(format!("{name}:"))
+            .window_name(&window.name)
+            .start_directory(&window.path))?;
+        if !output.status().success() {
+            let stderr = output.stderr();
+            let error = String::from_utf8_lossy(&stderr);
+            return Err(anyhow!(
+                "Failed to create window '{}': {}",
+                window.name,
+                error
+            ));
+        }
+    }
+
+    if let Some(dir) = &archived.start_dir {
+        set_start_dir(name, Some(dir))?;
+    }
+    Ok(())
+}
+
+// `resize-pane -Z` toggles, so look first
+fn zoom_window(window_id: &str) -> Result<()> {
+    use tmux_interface::{DisplayMessage, ResizePane};
+
+    let output = run(DisplayMessage::new()
+        .print()
+        .target_pane(window_id)
+        .message("#{window_zoomed_flag}"))?;
+    if !output.status().success() {
+        return Err(anyhow!("Failed to look up window {}", window_id));
+    }
+    if String::from_utf8_lossy(&output.stdout()).trim() == "1" {
+        return Ok(());
+    }
+
+    let output = run(ResizePane::new().zoom().target_pane(window_id))?;
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to zoom window: {}", error.trim()));
+    }
+    Ok(())
+}
+
+// The popup stays open until Enter so the output can be read
+fn run_in_popup(path: &str, command: &str, env: &[(String, String)]) -> Result<Option<i32>> {
+    use tmux_interface::{DisplayPopup, Size};
+
+    let wrapped = format!(
+        "sh -c {}; status=$?; printf '\\n[exit %s] ' \"$status\"; read _; exit \"$status\"",
+        shell_quote(command)
+    );
+    let mut popup = DisplayPopup::new()
+        .close_on_exit()
+        .start_directory(path)
+        .width(Size::Percentage(80))
+        .height(Size::Percentage(80));
+    for (name, value) in env {
+        popup = popup.environment(name.as_str(), value.as_str());
+    }
+    let output = run(popup.shell_command(wrapped))?;
+
+    // tmux itself failing says why on stderr, the command's own output went to the popup
+    let status = output.status();
+    let stderr = output.stderr();
+    if !status.success() && !stderr.is_empty() {
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to open popup: {}", error.trim()));
+    }
+    Ok(status.code())
+}
+
+fn open_popup(path: &str, command: &str, width: u16, height: u16) -> Result<()> {
+    use tmux_interface::{DisplayPopup, Size};
+
+    let output = run(DisplayPopup::new()
+        .close_on_exit() // -E flag
+        .start_directory(path)
+        .width(Size::Percentage(width.into()))
+        .height(Size::Percentage(height.into()))
+        .shell_command(command))?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to open popup: {}", error));
+    }
+
+    Ok(())
+}
+
+fn switch_to_session_and_window(
     session_name: &str,
     window_name: &str,
-    path: &std::path::Path,
+    path: &Path,
+    on_create: Option<&str>,
+    layout: &PaneLayout,
 ) -> Result<()> {
-    use tmux_interface::{HasSession, NewSession, NewWindow, SwitchClient, Tmux};
+    use tmux_interface::{HasSession, NewSession, NewWindow, SwitchClient};
+
+    let shell = project_shell(layout.environment, path);
 
     // Try to find the window in the session
     match find_window_in_session(session_name, window_name)? {
@@ -348,39 +1773,47 @@ pub fn switch_to_session_and_window(
         }
         None => {
             // Window doesn't exist, check if session exists
-            let session_exists = Tmux::with_command(HasSession::new().target_session(session_name))
-                .output()
+            let session_exists = run(HasSession::new().target_session(session_name))
                 .map(|output| output.status().success())
                 .unwrap_or(false);
 
             if session_exists {
                 // Create new window in existing session
                 let path_str = path.to_str().unwrap_or("");
-                let output = Tmux::with_command(
-                    NewWindow::new()
-                        .target_window(session_name)
-                        .window_name(window_name)
-                        .start_directory(path_str)
-                        .select(), // -S flag
-                )
-                .output()?;
+                let mut new_window = NewWindow::new()
+                    .target_window(session_name)
+                    .window_name(window_name)
+                    .start_directory(path_str)
+                    .select() // -S flag
+                    .print()
+                    .format("#{window_id}");
+                if let Some(shell) = &shell {
+                    new_window = new_window.shell_command(shell.as_str());
+                }
+                let output = run(new_window)?;
 
                 if !output.status().success() {
                     let stderr = output.stderr();
                     let error = String::from_utf8_lossy(&stderr);
                     return Err(anyhow::anyhow!("Failed to create window: {}", error));
                 }
+
+                let window_id = String::from_utf8_lossy(&output.stdout()).trim().to_string();
+                set_up_window(&window_id, path_str, on_create, layout, shell.as_deref())?;
             } else {
                 // Create new session with window
                 let path_str = path.to_str().unwrap_or("");
-                let output = Tmux::with_command(
-                    NewSession::new()
-                        .detached() // -d flag
-                        .session_name(session_name)
-                        .window_name(window_name)
-                        .start_directory(path_str),
-                )
-                .output()?;
+                let mut new_session = NewSession::new()
+                    .detached() // -d flag
+                    .session_name(session_name)
+                    .window_name(window_name)
+                    .start_directory(path_str)
+                    .print()
+                    .format("#{window_id}");
+                if let Some(shell) = &shell {
+                    new_session = new_session.shell_command(shell.as_str());
+                }
+                let output = run(new_session)?;
 
                 if !output.status().success() {
                     let stderr = output.stderr();
@@ -388,11 +1821,12 @@ pub fn switch_to_session_and_window(
                     return Err(anyhow::anyhow!("Failed to create session: {}", error));
                 }
 
+                let window_id = String::from_utf8_lossy(&output.stdout()).trim().to_string();
+                set_up_window(&window_id, path_str, on_create, layout, shell.as_deref())?;
+
                 // Switch to the session:window
                 let session_window_name = format!("{session_name}:{window_name}");
-                let switch_output =
-                    Tmux::with_command(SwitchClient::new().target_session(&session_window_name))
-                        .output()?;
+                let switch_output = run(SwitchClient::new().target_session(&session_window_name))?;
 
                 if !switch_output.status().success() {
                     let stderr = switch_output.stderr();
@@ -408,3 +1842,492 @@ pub fn switch_to_session_and_window(
         }
     }
 }
+
+// Run `on_create` in a fresh window, then split off the layout's panes, in
+// `shell` like the first one. The first pane keeps the focus
+fn set_up_window(
+    window_id: &str,
+    path: &str,
+    on_create: Option<&str>,
+    layout: &PaneLayout,
+    shell: Option<&str>,
+) -> Result<()> {
+    use tmux_interface::{SelectLayout, SplitWindow};
+
+    if let Some(command) = on_create {
+        send_command(window_id, command)?;
+    }
+
+    for command in &layout.panes {
+        let mut split = SplitWindow::new()
+            .detached() // -d flag, stay in the first pane
+            .target_pane(window_id)
+            .start_directory(path)
+            .print()
+            .format("#{pane_id}");
+        if let Some(shell) = shell {
+            split = split.shell_command(shell);
+        }
+        let output = run(split)?;
+
+        if !output.status().success() {
+            let stderr = output.stderr();
+            let error = String::from_utf8_lossy(&stderr);
+            return Err(anyhow!("Failed to split window: {}", error));
+        }
+
+        if !command.is_empty() {
+            let pane_id = String::from_utf8_lossy(&output.stdout()).trim().to_string();
+            send_command(&pane_id, command)?;
+        }
+    }
+
+    if let Some(name) = &layout.layout {
+        let output = run(SelectLayout::new()
+            .target_pane(window_id)
+            .layout_name(name.as_str()))?;
+
+        if !output.status().success() {
+            let stderr = output.stderr();
+            let error = String::from_utf8_lossy(&stderr);
+            return Err(anyhow!("Failed to select layout {}: {}", name, error));
+        }
+    }
+
+    Ok(())
+}
+
+// Type a command into the target window's active pane and press Enter
+fn send_command(target: &str, command: &str) -> Result<()> {
+    use tmux_interface::SendKeys;
+
+    let output = run(SendKeys::new()
+        .disable_lookup() // -l flag, send the command literally
+        .target_pane(target)
+        .key(command))?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to send command to {}: {}", target, error));
+    }
+
+    let output = run(SendKeys::new().target_pane(target).key("Enter"))?;
+
+    if !output.status().success() {
+        let stderr = output.stderr();
+        let error = String::from_utf8_lossy(&stderr);
+        return Err(anyhow!("Failed to send command to {}: {}", target, error));
+    }
+
+    Ok(())
+}
+
+pub fn refresh_hook_command() -> String {
+    format!("wait-for -S {REFRESH_CHANNEL}")
+}
+
+pub fn refresh_hook_name(hook: &str) -> String {
+    format!("{hook}[{REFRESH_HOOK_INDEX}]")
+}
+
+pub fn install_refresh_hooks() -> Result<()> {
+    use tmux_interface::SetHook;
+
+    let command = refresh_hook_command();
+    for hook in REFRESH_HOOKS {
+        let output = run(SetHook::new()
+            .global()
+            .hook_name(refresh_hook_name(hook))
+            .command(&command))?;
+
+        if !output.status().success() {
+            let stderr = output.stderr();
+            let error = String::from_utf8_lossy(&stderr);
+            return Err(anyhow!("Failed to set hook '{}': {}", hook, error));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn refresh_hooks_installed() -> bool {
+    use tmux_interface::ShowHooks;
+
+    run(ShowHooks::new().global())
+        .map(|output| {
+            let stdout_data = output.stdout();
+            String::from_utf8_lossy(&stdout_data).contains(REFRESH_CHANNEL)
+        })
+        .unwrap_or(false)
+}
+
+// Blocks until one of the refresh hooks fires (or someone signals the channel)
+pub fn wait_for_refresh() -> Result<()> {
+    use tmux_interface::WaitFor;
+
+    let output = run(WaitFor::new().channel(REFRESH_CHANNEL))?;
+
+    if !output.status().success() {
+        return Err(anyhow!("Failed to wait for channel: {}", REFRESH_CHANNEL));
+    }
+
+    Ok(())
+}
+
+pub fn signal_refresh() -> Result<()> {
+    use tmux_interface::WaitFor;
+
+    let output = run(WaitFor::new().woken().channel(REFRESH_CHANNEL))?;
+
+    if !output.status().success() {
+        return Err(anyhow!("Failed to signal channel: {}", REFRESH_CHANNEL));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_socket_name() {
+        assert_eq!(
+            parse_socket_name("/tmp/tmux-1000/default,4242,0").as_deref(),
+            Some("default")
+        );
+        assert_eq!(
+            parse_socket_name("/tmp/tmux-1000/work,77,3").as_deref(),
+            Some("work")
+        );
+        assert_eq!(parse_socket_name(""), None);
+    }
+
+    #[test]
+    fn test_parse_server_info() {
+        let mut fields = vec!["3.3a", "/tmp/tmux-0/default", "42"];
+        fields.extend(FORMAT_FIELDS.iter().map(|_| "1"));
+        fields[3 + 8] = ""; // window_linked
+        let info = parse_server_info(&(fields.join("\x1f") + "\n"), 2).unwrap();
+        assert_eq!(info.version, "3.3a");
+        assert_eq!(info.socket_path, "/tmp/tmux-0/default");
+        assert_eq!(info.clients, 2);
+        assert_eq!(info.missing_formats, ["window_linked"]);
+
+        assert_eq!(parse_server_info("3.3a\x1f/tmp/sock\x1f42", 0), None);
+    }
+
+    #[test]
+    fn test_parse_option_values() {
+        assert_eq!(
+            parse_option_values("1|0|on|off\n", 4),
+            Some(vec![true, false, true, false])
+        );
+        assert_eq!(parse_option_values("1|0\n", 4), None);
+    }
+
+    #[test]
+    fn test_parse_environment() {
+        let vars = parse_environment("DISPLAY=:1\n-SSH_AUTH_SOCK\nOPTS=a=b\n\n");
+        assert_eq!(
+            vars,
+            [
+                EnvVar {
+                    name: "DISPLAY".to_string(),
+                    value: Some(":1".to_string())
+                },
+                EnvVar {
+                    name: "SSH_AUTH_SOCK".to_string(),
+                    value: None
+                },
+                EnvVar {
+                    name: "OPTS".to_string(),
+                    value: Some("a=b".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attach_command() {
+        assert_eq!(
+            attach_command_for("scratch", Some("/tmp/tmux-1000/default")),
+            "env -u TMUX tmux -S '/tmp/tmux-1000/default' new-session -A -s 'scratch'"
+        );
+        assert_eq!(
+            attach_command_for("it's", None),
+            "env -u TMUX tmux new-session -A -s 'it'\\''s'"
+        );
+    }
+
+    #[test]
+    fn test_project_shell() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        let quoted = shell_quote(&path.to_string_lossy());
+        assert_eq!(project_shell(ProjectEnv::Auto, path), None);
+
+        std::fs::create_dir_all(path.join("venv/bin")).unwrap();
+        std::fs::write(path.join("venv/bin/activate"), "").unwrap();
+        let venv = project_shell(ProjectEnv::Venv, path).unwrap();
+        assert!(venv.starts_with(&format!(
+            "VIRTUAL_ENV={} ",
+            shell_quote(&path.join("venv").to_string_lossy())
+        )));
+        assert_eq!(project_shell(ProjectEnv::Auto, path), Some(venv));
+        assert_eq!(project_shell(ProjectEnv::Direnv, path), None);
+        assert_eq!(project_shell(ProjectEnv::None, path), None);
+
+        // An .envrc wins over the virtualenv in auto mode
+        std::fs::write(path.join(".envrc"), "layout python").unwrap();
+        let direnv = project_shell(ProjectEnv::Auto, path).unwrap();
+        assert!(direnv.contains(&format!("exec direnv exec {quoted} ")));
+        assert_eq!(project_shell(ProjectEnv::Direnv, path), Some(direnv));
+    }
+
+    // `list-windows -a` output written with '|' in place of the separator
+    fn parse_fixture(output: &str) -> Vec<TmuxSession> {
+        parse_all_windows(&output.replace('|', "\x1f"), &[])
+    }
+
+    #[test]
+    fn test_parse_all_windows() {
+        let sessions = parse_fixture(concat!(
+            "main|1|1700000900|/src|1|1|release prep|@1|0|1|1|0|1700000000|0|0|0|nvim|/src/app|fix #42|red|editor\n",
+            "main|1|1700000900|/src|1|1|release prep|@2|3|0|2|1|1700000300|1|1|1|tail|/var/log|||logs\n",
+            "work|0|1700000600||0|0||@5|1|1|1|0|1700000600|0|0|0|zsh|/src/api|||api\n",
+            "work|0|1700000600||0|0||@2|2|0|2|1|1700000300|1|1|1|tail|/var/log|||logs\n",
+        ));
+
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].name, "main");
+        assert_eq!(sessions[0].windows.len(), 2);
+        assert_eq!(sessions[0].windows[1].id, "@2");
+        assert_eq!(sessions[0].windows[1].index, 3);
+        assert_eq!(sessions[0].windows[1].panes, 2);
+        assert!(sessions[0].windows[1].zoomed);
+        assert_eq!(sessions[0].windows[1].activity, 1_700_000_300);
+        assert_eq!(sessions[0].windows[1].command, "tail");
+        assert_eq!(sessions[0].windows[1].path, "/var/log");
+        assert!(sessions[0].windows[1].dead);
+        assert!(sessions[0].windows[1].piped);
+        assert_eq!(sessions[0].note.as_deref(), Some("release prep"));
+        assert_eq!(sessions[1].note, None);
+        assert_eq!(sessions[0].windows[0].note.as_deref(), Some("fix #42"));
+        assert_eq!(sessions[0].windows[1].note, None);
+        assert_eq!(sessions[0].windows[0].color.as_deref(), Some("red"));
+        assert_eq!(sessions[0].windows[1].color, None);
+        assert!(!sessions[0].windows[0].piped);
+        assert!(sessions[0].windows[1].linked);
+        assert!(!sessions[0].windows[0].linked);
+        assert_eq!(sessions[1].windows[1].id, "@2");
+        assert_eq!(sessions[0].windows[1].name, "logs");
+        assert!(!sessions[0].windows[1].active);
+        assert_eq!(sessions[1].name, "work");
+        assert_eq!(sessions[1].windows[0].session_name, "work");
+        assert_eq!(sessions[0].attached, 1);
+        assert_eq!(sessions[0].start_dir.as_deref(), Some("/src"));
+        assert_eq!(sessions[1].start_dir, None);
+        assert_eq!((sessions[0].base_index, sessions[0].renumber), (1, true));
+        assert_eq!((sessions[1].base_index, sessions[1].renumber), (0, false));
+        assert!(sessions[1].is_idle(1_700_000_600 + 3600, 3600));
+        assert!(!sessions[0].is_idle(1_700_000_900 + 3600, 3600));
+        assert!(sessions[1].windows[0].active);
+    }
+
+    #[test]
+    fn test_window_format_user_options() {
+        assert_eq!(
+            format_user_options("{name} {@owner} {@owner} {@team-id} {@bad name} {@}"),
+            ["@owner", "@team-id"]
+        );
+
+        let options = ["@owner".to_string(), "@team-id".to_string()];
+        assert!(
+            window_format(&options).ends_with("\x1f#{@owner}\x1f#{@team-id}\x1f#{window_name}")
+        );
+        let line = "@1\x1f0\x1f1\x1f1\x1f0\x1f0\x1f0\x1f0\x1f0\x1fzsh\x1f/src\x1f\x1f\x1falice\x1f\x1feditor";
+        let window = parse_window("main", line, &options).unwrap();
+        assert_eq!(window.name, "editor");
+        assert_eq!(window.user_options["@owner"], "alice");
+        // Too few fields for the options asked for
+        let more = [options[0].clone(), options[1].clone(), "@team".to_string()];
+        assert!(parse_window("main", line, &more).is_none());
+
+        assert_eq!(
+            window.format("{index}:{name} {@owner}{@team-id} {command} {path} {unknown} {"),
+            "0:editor alice zsh /src {unknown} {"
+        );
+    }
+
+    #[test]
+    fn test_parse_separators_in_names() {
+        // ':' and '|' are ordinary characters in names and paths
+        let output = concat!(
+            "a|b\x1f0\x1f0\x1f/x:y\x1f0\x1f0\x1f\x1f@1\x1f0\x1f1\x1f1\x1f0\x1f0\x1f0\x1f0\x1f0",
+            "\x1fzsh\x1f/tmp/a|b\x1f\x1f\x1fhost:8080 | logs\n",
+        );
+        let sessions = parse_all_windows(output, &[]);
+        assert_eq!(sessions[0].name, "a|b");
+        assert_eq!(sessions[0].start_dir.as_deref(), Some("/x:y"));
+        assert_eq!(sessions[0].windows[0].path, "/tmp/a|b");
+        assert_eq!(sessions[0].windows[0].name, "host:8080 | logs");
+
+        // The name takes the rest of the line, separators and all
+        let window = parse_window(
+            "main",
+            "@1\x1f0\x1f1\x1f1\x1f0\x1f0\x1f0\x1f0\x1f0\x1fzsh\x1f/\x1f\x1f\x1fa\x1fb",
+            &[],
+        );
+        assert_eq!(window.unwrap().name, "a\x1fb");
+    }
+
+    #[test]
+    fn test_move_window_between_sessions() {
+        let output = concat!(
+            "main|1|0||0|0||@1|0|1|1|0|0|0|0|0|zsh|/|||editor\n",
+            "main|1|0||0|0||@2|1|0|1|0|0|0|0|0|zsh|/|||shell\n",
+            "work|0|0||0|0||@3|1|1|1|0|0|0|0|0|zsh|/|||api\n",
+            "work|0|0||0|0||@4|2|0|1|0|0|0|0|0|zsh|/|||db\n",
+            "solo|0|0||0|0||@5|0|1|1|0|0|0|0|0|zsh|/|||notes\n",
+        );
+        let mut sessions = parse_fixture(output);
+
+        // Before @4 takes its index 2, db shifts up
+        assert!(move_window(&mut sessions, "@1", "@4", true));
+        let work: Vec<(&str, u32, &str)> = sessions[1]
+            .windows
+            .iter()
+            .map(|w| (w.id.as_str(), w.index, w.session_name.as_str()))
+            .collect();
+        assert_eq!(
+            work,
+            [("@3", 1, "work"), ("@1", 2, "work"), ("@4", 3, "work")]
+        );
+
+        // Moving the last window away ends its session
+        assert!(move_window(&mut sessions, "@5", "@2", false));
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].windows[1].id, "@5");
+        assert_eq!(sessions[0].windows[1].index, 2);
+
+        assert!(!move_window(&mut sessions, "@9", "@2", false));
+        assert!(!move_window(&mut sessions, "@2", "@2", false));
+    }
+
+    #[test]
+    fn test_move_to_index() {
+        let output = concat!(
+            "main|1|0||0|0||@1|0|1|1|0|0|0|0|0|zsh|/|||editor\n",
+            "main|1|0||0|0||@2|1|0|1|0|0|0|0|0|zsh|/|||shell\n",
+            "main|1|0||0|0||@3|2|0|1|0|0|0|0|0|zsh|/|||logs\n",
+        );
+        let mut session = parse_fixture(output).remove(0);
+        let order = |session: &TmuxSession| -> Vec<(String, u32)> {
+            session
+                .windows
+                .iter()
+                .map(|w| (w.id.clone(), w.index))
+                .collect()
+        };
+
+        assert!(session.move_to_index("@1", 9));
+        assert_eq!(
+            order(&session),
+            [("@2".into(), 1), ("@3".into(), 2), ("@1".into(), 9)]
+        );
+        assert!(!session.move_to_index("@1", 2));
+        assert!(!session.move_to_index("@9", 5));
+
+        // Renumbering closes the gaps again
+        session.renumber = true;
+        assert!(session.move_to_index("@3", 0));
+        assert_eq!(
+            order(&session),
+            [("@3".into(), 0), ("@2".into(), 1), ("@1".into(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_link_and_unlink_window() {
+        let output = concat!(
+            "main|1|0||0|0||@1|0|1|1|0|0|0|0|0|zsh|/|||editor\n",
+            "work|0|0||0|0||@3|4|1|1|0|0|0|0|0|zsh|/|||api\n",
+        );
+        let mut sessions = parse_fixture(output);
+
+        assert!(link_window(&mut sessions, "@1", "work"));
+        assert!(!link_window(&mut sessions, "@1", "work"));
+        assert!(!link_window(&mut sessions, "@1", "gone"));
+        // Index 0 is free in front of api
+        let linked = &sessions[1].windows[0];
+        assert_eq!((linked.id.as_str(), linked.index), ("@1", 0));
+        assert_eq!(linked.session_name, "work");
+        assert!(linked.linked && sessions[0].windows[0].linked);
+
+        // Unlinking the only copy left in main ends that session
+        assert!(unlink_window(&mut sessions, "main", "@1"));
+        assert_eq!(sessions.len(), 1);
+        assert!(!sessions[0].windows[0].linked);
+        assert!(!unlink_window(&mut sessions, "work", "@1"));
+    }
+
+    #[test]
+    fn test_break_window() {
+        let output = concat!(
+            "main|1|0||0|0||@1|0|1|1|0|0|0|0|0|zsh|/|||editor\n",
+            "main|1|0||0|0||@2|1|0|1|0|0|0|0|0|zsh|/|||shell\n",
+            "work|0|0||0|0||@3|4|1|1|0|0|0|0|0|zsh|/|||api\n",
+        );
+        let mut sessions = parse_fixture(output);
+
+        assert!(!break_window(&mut sessions, "main", "@2", "work"));
+        assert!(!break_window(&mut sessions, "main", "@3", "api"));
+        assert!(break_window(&mut sessions, "main", "@2", "shell"));
+        assert_eq!(sessions[0].windows.len(), 1);
+        let shell = &sessions[2].windows[0];
+        assert_eq!((shell.id.as_str(), shell.index), ("@2", 0));
+        assert_eq!(shell.session_name, "shell");
+        assert!(shell.active);
+
+        // The last window of a session takes the session with it
+        assert!(break_window(&mut sessions, "work", "@3", "api"));
+        let names: Vec<&str> = sessions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["main", "shell", "api"]);
+    }
+
+    #[test]
+    fn test_base_index_and_renumber() {
+        // main numbers from 1 and renumbers, work numbers from 1 and keeps gaps
+        let output = concat!(
+            "main|1|0||1|1||@1|1|1|1|0|0|0|0|0|zsh|/|||editor\n",
+            "main|1|0||1|1||@2|2|0|1|0|0|0|0|0|zsh|/|||shell\n",
+            "main|1|0||1|1||@3|3|0|1|0|0|0|0|0|zsh|/|||logs\n",
+            "work|0|0||1|0||@4|1|1|1|0|0|0|0|0|zsh|/|||api\n",
+            "work|0|0||1|0||@5|3|0|1|0|0|0|0|0|zsh|/|||db\n",
+        );
+        let mut sessions = parse_fixture(output);
+        let indices = |session: &TmuxSession| -> Vec<(String, u32)> {
+            session
+                .windows
+                .iter()
+                .map(|w| (w.id.clone(), w.index))
+                .collect()
+        };
+
+        assert!(move_window(&mut sessions, "@1", "@4", false));
+        assert_eq!(indices(&sessions[0]), [("@2".into(), 1), ("@3".into(), 2)]);
+        assert_eq!(
+            indices(&sessions[1]),
+            [("@4".into(), 1), ("@1".into(), 2), ("@5".into(), 3)]
+        );
+
+        assert!(link_window(&mut sessions, "@2", "work"));
+        assert_eq!(sessions[1].windows[3].index, 4);
+
+        assert!(break_window(&mut sessions, "main", "@3", "logs"));
+        assert_eq!(indices(&sessions[0]), [("@2".into(), 1)]);
+        assert_eq!(indices(&sessions[2]), [("@3".into(), 1)]);
+    }
+}
@@ -0,0 +1,642 @@
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use super::{
+    ArchivedSession, EnvVar, OptionToggle, PaneLayout, ServerInfo, TmuxBackend, TmuxSession,
+    TmuxWindow, break_window, link_window, move_window, unlink_window,
+};
+
+/// In-memory tmux server for tests
+#[derive(Default)]
+pub struct FakeTmux {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    sessions: Vec<TmuxSession>,
+    current_session: Option<String>,
+    clipboard: Option<String>,
+    popups: Vec<(String, String)>,       // (path, command)
+    pipes: HashMap<String, String>,      // Window ID to the command its pane pipes into
+    scrollback: HashMap<String, String>, // Window ID to what capture_pane returns
+    environments: HashMap<String, BTreeMap<String, Option<String>>>,
+    options: HashMap<(String, &'static str), bool>, // (window ID or session, option)
+    next_window_id: usize,
+    version: Option<String>, // Reported by server_info, 3.4 if unset
+    fail: bool,
+}
+
+impl State {
+    // Takes the first free index from base-index on, like tmux
+    fn new_window(&mut self, session_name: &str, name: &str) -> TmuxWindow {
+        let id = format!("@{}", self.next_window_id);
+        self.next_window_id += 1;
+        let index = self
+            .sessions
+            .iter()
+            .find(|s| s.name == session_name)
+            .map(|s| s.next_free_index())
+            .unwrap_or(0);
+        TmuxWindow {
+            id,
+            index,
+            name: name.to_string(),
+            session_name: session_name.to_string(),
+            active: false,
+            panes: 1,
+            zoomed: false,
+            activity: 0,
+            command: "zsh".to_string(),
+            path: "/tmp".to_string(),
+            dead: false,
+            linked: false,
+            piped: false,
+            note: None,
+            color: None,
+            user_options: Default::default(),
+        }
+    }
+
+    fn session_mut(&mut self, session_name: &str) -> Result<&mut TmuxSession> {
+        self.sessions
+            .iter_mut()
+            .find(|s| s.name == session_name)
+            .ok_or_else(|| anyhow!("can't find session: {session_name}"))
+    }
+
+    fn window_mut(&mut self, session_name: &str, window_id: &str) -> Result<&mut TmuxWindow> {
+        self.session_mut(session_name)?
+            .windows
+            .iter_mut()
+            .find(|w| w.id == window_id)
+            .ok_or_else(|| anyhow!("can't find window: {window_id}"))
+    }
+
+    fn select_window(&mut self, session_name: &str, window_id: &str) -> Result<()> {
+        self.window_mut(session_name, window_id)?;
+        for window in &mut self.session_mut(session_name)?.windows {
+            window.active = window.id == window_id;
+        }
+        self.current_session = Some(session_name.to_string());
+        Ok(())
+    }
+}
+
+impl FakeTmux {
+    /// A server with the given sessions and window names. The first window of
+    /// each session is active and the first session is attached
+    pub fn with_sessions(layout: &[(&str, &[&str])]) -> Self {
+        let mut state = State::default();
+        for (session_name, window_names) in layout {
+            state.sessions.push(TmuxSession {
+                name: session_name.to_string(),
+                windows: Vec::new(),
+                attached: 0,
+                activity: 0,
+                start_dir: None,
+                base_index: 0,
+                renumber: false,
+                note: None,
+            });
+            for name in *window_names {
+                let window = state.new_window(session_name, name);
+                state.sessions.last_mut().unwrap().windows.push(window);
+            }
+            if let Some(first) = state.sessions.last_mut().unwrap().windows.first_mut() {
+                first.active = true;
+            }
+        }
+        state.current_session = layout.first().map(|(name, _)| name.to_string());
+
+        Self {
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Snapshot of the server state
+    pub fn sessions(&self) -> Vec<TmuxSession> {
+        self.state.lock().unwrap().sessions.clone()
+    }
+
+    pub fn current_session(&self) -> Option<String> {
+        self.state.lock().unwrap().current_session.clone()
+    }
+
+    pub fn clipboard(&self) -> Option<String> {
+        self.state.lock().unwrap().clipboard.clone()
+    }
+
+    pub fn popups(&self) -> Vec<(String, String)> {
+        self.state.lock().unwrap().popups.clone()
+    }
+
+    pub fn set_scrollback(&self, window_id: &str, text: &str) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .scrollback
+            .insert(window_id.to_string(), text.to_string());
+    }
+
+    pub fn pipe(&self, window_id: &str) -> Option<String> {
+        self.state.lock().unwrap().pipes.get(window_id).cloned()
+    }
+
+    pub fn set_session_activity(&self, session_name: &str, activity: i64) {
+        let mut state = self.state.lock().unwrap();
+        if let Ok(session) = state.session_mut(session_name) {
+            session.activity = activity;
+        }
+    }
+
+    /// Set a window's `@option` the way `set -w` does. Unlike tmux, the
+    /// fake returns it whether or not `window_format` asks for it
+    pub fn set_user_option(&self, window_id: &str, name: &str, value: &str) {
+        let mut state = self.state.lock().unwrap();
+        for session in &mut state.sessions {
+            for window in session.windows.iter_mut().filter(|w| w.id == window_id) {
+                window
+                    .user_options
+                    .insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+
+    pub fn set_version(&self, version: &str) {
+        self.state.lock().unwrap().version = Some(version.to_string());
+    }
+
+    /// Number the session's windows from `base_index` on, like `set
+    /// base-index` followed by `move-window -r`
+    pub fn set_base_index(&self, session_name: &str, base_index: u32, renumber: bool) {
+        let mut state = self.state.lock().unwrap();
+        if let Ok(session) = state.session_mut(session_name) {
+            session.base_index = base_index;
+            session.renumber = true;
+            session.renumber_windows();
+            session.renumber = renumber;
+        }
+    }
+
+    /// Make every following command fail, like a server that went away
+    pub fn fail_commands(&self, fail: bool) {
+        self.state.lock().unwrap().fail = fail;
+    }
+
+    fn state(&self) -> Result<MutexGuard<'_, State>> {
+        let state = self.state.lock().unwrap();
+        if state.fail {
+            return Err(anyhow!("no server running"));
+        }
+        Ok(state)
+    }
+}
+
+impl TmuxBackend for FakeTmux {
+    fn list_sessions(&self) -> Result<Vec<TmuxSession>> {
+        let state = self.state()?;
+        let mut sessions = state.sessions.clone();
+        // The only client is attached to the current session
+        for session in &mut sessions {
+            session.attached = u32::from(state.current_session.as_deref() == Some(&session.name));
+        }
+        Ok(sessions)
+    }
+
+    fn current_session_name(&self) -> Result<Option<String>> {
+        Ok(self.state()?.current_session.clone())
+    }
+
+    fn window_path(&self, session_name: &str, window_id: &str) -> Result<String> {
+        Ok(self
+            .state()?
+            .window_mut(session_name, window_id)?
+            .path
+            .clone())
+    }
+
+    fn switch_to_window(&self, session_name: &str, window_id: &str) -> Result<()> {
+        self.state()?.select_window(session_name, window_id)
+    }
+
+    fn switch_to_session(&self, session_name: &str) -> Result<()> {
+        let mut state = self.state()?;
+        state.session_mut(session_name)?;
+        state.current_session = Some(session_name.to_string());
+        Ok(())
+    }
+
+    fn switch_to_session_and_window(
+        &self,
+        session_name: &str,
+        window_name: &str,
+        path: &Path,
+        on_create: Option<&str>,
+        layout: &PaneLayout,
+    ) -> Result<()> {
+        let mut state = self.state()?;
+        if state.session_mut(session_name).is_err() {
+            state.sessions.push(TmuxSession {
+                name: session_name.to_string(),
+                windows: Vec::new(),
+                attached: 0,
+                activity: 0,
+                start_dir: None,
+                base_index: 0,
+                renumber: false,
+                note: None,
+            });
+        }
+
+        let existing = state
+            .session_mut(session_name)?
+            .windows
+            .iter()
+            .find(|w| w.name == window_name)
+            .map(|w| w.id.clone());
+        let window_id = match existing {
+            Some(id) => id,
+            None => {
+                let mut window = state.new_window(session_name, window_name);
+                window.path = path.to_string_lossy().to_string();
+                if let Some(command) = on_create {
+                    window.command = command.to_string();
+                }
+                window.panes += layout.panes.len() as u32;
+                let id = window.id.clone();
+                state.session_mut(session_name)?.windows.push(window);
+                id
+            }
+        };
+
+        state.select_window(session_name, &window_id)
+    }
+
+    fn rename_window(&self, session_name: &str, window_id: &str, new_name: &str) -> Result<()> {
+        let mut state = self.state()?;
+        state.window_mut(session_name, window_id)?;
+        for window in state.sessions.iter_mut().flat_map(|s| s.windows.iter_mut()) {
+            if window.id == window_id {
+                window.name = new_name.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    fn rename_session(&self, old_name: &str, new_name: &str) -> Result<()> {
+        let mut state = self.state()?;
+        let session = state.session_mut(old_name)?;
+        session.name = new_name.to_string();
+        for window in &mut session.windows {
+            window.session_name = new_name.to_string();
+        }
+        if state.current_session.as_deref() == Some(old_name) {
+            state.current_session = Some(new_name.to_string());
+        }
+        Ok(())
+    }
+
+    fn delete_window(&self, session_name: &str, window_id: &str) -> Result<()> {
+        let mut state = self.state()?;
+        state.window_mut(session_name, window_id)?;
+        let session = state.session_mut(session_name)?;
+        session.windows.retain(|w| w.id != window_id);
+        session.renumber_windows();
+
+        // Like tmux, a session goes away with its last window
+        if session.windows.is_empty() {
+            drop(state);
+            return self.kill_session(session_name);
+        }
+        Ok(())
+    }
+
+    fn kill_session(&self, session_name: &str) -> Result<()> {
+        let mut state = self.state()?;
+        state.session_mut(session_name)?;
+        state.sessions.retain(|s| s.name != session_name);
+        if state.current_session.as_deref() == Some(session_name) {
+            state.current_session = state.sessions.first().map(|s| s.name.clone());
+        }
+        Ok(())
+    }
+
+    fn create_new_window(&self, session_name: &str, path: Option<&str>) -> Result<TmuxWindow> {
+        let mut state = self.state()?;
+        state.session_mut(session_name)?;
+        let mut window = state.new_window(session_name, "bash");
+        if let Some(path) = path {
+            window.path = path.to_string();
+        }
+        state
+            .session_mut(session_name)?
+            .windows
+            .push(window.clone());
+        Ok(window)
+    }
+
+    fn set_start_dir(&self, session_name: &str, path: Option<&str>) -> Result<()> {
+        self.state()?.session_mut(session_name)?.start_dir = path.map(str::to_string);
+        Ok(())
+    }
+
+    fn set_note(
+        &self,
+        session_name: &str,
+        window_id: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<()> {
+        let mut state = self.state()?;
+        let note = note.map(str::to_string);
+        match window_id {
+            Some(window_id) => state.window_mut(session_name, window_id)?.note = note,
+            None => state.session_mut(session_name)?.note = note,
+        }
+        Ok(())
+    }
+
+    fn set_window_color(&self, window_id: &str, color: Option<&str>) -> Result<()> {
+        let mut state = self.state()?;
+        // Window options are shared by every session the window is linked to
+        let windows: Vec<&mut TmuxWindow> = state
+            .sessions
+            .iter_mut()
+            .flat_map(|s| s.windows.iter_mut())
+            .filter(|w| w.id == window_id)
+            .collect();
+        if windows.is_empty() {
+            return Err(anyhow!("can't find window: {window_id}"));
+        }
+        for window in windows {
+            window.color = color.map(str::to_string);
+        }
+        Ok(())
+    }
+
+    fn swap_windows(&self, session_name: &str, window1_id: &str, window2_id: &str) -> Result<()> {
+        let mut state = self.state()?;
+        let session = state.session_mut(session_name)?;
+        if session.swap_windows(window1_id, window2_id) {
+            Ok(())
+        } else {
+            Err(anyhow!("can't find window to swap"))
+        }
+    }
+
+    fn move_window(&self, window_id: &str, target_window_id: &str, before: bool) -> Result<()> {
+        let mut state = self.state()?;
+        if !move_window(&mut state.sessions, window_id, target_window_id, before) {
+            return Err(anyhow!("can't find window to move"));
+        }
+        if let Some(current) = state.current_session.clone()
+            && state.session_mut(&current).is_err()
+        {
+            state.current_session = state.sessions.first().map(|s| s.name.clone());
+        }
+        Ok(())
+    }
+
+    fn move_window_to_index(&self, session_name: &str, window_id: &str, index: u32) -> Result<()> {
+        let mut state = self.state()?;
+        let session = state.session_mut(session_name)?;
+        if session.move_to_index(window_id, index) {
+            Ok(())
+        } else {
+            Err(anyhow!("index {index} in use"))
+        }
+    }
+
+    fn link_window(&self, window_id: &str, target_session: &str) -> Result<()> {
+        let mut state = self.state()?;
+        state.session_mut(target_session)?;
+        if link_window(&mut state.sessions, window_id, target_session) {
+            Ok(())
+        } else {
+            Err(anyhow!("can't link window: {window_id}"))
+        }
+    }
+
+    fn unlink_window(&self, session_name: &str, window_id: &str) -> Result<()> {
+        let mut state = self.state()?;
+        state.window_mut(session_name, window_id)?;
+        if !unlink_window(&mut state.sessions, session_name, window_id) {
+            return Err(anyhow!("window only linked to one session"));
+        }
+        if state.current_session.as_deref() == Some(session_name)
+            && state.session_mut(session_name).is_err()
+        {
+            state.current_session = state.sessions.first().map(|s| s.name.clone());
+        }
+        Ok(())
+    }
+
+    fn read_options(
+        &self,
+        session_name: &str,
+        window_id: &str,
+        toggles: &[OptionToggle],
+    ) -> Result<Vec<bool>> {
+        let mut state = self.state()?;
+        state.window_mut(session_name, window_id)?;
+        Ok(toggles
+            .iter()
+            .map(|toggle| {
+                let owner = if toggle.window {
+                    window_id
+                } else {
+                    session_name
+                };
+                state.options.get(&(owner.to_string(), toggle.name)) == Some(&true)
+            })
+            .collect())
+    }
+
+    fn set_option(
+        &self,
+        session_name: &str,
+        window_id: &str,
+        toggle: &OptionToggle,
+        on: bool,
+    ) -> Result<()> {
+        let mut state = self.state()?;
+        state.window_mut(session_name, window_id)?;
+        let owner = if toggle.window {
+            window_id
+        } else {
+            session_name
+        };
+        state.options.insert((owner.to_string(), toggle.name), on);
+        Ok(())
+    }
+
+    fn server_info(&self) -> Result<ServerInfo> {
+        let state = self.state()?;
+        Ok(ServerInfo {
+            version: state.version.clone().unwrap_or_else(|| "3.4".to_string()),
+            socket_path: "/tmp/tmux-1000/default".to_string(),
+            pid: "4242".to_string(),
+            clients: 1,
+            missing_formats: Vec::new(),
+        })
+    }
+
+    fn session_environment(&self, session_name: &str) -> Result<Vec<EnvVar>> {
+        let mut state = self.state()?;
+        state.session_mut(session_name)?;
+        let vars = state
+            .environments
+            .get(session_name)
+            .cloned()
+            .unwrap_or_default();
+        Ok(vars
+            .into_iter()
+            .map(|(name, value)| EnvVar { name, value })
+            .collect())
+    }
+
+    fn set_environment(&self, session_name: &str, name: &str, value: Option<&str>) -> Result<()> {
+        let mut state = self.state()?;
+        state.session_mut(session_name)?;
+        let vars = state
+            .environments
+            .entry(session_name.to_string())
+            .or_default();
+        match value {
+            Some(value) => vars.insert(name.to_string(), Some(value.to_string())),
+            None => vars.remove(name),
+        };
+        Ok(())
+    }
+
+    fn break_window(&self, window_id: &str, session_name: &str, _path: &str) -> Result<()> {
+        let mut state = self.state()?;
+        let source = state
+            .sessions
+            .iter()
+            .find(|s| s.windows.iter().any(|w| w.id == window_id))
+            .map(|s| s.name.clone())
+            .ok_or_else(|| anyhow!("can't find window: {window_id}"))?;
+        if !break_window(&mut state.sessions, &source, window_id, session_name) {
+            return Err(anyhow!("duplicate session: {session_name}"));
+        }
+        if state.current_session.as_deref() == Some(&source) && state.session_mut(&source).is_err()
+        {
+            state.current_session = Some(session_name.to_string());
+        }
+        Ok(())
+    }
+
+    fn pipe_pane(&self, window_id: &str, command: Option<&str>) -> Result<()> {
+        let mut state = self.state()?;
+        let windows: Vec<&mut TmuxWindow> = state
+            .sessions
+            .iter_mut()
+            .flat_map(|s| s.windows.iter_mut())
+            .filter(|w| w.id == window_id)
+            .collect();
+        if windows.is_empty() {
+            return Err(anyhow!("can't find window: {window_id}"));
+        }
+        for window in windows {
+            window.piped = command.is_some();
+        }
+        match command {
+            Some(command) => state
+                .pipes
+                .insert(window_id.to_string(), command.to_string()),
+            None => state.pipes.remove(window_id),
+        };
+        Ok(())
+    }
+
+    fn capture_pane(&self, window_id: &str) -> Result<String> {
+        let state = self.state()?;
+        if !state
+            .sessions
+            .iter()
+            .flat_map(|s| &s.windows)
+            .any(|w| w.id == window_id)
+        {
+            return Err(anyhow!("can't find window: {window_id}"));
+        }
+        Ok(state.scrollback.get(window_id).cloned().unwrap_or_default())
+    }
+
+    fn copy_to_clipboard(&self, text: &str) -> Result<()> {
+        self.state()?.clipboard = Some(text.to_string());
+        Ok(())
+    }
+
+    fn open_window(&self, session_name: &str, name: &str, path: &str, command: &str) -> Result<()> {
+        let mut state = self.state()?;
+        state.session_mut(session_name)?;
+        let mut window = state.new_window(session_name, name);
+        window.path = path.to_string();
+        window.command = command.to_string();
+        let window_id = window.id.clone();
+        state.session_mut(session_name)?.windows.push(window);
+        state.select_window(session_name, &window_id)
+    }
+
+    fn open_popup(&self, path: &str, command: &str, _width: u16, _height: u16) -> Result<()> {
+        self.state()?
+            .popups
+            .push((path.to_string(), command.to_string()));
+        Ok(())
+    }
+
+    fn zoom_window(&self, window_id: &str) -> Result<()> {
+        let mut state = self.state()?;
+        let mut found = false;
+        for window in state.sessions.iter_mut().flat_map(|s| s.windows.iter_mut()) {
+            if window.id == window_id {
+                window.zoomed = true;
+                found = true;
+            }
+        }
+        match found {
+            true => Ok(()),
+            false => Err(anyhow!("can't find window: {window_id}")),
+        }
+    }
+
+    fn run_in_popup(
+        &self,
+        path: &str,
+        command: &str,
+        _env: &[(String, String)],
+    ) -> Result<Option<i32>> {
+        self.state()?
+            .popups
+            .push((path.to_string(), command.to_string()));
+        Ok(Some(0))
+    }
+
+    fn restore_session(&self, archived: &ArchivedSession) -> Result<()> {
+        let mut state = self.state()?;
+        if state.session_mut(&archived.name).is_ok() {
+            return Err(anyhow!("duplicate session: {}", archived.name));
+        }
+        state.sessions.push(TmuxSession {
+            name: archived.name.clone(),
+            windows: Vec::new(),
+            attached: 0,
+            activity: 0,
+            start_dir: archived.start_dir.clone(),
+            base_index: 0,
+            renumber: false,
+            note: None,
+        });
+        for archived_window in &archived.windows {
+            let mut window = state.new_window(&archived.name, &archived_window.name);
+            window.path = archived_window.path.clone();
+            state.session_mut(&archived.name)?.windows.push(window);
+        }
+        if let Some(first) = state.session_mut(&archived.name)?.windows.first_mut() {
+            first.active = true;
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,17 @@
 use ratatui::Frame;
 
-use crate::app::{App, LineType, Mode};
+use crate::app::{App, DeleteItems, LineType, Mode};
 use crate::widgets::confirm_prompt::ConfirmPrompt;
 
 /// Render a confirmation prompt for the current app state
 pub fn render_confirmation_prompt(app: &App, frame: &mut Frame) {
+    if app.mode == Mode::DeleteConfirm
+        && let Some(items) = app.pending_delete()
+    {
+        render_delete_items(app, items, frame);
+        return;
+    }
+
     match app.mode {
         Mode::Rename => {
             // Determine if we're renaming a window or session
@@ -18,8 +25,130 @@ pub fn render_confirmation_prompt(app: &App, frame: &mut Frame) {
             };
 
             let prompt = ConfirmPrompt::new(title, message)
+                .anchor(app.config.popup.anchor)
+                .max_width_percent(app.config.popup.max_width)
+                .input(&app.popup_input)
+                .cursor(app.popup_input.cursor_column())
+                .border_style(app.config.colors.popup_border.to_ratatui_style())
+                .text_style(app.config.colors.popup_text.to_ratatui_style())
+                .input_style(app.config.colors.popup_input.to_ratatui_style());
+
+            prompt.render(frame, frame.area());
+        }
+        Mode::Link => {
+            let window_name = app
+                .tree_lines
+                .get(app.selected_index)
+                .and_then(|line| line.window.as_ref())
+                .map_or("window", |w| &w.name);
+            let message = format!("Link window '{window_name}' into session:");
+
+            let prompt = ConfirmPrompt::new("Link Window", &message)
+                .anchor(app.config.popup.anchor)
+                .max_width_percent(app.config.popup.max_width)
                 .input(&app.popup_input)
-                .show_cursor(true)
+                .cursor(app.popup_input.cursor_column())
+                .border_style(app.config.colors.popup_border.to_ratatui_style())
+                .text_style(app.config.colors.popup_text.to_ratatui_style())
+                .input_style(app.config.colors.popup_input.to_ratatui_style());
+
+            prompt.render(frame, frame.area());
+        }
+        Mode::StartDir => {
+            let session_name = app
+                .tree_lines
+                .get(app.selected_index)
+                .and_then(|line| line.session_name.as_deref())
+                .unwrap_or("session");
+            let message = format!("New windows in '{session_name}' start in (empty to reset):");
+
+            let prompt = ConfirmPrompt::new("Start Directory", &message)
+                .anchor(app.config.popup.anchor)
+                .max_width_percent(app.config.popup.max_width)
+                .input(&app.popup_input)
+                .cursor(app.popup_input.cursor_column())
+                .border_style(app.config.colors.popup_border.to_ratatui_style())
+                .text_style(app.config.colors.popup_text.to_ratatui_style())
+                .input_style(app.config.colors.popup_input.to_ratatui_style());
+
+            prompt.render(frame, frame.area());
+        }
+        Mode::Note => {
+            let target = match app.tree_lines.get(app.selected_index) {
+                Some(line) => match (&line.window, &line.session_name) {
+                    (Some(window), _) => format!("window '{}'", window.name),
+                    (None, Some(session_name)) => format!("session '{session_name}'"),
+                    (None, None) => String::from("selection"),
+                },
+                None => String::from("selection"),
+            };
+            let message = format!("Note on {target} (empty to remove):");
+
+            let prompt = ConfirmPrompt::new("Note", &message)
+                .anchor(app.config.popup.anchor)
+                .max_width_percent(app.config.popup.max_width)
+                .input(&app.popup_input)
+                .cursor(app.popup_input.cursor_column())
+                .border_style(app.config.colors.popup_border.to_ratatui_style())
+                .text_style(app.config.colors.popup_text.to_ratatui_style())
+                .input_style(app.config.colors.popup_input.to_ratatui_style());
+
+            prompt.render(frame, frame.area());
+        }
+        Mode::Shell => {
+            let target = match app.tree_lines.get(app.selected_index) {
+                Some(line) => match (&line.window, &line.session_name) {
+                    (Some(window), _) => format!("window '{}'", window.name),
+                    (None, Some(session_name)) => format!("session '{session_name}'"),
+                    (None, None) => String::from("selection"),
+                },
+                None => String::from("selection"),
+            };
+            let message =
+                format!("Run for {target} ($PORTAL_SESSION, $PORTAL_WINDOW, $PORTAL_PATH):");
+
+            let prompt = ConfirmPrompt::new("Shell Command", &message)
+                .anchor(app.config.popup.anchor)
+                .max_width_percent(app.config.popup.max_width)
+                .input(&app.popup_input)
+                .cursor(app.popup_input.cursor_column())
+                .border_style(app.config.colors.popup_border.to_ratatui_style())
+                .text_style(app.config.colors.popup_text.to_ratatui_style())
+                .input_style(app.config.colors.popup_input.to_ratatui_style());
+
+            prompt.render(frame, frame.area());
+        }
+        Mode::MoveIndex => {
+            let Some(window) = app
+                .tree_lines
+                .get(app.selected_index)
+                .and_then(|line| line.window.as_ref())
+            else {
+                return;
+            };
+            let message = format!("Move window '{}' to index:", window.name);
+
+            let prompt = ConfirmPrompt::new("Move Window", &message)
+                .anchor(app.config.popup.anchor)
+                .max_width_percent(app.config.popup.max_width)
+                .input(&app.popup_input)
+                .cursor(app.popup_input.cursor_column())
+                .border_style(app.config.colors.popup_border.to_ratatui_style())
+                .text_style(app.config.colors.popup_text.to_ratatui_style())
+                .input_style(app.config.colors.popup_input.to_ratatui_style());
+
+            prompt.render(frame, frame.area());
+        }
+        Mode::Environment => {
+            let Some(input) = &app.env_input else {
+                return;
+            };
+
+            let prompt = ConfirmPrompt::new("Set Variable", "Enter NAME=value:")
+                .anchor(app.config.popup.anchor)
+                .max_width_percent(app.config.popup.max_width)
+                .input(input)
+                .cursor(input.cursor_column())
                 .border_style(app.config.colors.popup_border.to_ratatui_style())
                 .text_style(app.config.colors.popup_text.to_ratatui_style())
                 .input_style(app.config.colors.popup_input.to_ratatui_style());
@@ -34,9 +163,7 @@ pub fn render_confirmation_prompt(app: &App, frame: &mut Frame) {
                         let window_name = line.window.as_ref().map_or("window", |w| &w.name);
                         (
                             "Delete Window",
-                            format!(
-                                "Are you sure you want to delete window '{window_name}'? (y/n)"
-                            ),
+                            format!("Are you sure you want to delete window '{window_name}'?"),
                         )
                     }
                     LineType::Session => {
@@ -47,7 +174,8 @@ pub fn render_confirmation_prompt(app: &App, frame: &mut Frame) {
                         (
                             "Delete Session",
                             format!(
-                                "Are you sure you want to delete session '{session_name_str}'? (y/n)"
+                                "Are you sure you want to delete session '{session_name_str}'?{}",
+                                attached_note(app, &session_name_str)
                             ),
                         )
                     }
@@ -55,13 +183,59 @@ pub fn render_confirmation_prompt(app: &App, frame: &mut Frame) {
             } else {
                 (
                     "Delete",
-                    String::from("Are you sure you want to delete this item? (y/n)"),
+                    String::from("Are you sure you want to delete this item?"),
                 )
             };
 
             let prompt = ConfirmPrompt::new(title, &message)
+                .anchor(app.config.popup.anchor)
+                .max_width_percent(app.config.popup.max_width)
+                .border_style(app.config.colors.popup_border.to_ratatui_style())
+                .text_style(app.config.colors.popup_text.to_ratatui_style())
+                .buttons(app.confirm_button);
+
+            prompt.render(frame, frame.area());
+        }
+        Mode::MoveConfirm => {
+            let Some(pending) = &app.pending_move else {
+                return;
+            };
+            let message = format!(
+                "Move window '{}' to session '{}'?",
+                pending.window.name, pending.target_session
+            );
+
+            let prompt = ConfirmPrompt::new("Move Window", &message)
+                .anchor(app.config.popup.anchor)
+                .max_width_percent(app.config.popup.max_width)
                 .border_style(app.config.colors.popup_border.to_ratatui_style())
-                .text_style(app.config.colors.popup_text.to_ratatui_style());
+                .text_style(app.config.colors.popup_text.to_ratatui_style())
+                .buttons(app.confirm_button);
+
+            prompt.render(frame, frame.area());
+        }
+        Mode::CollisionConfirm => {
+            let Some((existing, suffixed)) = &app.pending_collision else {
+                return;
+            };
+            let open_in = app
+                .collision_window()
+                .map(|w| format!(" in {}", w.path))
+                .unwrap_or_default();
+            let message = format!(
+                "Window '{}:{}' is already open{open_in}.\n\nYes opens {} in a new session '{}', No switches to the existing window.",
+                existing.session_name,
+                existing.window_name,
+                existing.full_path.display(),
+                suffixed.session_name,
+            );
+
+            let prompt = ConfirmPrompt::new("Session Exists", &message)
+                .anchor(app.config.popup.anchor)
+                .max_width_percent(app.config.popup.max_width)
+                .border_style(app.config.colors.popup_border.to_ratatui_style())
+                .text_style(app.config.colors.popup_text.to_ratatui_style())
+                .buttons(app.confirm_button);
 
             prompt.render(frame, frame.area());
         }
@@ -70,3 +244,44 @@ pub fn render_confirmation_prompt(app: &App, frame: &mut Frame) {
         }
     }
 }
+
+// Several items at once: the question, then everything it kills
+fn render_delete_items(app: &App, items: &DeleteItems, frame: &mut Frame) {
+    let mut lines = vec![app.popup_input.to_string(), String::new()];
+    lines.extend(
+        items
+            .sessions
+            .iter()
+            .map(|name| format!("- session '{name}'{}", attached_note(app, name))),
+    );
+    lines.extend(
+        items
+            .windows
+            .iter()
+            .map(|w| format!("- window '{}:{}'", w.session_name, w.name)),
+    );
+
+    let message = lines.join("\n");
+    let prompt = ConfirmPrompt::new("Delete", &message)
+        .anchor(app.config.popup.anchor)
+        .max_width_percent(app.config.popup.max_width)
+        .border_style(app.config.colors.popup_border.to_ratatui_style())
+        .text_style(app.config.colors.popup_text.to_ratatui_style())
+        .buttons(app.confirm_button);
+
+    prompt.render(frame, frame.area());
+}
+
+// Killing a session detaches whoever is looking at it
+fn attached_note(app: &App, session_name: &str) -> String {
+    let attached = app
+        .sessions
+        .iter()
+        .find(|s| s.name == session_name)
+        .map_or(0, |s| s.attached);
+    match attached {
+        0 => String::new(),
+        1 => " (1 client attached)".to_string(),
+        n => format!(" ({n} clients attached)"),
+    }
+}
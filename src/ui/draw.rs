@@ -1,40 +1,92 @@
+use chrono::Local;
 use ratatui::{
     Frame,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::Style,
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
 };
 
+use crate::doctor::Status;
+use crate::filter::Filter;
+use crate::tmux::OPTION_TOGGLES;
 use crate::ui::confirm::render_confirmation_prompt;
 use crate::ui::help::render_help_panel;
+use crate::ui::layouts::render_layout_overlay;
+use crate::ui::pins::render_pin_overlay;
+use crate::ui::text::{format_age, format_count, format_duration, truncate_end, truncate_start};
+use crate::widgets::toast::{Toast, ToastStack};
 use crate::{
-    app::{App, LineType, Mode},
-    config::ColorConfig,
+    app::{App, LineType, Mode, Staleness, TreeLine},
+    config::{ColorConfig, LineNumberMode, ModeLabelsConfig, StatusSegmentKind},
+    input::TextInput,
+    messages::Level,
+    search::{SearchResult, new_session_result},
 };
+use std::path::Path;
+use std::time::Instant;
+use unicode_width::UnicodeWidthStr;
 
 // Helper function to get the display text for a mode
-fn get_mode_text(mode: &Mode) -> &'static str {
+fn get_mode_text<'a>(mode: &Mode, labels: &'a ModeLabelsConfig) -> &'a str {
     match mode {
-        Mode::Window => "-- WINDOW --",
-        Mode::Rename => "-- RENAME --",
-        Mode::Search => "-- SEARCH --",
-        Mode::QuickSearch => "-- QUICK --",
-        Mode::Session => "-- SESSION --",
-        Mode::DeleteConfirm => "-- CONFIRM --",
+        Mode::Window | Mode::Watch => &labels.window,
+        Mode::Rename => &labels.rename,
+        Mode::Link => &labels.link,
+        Mode::StartDir => &labels.start_dir,
+        Mode::Note => &labels.note,
+        Mode::Shell => &labels.shell,
+        Mode::MoveIndex => &labels.move_index,
+        Mode::Search => &labels.search,
+        Mode::QuickSearch => &labels.quick_search,
+        Mode::Session => &labels.session,
+        Mode::DeleteConfirm | Mode::MoveConfirm | Mode::CollisionConfirm => &labels.confirm,
+        Mode::Messages => &labels.messages,
+        Mode::Environment => &labels.environment,
+        Mode::Options => &labels.options,
+        Mode::Info => &labels.info,
+        Mode::Archive => &labels.archive,
+        Mode::Stats => &labels.stats,
     }
 }
 fn get_mode_style(mode: &Mode, colors: &ColorConfig) -> Style {
     match mode {
-        Mode::Window => colors.border_list.to_ratatui_style(),
-        Mode::Rename => colors.border_prompt.to_ratatui_style(),
+        Mode::Window | Mode::Watch => colors.border_list.to_ratatui_style(),
+        Mode::Rename | Mode::Link | Mode::StartDir | Mode::Note | Mode::Shell | Mode::MoveIndex => {
+            colors.border_prompt.to_ratatui_style()
+        }
         Mode::Search | Mode::QuickSearch => colors.border_search.to_ratatui_style(),
-        Mode::Session => colors.border_list.to_ratatui_style(), // TODO: Add session mode color
-        Mode::DeleteConfirm => colors.border_prompt.to_ratatui_style(), // Use insert color for delete confirmation
+        Mode::Session => colors.border_session.to_ratatui_style(),
+        // Use insert color for confirmations
+        Mode::DeleteConfirm | Mode::MoveConfirm | Mode::CollisionConfirm => {
+            colors.border_prompt.to_ratatui_style()
+        }
+        Mode::Messages
+        | Mode::Environment
+        | Mode::Options
+        | Mode::Info
+        | Mode::Archive
+        | Mode::Stats => colors.border_list.to_ratatui_style(),
     }
 }
 
+// Below this the layout has no room for the tree, prompts and status bar
+const MIN_WIDTH: u16 = 30;
+const MIN_HEIGHT: u16 = 5;
+
+// Longer notes are cut so the names next to them stay readable
+const NOTE_WIDTH: usize = 40;
+
+const SEARCH_PROMPT: &str = "Search: ";
+
 pub fn draw(f: &mut Frame, app: &mut App) {
+    let area = f.area();
+    // The watch view is meant for narrow side panes
+    if app.mode != Mode::Watch && (area.width < MIN_WIDTH || area.height < MIN_HEIGHT) {
+        draw_too_small(f, area);
+        return;
+    }
+
     match app.mode {
         Mode::Search => {
             draw_search_interface(f, app);
@@ -45,6 +97,29 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         Mode::Session => {
             draw_session_mode_interface(f, app);
         }
+        Mode::Messages => {
+            draw_messages_interface(f, app);
+        }
+        Mode::Environment => {
+            draw_environment_interface(f, app);
+        }
+        Mode::Options => {
+            draw_options_interface(f, app);
+        }
+        Mode::Info => {
+            draw_info_interface(f, app);
+        }
+        Mode::Archive => {
+            draw_archive_interface(f, app);
+        }
+        Mode::Stats => {
+            draw_stats_interface(f, app);
+        }
+        Mode::Watch => {
+            let area = f.area();
+            app.update_scroll_offset(area.height as usize * tree_columns(app, area));
+            draw_main_content(f, app, area);
+        }
         _ => {
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
@@ -52,7 +127,8 @@ pub fn draw(f: &mut Frame, app: &mut App) {
                 .split(f.area());
 
             // Update scroll offset based on current viewport size
-            app.update_scroll_offset(chunks[0].height as usize);
+            let columns = tree_columns(app, chunks[0]);
+            app.update_scroll_offset(chunks[0].height as usize * columns);
 
             draw_main_content(f, app, chunks[0]);
             draw_status_bar(f, app, chunks[1]);
@@ -60,55 +136,630 @@ pub fn draw(f: &mut Frame, app: &mut App) {
             if app.show_popup {
                 render_confirmation_prompt(app, f);
             }
+            if app.show_pin_overlay {
+                render_pin_overlay(app, f);
+            }
+            if let Some(result) = &app.pending_layout {
+                render_layout_overlay(app, result, f);
+            }
         }
     }
+
+    draw_toasts(f, app);
+}
+
+// Placeholder until the terminal is resized to something usable
+fn draw_too_small(f: &mut Frame, area: Rect) {
+    let message = format!(
+        "Terminal too small\nneed {MIN_WIDTH}x{MIN_HEIGHT}, have {}x{}",
+        area.width, area.height
+    );
+
+    // Center vertically when there is room for both lines
+    let top = area.height.saturating_sub(2) / 2;
+    let text_area = Rect {
+        y: area.y + top,
+        height: area.height - top,
+        ..area
+    };
+
+    f.render_widget(
+        Paragraph::new(message)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
+        text_area,
+    );
 }
 
-fn draw_main_content(f: &mut Frame, app: &App, area: Rect) {
+// Recent messages stacked above the status bar
+fn draw_toasts(f: &mut Frame, app: &App) {
+    let toasts: Vec<Toast> = app
+        .messages
+        .toasts(Instant::now())
+        .into_iter()
+        .map(|message| Toast {
+            text: &message.text,
+            style: message_style(message.level, &app.config.colors),
+        })
+        .collect();
+    if toasts.is_empty() {
+        return;
+    }
+
+    let area = f.area();
+    let area = Rect {
+        height: area.height.saturating_sub(1), // Keep the status bar visible
+        ..area
+    };
+    // Leave most of the tree readable on narrow terminals
+    let max_width = (area.width * 2 / 3).max(20);
+    f.render_widget(ToastStack::new(toasts).max_width(max_width), area);
+}
+
+fn message_style(level: Level, colors: &ColorConfig) -> Style {
+    match level {
+        Level::Info => colors.status_text.to_ratatui_style(),
+        Level::Error => colors.error_text.to_ratatui_style(),
+    }
+}
+
+fn draw_messages_interface(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.area());
+
+    let entries = app.messages.entries();
+    let items: Vec<ListItem> = if entries.is_empty() {
+        vec![ListItem::new(" No messages").style(app.config.colors.status_text.to_ratatui_style())]
+    } else {
+        entries
+            .iter()
+            .map(|message| {
+                let age = message.created.elapsed().as_secs();
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!(" {age:>5}s ago  "),
+                        app.config.colors.status_text.to_ratatui_style(),
+                    ),
+                    Span::styled(
+                        message.text.clone(),
+                        message_style(message.level, &app.config.colors),
+                    ),
+                ]))
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Messages")
+                .border_style(app.config.colors.border_list.to_ratatui_style()),
+        )
+        .highlight_style(app.config.colors.window_selected.to_ratatui_style());
+
+    let mut list_state = ListState::default();
+    if !entries.is_empty() {
+        list_state.select(Some(app.messages_scroll));
+    }
+
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+    draw_status_bar(f, app, chunks[1]);
+}
+
+fn draw_environment_interface(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.area());
+
+    let dim = app.config.colors.status_text.to_ratatui_style();
+    let items: Vec<ListItem> = if app.env_vars.is_empty() {
+        vec![ListItem::new(" No session variables").style(dim)]
+    } else {
+        app.env_vars
+            .iter()
+            .map(|var| match &var.value {
+                Some(value) => ListItem::new(Line::from(vec![
+                    Span::raw(format!(" {}", var.name)),
+                    Span::styled(format!("={value}"), dim),
+                ])),
+                // Removed from the environment of new processes
+                None => ListItem::new(format!(" -{}", var.name)).style(dim),
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Environment of '{}'", app.env_session))
+                .border_style(app.config.colors.border_list.to_ratatui_style()),
+        )
+        .highlight_style(app.config.colors.window_selected.to_ratatui_style());
+
+    let mut list_state = ListState::default();
+    if !app.env_vars.is_empty() {
+        list_state.select(Some(app.env_selected));
+    }
+
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+    draw_status_bar(f, app, chunks[1]);
+
+    if app.env_input.is_some() {
+        render_confirmation_prompt(app, f);
+    }
+}
+
+fn draw_options_interface(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.area());
+
+    let dim = app.config.colors.status_text.to_ratatui_style();
+    let items: Vec<ListItem> = OPTION_TOGGLES
+        .iter()
+        .enumerate()
+        .map(|(i, toggle)| {
+            // Unknown until tmux answered
+            let check = match app.option_values.get(i) {
+                Some(true) => "[x]",
+                Some(false) => "[ ]",
+                None => "[?]",
+            };
+            let scope = if toggle.window { "window" } else { "session" };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!(" {check} {:<20}", toggle.name)),
+                Span::styled(scope, dim),
+            ]))
+        })
+        .collect();
+
+    let title = match &app.options_window {
+        Some(window) => format!("Options of '{}:{}'", window.session_name, window.name),
+        None => "Options".to_string(),
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(app.config.colors.border_list.to_ratatui_style()),
+        )
+        .highlight_style(app.config.colors.window_selected.to_ratatui_style());
+
+    let mut list_state = ListState::default();
+    list_state.select(Some(app.options_selected));
+
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+    draw_status_bar(f, app, chunks[1]);
+}
+
+fn draw_archive_interface(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.area());
+
+    let colors = &app.config.colors;
+    let dim = colors.activity.to_ratatui_style();
+    let now = Local::now().timestamp();
+    let items: Vec<ListItem> = if app.archive.is_empty() {
+        vec![ListItem::new(" Nothing archived, `a` in session mode archives a session").style(dim)]
+    } else {
+        app.archive
+            .iter()
+            .map(|archived| {
+                let windows: Vec<&str> = archived.windows.iter().map(|w| w.name.as_str()).collect();
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!(" {:>3} ", format_age(now - archived.archived_at)),
+                        dim,
+                    ),
+                    Span::styled(archived.name.clone(), colors.session.to_ratatui_style()),
+                    Span::raw(format!("  {}", windows.join(", "))),
+                ]))
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Archived sessions")
+                .border_style(colors.border_list.to_ratatui_style()),
+        )
+        .highlight_style(colors.window_selected.to_ratatui_style());
+
+    let mut list_state = ListState::default();
+    if !app.archive.is_empty() {
+        list_state.select(Some(app.archive_selected));
+    }
+
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+    draw_status_bar(f, app, chunks[1]);
+}
+
+fn draw_info_interface(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.area());
+
+    let colors = &app.config.colors;
+    let width = app
+        .info_checks
+        .iter()
+        .map(|c| c.label.len())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<ListItem> = if app.info_checks.is_empty() {
+        vec![ListItem::new(" Asking tmux...").style(colors.status_text.to_ratatui_style())]
+    } else {
+        app.info_checks
+            .iter()
+            .map(|check| {
+                let (status, style) = match check.status {
+                    Status::Ok => ("ok  ", colors.status_text.to_ratatui_style()),
+                    Status::Warning => ("warn", colors.zoomed.to_ratatui_style()),
+                    Status::Error => ("FAIL", colors.error_text.to_ratatui_style()),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!(" {status}  "), style),
+                    Span::raw(format!("{:<width$}  {}", check.label, check.value)),
+                ]))
+            })
+            .collect()
+    };
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Server")
+            .border_style(colors.border_list.to_ratatui_style()),
+    );
+
+    f.render_widget(list, chunks[0]);
+    draw_status_bar(f, app, chunks[1]);
+}
+
+// Sessions listed under "Most used"
+const STATS_TOP: usize = 10;
+
+// Most used sessions, windows never switched to and how the totals moved,
+// to help decide what to prune
+fn draw_stats_interface(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.area());
+
+    let colors = &app.config.colors;
+    let header =
+        |text: String| ListItem::new(Line::styled(text, colors.session.to_ratatui_style()));
+    let row = |text: String| ListItem::new(Line::styled(text, colors.help_text.to_ratatui_style()));
+    let now = Local::now().timestamp();
     let mut items = Vec::new();
 
-    // Calculate the visible range based on scroll offset
+    let top = app.usage.top_sessions();
+    items.push(header(" Most used sessions".to_string()));
+    if top.is_empty() {
+        items.push(row(
+            "   Nothing recorded yet, switch to some windows first".to_string()
+        ));
+    }
+    let width = top.iter().map(|s| s.name.width()).max().unwrap_or(0);
+    for session in top.iter().take(STATS_TOP) {
+        let name = &session.name;
+        items.push(row(format!(
+            "   {:>5}  {name:<width$}  last {} ago",
+            session.activations,
+            format_age(now - session.last_used)
+        )));
+    }
+
+    let never = app.usage.never_visited(&app.sessions);
+    items.push(ListItem::new(""));
+    items.push(header(format!(" Never visited ({})", never.len())));
+    for window in never {
+        items.push(row(format!("   {}:{}", window.session_name, window.name)));
+    }
+
+    if let (Some(first), Some(last)) = (app.usage.days.first(), app.usage.days.last()) {
+        let days = last.day - first.day + 1;
+        items.push(ListItem::new(""));
+        let title = match days {
+            1 => " Trend today".to_string(),
+            _ => format!(" Trend over {days} days"),
+        };
+        items.push(header(title));
+        let sessions: Vec<usize> = app.usage.days.iter().map(|d| d.sessions).collect();
+        let windows: Vec<usize> = app.usage.days.iter().map(|d| d.windows).collect();
+        for (label, counts) in [("Sessions", sessions), ("Windows ", windows)] {
+            items.push(row(format!(
+                "   {label}  {:>4} → {:<4}  {}",
+                counts[0],
+                counts[counts.len() - 1],
+                sparkline(&counts)
+            )));
+        }
+    }
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Usage")
+            .border_style(colors.border_list.to_ratatui_style()),
+    );
+
+    f.render_widget(list, chunks[0]);
+    draw_status_bar(f, app, chunks[1]);
+}
+
+// One bar per value, scaled to the largest
+fn sparkline(values: &[usize]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    values
+        .iter()
+        .map(|&value| BARS[(value * (BARS.len() - 1)).div_ceil(max)])
+        .collect()
+}
+
+// Sessions left alone for long get the idle or stale style on top, the one
+// this client is in its own
+fn session_style(tree_line: &TreeLine, app: &App, now: i64) -> Style {
+    let colors = &app.config.colors;
+    let style = colors.session.to_ratatui_style();
+    let Some(session_name) = &tree_line.session_name else {
+        return style;
+    };
+    if app.current_session.as_ref() == Some(session_name) {
+        return style.patch(colors.session_current.to_ratatui_style());
+    }
+    match app.session_staleness(session_name, now) {
+        Staleness::Fresh => style,
+        Staleness::Idle => style.patch(colors.session_idle.to_ratatui_style()),
+        Staleness::Stale => style.patch(colors.session_stale.to_ratatui_style()),
+    }
+}
+
+// Pane count, zoom and link flags after a window's name, each in its own style
+fn window_indicators(tree_line: &TreeLine, colors: &ColorConfig) -> Vec<Span<'static>> {
+    let Some(window) = &tree_line.window else {
+        return Vec::new();
+    };
+
+    let mut spans = Vec::new();
+    if window.panes > 1 {
+        spans.push(Span::styled(
+            format!(" ({} panes)", window.panes),
+            colors.pane_count.to_ratatui_style(),
+        ));
+    }
+    if window.zoomed {
+        spans.push(Span::styled(" (zoomed)", colors.zoomed.to_ratatui_style()));
+    }
+    if window.linked {
+        spans.push(Span::styled(" (linked)", colors.linked.to_ratatui_style()));
+    }
+    if window.piped {
+        spans.push(Span::styled(
+            " (logging)",
+            colors.logging.to_ratatui_style(),
+        ));
+    }
+    spans
+}
+
+// Active or inactive window style, with the color given with `c` on top
+fn window_style(tree_line: &TreeLine, app: &App) -> Style {
+    let window = tree_line.window.as_ref();
+    let colors = &app.config.colors;
+    let style = if window.is_some_and(|w| w.active) {
+        let style = colors.window_active.to_ratatui_style();
+        match tree_line.session_name == app.current_session {
+            true => style.patch(colors.window_current.to_ratatui_style()),
+            false => style,
+        }
+    } else {
+        colors.window_inactive.to_ratatui_style()
+    };
+    match window.and_then(|w| w.color.as_deref()) {
+        Some(color) => style.patch(app.config.window_color_style(color)),
+        None => style,
+    }
+}
+
+// "● 2" on sessions with clients attached, someone may be using them
+fn attached_span(tree_line: &TreeLine, app: &App) -> Option<Span<'static>> {
+    if tree_line.line_type != LineType::Session {
+        return None;
+    }
+    let session_name = tree_line.session_name.as_ref()?;
+    let session = app.sessions.iter().find(|s| &s.name == session_name)?;
+    (session.attached > 0).then(|| {
+        Span::styled(
+            format!(" ● {}", session.attached),
+            app.config.colors.attached.to_ratatui_style(),
+        )
+    })
+}
+
+// "# note" of the window, or of the session on session lines
+fn note_span(tree_line: &TreeLine, app: &App) -> Option<Span<'static>> {
+    let note = match &tree_line.window {
+        Some(window) => window.note.as_deref(),
+        None => tree_line
+            .session_name
+            .as_ref()
+            .and_then(|name| app.sessions.iter().find(|s| &s.name == name))
+            .and_then(|session| session.note.as_deref()),
+    }?;
+    Some(Span::styled(
+        format!("  # {}", truncate_end(note, NOTE_WIDTH)),
+        app.config.colors.note.to_ratatui_style(),
+    ))
+}
+
+// Right-aligned time since the window's last activity, if enabled
+fn activity_age(tree_line: &TreeLine, app: &App, now: i64) -> Option<Span<'static>> {
+    let window = tree_line
+        .window
+        .as_ref()
+        .filter(|_| app.config.show_activity)?;
+    Some(Span::styled(
+        format!(" {:>3}", format_age(now - window.activity)),
+        app.config.colors.activity.to_ratatui_style(),
+    ))
+}
+
+// Pad the line out to `width` and put the age in the last columns
+fn push_age_column(spans: &mut Vec<Span<'static>>, age: Option<Span<'static>>, width: usize) {
+    if let Some(age) = age {
+        let padding = width.saturating_sub(spans_width(spans) + age.content.width());
+        spans.push(Span::raw(" ".repeat(padding)));
+        spans.push(age);
+    }
+}
+
+fn spans_width(spans: &[Span]) -> usize {
+    spans.iter().map(|span| span.content.width()).sum()
+}
+
+// Number of columns the tree flows into, more than one only on wide terminals
+fn tree_columns(app: &App, area: Rect) -> usize {
+    match app.config.two_column_min_width {
+        Some(min_width) if area.width >= min_width => 2,
+        _ => 1,
+    }
+}
+
+// Areas of the tree's columns and the first line shown in each. Lines
+// continue at the top of the next column
+fn tree_column_areas(app: &App, area: Rect) -> Vec<(Rect, usize)> {
+    let columns = tree_columns(app, area);
+    let column_areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+        .spacing(1)
+        .split(area);
+
     let viewport_height = area.height as usize;
-    let start_idx = app.scroll_offset;
-    let end_idx = (start_idx + viewport_height).min(app.tree_lines.len());
+    column_areas
+        .iter()
+        .enumerate()
+        .map(|(column, column_area)| (*column_area, app.scroll_offset + column * viewport_height))
+        .collect()
+}
+
+fn draw_main_content(f: &mut Frame, app: &mut App, area: Rect) {
+    // Remembered for mapping mouse clicks to lines
+    app.tree_columns = tree_column_areas(app, area);
+
+    // Calculate the visible range based on scroll offset
+    for &(column_area, start_idx) in &app.tree_columns {
+        let end_idx = (start_idx + column_area.height as usize).min(app.tree_lines.len());
+        if start_idx < end_idx {
+            draw_tree_column(f, app, start_idx, end_idx, column_area);
+        }
+    }
+}
+
+fn draw_tree_column(f: &mut Frame, app: &App, start_idx: usize, end_idx: usize, area: Rect) {
+    let mut items = Vec::new();
+    let now = Local::now().timestamp();
 
     for i in start_idx..end_idx {
         let tree_line = &app.tree_lines[i];
-        let is_selected = i == app.selected_index;
+        // The watch view has no cursor, only the active window's style
+        let is_selected = i == app.selected_index && app.mode != Mode::Watch;
+
+        let marked = match &tree_line.window {
+            Some(window) => app.marked_windows.contains(&window.id),
+            None => tree_line
+                .session_name
+                .as_ref()
+                .is_some_and(|name| app.marked_sessions.contains(name)),
+        };
 
-        // Check if this window has a pin
-        let shortcut = if let Some(window) = &tree_line.window {
+        // Check if this window has a pin, marks show where there is none
+        let pin = tree_line.window.as_ref().and_then(|window| {
             app.pins
                 .iter()
                 .find(|(_, (s, w))| s == &window.session_name && w == &window.id)
                 .map(|(slot, _)| format!("{} ", slot))
-                .unwrap_or_else(|| "  ".to_string())
-        } else {
-            "  ".to_string()
+        });
+        let shortcut = match pin {
+            Some(pin) => pin,
+            None if marked => "* ".to_string(),
+            None => "  ".to_string(),
         };
 
-        let display_content = format!("{}{}", shortcut, tree_line.content);
+        let mut indicators = window_indicators(tree_line, &app.config.colors);
+        indicators.extend(attached_span(tree_line, app));
+        indicators.extend(note_span(tree_line, app));
+        let age = activity_age(tree_line, app, now);
+        let age_width = age.as_ref().map_or(0, |age| age.content.width());
+        let content_width = (area.width as usize).saturating_sub(
+            shortcut.width() + tree_line.suffix.width() + spans_width(&indicators) + age_width,
+        );
+        let content = truncate_end(&tree_line.content, content_width);
+        let mut spans = vec![Span::raw(shortcut)];
+        match app.tree_search_match(tree_line) {
+            // The window name comes after the tree characters, and last
+            // unless `window_format` puts more behind it
+            Some(range) => {
+                let name_start = tree_line
+                    .window
+                    .as_ref()
+                    .and_then(|w| tree_line.content.rfind(&w.name))
+                    .map_or(0, |start| tree_line.content[..start].chars().count());
+                let indices: Vec<usize> = range.map(|i| name_start + i).collect();
+                spans.extend(create_highlighted_spans(
+                    &content,
+                    &indices,
+                    Style::default(),
+                    app.config.colors.search_highlight.to_ratatui_style(),
+                ));
+            }
+            None => spans.push(Span::raw(content)),
+        }
+        spans.push(Span::raw(tree_line.suffix.clone()));
+        spans.extend(indicators);
+        push_age_column(&mut spans, age, area.width as usize);
 
         let style = match tree_line.line_type {
             LineType::Session => {
-                if is_selected {
+                if is_selected && app.mode == Mode::Session {
+                    app.config.colors.session_mode_selected.to_ratatui_style()
+                } else if is_selected {
                     app.config.colors.session_selected.to_ratatui_style()
                 } else {
-                    app.config.colors.session.to_ratatui_style()
+                    session_style(tree_line, app, now)
                 }
             }
             LineType::Window => {
                 if is_selected {
                     app.config.colors.window_selected.to_ratatui_style()
-                } else if tree_line.window.as_ref().is_some_and(|w| w.active) {
-                    app.config.colors.window_active.to_ratatui_style()
                 } else {
-                    app.config.colors.window_inactive.to_ratatui_style()
+                    window_style(tree_line, app)
                 }
             }
         };
 
-        items.push(ListItem::new(display_content).style(style));
+        let style = if marked {
+            style.patch(app.config.colors.marked.to_ratatui_style())
+        } else {
+            style
+        };
+
+        items.push(ListItem::new(Line::from(spans)).style(style));
     }
 
     let list = List::new(items);
@@ -122,42 +773,109 @@ fn draw_main_content(f: &mut Frame, app: &App, area: Rect) {
 }
 
 fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
+    let segments = &app.config.status_bar.segments;
+    let constraints: Vec<Constraint> = segments
+        .iter()
+        .map(|segment| match (segment.width, segment.kind) {
+            (Some(width), _) => Constraint::Length(width),
+            // Leading space plus the widest label
+            (None, StatusSegmentKind::Mode) => {
+                Constraint::Length(app.config.mode_labels.max_width() as u16 + 1)
+            }
+            (None, _) => Constraint::Min(10),
+        })
+        .collect();
+
     let status_chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Length(14), // Mode indicator
-            Constraint::Min(10),    // Help text - give it more space
-            Constraint::Length(30), // Stats/error
-        ])
+        .constraints(constraints)
         .split(area);
 
-    // Mode indicator (left)
-    let mode_text = get_mode_text(&app.mode);
-    let mode_style = get_mode_style(&app.mode, &app.config.colors);
-
-    let mode_paragraph = Paragraph::new(format!(" {mode_text}")).style(mode_style);
-
-    f.render_widget(mode_paragraph, status_chunks[0]);
+    for (segment, chunk) in segments.iter().zip(status_chunks.iter()) {
+        draw_status_segment(f, app, segment.kind, *chunk);
+    }
+}
 
-    // Help text (center) - using our new help panel widget
-    render_help_panel(app, status_chunks[1], f.buffer_mut());
+fn draw_status_segment(f: &mut Frame, app: &App, kind: StatusSegmentKind, area: Rect) {
+    let status_style = app.config.colors.status_text.to_ratatui_style();
 
-    // Error message or session count (right)
-    let right_content = if let Some(error) = &app.error_message {
-        Paragraph::new(format!(" {error}")).style(app.config.colors.error_text.to_ratatui_style())
-    } else {
-        let session_count = app.sessions.len();
-        let window_count: usize = app.sessions.iter().map(|s| s.windows.len()).sum();
-        Paragraph::new(format!(
-            " Sessions: {session_count} | Windows: {window_count}"
-        ))
-        .style(app.config.colors.status_text.to_ratatui_style())
+    let paragraph = match kind {
+        StatusSegmentKind::Mode if app.index_jump.is_some() => {
+            let input = app.index_jump.as_deref().unwrap_or_default();
+            Paragraph::new(format!(" :{input}"))
+                .style(app.config.colors.numeric_buffer.to_ratatui_style())
+        }
+        StatusSegmentKind::Mode if app.tree_search_input.is_some() => {
+            let input = app.tree_search_input.as_deref().unwrap_or_default();
+            Paragraph::new(format!(" ?{input}"))
+                .style(app.config.colors.numeric_buffer.to_ratatui_style())
+        }
+        StatusSegmentKind::Mode => {
+            let mode_text = get_mode_text(&app.mode, &app.config.mode_labels);
+            let mode_style = get_mode_style(&app.mode, &app.config.colors);
+            Paragraph::new(format!(" {mode_text}")).style(mode_style)
+        }
+        StatusSegmentKind::Help => {
+            render_help_panel(app, area, f.buffer_mut());
+            return;
+        }
+        StatusSegmentKind::Counts => {
+            // Slow tmux commands and scans shouldn't look like a frozen UI
+            let busy = app
+                .busy_note()
+                .map(|note| format!(" {note}"))
+                .unwrap_or_default();
+            if app.mode == Mode::Search {
+                Paragraph::new(format!(
+                    "{busy} Results: {} • {}",
+                    app.search_results.len(),
+                    search_stats(app)
+                ))
+                .style(status_style)
+            } else {
+                let session_count = app.sessions.len();
+                let window_count: usize = app.sessions.iter().map(|s| s.windows.len()).sum();
+                // The active view goes first, it explains why windows are missing
+                let view = app
+                    .active_view()
+                    .map(|view| format!(" [{}]", view.name))
+                    .unwrap_or_default();
+                let recording = if app.is_recording() { " [rec]" } else { "" };
+                Paragraph::new(format!(
+                    "{busy}{recording}{view} Sessions: {session_count} | Windows: {window_count}"
+                ))
+                .style(status_style)
+            }
+        }
+        StatusSegmentKind::Session => {
+            let session = app.current_session.as_deref().unwrap_or("-");
+            Paragraph::new(format!(" [{session}]")).style(status_style)
+        }
+        StatusSegmentKind::History => {
+            let recent: Vec<String> = app
+                .history
+                .iter()
+                .map(|entry| {
+                    let window = if entry.window_name.is_empty() {
+                        &entry.window_id
+                    } else {
+                        &entry.window_name
+                    };
+                    format!("{}:{}", entry.session_name, window)
+                })
+                .collect();
+            let text = truncate_end(&recent.join("  "), (area.width as usize).saturating_sub(1));
+            Paragraph::new(format!(" {text}")).style(status_style)
+        }
+        StatusSegmentKind::Clock => Paragraph::new(format!(" {} ", Local::now().format("%H:%M")))
+            .style(status_style)
+            .alignment(Alignment::Right),
     };
 
-    f.render_widget(right_content, status_chunks[2]);
+    f.render_widget(paragraph, area);
 }
 
-fn draw_session_mode_interface(f: &mut Frame, app: &App) {
+fn draw_session_mode_interface(f: &mut Frame, app: &mut App) {
     // Use the same layout as normal mode but with session mode indicators
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -176,81 +894,135 @@ fn draw_search_interface(f: &mut Frame, app: &App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // Search input
-            Constraint::Min(0),    // Search results
-            Constraint::Length(1), // Status bar
+            Constraint::Length(search_bar_height(app)), // Search input
+            Constraint::Min(0),                         // Search results
+            Constraint::Length(1),                      // Status bar
         ])
         .split(f.area());
 
-    // Search input box
-    let search_input = Paragraph::new(format!("Search: {}", app.search_query))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Fuzzy Search (session/window)")
-                .border_style(app.config.colors.border_search.to_ratatui_style()),
-        )
-        .style(app.config.colors.popup_input.to_ratatui_style());
-
-    f.render_widget(search_input, chunks[0]);
+    draw_search_input(
+        f,
+        app,
+        "Fuzzy Search (session/window)",
+        &app.search_query,
+        chunks[0],
+    );
 
     // Search results
-    let results: Vec<ListItem> = app
-        .search_results
-        .iter()
-        .enumerate()
-        .map(|(i, result)| {
-            let is_selected = i == app.search_selected_index;
-            let base_style = if is_selected {
-                app.config.colors.window_selected.to_ratatui_style()
-            } else {
-                app.config.colors.window_inactive.to_ratatui_style()
-            };
+    let width = chunks[1].width as usize;
+    let result_item = |i: usize, result: &SearchResult| {
+        let is_selected = i == app.search_selected_index;
+        let base_style = if is_selected {
+            app.config.colors.window_selected.to_ratatui_style()
+        } else {
+            app.config.colors.window_inactive.to_ratatui_style()
+        };
 
-            let _content_text = format!(
-                "{} → {} ({})",
-                result.display_text,
-                result.session_name,
-                result.full_path.display()
-            );
-
-            // Create highlighted spans for the display text part
-            let highlighted_spans = create_highlighted_spans(
-                &result.display_text,
-                &result.match_indices,
-                base_style,
-                app.config.colors.search_highlight.to_ratatui_style(), // Use search_highlight color
-            );
-
-            // Add the rest of the content (session and path info)
-            let mut all_spans = highlighted_spans;
-            all_spans.push(Span::styled(
-                format!(
-                    " → {} ({})",
-                    result.session_name,
-                    result.full_path.display()
-                ),
-                base_style,
-            ));
-
-            ListItem::new(Line::from(all_spans))
-        })
-        .collect();
+        let display_text = truncate_end(&result.display_text, width);
+
+        // Create highlighted spans for the display text part
+        let highlighted_spans = create_highlighted_spans(
+            &display_text,
+            &result.match_indices,
+            base_style,
+            app.config.colors.search_highlight.to_ratatui_style(), // Use search_highlight color
+        );
+
+        // Add the rest of the content (session and path info), shortening
+        // the path from the front and dropping it when there is no room
+        let mut all_spans = highlighted_spans;
+        let session_part = format!(" → {}", result.session_name);
+        let remaining = width.saturating_sub(display_text.width());
+        let path_width = remaining.saturating_sub(session_part.width() + 3);
+        let mut tail = truncate_end(&session_part, remaining);
+        if path_width >= 2 {
+            let path = result.full_path.display().to_string();
+            tail.push_str(&format!(" ({})", truncate_start(&path, path_width)));
+        }
+        all_spans.push(Span::styled(tail, base_style));
+
+        ListItem::new(Line::from(all_spans))
+    };
 
-    let results_list = List::new(results);
+    // With more than one pattern, results sit under a header per group
+    let grouped = app.search_groups.len() > 1;
+    let mut results: Vec<ListItem> = Vec::new();
+    let mut selected_row = None;
+    let mut next = app.search_results.iter().enumerate().peekable();
+    for (group, count) in &app.search_groups {
+        if grouped {
+            let collapsed = app.collapsed_groups.contains(group);
+            let marker = if collapsed { '▸' } else { '▾' };
+            let header = format!("{marker} {group} ({count})");
+            results.push(ListItem::new(Line::styled(
+                truncate_end(&header, width),
+                group_style(app, group),
+            )));
+        }
+        while let Some((i, result)) = next.next_if(|(_, r)| r.group == *group) {
+            if i == app.search_selected_index {
+                selected_row = Some(results.len());
+            }
+            results.push(result_item(i, result));
+        }
+    }
 
-    f.render_widget(results_list, chunks[1]);
+    // Keep the selected result in view
+    let mut list_state = ListState::default().with_selected(selected_row);
+    f.render_stateful_widget(List::new(results), chunks[1], &mut list_state);
+
+    // Nothing matched: Enter launches a session named after the query
+    if app.search_results.is_empty()
+        && let Some(new) = new_session_result(&app.search_query, Path::new(""))
+    {
+        let hint = format!(
+            "Enter: create session '{}' in {}",
+            new.session_name, app.config.new_session_dir
+        );
+        f.render_widget(
+            Paragraph::new(truncate_end(&hint, width))
+                .style(app.config.colors.help_text.to_ratatui_style()),
+            chunks[1],
+        );
+    }
 
     // Status bar
-    draw_search_status_bar(f, app, chunks[2]);
+    draw_status_bar(f, app, chunks[2]);
+}
+
+// Where search time goes: "1,243 projects • scanned in 180ms • matched in
+// 2ms • cache 2m old"
+fn search_stats(app: &App) -> String {
+    let provider = &app.search_provider;
+    let Some(stats) = provider.scan_stats() else {
+        return "not scanned yet".to_string();
+    };
+    format!(
+        "{} projects • scanned in {} • matched in {} • cache {} old",
+        format_count(provider.project_count()),
+        format_duration(stats.duration),
+        format_duration(provider.match_time()),
+        format_age(stats.finished.elapsed().as_secs() as i64)
+    )
+}
+
+// A pattern's own header style, or the session style otherwise
+fn group_style(app: &App, group: &str) -> Style {
+    app.config
+        .search_patterns
+        .iter()
+        .find(|p| p.name == group)
+        .and_then(|p| p.style.as_ref())
+        .unwrap_or(&app.config.colors.session)
+        .to_ratatui_style()
 }
 
-fn create_highlighted_spans<'a>(
-    text: &'a str,
+fn create_highlighted_spans(
+    text: &str,
     match_indices: &[usize],
     normal_style: Style,
     highlight_style: Style,
-) -> Vec<Span<'a>> {
+) -> Vec<Span<'static>> {
     let mut spans = Vec::new();
     let chars: Vec<char> = text.chars().collect();
     let mut current_span = String::new();
@@ -296,7 +1068,7 @@ fn draw_quick_search_interface(f: &mut Frame, app: &App) {
         .split(f.area());
 
     // Calculate the area for the tree view, accounting for the search bar height
-    let search_bar_height = 3;
+    let search_bar_height = search_bar_height(app);
     let tree_area = Rect {
         x: chunks[0].x,
         y: chunks[0].y + search_bar_height,
@@ -316,17 +1088,43 @@ fn draw_quick_search_interface(f: &mut Frame, app: &App) {
         height: search_bar_height,
     };
 
-    let search_input = Paragraph::new(format!("Search: {}", app.quick_search_query))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Quick Search (active sessions/windows)")
-                .border_style(app.config.colors.border_search.to_ratatui_style()),
-        )
-        .style(app.config.colors.popup_input.to_ratatui_style());
-
     f.render_widget(Clear, search_area); // Clear the background
-    f.render_widget(search_input, search_area);
+    draw_search_input(
+        f,
+        app,
+        "Quick Search (active sessions/windows)",
+        &app.quick_search_query,
+        search_area,
+    );
+}
+
+fn search_bar_height(app: &App) -> u16 {
+    match app.config.popup.boxed_search_bar() {
+        true => 3,
+        false => 1,
+    }
+}
+
+// "Search: <query>" in a titled box, or on a bare line with
+// `search_bar_height = 1`, with the cursor in it
+fn draw_search_input(f: &mut Frame, app: &App, title: &str, query: &TextInput, area: Rect) {
+    let input = Paragraph::new(format!("{SEARCH_PROMPT}{query}"))
+        .style(app.config.colors.popup_input.to_ratatui_style());
+    let column = (SEARCH_PROMPT.len() + query.cursor_column()) as u16;
+
+    if app.config.popup.boxed_search_bar() {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(app.config.colors.border_search.to_ratatui_style());
+        f.render_widget(input.block(block), area);
+        let x = (area.x + 1 + column).min(area.right().saturating_sub(2));
+        f.set_cursor_position((x, area.y + 1));
+    } else {
+        f.render_widget(input, area);
+        let x = (area.x + column).min(area.right().saturating_sub(1));
+        f.set_cursor_position((x, area.y));
+    }
 }
 
 fn draw_main_content_with_quick_search_highlights(f: &mut Frame, app: &App, area: Rect) {
@@ -336,9 +1134,16 @@ fn draw_main_content_with_quick_search_highlights(f: &mut Frame, app: &App, area
     let line_numbers = app.get_window_line_numbers();
     let mut items = Vec::new();
     let matcher = SkimMatcherV2::default().ignore_case();
+    // Only the fuzzy part of the query gets highlighted, not cmd:/path:/...
+    let filter = Filter::parse(&app.quick_search_query);
+    let now = Local::now().timestamp();
 
-    // Always use 3 characters for line numbers
+    // Always use 3 characters for line numbers, none at all when they're off
     let line_number_width = 3;
+    let gutter_width = match app.line_number_mode {
+        LineNumberMode::Off => 0,
+        _ => line_number_width + app.config.line_numbers.padding,
+    };
 
     for (i, tree_line) in app.tree_lines.iter().enumerate() {
         let is_selected = i == app.selected_index;
@@ -348,36 +1153,39 @@ fn draw_main_content_with_quick_search_highlights(f: &mut Frame, app: &App, area
             .get(app.quick_search_selected_index)
             == Some(&i);
 
-        let (line_number_str, line_number_style) = if tree_line.line_type == LineType::Window {
-            if let Some(&relative_num) = line_numbers.get(&i) {
-                if relative_num == 0 {
-                    let padding = " ".repeat(app.config.line_numbers.padding);
-                    (
-                        format!("{:<width$}{}", "0", padding, width = line_number_width),
-                        app.config
-                            .line_numbers
-                            .current_line_color
-                            .to_ratatui_style(),
-                    )
-                } else {
-                    let padding = " ".repeat(app.config.line_numbers.padding);
-                    (
-                        format!(
-                            "{:>width$}{}",
-                            relative_num.abs(),
-                            padding,
-                            width = line_number_width
-                        ),
-                        app.config.line_numbers.other_lines_color.to_ratatui_style(),
-                    )
-                }
+        let (line_number_str, line_number_style) = if gutter_width == 0 {
+            (String::new(), Style::default())
+        } else if let Some(&number) = line_numbers.get(&i) {
+            let padding = " ".repeat(app.config.line_numbers.padding);
+            if i == app.selected_index && app.line_number_mode == LineNumberMode::Absolute {
+                (
+                    format!("{:>width$}{}", number, padding, width = line_number_width),
+                    app.config
+                        .line_numbers
+                        .current_line_color
+                        .to_ratatui_style(),
+                )
+            } else if number == 0 {
+                (
+                    format!("{:<width$}{}", "0", padding, width = line_number_width),
+                    app.config
+                        .line_numbers
+                        .current_line_color
+                        .to_ratatui_style(),
+                )
             } else {
-                let total_width = line_number_width + app.config.line_numbers.padding;
-                (" ".repeat(total_width), Style::default())
+                (
+                    format!(
+                        "{:>width$}{}",
+                        number.abs(),
+                        padding,
+                        width = line_number_width
+                    ),
+                    app.config.line_numbers.other_lines_color.to_ratatui_style(),
+                )
             }
         } else {
-            let total_width = line_number_width + app.config.line_numbers.padding;
-            (" ".repeat(total_width), Style::default())
+            (" ".repeat(gutter_width), Style::default())
         };
 
         // Determine base content style - PRIORITY ORDER MATTERS!
@@ -394,68 +1202,75 @@ fn draw_main_content_with_quick_search_highlights(f: &mut Frame, app: &App, area
             app.config.colors.quick_search_match.to_ratatui_style()
         } else {
             match tree_line.line_type {
-                LineType::Session => app.config.colors.session.to_ratatui_style(),
-                LineType::Window => {
-                    if tree_line.window.as_ref().is_some_and(|w| w.active) {
-                        app.config.colors.window_active.to_ratatui_style()
-                    } else {
-                        app.config.colors.window_inactive.to_ratatui_style()
-                    }
-                }
+                LineType::Session => session_style(tree_line, app, now),
+                LineType::Window => window_style(tree_line, app),
             }
         };
 
+        let mut indicators = window_indicators(tree_line, &app.config.colors);
+        indicators.extend(attached_span(tree_line, app));
+        indicators.extend(note_span(tree_line, app));
+        let age = activity_age(tree_line, app, now);
+        let age_width = age.as_ref().map_or(0, |age| age.content.width());
+        let content_width = (area.width as usize).saturating_sub(
+            line_number_str.width()
+                + tree_line.suffix.width()
+                + spans_width(&indicators)
+                + age_width,
+        );
+        let content = truncate_end(&tree_line.content, content_width);
+
         // Create content spans with highlighting for quick search matches
-        let content_spans = if is_quick_search_match
-            && !is_quick_search_selected
-            && !app.quick_search_query.is_empty()
-        {
-            // Only do fuzzy highlighting for matches that are NOT the selected item
-            // (selected item gets full highlight via base_content_style)
-
-            // Get the search text for this line
-            let search_text = match tree_line.line_type {
-                LineType::Session => {
-                    if let Some(ref session_name) = tree_line.session_name {
-                        session_name.clone()
-                    } else {
-                        tree_line.content.clone()
-                    }
-                }
-                LineType::Window => {
-                    if let Some(window) = &tree_line.window {
+        let content_spans =
+            if is_quick_search_match && !is_quick_search_selected && !filter.text.is_empty() {
+                // Only do fuzzy highlighting for matches that are NOT the selected item
+                // (selected item gets full highlight via base_content_style)
+
+                // Get the search text for this line
+                let search_text = match tree_line.line_type {
+                    LineType::Session => {
                         if let Some(ref session_name) = tree_line.session_name {
-                            format!("{}:{}", session_name, window.name)
+                            session_name.clone()
                         } else {
-                            window.name.clone()
+                            tree_line.content.clone()
                         }
-                    } else {
-                        tree_line.content.clone()
                     }
-                }
-            };
+                    LineType::Window => {
+                        if let Some(window) = &tree_line.window {
+                            if let Some(ref session_name) = tree_line.session_name {
+                                format!("{}:{}", session_name, window.name)
+                            } else {
+                                window.name.clone()
+                            }
+                        } else {
+                            tree_line.content.clone()
+                        }
+                    }
+                };
 
-            // Get match indices for highlighting
-            if let Some((_, indices)) = matcher.fuzzy_indices(&search_text, &app.quick_search_query)
-            {
-                // Create highlighted spans for the display content
-                create_highlighted_spans_for_content(
-                    &tree_line.content,
-                    &search_text,
-                    &indices,
-                    base_content_style,
-                    app.config.colors.search_highlight.to_ratatui_style(),
-                )
+                // Get match indices for highlighting
+                if let Some((_, indices)) = matcher.fuzzy_indices(&search_text, &filter.text) {
+                    // Create highlighted spans for the display content
+                    create_highlighted_spans_for_content(
+                        &content,
+                        &search_text,
+                        &indices,
+                        base_content_style,
+                        app.config.colors.search_highlight.to_ratatui_style(),
+                    )
+                } else {
+                    vec![Span::styled(content.clone(), base_content_style)]
+                }
             } else {
-                vec![Span::styled(tree_line.content.clone(), base_content_style)]
-            }
-        } else {
-            // For selected items or non-matches, use the base style (which already has correct priority)
-            vec![Span::styled(tree_line.content.clone(), base_content_style)]
-        };
+                // For selected items or non-matches, use the base style (which already has correct priority)
+                vec![Span::styled(content.clone(), base_content_style)]
+            };
 
         let mut all_spans = vec![Span::styled(line_number_str.clone(), line_number_style)];
         all_spans.extend(content_spans);
+        all_spans.push(Span::styled(tree_line.suffix.clone(), base_content_style));
+        all_spans.extend(indicators);
+        push_age_column(&mut all_spans, age, area.width as usize);
 
         items.push(ListItem::new(Line::from(all_spans)));
     }
@@ -466,15 +1281,15 @@ fn draw_main_content_with_quick_search_highlights(f: &mut Frame, app: &App, area
 }
 
 // Helper function to create highlighted spans for content based on search matches
-fn create_highlighted_spans_for_content<'a>(
-    display_content: &'a str,
+fn create_highlighted_spans_for_content(
+    display_content: &str,
     search_text: &str,
     match_indices: &[usize],
     normal_style: Style,
     highlight_style: Style,
-) -> Vec<Span<'a>> {
+) -> Vec<Span<'static>> {
     // For session:window format, we need to map indices back to display content
-    if search_text.contains(':') && !display_content.contains(':') {
+    if search_text.contains(':') && !display_content.contains(search_text) {
         // This is a window where we searched "session:window" but display is just the window content
         // For now, just return the content with normal style since mapping is complex
         vec![Span::styled(display_content.to_string(), normal_style)]
@@ -488,31 +1303,3 @@ fn create_highlighted_spans_for_content<'a>(
         )
     }
 }
-
-fn draw_search_status_bar(f: &mut Frame, app: &App, area: Rect) {
-    // Use the same layout as the normal status bar
-    let status_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Length(14), // Mode indicator
-            Constraint::Min(10),    // Help text
-            Constraint::Length(30), // Stats/error
-        ])
-        .split(area);
-
-    // Mode indicator (left)
-    let mode_text = get_mode_text(&app.mode);
-    let mode_style = get_mode_style(&app.mode, &app.config.colors);
-    let mode_paragraph = Paragraph::new(format!(" {mode_text}")).style(mode_style);
-
-    f.render_widget(mode_paragraph, status_chunks[0]);
-
-    // Help text (center)
-    render_help_panel(app, status_chunks[1], f.buffer_mut());
-
-    // Right section - show search count
-    let right_content = Paragraph::new(format!(" Results: {} ", app.search_results.len()))
-        .style(app.config.colors.status_text.to_ratatui_style());
-
-    f.render_widget(right_content, status_chunks[2]);
-}
@@ -1,137 +1,309 @@
 use ratatui::prelude::*;
 
 use crate::app::{App, Mode};
+use crate::doctor::Feature;
+use crate::keymap::{Action, Scope};
 use crate::widgets::help_panel::{HelpItem, HelpPanel};
 
 /// Get the appropriate help items based on the current app mode
 pub fn get_help_items_for_mode(mode: &Mode) -> Vec<HelpItem<'static>> {
     match mode {
-        Mode::Window => vec![
-            HelpItem {
-                key: "q",
-                description: "quit",
-            },
+        // The rest comes from the keymap, see WINDOW_HELP
+        Mode::Window => vec![HelpItem {
+            key: "a-z",
+            description: "jump by first letter",
+        }],
+        Mode::Rename | Mode::Link | Mode::StartDir | Mode::Note | Mode::Shell | Mode::MoveIndex => {
+            vec![
+                HelpItem {
+                    key: "Esc",
+                    description: "cancel",
+                },
+                HelpItem {
+                    key: "Enter",
+                    description: "confirm",
+                },
+            ]
+        }
+        Mode::Search => vec![
             HelpItem {
-                key: "j/k",
-                description: "move",
+                key: "Esc",
+                description: "cancel",
             },
             HelpItem {
                 key: "Enter",
                 description: "select",
             },
             HelpItem {
-                key: "r/,",
-                description: "rename",
+                key: "C-r/C-x",
+                description: "rescan/forget",
             },
             HelpItem {
-                key: "x",
-                description: "delete",
+                key: "Tab",
+                description: "fold group",
             },
+        ],
+        Mode::QuickSearch => vec![
             HelpItem {
-                key: "R",
-                description: "refresh",
+                key: "Esc",
+                description: "cancel",
             },
             HelpItem {
-                key: "/",
-                description: "search",
+                key: "Enter",
+                description: "select",
             },
+        ],
+        Mode::Session => Vec::new(),
+        Mode::DeleteConfirm => vec![
             HelpItem {
-                key: "F",
-                description: "find project",
+                key: "y",
+                description: "confirm delete",
             },
             HelpItem {
-                key: "S",
-                description: "session mode",
+                key: "←/→",
+                description: "choose",
             },
             HelpItem {
-                key: "J/K",
-                description: "move item",
+                key: "n/Esc",
+                description: "cancel",
             },
+        ],
+        Mode::MoveConfirm => vec![
             HelpItem {
-                key: "C",
-                description: "create window",
+                key: "y",
+                description: "confirm move",
             },
-        ],
-        Mode::Rename => vec![
             HelpItem {
-                key: "Esc",
-                description: "cancel",
+                key: "←/→",
+                description: "choose",
             },
             HelpItem {
-                key: "Enter",
-                description: "confirm",
+                key: "n/Esc",
+                description: "cancel",
             },
         ],
-        Mode::Search => vec![
+        Mode::CollisionConfirm => vec![
             HelpItem {
-                key: "Esc",
-                description: "cancel",
+                key: "y",
+                description: "new session",
             },
             HelpItem {
-                key: "Enter",
-                description: "select",
+                key: "n",
+                description: "existing",
             },
             HelpItem {
-                key: "↑/↓",
-                description: "navigate",
+                key: "←/→",
+                description: "choose",
             },
-        ],
-        Mode::QuickSearch => vec![
             HelpItem {
                 key: "Esc",
                 description: "cancel",
             },
+        ],
+        Mode::Archive => vec![
             HelpItem {
                 key: "Enter",
-                description: "select",
+                description: "restore",
             },
             HelpItem {
-                key: "↑/↓",
-                description: "navigate",
+                key: "x",
+                description: "forget",
             },
-        ],
-        Mode::Session => vec![
             HelpItem {
                 key: "q/Esc",
-                description: "normal mode",
+                description: "back",
             },
+        ],
+        Mode::Watch => vec![HelpItem {
+            key: "q/Esc",
+            description: "quit",
+        }],
+        Mode::Stats => vec![HelpItem {
+            key: "q/Esc",
+            description: "back",
+        }],
+        Mode::Info => vec![
             HelpItem {
-                key: "j/k",
-                description: "move between sessions",
+                key: "R",
+                description: "check again",
             },
             HelpItem {
-                key: "Enter",
-                description: "switch to session",
+                key: "q/Esc",
+                description: "back",
             },
+        ],
+        Mode::Options => vec![
             HelpItem {
-                key: "/r",
-                description: "rename session",
+                key: "Space",
+                description: "toggle",
             },
             HelpItem {
-                key: "x",
-                description: "delete session",
+                key: "R",
+                description: "reload",
             },
             HelpItem {
-                key: "J/K",
-                description: "move session",
+                key: "q/Esc",
+                description: "back",
             },
         ],
-        Mode::DeleteConfirm => vec![
+        Mode::Environment => vec![
             HelpItem {
-                key: "y",
-                description: "confirm delete",
+                key: "a/e",
+                description: "add/edit",
             },
             HelpItem {
-                key: "n/Esc",
-                description: "cancel",
+                key: "x",
+                description: "unset",
+            },
+            HelpItem {
+                key: "q/Esc",
+                description: "back",
             },
         ],
+        Mode::Messages => vec![HelpItem {
+            key: "q/Esc",
+            description: "back",
+        }],
     }
 }
 
+// Help bar entries whose keys come from the keymap, so that presets and
+// `[keys]` show up: the first key of each action, joined by "/"
+type HelpTable = &'static [(&'static [Action], &'static str)];
+
+const WINDOW_HELP: HelpTable = &[
+    (&[Action::Quit], "quit"),
+    (&[Action::Down, Action::Up], "move"),
+    (
+        &[Action::PreviousSession, Action::NextSession],
+        "prev/next session",
+    ),
+    (&[Action::Select], "select"),
+    (&[Action::SelectZoomed], "select zoomed"),
+    (&[Action::Rename], "rename"),
+    (&[Action::Delete], "delete"),
+    (&[Action::CloseOthers], "close others"),
+    (&[Action::Mark], "mark"),
+    (&[Action::Refresh], "refresh"),
+    (&[Action::QuickSearch], "search"),
+    (&[Action::FindProject], "find project"),
+    (&[Action::SessionMode], "session mode"),
+    (&[Action::MoveItemDown, Action::MoveItemUp], "move item"),
+    (&[Action::CreateWindow], "create window"),
+    (&[Action::Messages], "messages"),
+    (&[Action::TreeSearch], "find in tree"),
+    (
+        &[Action::SearchNext, Action::SearchPrevious],
+        "next/prev match",
+    ),
+    (&[Action::LineNumbers], "line numbers"),
+    (&[Action::SortByActivity], "sort by activity"),
+    (&[Action::FlatList], "flat list"),
+    (&[Action::CycleView], "views"),
+    (&[Action::Environment], "environment"),
+    (&[Action::StartDir], "start directory"),
+    (&[Action::Options], "options"),
+    (&[Action::ServerInfo], "server info"),
+    (&[Action::Stats], "usage stats"),
+    (&[Action::ExportTree], "copy tree as markdown"),
+    (&[Action::ToggleLogging], "log pane"),
+    (&[Action::CaptureScrollback], "save scrollback"),
+    (&[Action::Note], "note"),
+    (&[Action::CycleColor], "color window"),
+    (&[Action::Shell], "shell command"),
+    (&[Action::MoveToIndex], "move to index"),
+    (
+        &[Action::LinkWindow, Action::UnlinkWindow],
+        "link/unlink window",
+    ),
+    (&[Action::BreakOut], "break out to session"),
+    (
+        &[Action::Scratch, Action::SendToScratch],
+        "scratch popup/send",
+    ),
+    (&[Action::ShowPins], "show pins"),
+    (&[Action::GoToIndex], "go to index"),
+    (
+        &[Action::JumpBack, Action::JumpForward],
+        "jump back/forward",
+    ),
+    (&[Action::RecentInSession], "recent in session"),
+    (&[Action::CopyTarget, Action::CopyPath], "copy target/path"),
+    (&[Action::Repeat], "repeat change"),
+    (
+        &[Action::RecordMacro, Action::ReplayMacro],
+        "record/replay macro",
+    ),
+];
+
+const SESSION_HELP: HelpTable = &[
+    (&[Action::Back], "normal mode"),
+    (&[Action::Down, Action::Up], "move between sessions"),
+    (&[Action::Select], "switch to session"),
+    (&[Action::Rename], "rename session"),
+    (&[Action::Delete], "delete session"),
+    (&[Action::Prune], "prune idle"),
+    (
+        &[Action::Archive, Action::ArchiveBrowser],
+        "archive/archived",
+    ),
+    (&[Action::Mark], "mark"),
+    (&[Action::MoveItemDown, Action::MoveItemUp], "move session"),
+];
+
+const LIST_HELP: HelpTable = &[(&[Action::Down, Action::Up], "scroll")];
+
+const SEARCH_HELP: HelpTable = &[
+    (&[Action::Down, Action::Up], "navigate"),
+    (&[Action::NewSession], "new session"),
+];
+
+const QUICK_SEARCH_HELP: HelpTable = &[(&[Action::Down, Action::Up], "navigate")];
+
+// Quick search cycles line numbers with the window mode key
+const LINE_NUMBERS_HELP: HelpTable = &[(&[Action::LineNumbers], "line numbers")];
+
+/// Keymap entries of the help bar for the current mode, with their labels.
+/// Entries with an unbound action are left out
+fn keymap_help_items(app: &App) -> Vec<(String, &'static str)> {
+    let tables: &[(Scope, HelpTable)] = match app.mode {
+        Mode::Window => &[(Scope::Window, WINDOW_HELP)],
+        Mode::Session => &[(Scope::Session, SESSION_HELP)],
+        Mode::Messages => &[(Scope::List, LIST_HELP)],
+        Mode::Search => &[(Scope::Search, SEARCH_HELP)],
+        Mode::QuickSearch => &[
+            (Scope::Search, QUICK_SEARCH_HELP),
+            (Scope::Window, LINE_NUMBERS_HELP),
+        ],
+        _ => return Vec::new(),
+    };
+
+    tables
+        .iter()
+        .flat_map(|(scope, entries)| entries.iter().map(move |entry| (*scope, entry)))
+        // Keys that would only say the server is too old
+        .filter(|(_, (actions, _))| {
+            (app.supports(Feature::Popup) || !actions.contains(&Action::Scratch))
+                && (app.supports(Feature::Clipboard) || !actions.contains(&Action::CopyTarget))
+        })
+        .filter_map(|(scope, (actions, description))| {
+            let keys = actions
+                .iter()
+                .map(|action| app.keymap.first_key(scope, *action).map(|k| k.to_string()))
+                .collect::<Option<Vec<_>>>()?;
+            Some((keys.join("/"), *description))
+        })
+        .collect()
+}
+
 /// Render the help panel for the current app state
 pub fn render_help_panel(app: &App, area: Rect, buf: &mut Buffer) {
-    let help_items = get_help_items_for_mode(&app.mode);
+    let bound = keymap_help_items(app);
+    let mut help_items: Vec<HelpItem> = bound
+        .iter()
+        .map(|(key, description)| HelpItem { key, description })
+        .collect();
+    help_items.extend(get_help_items_for_mode(&app.mode));
 
     let help_panel = HelpPanel::new(help_items)
         .key_style(app.config.colors.help_key.to_ratatui_style())
@@ -139,3 +311,61 @@ pub fn render_help_panel(app: &App, area: Rect, buf: &mut Buffer) {
 
     help_panel.render(area, buf);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, KeySpec, KeymapPreset};
+    use crate::tmux::fake::FakeTmux;
+    use std::sync::Arc;
+
+    // The help bar as text, on one wide line
+    fn help_bar(config: Config) -> String {
+        let app = App::with_backend(config, Arc::new(FakeTmux::with_sessions(&[])));
+        let area = Rect::new(0, 0, 1000, 1);
+        let mut buf = Buffer::empty(area);
+        render_help_panel(&app, area, &mut buf);
+        buf.content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[test]
+    fn test_help_follows_keys() {
+        let bar = help_bar(Config::default());
+        assert!(bar.contains(" q:quit "));
+        assert!(bar.contains(" j/k:move "));
+        assert!(bar.contains(" /:search "));
+
+        let mut config = Config::default();
+        let keys = &mut config.keys.window;
+        keys.insert("quit".to_string(), KeySpec::One("C-q".to_string()));
+        keys.insert("quick_search".to_string(), KeySpec::One("s".to_string()));
+        keys.insert("delete".to_string(), KeySpec::Many(vec![]));
+        let bar = help_bar(config);
+        assert!(bar.contains(" C-q:quit "));
+        assert!(bar.contains(" s:search "));
+        assert!(!bar.contains(":delete "));
+    }
+
+    #[test]
+    fn test_help_follows_preset() {
+        let config = Config {
+            keymap: KeymapPreset::Emacs,
+            ..Config::default()
+        };
+        let bar = help_bar(config.clone());
+        assert!(bar.contains(" C-n/C-p:move "));
+        assert!(bar.contains(" M-l:line numbers "));
+        assert!(!bar.contains("j/k"));
+
+        let mut app = App::with_backend(config, Arc::new(FakeTmux::with_sessions(&[])));
+        app.mode = Mode::Search;
+        let items = keymap_help_items(&app);
+        assert_eq!(
+            items,
+            [
+                ("C-n/C-p".to_string(), "navigate"),
+                ("M-Enter".to_string(), "new session")
+            ]
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use ratatui::Frame;
+
+use crate::app::App;
+use crate::search::SearchResult;
+use crate::widgets::shortcut_overlay::{Shortcut, ShortcutOverlay};
+
+/// Render the pane layouts to pick from for a window about to be created
+pub fn render_layout_overlay(app: &App, result: &SearchResult, frame: &mut Frame) {
+    let choices = app.layout_choices(result);
+    let keys: Vec<String> = (1..=choices.len()).map(|n| n.to_string()).collect();
+    let shortcuts = keys
+        .iter()
+        .zip(&choices)
+        .map(|(key, (name, _))| Shortcut {
+            key,
+            target: name.as_str(),
+        })
+        .collect();
+
+    let title = format!("Open {}", result.window_name);
+    let overlay = ShortcutOverlay::new(&title, shortcuts)
+        .key_style(app.config.colors.help_key.to_ratatui_style())
+        .target_style(app.config.colors.popup_text.to_ratatui_style())
+        .border_style(app.config.colors.popup_border.to_ratatui_style())
+        .anchor(app.config.popup.anchor);
+
+    frame.render_widget(overlay, frame.area());
+}
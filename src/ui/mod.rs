@@ -1,5 +1,8 @@
 pub mod confirm;
 pub mod help;
+pub mod layouts;
+pub mod pins;
+pub mod text;
 
 // Re-export the main draw function
 pub use self::draw::draw;
@@ -0,0 +1,33 @@
+use ratatui::Frame;
+
+use crate::app::App;
+use crate::widgets::shortcut_overlay::{Shortcut, ShortcutOverlay};
+
+/// Render the list of pinned windows the digit keys jump to
+pub fn render_pin_overlay(app: &App, frame: &mut Frame) {
+    let targets = app.pin_targets();
+    let keys: Vec<String> = targets.iter().map(|(slot, _)| slot.to_string()).collect();
+
+    let shortcuts = if targets.is_empty() {
+        vec![Shortcut {
+            key: "⇧1-9",
+            target: "pin the selected window",
+        }]
+    } else {
+        keys.iter()
+            .zip(&targets)
+            .map(|(key, (_, target))| Shortcut {
+                key,
+                target: target.as_str(),
+            })
+            .collect()
+    };
+
+    let overlay = ShortcutOverlay::new("Pins", shortcuts)
+        .key_style(app.config.colors.help_key.to_ratatui_style())
+        .target_style(app.config.colors.popup_text.to_ratatui_style())
+        .border_style(app.config.colors.popup_border.to_ratatui_style())
+        .anchor(app.config.popup.anchor);
+
+    frame.render_widget(overlay, frame.area());
+}
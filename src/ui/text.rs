@@ -0,0 +1,163 @@
+use std::time::Duration;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const ELLIPSIS: char = '…';
+
+/// Cut `text` to at most `width` terminal columns, ending in an ellipsis when
+/// anything was dropped
+pub fn truncate_end(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut output = String::new();
+    let mut used = 0;
+    for ch in text.chars() {
+        let ch_width = ch.width().unwrap_or(0);
+        // Keep one column for the ellipsis
+        if used + ch_width > width - 1 {
+            break;
+        }
+        output.push(ch);
+        used += ch_width;
+    }
+    output.push(ELLIPSIS);
+    output
+}
+
+/// Like `truncate_end`, but keeps the end of `text`; for paths, where the
+/// last components say the most
+pub fn truncate_start(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for ch in text.chars().rev() {
+        let ch_width = ch.width().unwrap_or(0);
+        if used + ch_width > width - 1 {
+            break;
+        }
+        kept.push(ch);
+        used += ch_width;
+    }
+    std::iter::once(ELLIPSIS)
+        .chain(kept.into_iter().rev())
+        .collect()
+}
+
+/// Rows `text` takes when wrapped at word boundaries to `width` columns,
+/// like a `Paragraph` with `Wrap`. Words wider than a row are broken up
+pub fn wrapped_height(text: &str, width: usize) -> usize {
+    let width = width.max(1);
+    let rows: usize = text
+        .lines()
+        .map(|line| {
+            let mut rows = 1;
+            let mut used = 0;
+            for word in line.split_whitespace() {
+                let word_width = word.width().max(1);
+                if used > 0 && used + 1 + word_width <= width {
+                    used += 1 + word_width;
+                    continue;
+                }
+                if used > 0 {
+                    rows += 1;
+                }
+                let extra = (word_width - 1) / width;
+                rows += extra;
+                used = word_width - extra * width;
+            }
+            rows
+        })
+        .sum();
+    rows.max(1)
+}
+
+/// Count with thousands separators, e.g. "1,243"
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Short duration like "3ms", "180ms" or "2.4s"
+pub fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis < 1000 {
+        format!("{millis}ms")
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
+
+/// Coarse age like "45s", "5m", "2h" or "3d"
+pub fn format_age(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_count_and_duration() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1243), "1,243");
+        assert_eq!(format_count(1_000_000), "1,000,000");
+        assert_eq!(format_duration(Duration::from_micros(2500)), "2ms");
+        assert_eq!(format_duration(Duration::from_millis(180)), "180ms");
+        assert_eq!(format_duration(Duration::from_millis(2449)), "2.4s");
+    }
+
+    #[test]
+    fn test_truncate_by_display_width() {
+        assert_eq!(truncate_end("editor", 10), "editor");
+        assert_eq!(truncate_end("editor", 4), "edi…");
+        // Wide characters take two columns each
+        assert_eq!(truncate_end("日本語のウィンドウ", 7), "日本語…");
+        assert_eq!(truncate_end("日本語", 0), "");
+        assert_eq!(truncate_start("/home/me/git/project", 10), "…t/project");
+        assert_eq!(truncate_start("/srv/日本", 4), "…本");
+    }
+
+    #[test]
+    fn test_wrapped_height() {
+        assert_eq!(wrapped_height("", 10), 1);
+        assert_eq!(wrapped_height("Delete window 'editor'?", 30), 1);
+        assert_eq!(wrapped_height("Delete window 'editor'?", 15), 2);
+        assert_eq!(wrapped_height("one\n\n- two", 10), 3);
+        // A long name gets rows of its own
+        assert_eq!(wrapped_height("Delete 'aaaaaaaaaaaaaaaaaaaaaaaaa'?", 10), 4);
+        assert_eq!(wrapped_height("日本語 日本語", 6), 2);
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(-5), "0s");
+        assert_eq!(format_age(45), "45s");
+        assert_eq!(format_age(5 * 60 + 30), "5m");
+        assert_eq!(format_age(2 * 3600), "2h");
+        assert_eq!(format_age(3 * 86400 + 10), "3d");
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::tmux::{TmuxSession, TmuxWindow};
+
+// Days of session and window totals kept for the trend
+const DAYS_KEPT: usize = 30;
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// How often windows were switched to from the portal, and how many sessions
+/// and windows there were each day. Kept by name, window IDs don't survive a
+/// server restart
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub windows: Vec<WindowUsage>,
+    #[serde(default)]
+    pub days: Vec<DayCount>, // Oldest first
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowUsage {
+    pub session_name: String,
+    pub window_name: String,
+    pub activations: u32,
+    pub last_used: i64, // Unix time
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DayCount {
+    pub day: i64, // Days since the Unix epoch
+    pub sessions: usize,
+    pub windows: usize,
+}
+
+/// A session's switches summed over its windows
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionUsage {
+    pub name: String,
+    pub activations: u32,
+    pub last_used: i64,
+}
+
+impl Usage {
+    pub fn record_activation(&mut self, session_name: &str, window_name: &str, now: i64) {
+        match self
+            .windows
+            .iter_mut()
+            .find(|w| w.session_name == session_name && w.window_name == window_name)
+        {
+            Some(usage) => {
+                usage.activations += 1;
+                usage.last_used = now;
+            }
+            None => self.windows.push(WindowUsage {
+                session_name: session_name.to_string(),
+                window_name: window_name.to_string(),
+                activations: 1,
+                last_used: now,
+            }),
+        }
+    }
+
+    /// Note today's totals, the latest count of the day wins. Returns whether
+    /// anything changed, so unchanged refreshes don't write the file
+    pub fn record_counts(&mut self, sessions: &[TmuxSession], now: i64) -> bool {
+        let count = DayCount {
+            day: now.div_euclid(SECONDS_PER_DAY),
+            sessions: sessions.len(),
+            windows: sessions.iter().map(|s| s.windows.len()).sum(),
+        };
+        match self.days.last_mut() {
+            Some(last) if *last == count => return false,
+            Some(last) if last.day == count.day => *last = count,
+            _ => self.days.push(count),
+        }
+        let excess = self.days.len().saturating_sub(DAYS_KEPT);
+        self.days.drain(..excess);
+        true
+    }
+
+    /// Keep the counts of a window renamed from the portal
+    pub fn rename_window(&mut self, session_name: &str, old_name: &str, new_name: &str) {
+        for usage in &mut self.windows {
+            if usage.session_name == session_name && usage.window_name == old_name {
+                usage.window_name = new_name.to_string();
+            }
+        }
+    }
+
+    pub fn rename_session(&mut self, old_name: &str, new_name: &str) {
+        for usage in &mut self.windows {
+            if usage.session_name == old_name {
+                usage.session_name = new_name.to_string();
+            }
+        }
+    }
+
+    /// Sessions by switches, most used first
+    pub fn top_sessions(&self) -> Vec<SessionUsage> {
+        let mut by_session: HashMap<&str, SessionUsage> = HashMap::new();
+        for usage in &self.windows {
+            let session = by_session
+                .entry(&usage.session_name)
+                .or_insert_with(|| SessionUsage {
+                    name: usage.session_name.clone(),
+                    activations: 0,
+                    last_used: 0,
+                });
+            session.activations += usage.activations;
+            session.last_used = session.last_used.max(usage.last_used);
+        }
+        let mut sessions: Vec<SessionUsage> = by_session.into_values().collect();
+        sessions.sort_by(|a, b| {
+            b.activations
+                .cmp(&a.activations)
+                .then(b.last_used.cmp(&a.last_used))
+        });
+        sessions
+    }
+
+    /// Open windows that were never switched to, candidates for pruning
+    pub fn never_visited<'a>(&self, sessions: &'a [TmuxSession]) -> Vec<&'a TmuxWindow> {
+        sessions
+            .iter()
+            .flat_map(|s| &s.windows)
+            .filter(|window| {
+                !self.windows.iter().any(|usage| {
+                    usage.session_name == window.session_name && usage.window_name == window.name
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str, windows: &[&str]) -> TmuxSession {
+        TmuxSession {
+            name: name.to_string(),
+            windows: windows
+                .iter()
+                .enumerate()
+                .map(|(i, window)| TmuxWindow {
+                    id: format!("@{name}{i}"),
+                    index: i as u32,
+                    name: window.to_string(),
+                    session_name: name.to_string(),
+                    active: false,
+                    panes: 1,
+                    zoomed: false,
+                    activity: 0,
+                    command: "zsh".to_string(),
+                    path: "/tmp".to_string(),
+                    dead: false,
+                    linked: false,
+                    piped: false,
+                    note: None,
+                    color: None,
+                    user_options: Default::default(),
+                })
+                .collect(),
+            attached: 0,
+            activity: 0,
+            start_dir: None,
+            base_index: 0,
+            renumber: false,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_most_used_and_never_visited() {
+        let mut usage = Usage::default();
+        usage.record_activation("main", "editor", 10);
+        usage.record_activation("work", "api", 20);
+        usage.record_activation("work", "db", 30);
+        usage.record_activation("main", "editor", 40);
+        usage.record_activation("main", "editor", 50);
+        usage.rename_session("work", "job");
+
+        let top = usage.top_sessions();
+        let top: Vec<(&str, u32, i64)> = top
+            .iter()
+            .map(|s| (s.name.as_str(), s.activations, s.last_used))
+            .collect();
+        assert_eq!(top, [("main", 3, 50), ("job", 2, 30)]);
+
+        usage.rename_window("main", "editor", "nvim");
+        let sessions = [session("main", &["nvim", "logs"]), session("job", &["api"])];
+        let never: Vec<&str> = usage
+            .never_visited(&sessions)
+            .iter()
+            .map(|w| w.name.as_str())
+            .collect();
+        assert_eq!(never, ["logs"]);
+    }
+
+    #[test]
+    fn test_daily_counts() {
+        let mut usage = Usage::default();
+        let sessions = [session("main", &["editor", "shell"])];
+        assert!(usage.record_counts(&sessions, 100));
+        assert!(!usage.record_counts(&sessions, 200));
+
+        // Later the same day replaces the count, a new day adds one
+        let more = [session("main", &["editor"]), session("work", &["api"])];
+        assert!(usage.record_counts(&more, 300));
+        assert_eq!(usage.days.len(), 1);
+        assert_eq!(usage.days[0].sessions, 2);
+        for day in 1..=40 {
+            usage.record_counts(&sessions, day * SECONDS_PER_DAY);
+        }
+        assert_eq!(usage.days.len(), DAYS_KEPT);
+        assert_eq!(usage.days.last().unwrap().day, 40);
+    }
+}
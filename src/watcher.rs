@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::tmux::{signal_refresh, wait_for_refresh};
+
+/// Listens for the tmux hooks installed by `install-hooks` on a background
+/// thread and reports when the session tree needs to be refreshed
+pub struct RefreshWatcher {
+    receiver: Receiver<()>,
+    stop: Arc<AtomicBool>,
+}
+
+impl RefreshWatcher {
+    /// Start waiting on the refresh channel
+    pub fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        thread::spawn(move || {
+            while wait_for_refresh().is_ok() {
+                if thread_stop.load(Ordering::Relaxed) || sender.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { receiver, stop }
+    }
+
+    /// Whether any hook fired since the last call
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        while self.receiver.try_recv().is_ok() {
+            changed = true;
+        }
+        changed
+    }
+}
+
+impl Drop for RefreshWatcher {
+    fn drop(&mut self) {
+        // Wake the blocked `tmux wait-for` so it does not outlive the portal
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = signal_refresh();
+    }
+}
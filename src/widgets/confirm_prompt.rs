@@ -3,6 +3,31 @@ use ratatui::{
     prelude::*,
     widgets::{Block, Borders, Clear, Paragraph, Wrap},
 };
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::PopupAnchor;
+use crate::ui::text::wrapped_height;
+
+// "[ Yes ]   [ No ]"
+const BUTTONS_WIDTH: usize = 16;
+
+/// The buttons of a yes/no prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfirmButton {
+    Yes,
+    #[default]
+    No,
+}
+
+impl ConfirmButton {
+    /// The other button, for ←/→/Tab
+    pub fn toggle(self) -> Self {
+        match self {
+            ConfirmButton::Yes => ConfirmButton::No,
+            ConfirmButton::No => ConfirmButton::Yes,
+        }
+    }
+}
 
 /// A generic confirmation prompt widget
 pub struct ConfirmPrompt<'a> {
@@ -12,14 +37,20 @@ pub struct ConfirmPrompt<'a> {
     message: &'a str,
     /// Input text (if any)
     input: Option<&'a str>,
-    /// Whether to show the cursor
-    show_cursor: bool,
+    /// Column of the cursor in the input, if it's shown
+    cursor: Option<usize>,
     /// Border style
     border_style: Style,
     /// Text style
     text_style: Style,
     /// Input style
     input_style: Style,
+    /// Focused button, when the prompt shows [Yes] and [No]
+    buttons: Option<ConfirmButton>,
+    /// Vertical position in the area
+    anchor: PopupAnchor,
+    /// Percent of the area's width the prompt grows to before wrapping
+    max_width_percent: u16,
 }
 
 impl<'a> ConfirmPrompt<'a> {
@@ -29,10 +60,13 @@ impl<'a> ConfirmPrompt<'a> {
             title,
             message,
             input: None,
-            show_cursor: false,
+            cursor: None,
             border_style: Style::default(),
             text_style: Style::default(),
             input_style: Style::default(),
+            buttons: None,
+            anchor: PopupAnchor::default(),
+            max_width_percent: 80,
         }
     }
 
@@ -42,9 +76,9 @@ impl<'a> ConfirmPrompt<'a> {
         self
     }
 
-    /// Set whether to show the cursor
-    pub fn show_cursor(mut self, show: bool) -> Self {
-        self.show_cursor = show;
+    /// Show the cursor this many columns into the input
+    pub fn cursor(mut self, column: usize) -> Self {
+        self.cursor = Some(column);
         self
     }
 
@@ -66,10 +100,27 @@ impl<'a> ConfirmPrompt<'a> {
         self
     }
 
+    /// Place the prompt at the top, center or bottom
+    pub fn anchor(mut self, anchor: PopupAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Wrap the message once the prompt is this wide, in percent of the area
+    pub fn max_width_percent(mut self, percent: u16) -> Self {
+        self.max_width_percent = percent.clamp(1, 100);
+        self
+    }
+
+    /// Show [Yes] and [No] buttons below the message, with one focused
+    pub fn buttons(mut self, focused: ConfirmButton) -> Self {
+        self.buttons = Some(focused);
+        self
+    }
+
     /// Render the confirmation prompt
     pub fn render(&self, frame: &mut Frame, area: Rect) {
-        // Create a centered popup area
-        let popup_area = self.centered_rect(50, 20, area);
+        let (popup_area, message_height) = self.popup_area(area);
 
         // Clear the area behind the popup
         frame.render_widget(Clear, popup_area);
@@ -82,67 +133,128 @@ impl<'a> ConfirmPrompt<'a> {
 
         frame.render_widget(popup_block, popup_area);
 
-        // Calculate the inner area for content
+        // Calculate the inner area for content, one column of padding inside the border
         let inner_area = popup_area.inner(Margin {
             vertical: 1,
-            horizontal: 1,
+            horizontal: 2,
         });
 
+        // Input and buttons keep their rows when a long message doesn't fit
+        let footer = self.footer_height();
+        let message_height = message_height.min(inner_area.height.saturating_sub(footer));
+
         // Render the message
         let message_paragraph = Paragraph::new(self.message)
             .style(self.text_style)
             .wrap(Wrap { trim: true });
 
-        let message_height = 1; // Assuming message is a single line
         let message_area = Rect {
             x: inner_area.x,
             y: inner_area.y,
             width: inner_area.width,
-            height: message_height,
+            height: message_height.max(1).min(inner_area.height),
         };
 
         frame.render_widget(message_paragraph, message_area);
 
         // If there's input, render it below the message
         if let Some(input) = self.input {
-            let input_paragraph = Paragraph::new(input)
-                .style(self.input_style)
-                .wrap(Wrap { trim: true });
-
             let input_area = Rect {
                 x: inner_area.x,
                 y: inner_area.y + message_height + 1, // +1 for spacing
                 width: inner_area.width,
                 height: 1,
-            };
+            }
+            .intersection(inner_area);
+
+            // Scroll long input sideways to keep the cursor in view
+            let cursor = self.cursor.unwrap_or(0) as u16;
+            let scroll = (cursor + 1).saturating_sub(input_area.width);
+            let input_paragraph = Paragraph::new(input)
+                .style(self.input_style)
+                .scroll((0, scroll));
 
             frame.render_widget(input_paragraph, input_area);
 
-            // Position cursor at end of input if needed
-            if self.show_cursor {
-                frame.set_cursor_position((input_area.x + input.len() as u16, input_area.y));
+            if self.cursor.is_some() {
+                frame.set_cursor_position((input_area.x + cursor - scroll, input_area.y));
             }
         }
+
+        if let Some(focused) = self.buttons {
+            let buttons_area = Rect {
+                y: inner_area.y + inner_area.height.saturating_sub(1),
+                height: 1.min(inner_area.height),
+                ..inner_area
+            };
+            frame.render_widget(
+                Paragraph::new(self.button_line(focused)).alignment(Alignment::Center),
+                buttons_area,
+            );
+        }
     }
 
-    /// Helper function to create a centered rectangle
-    fn centered_rect(&self, percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-        let popup_layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Percentage((100 - percent_y) / 2),
-                Constraint::Percentage(percent_y),
-                Constraint::Percentage((100 - percent_y) / 2),
-            ])
-            .split(r);
-
-        Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage((100 - percent_x) / 2),
-                Constraint::Percentage(percent_x),
-                Constraint::Percentage((100 - percent_x) / 2),
-            ])
-            .split(popup_layout[1])[1]
+    // Rows below the message: the input and the buttons, each after a blank row
+    fn footer_height(&self) -> u16 {
+        let mut rows = 0;
+        if self.input.is_some() {
+            rows += 2;
+        }
+        if self.buttons.is_some() {
+            rows += 2;
+        }
+        rows
+    }
+
+    /// Area fitting the content, and the rows of the wrapped message. Wide
+    /// content wraps at `max_width_percent` of the screen, narrow content
+    /// still gets a readable box
+    fn popup_area(&self, area: Rect) -> (Rect, u16) {
+        const MIN_WIDTH: u16 = 40;
+        // Border and padding on each side
+        const CHROME: u16 = 4;
+
+        let content_width = self
+            .message
+            .lines()
+            .map(|line| line.width())
+            .chain(self.input.map(|input| input.width() + 1))
+            .chain(self.buttons.map(|_| BUTTONS_WIDTH))
+            .chain([self.title.width() + 2])
+            .max()
+            .unwrap_or(0) as u16;
+
+        let max_width = (area.width as u32 * self.max_width_percent as u32 / 100) as u16;
+        let max_width = max_width.max(MIN_WIDTH).min(area.width);
+        let width = (content_width + CHROME).clamp(MIN_WIDTH.min(max_width), max_width);
+
+        let message_height =
+            wrapped_height(self.message, width.saturating_sub(CHROME) as usize) as u16;
+        let height = (message_height + self.footer_height() + 2).min(area.height);
+
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: self.anchor.y(area, height),
+            width,
+            height,
+        };
+        (popup, message_height)
+    }
+
+    fn button_line(&self, focused: ConfirmButton) -> Line<'static> {
+        let button = |label: &'static str, button: ConfirmButton| {
+            let style = if button == focused {
+                self.text_style
+                    .add_modifier(Modifier::REVERSED | Modifier::BOLD)
+            } else {
+                self.text_style
+            };
+            Span::styled(label, style)
+        };
+        Line::from(vec![
+            button("[ Yes ]", ConfirmButton::Yes),
+            Span::raw("   "),
+            button("[ No ]", ConfirmButton::No),
+        ])
     }
 }
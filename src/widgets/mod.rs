@@ -1,2 +1,4 @@
 pub mod confirm_prompt;
 pub mod help_panel;
+pub mod shortcut_overlay;
+pub mod toast;
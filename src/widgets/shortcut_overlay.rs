@@ -0,0 +1,110 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::PopupAnchor;
+
+/// A key and what pressing it leads to
+pub struct Shortcut<'a> {
+    pub key: &'a str,
+    pub target: &'a str,
+}
+
+/// Boxed list of shortcuts in an area, sized to its content
+pub struct ShortcutOverlay<'a> {
+    /// Title on the border
+    title: &'a str,
+    /// Shortcuts to list, top to bottom
+    shortcuts: Vec<Shortcut<'a>>,
+    /// Key style
+    key_style: Style,
+    /// Target style
+    target_style: Style,
+    /// Border style
+    border_style: Style,
+    /// Vertical position in the area
+    anchor: PopupAnchor,
+}
+
+impl<'a> ShortcutOverlay<'a> {
+    /// Create a new overlay with the given title and shortcuts
+    pub fn new(title: &'a str, shortcuts: Vec<Shortcut<'a>>) -> Self {
+        Self {
+            title,
+            shortcuts,
+            key_style: Style::default(),
+            target_style: Style::default(),
+            border_style: Style::default(),
+            anchor: PopupAnchor::default(),
+        }
+    }
+
+    /// Set the key style
+    pub fn key_style(mut self, style: Style) -> Self {
+        self.key_style = style;
+        self
+    }
+
+    /// Set the target style
+    pub fn target_style(mut self, style: Style) -> Self {
+        self.target_style = style;
+        self
+    }
+
+    /// Set the border style
+    pub fn border_style(mut self, style: Style) -> Self {
+        self.border_style = style;
+        self
+    }
+
+    /// Place the overlay at the top, center or bottom
+    pub fn anchor(mut self, anchor: PopupAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+}
+
+impl<'a> Widget for ShortcutOverlay<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let content_width = self
+            .shortcuts
+            .iter()
+            .map(|s| s.key.width() + s.target.width() + 2)
+            .chain(std::iter::once(self.title.width()))
+            .max()
+            .unwrap_or(0);
+
+        // Borders plus one column of padding on each side
+        let width = (content_width as u16 + 4).min(area.width);
+        let height = (self.shortcuts.len() as u16 + 2).min(area.height);
+        let overlay_area = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: self.anchor.y(area, height),
+            width,
+            height,
+        };
+
+        let lines: Vec<Line> = self
+            .shortcuts
+            .iter()
+            .map(|s| {
+                Line::from(vec![
+                    Span::styled(format!(" {} ", s.key), self.key_style),
+                    Span::styled(format!(" {}", s.target), self.target_style),
+                ])
+            })
+            .collect();
+
+        Clear.render(overlay_area, buf);
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(self.title)
+                    .borders(Borders::ALL)
+                    .border_style(self.border_style),
+            )
+            .render(overlay_area, buf);
+    }
+}
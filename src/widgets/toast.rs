@@ -0,0 +1,62 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Clear, Paragraph},
+};
+
+/// A single toast: message text and the style to draw it in
+pub struct Toast<'a> {
+    pub text: &'a str,
+    pub style: Style,
+}
+
+/// Stack of one-line toasts anchored to the bottom-right corner of an area
+pub struct ToastStack<'a> {
+    /// Toasts to display, oldest first
+    toasts: Vec<Toast<'a>>,
+    /// Maximum width of a toast, longer messages are truncated
+    max_width: u16,
+}
+
+impl<'a> ToastStack<'a> {
+    /// Create a new stack with the given toasts
+    pub fn new(toasts: Vec<Toast<'a>>) -> Self {
+        Self {
+            toasts,
+            max_width: 60,
+        }
+    }
+
+    /// Set the maximum toast width
+    pub fn max_width(mut self, width: u16) -> Self {
+        self.max_width = width;
+        self
+    }
+}
+
+impl<'a> Widget for ToastStack<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let max_width = self.max_width.min(area.width);
+
+        // Newest toast sits at the bottom, older ones stack above it
+        for (row, toast) in self.toasts.iter().rev().enumerate() {
+            let row = row as u16;
+            if row >= area.height {
+                break;
+            }
+
+            let text = format!(" {} ", toast.text);
+            let width = (text.chars().count() as u16).min(max_width);
+            let toast_area = Rect {
+                x: area.right() - width,
+                y: area.bottom() - 1 - row,
+                width,
+                height: 1,
+            };
+
+            Clear.render(toast_area, buf);
+            Paragraph::new(text)
+                .style(toast.style)
+                .render(toast_area, buf);
+        }
+    }
+}
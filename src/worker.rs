@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+type Job<T> = Box<dyn FnOnce() -> T + Send>;
+
+/// Runs blocking jobs (tmux commands, directory scans) on a background thread
+/// so the UI keeps rendering; results come back in submission order
+pub struct Worker<T> {
+    jobs: Sender<Job<T>>,
+    results: Receiver<T>,
+    pending: VecDeque<String>, // Label of every job not reported back, oldest first
+    busy_since: Option<Instant>,
+}
+
+impl<T: Send + 'static> Worker<T> {
+    /// Start the worker thread, it exits once the worker is dropped
+    pub fn spawn() -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<Job<T>>();
+        let (result_sender, results) = mpsc::channel();
+
+        thread::spawn(move || {
+            for job in job_receiver {
+                if result_sender.send(job()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            jobs,
+            results,
+            pending: VecDeque::new(),
+            busy_since: None,
+        }
+    }
+
+    /// Queue a job, jobs run one at a time in the order they were submitted
+    pub fn submit<F>(&mut self, job: F)
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.submit_as("", job);
+    }
+
+    /// Queue a job with a note on what it does, shown while it's slow
+    pub fn submit_as<F>(&mut self, label: impl Into<String>, job: F)
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        if self.jobs.send(Box::new(job)).is_ok() {
+            self.pending.push_back(label.into());
+            self.busy_since.get_or_insert_with(Instant::now);
+        }
+    }
+
+    /// Take the next finished result without blocking
+    pub fn try_recv(&mut self) -> Option<T> {
+        let result = self.results.try_recv().ok()?;
+        self.pending.pop_front();
+        if self.pending.is_empty() {
+            self.busy_since = None;
+        }
+        Some(result)
+    }
+
+    /// Whether any submitted job has not reported back yet
+    pub fn is_busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// How long jobs have kept the worker busy without a break
+    pub fn busy_for(&self) -> Option<Duration> {
+        self.busy_since.map(|since| since.elapsed())
+    }
+
+    /// Label of the oldest labeled job still in flight
+    pub fn label(&self) -> Option<&str> {
+        self.pending
+            .iter()
+            .find(|label| !label.is_empty())
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_results_arrive_in_submission_order() {
+        let mut worker = Worker::spawn();
+        for i in 0..5 {
+            worker.submit(move || {
+                // Earlier jobs take longer, order must still be preserved
+                thread::sleep(Duration::from_millis(5 * (5 - i)));
+                i
+            });
+        }
+        assert!(worker.is_busy());
+        assert_eq!(worker.label(), None);
+        worker.submit_as("Counting", || 5);
+        assert_eq!(worker.label(), Some("Counting"));
+
+        let mut results = Vec::new();
+        while results.len() < 6 {
+            match worker.try_recv() {
+                Some(result) => results.push(result),
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+
+        assert_eq!(results, vec![0, 1, 2, 3, 4, 5]);
+        assert!(!worker.is_busy());
+        assert_eq!(worker.busy_for(), None);
+    }
+}